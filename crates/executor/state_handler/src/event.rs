@@ -1,13 +1,20 @@
+//! Events emitted during transactions, tracked per transaction level.
 #![allow(unused)]
 use allocator_api2::vec::Vec;
+pub use ixc_account_manager::events::EventMetadata;
 use ixc_message_api::AccountID;
 
 /// Represents event data with associated account information
 #[derive(Clone, Debug)]
 pub struct EventData {
+    /// The encoded event data.
     pub data: Vec<u8>,
+    /// The type selector of the event.
     pub type_selector: u64,
+    /// The account that emitted the event.
     pub sender: AccountID,
+    /// Where the event was emitted.
+    pub metadata: EventMetadata,
 }
 
 /// Manages event state with support for snapshots and reversions
@@ -15,12 +22,36 @@ pub struct EventData {
 pub struct EventState {
     // Vector of event vectors, where each inner vector represents events for a transaction level
     events: Vec<Vec<EventData>>,
+    block_height: u64,
+    tx_index: u32,
+    message_index: u32,
+    next_sequence: u64,
+    // Events of rolled back transaction levels, if they are being recorded
+    reverted: Option<Vec<EventData>>,
 }
 
 impl EventState {
     /// Creates a new EventState instance
     pub fn new() -> Self {
-        Self { events: Vec::new() }
+        Self::default()
+    }
+
+    /// Sets the block height and transaction index recorded with subsequent events
+    /// and resets the message index
+    pub fn set_tx_position(&mut self, block_height: u64, tx_index: u32) {
+        self.block_height = block_height;
+        self.tx_index = tx_index;
+        self.message_index = 0;
+    }
+
+    /// Sets the message index recorded with subsequent events
+    pub fn set_message_index(&mut self, message_index: u32) {
+        self.message_index = message_index;
+    }
+
+    /// Enables or disables keeping the events of rolled back transaction levels
+    pub fn record_reverted_events(&mut self, enabled: bool) {
+        self.reverted = enabled.then(Vec::new);
     }
 
     /// Takes a snapshot of the current event state
@@ -31,13 +62,22 @@ impl EventState {
         self.events.len()
     }
 
-    /// Adds an event at the current transaction level
-    pub fn emit_event(&mut self, sender: AccountID, type_selector: u64, data: Vec<u8>) {
+    /// Adds an event at the current transaction level,
+    /// emitted by a call at the given depth
+    pub fn emit_event(&mut self, sender: AccountID, type_selector: u64, data: Vec<u8>, depth: u32) {
         if self.events.is_empty() {
             // If no transaction level exists, create one
             self.events.push(Vec::new());
         }
 
+        let metadata = EventMetadata {
+            block_height: self.block_height,
+            tx_index: self.tx_index,
+            message_index: self.message_index,
+            depth,
+            sequence: self.next_sequence,
+        };
+        self.next_sequence += 1;
         let current_level = self
             .events
             .last_mut()
@@ -46,13 +86,20 @@ impl EventState {
             data,
             type_selector,
             sender,
+            metadata,
         });
     }
 
     /// Reverts the state to a previous snapshot level
     pub fn revert_to_snapshot(&mut self, snapshot_level: usize) {
-        // Remove all events after the snapshot level
-        self.events.truncate(snapshot_level);
+        // Remove all events after the snapshot level, keeping them if reverted events are recorded
+        if let Some(reverted) = &mut self.reverted {
+            for level in self.events.drain(snapshot_level.min(self.events.len())..) {
+                reverted.extend(level);
+            }
+        } else {
+            self.events.truncate(snapshot_level);
+        }
     }
 
     /// Commits the current transaction level by merging events with the previous level
@@ -73,6 +120,20 @@ impl EventState {
     pub fn get_all_events(&self) -> Vec<&EventData> {
         self.events.iter().flat_map(|level| level.iter()).collect()
     }
+
+    /// Returns the events of rolled back transaction levels in the order they were emitted,
+    /// or an empty slice if reverted events aren't recorded
+    pub fn get_reverted_events(&self) -> &[EventData] {
+        self.reverted.as_deref().unwrap_or_default()
+    }
+
+    /// Takes the recorded events of rolled back transaction levels
+    pub fn take_reverted_events(&mut self) -> Vec<EventData> {
+        self.reverted
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -93,15 +154,15 @@ mod tests {
         let snapshot1 = event_state.snapshot();
 
         // Emit some events at level 0
-        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event1"));
-        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event2"));
+        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event1"), 0);
+        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event2"), 0);
 
         // Take another snapshot - level 1
         let _ = event_state.snapshot();
 
         // Emit events at level 1
-        event_state.emit_event(AccountID::new(2), 0, create_test_data(b"event3"));
-        event_state.emit_event(AccountID::new(2), 0, create_test_data(b"event4"));
+        event_state.emit_event(AccountID::new(2), 0, create_test_data(b"event3"), 0);
+        event_state.emit_event(AccountID::new(2), 0, create_test_data(b"event4"), 0);
 
         // Verify level 1 events
         let current_events = event_state.get_current_events().unwrap();
@@ -113,7 +174,7 @@ mod tests {
         let _ = event_state.snapshot();
 
         // Emit events at level 2
-        event_state.emit_event(AccountID::new(3), 0, create_test_data(b"event5"));
+        event_state.emit_event(AccountID::new(3), 0, create_test_data(b"event5"), 0);
 
         // Revert to snapshot1 (level 0)
         event_state.revert_to_snapshot(snapshot1);
@@ -134,11 +195,11 @@ mod tests {
 
         // First transaction level
         let _ = event_state.snapshot();
-        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event1"));
+        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event1"), 0);
 
         // Second transaction level
         let _ = event_state.snapshot();
-        event_state.emit_event(AccountID::new(2), 0, create_test_data(b"event2"));
+        event_state.emit_event(AccountID::new(2), 0, create_test_data(b"event2"), 0);
 
         // Commit second level to first
         event_state.commit();
@@ -149,4 +210,59 @@ mod tests {
         assert_eq!(current_events[0].data, create_test_data(b"event1"));
         assert_eq!(current_events[1].data, create_test_data(b"event2"));
     }
+
+    #[test]
+    fn test_event_metadata_and_reverted_events() {
+        let mut event_state = EventState::new();
+        event_state.record_reverted_events(true);
+        event_state.set_tx_position(7, 2);
+
+        let _ = event_state.snapshot();
+        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event1"), 0);
+        event_state.set_message_index(1);
+        let _ = event_state.snapshot();
+        event_state.emit_event(AccountID::new(2), 0, create_test_data(b"event2"), 1);
+        event_state.commit();
+        let _ = event_state.snapshot();
+        event_state.emit_event(AccountID::new(3), 0, create_test_data(b"event3"), 1);
+        event_state.revert_to_snapshot(1);
+        event_state.emit_event(AccountID::new(1), 0, create_test_data(b"event4"), 0);
+
+        let metadata: Vec<EventMetadata> = event_state
+            .get_all_events()
+            .iter()
+            .map(|e| e.metadata)
+            .collect();
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(
+            metadata[0],
+            EventMetadata {
+                block_height: 7,
+                tx_index: 2,
+                message_index: 0,
+                depth: 0,
+                sequence: 0,
+            }
+        );
+        assert_eq!((metadata[1].message_index, metadata[1].depth), (1, 1));
+        assert_eq!(metadata[1].sequence, 1);
+        // the reverted event keeps its sequence number
+        assert_eq!(metadata[2].sequence, 3);
+
+        let reverted = event_state.get_reverted_events();
+        assert_eq!(reverted.len(), 1);
+        assert_eq!(reverted[0].data, create_test_data(b"event3"));
+        assert_eq!(reverted[0].metadata.depth, 1);
+        assert_eq!(reverted[0].metadata.sequence, 2);
+        assert_eq!(event_state.take_reverted_events().len(), 1);
+        assert!(event_state.get_reverted_events().is_empty());
+
+        // without recording, reverted events are discarded
+        event_state.record_reverted_events(false);
+        let _ = event_state.snapshot();
+        event_state.emit_event(AccountID::new(3), 0, create_test_data(b"event5"), 1);
+        event_state.revert_to_snapshot(1);
+        assert!(event_state.get_reverted_events().is_empty());
+        assert_eq!(event_state.get_all_events().len(), 3);
+    }
 }
@@ -1,8 +1,8 @@
 //! A state handler that can be used to store and retrieve state.
-mod event;
+pub mod event;
 mod snapshot_state;
 
-use crate::event::EventState;
+use crate::event::{EventData, EventState};
 use crate::snapshot_state::{Snapshot, SnapshotState};
 use allocator_api2::alloc::{Allocator, Global};
 use allocator_api2::vec::Vec;
@@ -40,6 +40,27 @@ impl<S: Store> StateHandler<S> {
         }
     }
 
+    /// Sets the block height and transaction index recorded with the events
+    /// emitted by the next transaction.
+    pub fn set_tx_position(&mut self, block_height: u64, tx_index: u32) {
+        self.event_state.set_tx_position(block_height, tx_index);
+    }
+
+    /// Enables or disables keeping the events emitted by rolled back transactions for debugging.
+    pub fn record_reverted_events(&mut self, enabled: bool) {
+        self.event_state.record_reverted_events(enabled);
+    }
+
+    /// Returns the events which haven't been rolled back in the order they were emitted.
+    pub fn events(&self) -> Vec<&EventData> {
+        self.event_state.get_all_events()
+    }
+
+    /// Returns the events emitted by rolled back transactions if they are being recorded.
+    pub fn reverted_events(&self) -> &[EventData] {
+        self.event_state.get_reverted_events()
+    }
+
     pub(crate) fn construct_key(
         account_id: AccountID,
         scope: Option<AccountID>,
@@ -100,6 +121,17 @@ impl<S: Store> StdStateManager for StateHandler<S> {
         }
     }
 
+    fn kv_value_len(
+        &self,
+        account_id: AccountID,
+        scope: Option<AccountID>,
+        key: &[u8],
+    ) -> Result<Option<usize>, ErrorCode> {
+        let constructed_key = Self::construct_key(account_id, scope, key, false);
+        let value = self.snapshot_state.get(&constructed_key, &Global)?;
+        Ok(value.map(|value| value.len()))
+    }
+
     fn kv_set(
         &mut self,
         account_id: AccountID,
@@ -149,7 +181,7 @@ impl<S: Store> StdStateManager for StateHandler<S> {
         scope: Option<AccountID>,
         key: &[u8],
         value: u128,
-    ) -> Result<(), ErrorCode> {
+    ) -> Result<u128, ErrorCode> {
         let constructed_key = Self::construct_key(account_id, scope, key, true);
 
         let bz = self.snapshot_state.get(&constructed_key, &Global)?;
@@ -162,7 +194,7 @@ impl<S: Store> StdStateManager for StateHandler<S> {
         let mut vec = Vec::new();
         vec.extend_from_slice(&new_value.to_le_bytes());
         self.snapshot_state.set(constructed_key, &vec);
-        Ok(())
+        Ok(new_value)
     }
 
     fn accumulator_safe_sub(
@@ -222,15 +254,23 @@ impl<S: Store> StdStateManager for StateHandler<S> {
         Ok(())
     }
 
+    /// Record the index of the message whose events are emitted next.
+    fn begin_message(&mut self, index: u32) -> Result<(), ErrorCode> {
+        self.event_state.set_message_index(index);
+        Ok(())
+    }
+
     /// Emit an event.
     fn emit_event(
         &mut self,
         sender: AccountID,
         type_selector: u64,
         data: &[u8],
+        depth: u32,
     ) -> Result<(), ErrorCode> {
         let data_vec = Vec::from(data);
-        self.event_state.emit_event(sender, type_selector, data_vec);
+        self.event_state
+            .emit_event(sender, type_selector, data_vec, depth);
         Ok(())
     }
 }
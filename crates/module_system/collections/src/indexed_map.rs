@@ -0,0 +1,564 @@
+//! A map with secondary indexes over fields of its values.
+use crate::iter::{prefix_end, Iter, Order};
+use crate::map::Map;
+use crate::prefix::Prefix;
+use crate::store_client::KVStoreClient;
+use allocator_api2::alloc::Allocator;
+use core::borrow::Borrow;
+use core::marker::PhantomData;
+use ixc_core::error::{convert_client_error, ClientError};
+use ixc_core::resource::{InitializationError, StateObjectResource};
+use ixc_core::result::ClientResult;
+use ixc_core::Context;
+use ixc_message_api::code::ErrorCode;
+use ixc_message_api::encoding::decode_kv_pairs;
+use ixc_schema::schema::SchemaValue;
+use ixc_schema::state_object::{
+    encode_object_key_pair, encode_object_key_prefix, IndexDescriptor, KeyFieldValue,
+    ObjectFieldValue, ObjectValue, StateObjectDescriptor, ValueField,
+};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+/// A map whose values are also indexed by some of their fields.
+/// `I` is either a single index, such as [`UniqueIndex`] or [`MultiIndex`], or a tuple of them.
+///
+/// The entries of the map are stored under the map's prefix followed by 0,
+/// and the entries of the index at position `n` under the map's prefix followed by `n + 1`.
+/// Each index entry has the indexed field followed by the primary key as its key
+/// and an empty value.
+pub struct IndexedMap<K: KeyFieldValue, V: ObjectValue, I: Indexes<K, V>> {
+    map: Map<K, V>,
+    indexes: I::State,
+}
+
+/// An index which allows only a single entry for each value of the field at position `FIELD`.
+pub struct UniqueIndex<const FIELD: usize>;
+
+/// An index which allows any number of entries for each value of the field at position `FIELD`.
+pub struct MultiIndex<const FIELD: usize>;
+
+/// An error that can occur when updating an indexed map.
+#[derive(Default, Debug, Clone, TryFromPrimitive, IntoPrimitive, SchemaValue, Copy)]
+#[repr(u8)]
+#[non_exhaustive]
+pub enum IndexError {
+    /// Another entry already has the same value for a field with a unique index.
+    #[default]
+    UniqueConstraintViolation,
+}
+
+/// An index over a field of the values `V` of an indexed map.
+pub trait Index<V: ObjectValue> {
+    /// The type of the indexed field.
+    type Field: KeyFieldValue;
+    /// The position of the indexed field in the value.
+    const FIELD: usize;
+    /// Whether each value of the field may only appear in a single entry.
+    const UNIQUE: bool;
+
+    /// Borrow the indexed field from a value.
+    fn field<'b, 'a>(value: &'b V::In<'a>) -> &'b <Self::Field as ObjectFieldValue>::In<'a>;
+
+    /// Borrow the indexed field from a decoded value.
+    fn field_out<'b, 'a>(value: &'b V::Out<'a>) -> &'b <Self::Field as ObjectFieldValue>::In<'a>;
+}
+
+impl<V: ValueField<F>, const F: usize> Index<V> for UniqueIndex<F> {
+    type Field = V::Field;
+    const FIELD: usize = F;
+    const UNIQUE: bool = true;
+
+    fn field<'b, 'a>(value: &'b V::In<'a>) -> &'b <Self::Field as ObjectFieldValue>::In<'a> {
+        V::field(value)
+    }
+
+    fn field_out<'b, 'a>(value: &'b V::Out<'a>) -> &'b <Self::Field as ObjectFieldValue>::In<'a> {
+        V::field_out(value)
+    }
+}
+
+impl<V: ValueField<F>, const F: usize> Index<V> for MultiIndex<F> {
+    type Field = V::Field;
+    const FIELD: usize = F;
+    const UNIQUE: bool = false;
+
+    fn field<'b, 'a>(value: &'b V::In<'a>) -> &'b <Self::Field as ObjectFieldValue>::In<'a> {
+        V::field(value)
+    }
+
+    fn field_out<'b, 'a>(value: &'b V::Out<'a>) -> &'b <Self::Field as ObjectFieldValue>::In<'a> {
+        V::field_out(value)
+    }
+}
+
+/// The set of indexes of an indexed map with keys `K` and values `V`.
+/// This trait is implemented for single indexes and tuples of up to three indexes.
+pub trait Indexes<K: KeyFieldValue, V: ObjectValue> {
+    /// The prefixes of the indexes.
+    type State;
+    /// The number of indexes.
+    const N: usize;
+
+    /// Initializes the indexes under the given map prefix.
+    fn init(prefix: &[u8]) -> Result<Self::State, InitializationError>;
+
+    /// Checks that replacing the `old` value at `key` with the `new` one
+    /// doesn't violate any unique index.
+    fn check(
+        state: &Self::State,
+        ctx: &Context,
+        key: &K::In<'_>,
+        old: Option<&V::Out<'_>>,
+        new: &V::In<'_>,
+    ) -> ClientResult<(), IndexError>;
+
+    /// Updates the indexes when the `old` value at `key` is replaced with the `new` one.
+    fn update(
+        state: &Self::State,
+        ctx: &mut Context,
+        key: &K::In<'_>,
+        old: Option<&V::Out<'_>>,
+        new: Option<&V::In<'_>>,
+    ) -> ClientResult<()>;
+
+    #[cfg(feature = "std")]
+    /// Gets the descriptors of the indexes of the indexed map with the given descriptor.
+    fn descriptors<'a>(
+        allocator: &'a dyn Allocator,
+        map: &StateObjectDescriptor<'a>,
+        index_names: &[&'a str],
+    ) -> allocator_api2::vec::Vec<IndexDescriptor<'a>, &'a dyn Allocator>;
+}
+
+/// Gives access to the index at position `N` of a set of indexes.
+pub trait IndexAt<K: KeyFieldValue, V: ObjectValue, const N: usize>: Indexes<K, V> {
+    /// The index at position `N`.
+    type Index: Index<V>;
+
+    /// Gets the prefix of the index at position `N`.
+    fn prefix(state: &Self::State) -> &Prefix;
+}
+
+fn check_index<K: KeyFieldValue, V: ObjectValue, I: Index<V>>(
+    prefix: &Prefix,
+    ctx: &Context,
+    key: &K::In<'_>,
+    old: Option<&V::Out<'_>>,
+    new: &V::In<'_>,
+) -> ClientResult<(), IndexError> {
+    if !I::UNIQUE {
+        return Ok(());
+    }
+    let mem = ctx.memory_manager();
+    let new_bz = encode_object_key_pair::<I::Field, K>(prefix.as_slice(), I::field(new), key, mem)?;
+    if let Some(old) = old {
+        let old_bz =
+            encode_object_key_pair::<I::Field, K>(prefix.as_slice(), I::field_out(old), key, mem)?;
+        if old_bz == new_bz {
+            return Ok(());
+        }
+    }
+    // any other entry with the same field value violates the constraint
+    let start = encode_object_key_prefix::<I::Field>(prefix.as_slice(), I::field(new), mem)?;
+    let end = prefix_end(start, mem);
+    let entries = KVStoreClient
+        .range(ctx, start, end, Order::Ascending, 2)
+        .map_err(convert_client_error)?;
+    for entry in decode_kv_pairs(entries)? {
+        let (entry_key, _) = entry?;
+        if entry_key != new_bz {
+            return Err(ClientError::new(ErrorCode::HandlerCode(
+                IndexError::UniqueConstraintViolation,
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn update_index<K: KeyFieldValue, V: ObjectValue, I: Index<V>>(
+    prefix: &Prefix,
+    ctx: &mut Context,
+    key: &K::In<'_>,
+    old: Option<&V::Out<'_>>,
+    new: Option<&V::In<'_>>,
+) -> ClientResult<()> {
+    let mem = ctx.memory_manager();
+    let old_bz = match old {
+        Some(old) => Some(encode_object_key_pair::<I::Field, K>(
+            prefix.as_slice(),
+            I::field_out(old),
+            key,
+            mem,
+        )?),
+        None => None,
+    };
+    let new_bz = match new {
+        Some(new) => Some(encode_object_key_pair::<I::Field, K>(
+            prefix.as_slice(),
+            I::field(new),
+            key,
+            mem,
+        )?),
+        None => None,
+    };
+    if old_bz == new_bz {
+        return Ok(());
+    }
+    if let Some(old_bz) = old_bz {
+        unsafe { KVStoreClient.delete(ctx, old_bz)? };
+    }
+    if let Some(new_bz) = new_bz {
+        unsafe { KVStoreClient.set(ctx, new_bz, &[])? };
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn index_descriptor<'a, V: ObjectValue, I: Index<V>>(
+    allocator: &'a dyn Allocator,
+    map: &StateObjectDescriptor<'a>,
+    position: u8,
+    name: Option<&&'a str>,
+) -> IndexDescriptor<'a> {
+    use allocator_api2::vec::Vec;
+    use ixc_schema::list::List;
+    let field = map.value_fields.as_slice()[I::FIELD];
+    let mut desc = IndexDescriptor::default();
+    desc.name = name.copied().unwrap_or(field.name);
+    let mut prefix = Vec::new_in(allocator);
+    prefix.push(position + 1);
+    desc.prefix = List::Owned(prefix);
+    let mut key_fields = Vec::new_in(allocator);
+    key_fields.push(field);
+    key_fields.extend_from_slice(map.key_fields.as_slice());
+    desc.key_fields = List::Owned(key_fields);
+    desc.unique = I::UNIQUE;
+    desc
+}
+
+macro_rules! impl_single_index {
+    ($index:ident) => {
+        impl<K: KeyFieldValue, V: ValueField<F>, const F: usize> Indexes<K, V> for $index<F> {
+            type State = Prefix;
+            const N: usize = 1;
+
+            fn init(prefix: &[u8]) -> Result<Self::State, InitializationError> {
+                Prefix::new(prefix, 1)
+            }
+
+            fn check(
+                state: &Self::State,
+                ctx: &Context,
+                key: &K::In<'_>,
+                old: Option<&V::Out<'_>>,
+                new: &V::In<'_>,
+            ) -> ClientResult<(), IndexError> {
+                check_index::<K, V, Self>(state, ctx, key, old, new)
+            }
+
+            fn update(
+                state: &Self::State,
+                ctx: &mut Context,
+                key: &K::In<'_>,
+                old: Option<&V::Out<'_>>,
+                new: Option<&V::In<'_>>,
+            ) -> ClientResult<()> {
+                update_index::<K, V, Self>(state, ctx, key, old, new)
+            }
+
+            #[cfg(feature = "std")]
+            fn descriptors<'a>(
+                allocator: &'a dyn Allocator,
+                map: &StateObjectDescriptor<'a>,
+                index_names: &[&'a str],
+            ) -> allocator_api2::vec::Vec<IndexDescriptor<'a>, &'a dyn Allocator> {
+                let mut descriptors = allocator_api2::vec::Vec::new_in(allocator);
+                descriptors.push(index_descriptor::<V, Self>(
+                    allocator,
+                    map,
+                    0,
+                    index_names.first(),
+                ));
+                descriptors
+            }
+        }
+
+        impl<K: KeyFieldValue, V: ValueField<F>, const F: usize> IndexAt<K, V, 0> for $index<F> {
+            type Index = Self;
+
+            fn prefix(state: &Self::State) -> &Prefix {
+                state
+            }
+        }
+    };
+}
+
+impl_single_index!(UniqueIndex);
+impl_single_index!(MultiIndex);
+
+macro_rules! impl_index_tuple {
+    ($n:expr; $($index:ident $i:tt),*) => {
+        impl<K: KeyFieldValue, V: ObjectValue, $($index: Index<V>),*> Indexes<K, V> for ($($index,)*) {
+            type State = ($(impl_index_tuple!(@prefix $index),)*);
+            const N: usize = $n;
+
+            fn init(prefix: &[u8]) -> Result<Self::State, InitializationError> {
+                Ok(($(Prefix::new(prefix, $i + 1)?,)*))
+            }
+
+            fn check(
+                state: &Self::State,
+                ctx: &Context,
+                key: &K::In<'_>,
+                old: Option<&V::Out<'_>>,
+                new: &V::In<'_>,
+            ) -> ClientResult<(), IndexError> {
+                $(check_index::<K, V, $index>(&state.$i, ctx, key, old, new)?;)*
+                Ok(())
+            }
+
+            fn update(
+                state: &Self::State,
+                ctx: &mut Context,
+                key: &K::In<'_>,
+                old: Option<&V::Out<'_>>,
+                new: Option<&V::In<'_>>,
+            ) -> ClientResult<()> {
+                $(update_index::<K, V, $index>(&state.$i, ctx, key, old, new)?;)*
+                Ok(())
+            }
+
+            #[cfg(feature = "std")]
+            fn descriptors<'a>(
+                allocator: &'a dyn Allocator,
+                map: &StateObjectDescriptor<'a>,
+                index_names: &[&'a str],
+            ) -> allocator_api2::vec::Vec<IndexDescriptor<'a>, &'a dyn Allocator> {
+                let mut descriptors = allocator_api2::vec::Vec::new_in(allocator);
+                $(descriptors.push(index_descriptor::<V, $index>(
+                    allocator,
+                    map,
+                    $i,
+                    index_names.get($i),
+                ));)*
+                descriptors
+            }
+        }
+    };
+    (@prefix $index:ident) => { Prefix };
+}
+
+impl_index_tuple!(1; A 0);
+impl_index_tuple!(2; A 0, B 1);
+impl_index_tuple!(3; A 0, B 1, C 2);
+
+macro_rules! impl_index_at {
+    ($n:tt, $at:ident; $($index:ident),*) => {
+        impl<K: KeyFieldValue, V: ObjectValue, $($index: Index<V>),*> IndexAt<K, V, $n> for ($($index,)*) {
+            type Index = $at;
+
+            fn prefix(state: &Self::State) -> &Prefix {
+                &state.$n
+            }
+        }
+    };
+}
+
+impl_index_at!(0, A; A);
+impl_index_at!(0, A; A, B);
+impl_index_at!(1, B; A, B);
+impl_index_at!(0, A; A, B, C);
+impl_index_at!(1, B; A, B, C);
+impl_index_at!(2, C; A, B, C);
+
+impl<K: KeyFieldValue, V: ObjectValue, I: Indexes<K, V>> IndexedMap<K, V, I> {
+    /// Gets the value of the map at the given key.
+    pub fn get<'a, 'b, L>(&self, ctx: &'a Context, key: L) -> ClientResult<Option<V::Out<'a>>>
+    where
+        L: Borrow<K::In<'b>>,
+    {
+        self.map.get(ctx, key)
+    }
+
+    /// Sets the value of the map at the given key and updates the indexes,
+    /// failing if the value would violate a unique index.
+    pub fn set<'a, L, U>(&self, ctx: &mut Context, key: L, value: U) -> ClientResult<(), IndexError>
+    where
+        L: Borrow<K::In<'a>>,
+        U: Borrow<V::In<'a>>,
+    {
+        let (key, value) = (key.borrow(), value.borrow());
+        let old = self
+            .map
+            .get_unborrowed(ctx, key)
+            .map_err(convert_client_error)?;
+        I::check(&self.indexes, ctx, key, old.as_ref(), value)?;
+        I::update(&self.indexes, ctx, key, old.as_ref(), Some(value))
+            .map_err(convert_client_error)?;
+        self.map.set(ctx, key, value).map_err(convert_client_error)
+    }
+
+    /// Deletes the value of the map at the given key along with its index entries.
+    pub fn delete<'a, L>(&self, ctx: &mut Context, key: L) -> ClientResult<()>
+    where
+        L: Borrow<K::In<'a>>,
+    {
+        let key = key.borrow();
+        let Some(old) = self.map.get_unborrowed(ctx, key)? else {
+            return Ok(());
+        };
+        I::update(&self.indexes, ctx, key, Some(&old), None)?;
+        self.map.delete(ctx, key)
+    }
+
+    /// Iterates over all the entries of the map in ascending key order.
+    pub fn iter<'a>(&self, ctx: &'a Context) -> ClientResult<Iter<'a, K, V>> {
+        self.map.iter(ctx)
+    }
+
+    /// Iterates over the entries of the map with keys from `start` inclusive to `end` exclusive
+    /// in the given order. A missing bound leaves that side of the range open.
+    pub fn range<'a>(
+        &self,
+        ctx: &'a Context,
+        start: Option<&K::In<'_>>,
+        end: Option<&K::In<'_>>,
+        order: Order,
+    ) -> ClientResult<Iter<'a, K, V>> {
+        self.map.range(ctx, start, end, order)
+    }
+
+    /// Gets the index at position `N`.
+    pub fn index<const N: usize>(&self) -> IndexRef<'_, K, V, I::Index>
+    where
+        I: IndexAt<K, V, N>,
+    {
+        IndexRef {
+            prefix: I::prefix(&self.indexes),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// A reference to an index of an indexed map, which looks up the keys of entries
+/// by the value of the indexed field.
+pub struct IndexRef<'a, K, V, I> {
+    prefix: &'a Prefix,
+    _phantom: PhantomData<(K, V, I)>,
+}
+
+impl<K: KeyFieldValue, V: ObjectValue, I: Index<V>> IndexRef<'_, K, V, I> {
+    /// Gets the key of the first entry with the given field value, if there is one.
+    /// For a unique index, this is the only such entry.
+    pub fn get<'a, 'b, L>(&self, ctx: &'a Context, field: L) -> ClientResult<Option<K::Out<'a>>>
+    where
+        L: Borrow<<I::Field as ObjectFieldValue>::In<'b>>,
+    {
+        match self.iter(ctx, field)?.next() {
+            Some(entry) => Ok(Some(entry?.1)),
+            None => Ok(None),
+        }
+    }
+
+    /// Iterates over the field values and keys of the entries with the given field value
+    /// in ascending key order.
+    pub fn iter<'a, 'b, L>(
+        &self,
+        ctx: &'a Context,
+        field: L,
+    ) -> ClientResult<IndexIter<'a, I::Field, K>>
+    where
+        L: Borrow<<I::Field as ObjectFieldValue>::In<'b>>,
+    {
+        let mem = ctx.memory_manager();
+        let start =
+            encode_object_key_prefix::<I::Field>(self.prefix.as_slice(), field.borrow(), mem)?;
+        let end = prefix_end(start, mem);
+        Ok(IndexIter(Iter::new(
+            ctx,
+            self.prefix.as_slice().len(),
+            start,
+            end,
+            Order::Ascending,
+        )))
+    }
+
+    /// Iterates over the field values and keys of the entries with field values
+    /// from `start` inclusive to `end` exclusive in the given order.
+    /// A missing bound leaves that side of the range open.
+    pub fn range<'a>(
+        &self,
+        ctx: &'a Context,
+        start: Option<&<I::Field as ObjectFieldValue>::In<'_>>,
+        end: Option<&<I::Field as ObjectFieldValue>::In<'_>>,
+        order: Order,
+    ) -> ClientResult<IndexIter<'a, I::Field, K>> {
+        let mem = ctx.memory_manager();
+        let prefix = self.prefix.as_slice();
+        let start = match start {
+            Some(start) => encode_object_key_prefix::<I::Field>(prefix, start, mem)?,
+            None => ixc_schema::state_object::encode_object_key::<()>(prefix, &(), mem)?,
+        };
+        let end = match end {
+            Some(end) => Some(encode_object_key_prefix::<I::Field>(prefix, end, mem)?),
+            None => prefix_end(prefix, mem),
+        };
+        Ok(IndexIter(Iter::new(ctx, prefix.len(), start, end, order)))
+    }
+}
+
+/// An iterator over the entries of an index, which are the values of the indexed field
+/// along with the keys of the entries of the map they refer to.
+pub struct IndexIter<'a, F, K>(Iter<'a, (F, K), ()>);
+
+impl<'a, F: KeyFieldValue, K: KeyFieldValue> Iterator for IndexIter<'a, F, K> {
+    type Item = ClientResult<(F::Out<'a>, K::Out<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.next()?.map(|(entry, ())| entry))
+    }
+}
+
+unsafe impl<K: KeyFieldValue, V: ObjectValue, I: Indexes<K, V>> StateObjectResource
+    for IndexedMap<K, V, I>
+{
+    unsafe fn new(scope: &[u8], prefix: u8) -> core::result::Result<Self, InitializationError> {
+        let prefix = Prefix::new(scope, prefix)?;
+        Ok(Self {
+            map: Map::new(Prefix::new(prefix.as_slice(), 0)?),
+            indexes: I::init(prefix.as_slice())?,
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        Self::indexed_descriptor(allocator, collection_name, key_names, value_names, &[])
+    }
+
+    #[cfg(feature = "std")]
+    fn indexed_descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+        index_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        use allocator_api2::vec::Vec;
+        use ixc_schema::list::List;
+        if !index_names.is_empty() && index_names.len() != I::N {
+            panic!("Expected {} index names for indexed map \"{}\", but got {:?}. Either leave out index(...) in #[state] to name the indexes after the indexed fields or name all of them. Ex. #[state(index(by_owner, by_name))].",
+                I::N, collection_name, index_names);
+        }
+        let mut desc = Map::<K, V>::descriptor(allocator, collection_name, key_names, value_names);
+        let mut prefix = Vec::new_in(allocator);
+        prefix.push(0);
+        desc.prefix = List::Owned(prefix);
+        desc.indexes = List::Owned(I::descriptors(allocator, &desc, index_names));
+        desc
+    }
+}
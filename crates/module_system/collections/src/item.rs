@@ -7,6 +7,7 @@ use core::borrow::Borrow;
 use ixc_core::resource::{InitializationError, StateObjectResource};
 use ixc_core::result::ClientResult;
 use ixc_core::Context;
+use ixc_message_api::code::HandlerCode;
 use ixc_schema::state_object::{ObjectValue, StateObjectDescriptor};
 
 /// A single item in storage.
@@ -40,6 +41,29 @@ where
     {
         self.map.set(ctx, (), value)
     }
+
+    /// Returns true if the item has been set.
+    pub fn has(&self, ctx: &Context) -> ClientResult<bool> {
+        self.map.has(ctx, ())
+    }
+
+    /// Clears the item and returns its value.
+    pub fn take<'a>(&self, ctx: &mut Context<'a>) -> ClientResult<V::Out<'a>> {
+        let v = self.map.take(ctx, ())?;
+        Ok(v.unwrap_or_default())
+    }
+
+    /// Updates the value of the item with the result of `f`, which is called with the current value.
+    /// If `f` returns `None`, the item is cleared, and if it returns an error,
+    /// the item is left unchanged and the error is returned.
+    pub fn update<'a, 'b, U, E, F>(&self, ctx: &mut Context<'a>, f: F) -> ClientResult<(), E>
+    where
+        U: Borrow<V::In<'b>>,
+        E: HandlerCode,
+        F: FnOnce(V::Out<'a>) -> ClientResult<Option<U>, E>,
+    {
+        self.map.update(ctx, (), |v| f(v.unwrap_or_default()))
+    }
 }
 
 unsafe impl<T: ObjectValue> StateObjectResource for Item<T> {
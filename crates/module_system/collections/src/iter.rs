@@ -0,0 +1,204 @@
+//! Iteration over the entries of collections in the order of their encoded keys.
+use crate::store_client::KVStoreClient;
+use allocator_api2::vec::Vec;
+use core::marker::PhantomData;
+use ixc_core::result::ClientResult;
+use ixc_core::Context;
+use ixc_message_api::encoding::{decode_kv_pairs, Decoder, KVPair};
+use ixc_message_api::handler::Allocator;
+use ixc_schema::mem::MemoryManager;
+use ixc_schema::state_object::{decode_object_key, decode_object_value, ObjectKey, ObjectValue};
+
+/// The order in which entries are iterated over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// From the smallest key to the largest.
+    #[default]
+    Ascending,
+    /// From the largest key to the smallest.
+    Descending,
+}
+
+// the number of entries which are fetched from storage at a time
+const PAGE_SIZE: usize = 64;
+
+/// Range queries on storage, which lets iterators hold on to a context
+/// without depending on its lifetime parameter.
+trait RangeQuery {
+    fn range(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+        order: Order,
+        limit: usize,
+    ) -> ClientResult<&[u8]>;
+}
+
+impl RangeQuery for Context<'_> {
+    fn range(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+        order: Order,
+        limit: usize,
+    ) -> ClientResult<&[u8]> {
+        KVStoreClient.range(self, start, end, order, limit)
+    }
+}
+
+/// An iterator over the raw key-value pairs in a range of storage,
+/// which are fetched a page at a time.
+pub(crate) struct RawIter<'a> {
+    ctx: &'a dyn RangeQuery,
+    start: &'a [u8],
+    end: Option<&'a [u8]>,
+    order: Order,
+    page: Option<Decoder<'a, KVPair<'a>>>,
+    // the number of entries in the current page
+    page_len: usize,
+    // the last key returned, which the next page starts from
+    last: Option<&'a [u8]>,
+    // a key to skip because it was already returned by the previous page
+    skip: Option<&'a [u8]>,
+    done: bool,
+}
+
+impl<'a> RawIter<'a> {
+    /// Iterates over the keys from `start` inclusive to `end` exclusive in the given order.
+    pub(crate) fn new(
+        ctx: &'a Context,
+        start: &'a [u8],
+        end: Option<&'a [u8]>,
+        order: Order,
+    ) -> Self {
+        Self {
+            ctx,
+            start,
+            end,
+            order,
+            page: None,
+            page_len: 0,
+            last: None,
+            skip: None,
+            done: false,
+        }
+    }
+
+    fn fetch_page(&mut self) -> ClientResult<()> {
+        let (start, end) = match (self.order, self.last) {
+            (_, None) => (self.start, self.end),
+            // the start of a range is inclusive, so the last key is returned again and skipped
+            (Order::Ascending, Some(last)) => (last, self.end),
+            (Order::Descending, Some(last)) => (self.start, Some(last)),
+        };
+        if self.order == Order::Ascending {
+            self.skip = self.last;
+        }
+        let bz = self.ctx.range(start, end, self.order, PAGE_SIZE)?;
+        let page = decode_kv_pairs(bz)?;
+        self.page_len = page.remaining();
+        self.page = Some(page);
+        Ok(())
+    }
+}
+
+impl<'a> Iterator for RawIter<'a> {
+    type Item = ClientResult<(&'a [u8], &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+            if let Some(page) = &mut self.page {
+                match page.next() {
+                    Some(Ok((key, value))) => {
+                        if self.skip == Some(key) {
+                            continue;
+                        }
+                        self.last = Some(key);
+                        return Some(Ok((key, value)));
+                    }
+                    Some(Err(e)) => {
+                        self.done = true;
+                        return Some(Err(e.into()));
+                    }
+                    None => {
+                        // a page which isn't full is the end of the range
+                        if self.page_len < PAGE_SIZE {
+                            self.done = true;
+                            return None;
+                        }
+                    }
+                }
+            }
+            if let Err(e) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Returns the smallest key which is greater than every key starting with `prefix`,
+/// or `None` if there is no such key.
+pub(crate) fn prefix_end<'a>(prefix: &[u8], allocator: &'a dyn Allocator) -> Option<&'a [u8]> {
+    let i = prefix.iter().rposition(|b| *b != u8::MAX)?;
+    let mut end = Vec::new_in(allocator);
+    end.extend_from_slice(&prefix[..=i]);
+    end[i] += 1;
+    Some(end.leak())
+}
+
+/// An iterator over the decoded entries of a collection.
+pub struct Iter<'a, K, V> {
+    raw: RawIter<'a>,
+    prefix_len: usize,
+    mem: &'a MemoryManager,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    /// Iterates over the entries with keys from `start` inclusive to `end` exclusive,
+    /// decoding keys after their first `prefix_len` bytes.
+    pub(crate) fn new(
+        ctx: &'a Context,
+        prefix_len: usize,
+        start: &'a [u8],
+        end: Option<&'a [u8]>,
+        order: Order,
+    ) -> Self {
+        Self {
+            raw: RawIter::new(ctx, start, end, order),
+            prefix_len,
+            mem: ctx.memory_manager(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, K: ObjectKey, V: ObjectValue> Iterator for Iter<'a, K, V> {
+    type Item = ClientResult<(K::Out<'a>, V::Out<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key_bz, value_bz) = match self.raw.next()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+        };
+        Some(decode_entry::<K, V>(
+            &key_bz[self.prefix_len..],
+            value_bz,
+            self.mem,
+        ))
+    }
+}
+
+pub(crate) fn decode_entry<'a, K: ObjectKey, V: ObjectValue>(
+    key_bz: &'a [u8],
+    value_bz: &'a [u8],
+    mem: &'a MemoryManager,
+) -> ClientResult<(K::Out<'a>, V::Out<'a>)> {
+    let key = decode_object_key::<K>(key_bz, mem)?;
+    let value = decode_object_value::<V>(value_bz, mem)?;
+    Ok((key, value))
+}
@@ -0,0 +1,107 @@
+//! The key_set module contains the `KeySet` struct, which represents a set of keys in storage.
+use crate::iter::{Iter, Order};
+use crate::prefix::Prefix;
+use crate::Map;
+use allocator_api2::alloc::Allocator;
+use core::borrow::Borrow;
+use ixc_core::resource::{InitializationError, StateObjectResource};
+use ixc_core::result::ClientResult;
+use ixc_core::Context;
+use ixc_schema::state_object::{KeyFieldValue, ObjectKey, StateObjectDescriptor};
+
+/// A set of keys, which are stored with empty values.
+pub struct KeySet<K> {
+    map: Map<K, ()>,
+}
+
+impl<K: ObjectKey> KeySet<K> {
+    /// Returns true if the key is in the set.
+    pub fn contains<'a, L>(&self, ctx: &Context, key: L) -> ClientResult<bool>
+    where
+        L: Borrow<K::In<'a>>,
+    {
+        Ok(self.map.get(ctx, key)?.is_some())
+    }
+
+    /// Adds the key to the set.
+    pub fn insert<'a, L>(&self, ctx: &mut Context, key: L) -> ClientResult<()>
+    where
+        L: Borrow<K::In<'a>>,
+    {
+        self.map.set(ctx, key, ())
+    }
+
+    /// Removes the key from the set.
+    pub fn remove<'a, L>(&self, ctx: &mut Context, key: L) -> ClientResult<()>
+    where
+        L: Borrow<K::In<'a>>,
+    {
+        self.map.delete(ctx, key)
+    }
+
+    /// Iterates over all the keys in the set in ascending order.
+    pub fn iter<'a>(&self, ctx: &'a Context) -> ClientResult<KeyIter<'a, K>> {
+        Ok(KeyIter(self.map.iter(ctx)?))
+    }
+
+    /// Iterates over the keys in the set from `start` inclusive to `end` exclusive
+    /// in the given order. A missing bound leaves that side of the range open.
+    pub fn range<'a>(
+        &self,
+        ctx: &'a Context,
+        start: Option<&K::In<'_>>,
+        end: Option<&K::In<'_>>,
+        order: Order,
+    ) -> ClientResult<KeyIter<'a, K>> {
+        Ok(KeyIter(self.map.range(ctx, start, end, order)?))
+    }
+}
+
+impl<A: KeyFieldValue, B: KeyFieldValue> KeySet<(A, B)> {
+    /// Iterates over the keys in the set which start with `a` in the given order.
+    pub fn iter_prefix<'a, 'b, L>(
+        &self,
+        ctx: &'a Context,
+        a: L,
+        order: Order,
+    ) -> ClientResult<KeyIter<'a, (A, B)>>
+    where
+        L: Borrow<A::In<'b>>,
+    {
+        Ok(KeyIter(self.map.iter_prefix(ctx, a, order)?))
+    }
+}
+
+/// An iterator over the keys of a collection.
+pub struct KeyIter<'a, K>(pub(crate) Iter<'a, K, ()>);
+
+impl<'a, K: ObjectKey> Iterator for KeyIter<'a, K> {
+    type Item = ClientResult<K::Out<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.next()?.map(|(key, ())| key))
+    }
+}
+
+unsafe impl<K: ObjectKey> StateObjectResource for KeySet<K> {
+    unsafe fn new(scope: &[u8], prefix: u8) -> core::result::Result<Self, InitializationError> {
+        let prefix = Prefix::new(scope, prefix)?;
+        Ok(Self {
+            map: Map::new(prefix),
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        if !value_names.is_empty() {
+            panic!("Key set \"{}\" doesn't have values, but got value names {:?}. Only specify key(...) in #[state] for key sets. Ex. #[state(key(account))].",
+                collection_name, value_names);
+        }
+        Map::<K, ()>::descriptor(allocator, collection_name, key_names, &[])
+    }
+}
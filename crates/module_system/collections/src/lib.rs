@@ -9,11 +9,27 @@ extern crate alloc;
 extern crate ixc_schema as ixc;
 
 pub mod accumulator;
+mod indexed_map;
 mod item;
+mod iter;
+mod key_set;
 mod map;
+mod multi_map;
 mod prefix;
+mod queue;
+mod sequence;
 mod store_client;
+mod time_queue;
 
 pub use accumulator::{Accumulator, AccumulatorMap};
+pub use indexed_map::{
+    Index, IndexAt, IndexError, IndexIter, IndexRef, IndexedMap, Indexes, MultiIndex, UniqueIndex,
+};
 pub use item::Item;
+pub use iter::{Iter, Order};
+pub use key_set::{KeyIter, KeySet};
 pub use map::Map;
+pub use multi_map::{MultiMap, MultiMapIter};
+pub use queue::{Deque, Queue, ValueIter};
+pub use sequence::{Sequence, SequenceError};
+pub use time_queue::TimeQueue;
@@ -1,19 +1,24 @@
 //! The map module contains the `Map` struct, which represents a key-value map in storage.
+use crate::iter::{prefix_end, Iter, Order};
 use crate::prefix::Prefix;
 use crate::store_client::KVStoreClient;
 use allocator_api2::vec::Vec;
 use core::borrow::Borrow;
 use core::marker::PhantomData;
+use ixc_core::error::convert_client_error;
 use ixc_core::resource::{InitializationError, StateObjectResource};
 use ixc_core::result::ClientResult;
 use ixc_core::Context;
+use ixc_message_api::code::HandlerCode;
 use ixc_message_api::handler::Allocator;
 use ixc_schema::encoding::Encoding;
 use ixc_schema::fields::FieldTypes;
 use ixc_schema::list::List;
+#[cfg(feature = "std")]
+use ixc_schema::page::{PageRequest, PageResponse};
 use ixc_schema::state_object::{
-    decode_object_value, encode_object_key, encode_object_value, ObjectKey, ObjectValue,
-    StateObjectDescriptor,
+    decode_object_value, encode_object_key, encode_object_key_prefix, encode_object_value,
+    KeyFieldValue, ObjectKey, ObjectValue, StateObjectDescriptor,
 };
 
 pub(crate) const MAX_SIZE: usize = 7;
@@ -53,6 +58,142 @@ impl<K: ObjectKey, V: ObjectValue> Map<K, V> {
         Ok(Some(value))
     }
 
+    /// Gets the value of the map at the given key without borrowing the context,
+    /// which is possible because the value is allocated by the context's memory manager.
+    pub(crate) fn get_unborrowed<'a>(
+        &self,
+        ctx: &Context<'a>,
+        key: &K::In<'_>,
+    ) -> ClientResult<Option<V::Out<'a>>> {
+        let key_bz = encode_object_key::<K>(self.prefix.as_slice(), key, ctx.memory_manager())?;
+        match KVStoreClient.get(ctx, key_bz)? {
+            None => Ok(None),
+            Some(value_bz) => Ok(Some(decode_object_value::<V>(
+                value_bz,
+                ctx.memory_manager(),
+            )?)),
+        }
+    }
+
+    /// Returns true if the map has a value at the given key.
+    pub fn has<'a, L>(&self, ctx: &Context, key: L) -> ClientResult<bool>
+    where
+        L: Borrow<K::In<'a>>,
+    {
+        let key_bz =
+            encode_object_key::<K>(self.prefix.as_slice(), key.borrow(), ctx.memory_manager())?;
+        Ok(KVStoreClient.get(ctx, key_bz)?.is_some())
+    }
+
+    /// Gets the value of the map at the given key or the default value if there is none.
+    pub fn get_or_default<'a, 'b, L>(&self, ctx: &'a Context, key: L) -> ClientResult<V::Out<'a>>
+    where
+        L: Borrow<K::In<'b>>,
+        V::Out<'a>: Default,
+    {
+        Ok(self.get(ctx, key)?.unwrap_or_default())
+    }
+
+    /// Iterates over all the entries of the map in ascending key order.
+    pub fn iter<'a>(&self, ctx: &'a Context) -> ClientResult<Iter<'a, K, V>> {
+        self.range(ctx, None, None, Order::Ascending)
+    }
+
+    /// Iterates over the entries of the map with keys from `start` inclusive to `end` exclusive
+    /// in the given order. A missing bound leaves that side of the range open.
+    pub fn range<'a>(
+        &self,
+        ctx: &'a Context,
+        start: Option<&K::In<'_>>,
+        end: Option<&K::In<'_>>,
+        order: Order,
+    ) -> ClientResult<Iter<'a, K, V>> {
+        let mem = ctx.memory_manager();
+        let prefix = self.prefix.as_slice();
+        let start = match start {
+            Some(start) => encode_object_key::<K>(prefix, start, mem)?,
+            None => encode_object_key::<()>(prefix, &(), mem)?,
+        };
+        let end = match end {
+            Some(end) => Some(encode_object_key::<K>(prefix, end, mem)?),
+            None => prefix_end(prefix, mem),
+        };
+        Ok(Iter::new(ctx, prefix.len(), start, end, order))
+    }
+
+    /// Returns a page of the entries of the map in the given order,
+    /// converting each entry to an item of the page with `f`.
+    /// The cursor of the page is the encoded key of the first entry after it, so the next page
+    /// starts from there even if entries were added or removed in between.
+    #[cfg(feature = "std")]
+    pub fn page<'a, T>(
+        &self,
+        ctx: &'a Context,
+        req: &PageRequest,
+        order: Order,
+        mut f: impl FnMut(K::Out<'a>, V::Out<'a>) -> T,
+    ) -> ClientResult<PageResponse<T>> {
+        use crate::iter::decode_entry;
+        use ixc_message_api::code::{ErrorCode, SystemCode};
+        use ixc_message_api::encoding::decode_kv_pairs;
+        let mem = ctx.memory_manager();
+        let prefix = self.prefix.as_slice();
+        let mut start = encode_object_key::<()>(prefix, &(), mem)?;
+        let mut end = prefix_end(prefix, mem);
+        if let Some(cursor) = req.cursor {
+            // a cursor for another collection would let the page include its entries
+            if !cursor.starts_with(prefix) {
+                return Err(ErrorCode::SystemCode(SystemCode::EncodingError).into());
+            }
+            let mut bound = Vec::new_in(mem);
+            bound.extend_from_slice(cursor);
+            match order {
+                Order::Ascending => start = bound.leak(),
+                Order::Descending => {
+                    // the end is exclusive, so it's the first key after the cursor
+                    bound.push(0);
+                    end = Some(bound.leak());
+                }
+            }
+        }
+        let limit = req.limit();
+        // fetch one more entry than the limit to get the cursor of the next page
+        let pairs = KVStoreClient.range(ctx, start, end, order, limit + 1)?;
+        let mut page = PageResponse::default();
+        for pair in decode_kv_pairs(pairs)? {
+            let (key_bz, value_bz) = pair?;
+            if page.items.len() == limit {
+                page.next_cursor = Some(key_bz.to_vec());
+                break;
+            }
+            let (key, value) = decode_entry::<K, V>(&key_bz[prefix.len()..], value_bz, mem)?;
+            page.items.push(f(key, value));
+        }
+        Ok(page)
+    }
+
+    /// Returns a page of the values of the map in the given order.
+    #[cfg(feature = "std")]
+    pub fn page_values<'a>(
+        &self,
+        ctx: &'a Context,
+        req: &PageRequest,
+        order: Order,
+    ) -> ClientResult<PageResponse<V::Out<'a>>> {
+        self.page(ctx, req, order, |_, value| value)
+    }
+
+    /// Returns a page of the keys of the map in the given order.
+    #[cfg(feature = "std")]
+    pub fn page_keys<'a>(
+        &self,
+        ctx: &'a Context,
+        req: &PageRequest,
+        order: Order,
+    ) -> ClientResult<PageResponse<K::Out<'a>>> {
+        self.page(ctx, req, order, |key, _| key)
+    }
+
     /// Sets the value of the map at the given key.
     pub fn set<'a, L, U>(&self, ctx: &mut Context, key: L, value: U) -> ClientResult<()>
     where
@@ -74,6 +215,83 @@ impl<K: ObjectKey, V: ObjectValue> Map<K, V> {
             encode_object_key::<K>(self.prefix.as_slice(), key.borrow(), ctx.memory_manager())?;
         unsafe { KVStoreClient.delete(ctx, key_bz) }
     }
+
+    /// Removes the value of the map at the given key and returns it, if there was one.
+    pub fn take<'a, 'b, L>(&self, ctx: &mut Context<'a>, key: L) -> ClientResult<Option<V::Out<'a>>>
+    where
+        L: Borrow<K::In<'b>>,
+    {
+        let key_bz =
+            encode_object_key::<K>(self.prefix.as_slice(), key.borrow(), ctx.memory_manager())?;
+        let value_bz = match KVStoreClient.get(ctx, key_bz)? {
+            None => return Ok(None),
+            Some(value_bz) => value_bz,
+        };
+        let value = decode_object_value::<V>(value_bz, ctx.memory_manager())?;
+        unsafe { KVStoreClient.delete(ctx, key_bz)? };
+        Ok(Some(value))
+    }
+
+    /// Updates the value of the map at the given key with the result of `f`,
+    /// which is called with the current value, if there is one.
+    /// If `f` returns `None`, the value is deleted, and if it returns an error,
+    /// the map is left unchanged and the error is returned.
+    pub fn update<'a, 'b, L, U, E, F>(
+        &self,
+        ctx: &mut Context<'a>,
+        key: L,
+        f: F,
+    ) -> ClientResult<(), E>
+    where
+        L: Borrow<K::In<'b>>,
+        U: Borrow<V::In<'b>>,
+        E: HandlerCode,
+        F: FnOnce(Option<V::Out<'a>>) -> ClientResult<Option<U>, E>,
+    {
+        let mem = ctx.memory_manager();
+        let key_bz = encode_object_key::<K>(self.prefix.as_slice(), key.borrow(), mem)?;
+        let old = match KVStoreClient
+            .get(ctx, key_bz)
+            .map_err(convert_client_error)?
+        {
+            None => None,
+            Some(value_bz) => Some(decode_object_value::<V>(value_bz, mem)?),
+        };
+        unsafe {
+            match f(old)? {
+                Some(value) => {
+                    let value_bz = encode_object_value::<V>(value.borrow(), mem)?;
+                    KVStoreClient.set(ctx, key_bz, value_bz)
+                }
+                None => KVStoreClient.delete(ctx, key_bz),
+            }
+            .map_err(convert_client_error)
+        }
+    }
+}
+
+impl<A: KeyFieldValue, B: KeyFieldValue, V: ObjectValue> Map<(A, B), V> {
+    /// Iterates over the entries of the map whose keys start with `a` in the given order.
+    pub fn iter_prefix<'a, 'b, L>(
+        &self,
+        ctx: &'a Context,
+        a: L,
+        order: Order,
+    ) -> ClientResult<Iter<'a, (A, B), V>>
+    where
+        L: Borrow<A::In<'b>>,
+    {
+        let mem = ctx.memory_manager();
+        let start = encode_object_key_prefix::<A>(self.prefix.as_slice(), a.borrow(), mem)?;
+        let end = prefix_end(start, mem);
+        Ok(Iter::new(
+            ctx,
+            self.prefix.as_slice().len(),
+            start,
+            end,
+            order,
+        ))
+    }
 }
 
 unsafe impl<K: ObjectKey, V: ObjectValue> StateObjectResource for Map<K, V> {
@@ -0,0 +1,151 @@
+//! A map which can hold several values for each key.
+use crate::iter::{prefix_end, Iter, Order};
+use crate::key_set::KeyIter;
+use crate::prefix::Prefix;
+use crate::store_client::KVStoreClient;
+use allocator_api2::alloc::Allocator;
+use core::borrow::Borrow;
+use core::marker::PhantomData;
+use ixc_core::resource::{InitializationError, StateObjectResource};
+use ixc_core::result::ClientResult;
+use ixc_core::Context;
+use ixc_schema::state_object::{
+    encode_object_key, encode_object_key_pair, encode_object_key_prefix, KeyFieldValue,
+    StateObjectDescriptor,
+};
+
+/// A map which can hold several values for each key, such as several hooks for a denom.
+/// Each key-value pair is stored like a `KeySet<(K, V)>`, so the values for a key
+/// are ordered by their encoding and looking up a single pair doesn't read the other values.
+pub struct MultiMap<K, V> {
+    _phantom: PhantomData<(K, V)>,
+    prefix: Prefix,
+}
+
+impl<K: KeyFieldValue, V: KeyFieldValue> MultiMap<K, V> {
+    /// Returns true if the value is one of the values for the key.
+    pub fn contains<'a, L, U>(&self, ctx: &Context, key: L, value: U) -> ClientResult<bool>
+    where
+        L: Borrow<K::In<'a>>,
+        U: Borrow<V::In<'a>>,
+    {
+        let key_bz = encode_object_key_pair::<K, V>(
+            self.prefix.as_slice(),
+            key.borrow(),
+            value.borrow(),
+            ctx.memory_manager(),
+        )?;
+        Ok(KVStoreClient.get(ctx, key_bz)?.is_some())
+    }
+
+    /// Adds the value to the values for the key, if it isn't already one of them.
+    pub fn insert<'a, L, U>(&self, ctx: &mut Context, key: L, value: U) -> ClientResult<()>
+    where
+        L: Borrow<K::In<'a>>,
+        U: Borrow<V::In<'a>>,
+    {
+        let key_bz = encode_object_key_pair::<K, V>(
+            self.prefix.as_slice(),
+            key.borrow(),
+            value.borrow(),
+            ctx.memory_manager(),
+        )?;
+        unsafe { KVStoreClient.set(ctx, key_bz, &[]) }
+    }
+
+    /// Removes the value from the values for the key.
+    pub fn remove<'a, L, U>(&self, ctx: &mut Context, key: L, value: U) -> ClientResult<()>
+    where
+        L: Borrow<K::In<'a>>,
+        U: Borrow<V::In<'a>>,
+    {
+        let key_bz = encode_object_key_pair::<K, V>(
+            self.prefix.as_slice(),
+            key.borrow(),
+            value.borrow(),
+            ctx.memory_manager(),
+        )?;
+        unsafe { KVStoreClient.delete(ctx, key_bz) }
+    }
+
+    /// Iterates over the values for the key in ascending order.
+    pub fn get<'a, 'b, L>(&self, ctx: &'a Context, key: L) -> ClientResult<MultiMapIter<'a, K, V>>
+    where
+        L: Borrow<K::In<'b>>,
+    {
+        self.iter_values(ctx, key, Order::Ascending)
+    }
+
+    /// Iterates over the values for the key in the given order.
+    pub fn iter_values<'a, 'b, L>(
+        &self,
+        ctx: &'a Context,
+        key: L,
+        order: Order,
+    ) -> ClientResult<MultiMapIter<'a, K, V>>
+    where
+        L: Borrow<K::In<'b>>,
+    {
+        let mem = ctx.memory_manager();
+        let prefix = self.prefix.as_slice();
+        let start = encode_object_key_prefix::<K>(prefix, key.borrow(), mem)?;
+        let end = prefix_end(start, mem);
+        Ok(MultiMapIter(Iter::new(
+            ctx,
+            prefix.len(),
+            start,
+            end,
+            order,
+        )))
+    }
+
+    /// Iterates over all the key-value pairs in ascending order.
+    pub fn iter<'a>(&self, ctx: &'a Context) -> ClientResult<KeyIter<'a, (K, V)>> {
+        let mem = ctx.memory_manager();
+        let prefix = self.prefix.as_slice();
+        let start = encode_object_key::<()>(prefix, &(), mem)?;
+        let end = prefix_end(prefix, mem);
+        Ok(KeyIter(Iter::new(
+            ctx,
+            prefix.len(),
+            start,
+            end,
+            Order::Ascending,
+        )))
+    }
+}
+
+/// An iterator over the values for a key in a [`MultiMap`].
+pub struct MultiMapIter<'a, K, V>(Iter<'a, (K, V), ()>);
+
+impl<'a, K: KeyFieldValue, V: KeyFieldValue> Iterator for MultiMapIter<'a, K, V> {
+    type Item = ClientResult<V::Out<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.next()?.map(|((_, value), ())| value))
+    }
+}
+
+unsafe impl<K: KeyFieldValue, V: KeyFieldValue> StateObjectResource for MultiMap<K, V> {
+    unsafe fn new(scope: &[u8], prefix: u8) -> Result<Self, InitializationError> {
+        let prefix = Prefix::new(scope, prefix)?;
+        Ok(Self {
+            _phantom: PhantomData,
+            prefix,
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        // the values are stored as the second part of the key, so they can be named
+        // either with key(key, value) or with key(key), value(value) in #[state]
+        let names: alloc::vec::Vec<&'a str> =
+            key_names.iter().chain(value_names).copied().collect();
+        crate::KeySet::<(K, V)>::descriptor(allocator, collection_name, &names, &[])
+    }
+}
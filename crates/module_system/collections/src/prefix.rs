@@ -1,7 +1,8 @@
 use crate::map::MAX_SIZE;
 use ixc_core::resource::InitializationError;
 
-pub(crate) struct Prefix {
+/// The bytes prefix of a collection's keys.
+pub struct Prefix {
     length: u8,
     data: [u8; 7],
 }
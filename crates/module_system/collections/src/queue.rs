@@ -0,0 +1,217 @@
+//! FIFO queues and double-ended queues.
+use crate::iter::{Iter, Order};
+use crate::prefix::Prefix;
+use crate::{Item, Map};
+use allocator_api2::alloc::Allocator;
+use core::borrow::Borrow;
+use ixc_core::resource::{InitializationError, StateObjectResource};
+use ixc_core::result::ClientResult;
+use ixc_core::Context;
+use ixc_schema::state_object::{ObjectValue, StateObjectDescriptor};
+
+/// A double-ended queue of values.
+///
+/// The positions of the first value and one past the last value are stored
+/// under the deque's prefix followed by 0, and each value under the deque's prefix
+/// followed by 1 and its position as an i64, so the values are stored in order.
+pub struct Deque<V> {
+    // the positions of the first value and one past the last value
+    bounds: Item<(i64, i64)>,
+    values: Map<i64, V>,
+}
+
+/// A first-in, first-out queue of values, stored like a [`Deque`].
+pub struct Queue<V> {
+    deque: Deque<V>,
+}
+
+impl<V: ObjectValue> Deque<V> {
+    /// Returns the number of values in the deque.
+    pub fn len(&self, ctx: &Context) -> ClientResult<u64> {
+        let (head, tail) = self.bounds.get(ctx)?;
+        Ok(tail.abs_diff(head))
+    }
+
+    /// Returns true if the deque has no values.
+    pub fn is_empty(&self, ctx: &Context) -> ClientResult<bool> {
+        Ok(self.len(ctx)? == 0)
+    }
+
+    /// Adds a value to the back of the deque.
+    pub fn push_back<'a, U>(&self, ctx: &mut Context, value: U) -> ClientResult<()>
+    where
+        U: Borrow<V::In<'a>>,
+    {
+        let (head, tail) = self.bounds.get(ctx)?;
+        self.values.set(ctx, tail, value)?;
+        self.bounds.set(ctx, (head, tail + 1))
+    }
+
+    /// Adds a value to the front of the deque.
+    pub fn push_front<'a, U>(&self, ctx: &mut Context, value: U) -> ClientResult<()>
+    where
+        U: Borrow<V::In<'a>>,
+    {
+        let (head, tail) = self.bounds.get(ctx)?;
+        self.values.set(ctx, head - 1, value)?;
+        self.bounds.set(ctx, (head - 1, tail))
+    }
+
+    /// Removes and returns the value at the front of the deque, if there is one.
+    pub fn pop_front<'a>(&self, ctx: &mut Context<'a>) -> ClientResult<Option<V::Out<'a>>> {
+        let (head, tail) = self.bounds.get(ctx)?;
+        if head == tail {
+            return Ok(None);
+        }
+        let value = self.values.get_unborrowed(ctx, &head)?;
+        self.values.delete(ctx, head)?;
+        self.bounds.set(ctx, (head + 1, tail))?;
+        Ok(value)
+    }
+
+    /// Removes and returns the value at the back of the deque, if there is one.
+    pub fn pop_back<'a>(&self, ctx: &mut Context<'a>) -> ClientResult<Option<V::Out<'a>>> {
+        let (head, tail) = self.bounds.get(ctx)?;
+        if head == tail {
+            return Ok(None);
+        }
+        let value = self.values.get_unborrowed(ctx, &(tail - 1))?;
+        self.values.delete(ctx, tail - 1)?;
+        self.bounds.set(ctx, (head, tail - 1))?;
+        Ok(value)
+    }
+
+    /// Returns the value at the front of the deque without removing it, if there is one.
+    pub fn peek_front<'a>(&self, ctx: &'a Context) -> ClientResult<Option<V::Out<'a>>> {
+        let (head, tail) = self.bounds.get(ctx)?;
+        if head == tail {
+            return Ok(None);
+        }
+        self.values.get(ctx, head)
+    }
+
+    /// Returns the value at the back of the deque without removing it, if there is one.
+    pub fn peek_back<'a>(&self, ctx: &'a Context) -> ClientResult<Option<V::Out<'a>>> {
+        let (head, tail) = self.bounds.get(ctx)?;
+        if head == tail {
+            return Ok(None);
+        }
+        self.values.get(ctx, tail - 1)
+    }
+
+    /// Iterates over the values in the deque in the given order,
+    /// where ascending order is from the front to the back.
+    /// Only the values in the deque when the iterator is created are visited.
+    pub fn iter<'a>(&self, ctx: &'a Context, order: Order) -> ClientResult<ValueIter<'a, V>> {
+        let (head, tail) = self.bounds.get(ctx)?;
+        Ok(ValueIter(self.values.range(
+            ctx,
+            Some(&head),
+            Some(&tail),
+            order,
+        )?))
+    }
+}
+
+impl<V: ObjectValue> Queue<V> {
+    /// Returns the number of values in the queue.
+    pub fn len(&self, ctx: &Context) -> ClientResult<u64> {
+        self.deque.len(ctx)
+    }
+
+    /// Returns true if the queue has no values.
+    pub fn is_empty(&self, ctx: &Context) -> ClientResult<bool> {
+        self.deque.is_empty(ctx)
+    }
+
+    /// Adds a value to the back of the queue.
+    pub fn push_back<'a, U>(&self, ctx: &mut Context, value: U) -> ClientResult<()>
+    where
+        U: Borrow<V::In<'a>>,
+    {
+        self.deque.push_back(ctx, value)
+    }
+
+    /// Removes and returns the value at the front of the queue, if there is one.
+    pub fn pop_front<'a>(&self, ctx: &mut Context<'a>) -> ClientResult<Option<V::Out<'a>>> {
+        self.deque.pop_front(ctx)
+    }
+
+    /// Returns the value at the front of the queue without removing it, if there is one.
+    pub fn peek<'a>(&self, ctx: &'a Context) -> ClientResult<Option<V::Out<'a>>> {
+        self.deque.peek_front(ctx)
+    }
+
+    /// Iterates over the values in the queue from the front to the back.
+    /// Only the values in the queue when the iterator is created are visited.
+    pub fn iter<'a>(&self, ctx: &'a Context) -> ClientResult<ValueIter<'a, V>> {
+        self.deque.iter(ctx, Order::Ascending)
+    }
+}
+
+/// An iterator over the values of a queue or deque.
+pub struct ValueIter<'a, V>(Iter<'a, i64, V>);
+
+impl<'a, V: ObjectValue> Iterator for ValueIter<'a, V> {
+    type Item = ClientResult<V::Out<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.0.next()?.map(|(_, value)| value))
+    }
+}
+
+unsafe impl<V: ObjectValue> StateObjectResource for Deque<V> {
+    unsafe fn new(scope: &[u8], prefix: u8) -> Result<Self, InitializationError> {
+        let prefix = Prefix::new(scope, prefix)?;
+        Ok(Deque {
+            bounds: Item::new(Prefix::new(prefix.as_slice(), 0)?),
+            values: Map::new(Prefix::new(prefix.as_slice(), 1)?),
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        use allocator_api2::vec::Vec;
+        use ixc_schema::list::List;
+        // the descriptor describes the values, which off-chain tools can read in key order
+        let key_names = if key_names.is_empty() {
+            &["position"]
+        } else {
+            key_names
+        };
+        let value_names = if value_names.is_empty() {
+            &[collection_name]
+        } else {
+            value_names
+        };
+        let mut desc =
+            Map::<i64, V>::descriptor(allocator, collection_name, key_names, value_names);
+        let mut prefix = Vec::new_in(allocator);
+        prefix.push(1);
+        desc.prefix = List::Owned(prefix);
+        desc
+    }
+}
+
+unsafe impl<V: ObjectValue> StateObjectResource for Queue<V> {
+    unsafe fn new(scope: &[u8], prefix: u8) -> Result<Self, InitializationError> {
+        Ok(Queue {
+            deque: Deque::new(scope, prefix)?,
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        Deque::<V>::descriptor(allocator, collection_name, key_names, value_names)
+    }
+}
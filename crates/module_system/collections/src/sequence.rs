@@ -0,0 +1,102 @@
+//! A monotonically increasing u64 sequence.
+use crate::prefix::Prefix;
+use crate::store_client::KVStoreClient;
+use crate::Item;
+use allocator_api2::alloc::Allocator;
+use ixc_core::error::{convert_client_error, ClientError};
+use ixc_core::resource::{InitializationError, StateObjectResource};
+use ixc_core::result::ClientResult;
+use ixc_core::Context;
+use ixc_message_api::code::ErrorCode;
+use ixc_schema::schema::SchemaValue;
+use ixc_schema::state_object::StateObjectDescriptor;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+/// A sequence of u64 values starting at 0, such as for auto-incrementing IDs.
+/// The sequence is stored as a native accumulator and [`Sequence::next`] is a single
+/// accumulator add which returns the incremented value.
+/// Since the returned value depends on every earlier increment, increments from
+/// concurrent transactions still conflict.
+pub struct Sequence {
+    prefix: Prefix,
+}
+
+/// An error that can occur when getting the next value of a sequence.
+#[derive(Default, Debug, Clone, TryFromPrimitive, IntoPrimitive, SchemaValue, Copy)]
+#[repr(u8)]
+#[non_exhaustive]
+pub enum SequenceError {
+    /// The sequence has no values left.
+    #[default]
+    Overflow,
+}
+
+impl Sequence {
+    /// Gets the next value of the sequence without incrementing it.
+    pub fn peek(&self, ctx: &Context) -> ClientResult<u64> {
+        let value = KVStoreClient.accumulator_get(ctx, self.prefix.as_slice())?;
+        // values are never set above u64::MAX
+        Ok(value as u64)
+    }
+
+    /// Gets the next value of the sequence and increments it,
+    /// returning an error if the sequence has reached u64::MAX.
+    pub fn next(&self, ctx: &mut Context) -> ClientResult<u64, SequenceError> {
+        let key = self.prefix.as_slice();
+        let next = unsafe {
+            KVStoreClient
+                .accumulator_add(ctx, key, 1)
+                .map_err(convert_client_error)?
+        };
+        if next > u64::MAX as u128 {
+            // undo the increment so the sequence stays at u64::MAX
+            unsafe {
+                KVStoreClient
+                    .accumulator_safe_sub(ctx, key, 1)
+                    .map_err(convert_client_error)?;
+            }
+            return Err(ClientError::new(ErrorCode::HandlerCode(
+                SequenceError::Overflow,
+            )));
+        }
+        Ok((next - 1) as u64)
+    }
+
+    /// Sets the next value of the sequence.
+    pub fn set(&self, ctx: &mut Context, value: u64) -> ClientResult<()> {
+        let current = self.peek(ctx)?;
+        let key = self.prefix.as_slice();
+        unsafe {
+            if value >= current {
+                KVStoreClient
+                    .accumulator_add(ctx, key, (value - current) as u128)
+                    .map(|_| ())
+            } else {
+                // the subtraction can't underflow because current is the stored value
+                KVStoreClient
+                    .accumulator_safe_sub(ctx, key, (current - value) as u128)
+                    .map(|_| ())
+            }
+        }
+    }
+}
+
+unsafe impl StateObjectResource for Sequence {
+    unsafe fn new(scope: &[u8], prefix: u8) -> Result<Self, InitializationError> {
+        let prefix = Prefix::new(scope, prefix)?;
+        Ok(Sequence { prefix })
+    }
+
+    #[cfg(feature = "std")]
+    fn descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        let mut desc = Item::<u128>::descriptor(allocator, collection_name, key_names, value_names);
+        desc.is_accumulator = true;
+        desc.is_sequence = true;
+        desc
+    }
+}
@@ -1,24 +1,35 @@
+use crate::iter::Order;
 use ixc_core::result::ClientResult;
+use ixc_core::state;
 use ixc_core::Context;
 use ixc_core_macros::message_selector;
 use ixc_message_api::code::ErrorCode;
 use ixc_message_api::handler::InvokeParams;
 use ixc_message_api::message::{MessageSelector, Request, Response};
 
-const GET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.get");
-const SET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.set");
-const DELETE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.delete");
+const ACCUMULATOR_GET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.accumulator_get");
+const ACCUMULATOR_ADD_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.accumulator_add");
+const ACCUMULATOR_SAFE_SUB_SELECTOR: MessageSelector =
+    message_selector!("ixc.store.v1.accumulator_safe_sub");
 
 pub(crate) struct KVStoreClient;
 
 impl KVStoreClient {
-    pub(crate) fn get<'a>(&self, ctx: &'a Context, key: &[u8]) -> ClientResult<Option<&'a [u8]>> {
-        let res = dynamic_query_state(ctx, &Request::new1(GET_SELECTOR, key.into()))?;
-        if let Some(res_bz) = res.out1().as_slice() {
-            Ok(Some(res_bz))
-        } else {
-            Ok(None)
-        }
+    pub(crate) fn get<'a>(&self, ctx: &Context<'a>, key: &[u8]) -> ClientResult<Option<&'a [u8]>> {
+        state::get(ctx, key)
+    }
+
+    /// Gets up to `limit` key-value pairs with keys from `start` inclusive to `end` exclusive
+    /// in the provided order, encoded with [`ixc_message_api::encoding::encode_kv_pairs`].
+    pub(crate) fn range<'a>(
+        &self,
+        ctx: &Context<'a>,
+        start: &[u8],
+        end: Option<&[u8]>,
+        order: Order,
+        limit: usize,
+    ) -> ClientResult<&'a [u8]> {
+        state::range(ctx, start, end, order == Order::Descending, limit)
     }
 
     pub(crate) unsafe fn set(
@@ -27,13 +38,42 @@ impl KVStoreClient {
         key: &[u8],
         value: &[u8],
     ) -> ClientResult<()> {
-        dynamic_update_state(ctx, &Request::new2(SET_SELECTOR, key.into(), value.into()))?;
-        Ok(())
+        state::set(ctx, key, value)
     }
 
     pub(crate) unsafe fn delete(&self, ctx: &mut Context, key: &[u8]) -> ClientResult<()> {
-        dynamic_update_state(ctx, &Request::new1(DELETE_SELECTOR, key.into()))?;
-        Ok(())
+        state::delete(ctx, key)
+    }
+
+    /// Gets the value of a native accumulator, which is stored apart from other values.
+    pub(crate) fn accumulator_get(&self, ctx: &Context, key: &[u8]) -> ClientResult<u128> {
+        let res = dynamic_query_state(ctx, &Request::new1(ACCUMULATOR_GET_SELECTOR, key.into()))?;
+        Ok(res.out1().expect_u128()?)
+    }
+
+    /// Adds to the value of a native accumulator and returns its new value.
+    pub(crate) unsafe fn accumulator_add(
+        &self,
+        ctx: &mut Context,
+        key: &[u8],
+        value: u128,
+    ) -> ClientResult<u128> {
+        let req = Request::new2(ACCUMULATOR_ADD_SELECTOR, key.into(), value.into());
+        let res = dynamic_update_state(ctx, &req)?;
+        Ok(res.out1().expect_u128()?)
+    }
+
+    /// Subtracts from the value of a native accumulator,
+    /// returning false without changing it if the value would go below zero.
+    pub(crate) unsafe fn accumulator_safe_sub(
+        &self,
+        ctx: &mut Context,
+        key: &[u8],
+        value: u128,
+    ) -> ClientResult<bool> {
+        let req = Request::new2(ACCUMULATOR_SAFE_SUB_SELECTOR, key.into(), value.into());
+        let res = dynamic_update_state(ctx, &req)?;
+        Ok(res.out1().expect_u64()? != 0)
     }
 }
 
@@ -0,0 +1,158 @@
+//! A collection of entries scheduled by time.
+use crate::iter::{decode_entry, prefix_end, Iter, Order};
+use crate::map::Map;
+use crate::prefix::Prefix;
+use crate::store_client::KVStoreClient;
+use allocator_api2::alloc::Allocator;
+use allocator_api2::vec::Vec;
+use core::borrow::Borrow;
+use core::marker::PhantomData;
+use ixc_core::resource::{InitializationError, StateObjectResource};
+use ixc_core::result::ClientResult;
+use ixc_core::Context;
+use ixc_message_api::encoding::decode_kv_pairs;
+use ixc_schema::mem::MemoryManager;
+use ixc_schema::state_object::{
+    decode_object_value, encode_object_key, encode_object_key_pair, encode_object_key_prefix,
+    encode_object_value, KeyFieldValue, ObjectValue, StateObjectDescriptor,
+};
+use simple_time::Time;
+
+/// A collection of entries keyed by a time and a key, such as for vesting schedules,
+/// timelocks or auction deadlines.
+/// Entries are stored like a `Map<(Time, K), V>`, so they are ordered by time
+/// and expired entries can be found without scanning the whole collection.
+pub struct TimeQueue<K, V> {
+    _phantom: PhantomData<(K, V)>,
+    prefix: Prefix,
+}
+
+impl<K: KeyFieldValue, V: ObjectValue> TimeQueue<K, V> {
+    /// Gets the value of the entry with the given time and key.
+    pub fn get<'a, 'b, L>(
+        &self,
+        ctx: &'a Context,
+        time: Time,
+        key: L,
+    ) -> ClientResult<Option<V::Out<'a>>>
+    where
+        L: Borrow<K::In<'b>>,
+    {
+        let mem = ctx.memory_manager();
+        let key_bz =
+            encode_object_key_pair::<Time, K>(self.prefix.as_slice(), &time, key.borrow(), mem)?;
+        match KVStoreClient.get(ctx, key_bz)? {
+            None => Ok(None),
+            Some(value_bz) => Ok(Some(decode_object_value::<V>(value_bz, mem)?)),
+        }
+    }
+
+    /// Sets the value of the entry with the given time and key.
+    pub fn insert<'a, L, U>(
+        &self,
+        ctx: &mut Context,
+        time: Time,
+        key: L,
+        value: U,
+    ) -> ClientResult<()>
+    where
+        L: Borrow<K::In<'a>>,
+        U: Borrow<V::In<'a>>,
+    {
+        let mem = ctx.memory_manager();
+        let key_bz =
+            encode_object_key_pair::<Time, K>(self.prefix.as_slice(), &time, key.borrow(), mem)?;
+        let value_bz = encode_object_value::<V>(value.borrow(), mem)?;
+        unsafe { KVStoreClient.set(ctx, key_bz, value_bz) }
+    }
+
+    /// Removes the entry with the given time and key.
+    pub fn remove<'a, L>(&self, ctx: &mut Context, time: Time, key: L) -> ClientResult<()>
+    where
+        L: Borrow<K::In<'a>>,
+    {
+        let key_bz = encode_object_key_pair::<Time, K>(
+            self.prefix.as_slice(),
+            &time,
+            key.borrow(),
+            ctx.memory_manager(),
+        )?;
+        unsafe { KVStoreClient.delete(ctx, key_bz) }
+    }
+
+    /// Iterates over all the entries in ascending time order.
+    pub fn iter<'a>(&self, ctx: &'a Context) -> ClientResult<Iter<'a, (Time, K), V>> {
+        self.range(ctx, None, None, Order::Ascending)
+    }
+
+    /// Iterates over the entries with times from `start` inclusive to `end` exclusive
+    /// in the given order. A missing bound leaves that side of the range open.
+    pub fn range<'a>(
+        &self,
+        ctx: &'a Context,
+        start: Option<Time>,
+        end: Option<Time>,
+        order: Order,
+    ) -> ClientResult<Iter<'a, (Time, K), V>> {
+        let mem = ctx.memory_manager();
+        let prefix = self.prefix.as_slice();
+        let start = match start {
+            Some(start) => encode_object_key_prefix::<Time>(prefix, &start, mem)?,
+            None => encode_object_key::<()>(prefix, &(), mem)?,
+        };
+        let end = match end {
+            Some(end) => Some(encode_object_key_prefix::<Time>(prefix, &end, mem)?),
+            None => prefix_end(prefix, mem),
+        };
+        Ok(Iter::new(ctx, prefix.len(), start, end, order))
+    }
+
+    /// Removes and returns up to `limit` entries with times at or before `now`,
+    /// earliest first. This is meant to be called regularly, such as at the end of each block,
+    /// with a limit that bounds the work done in a single call.
+    #[allow(clippy::type_complexity)]
+    pub fn pop_expired<'a>(
+        &self,
+        ctx: &mut Context<'a>,
+        now: Time,
+        limit: usize,
+    ) -> ClientResult<Vec<((Time, K::Out<'a>), V::Out<'a>), &'a MemoryManager>> {
+        let mem = ctx.memory_manager();
+        let prefix = self.prefix.as_slice();
+        let start = encode_object_key::<()>(prefix, &(), mem)?;
+        // the end is exclusive, so it's the first key after those at `now`
+        let end = prefix_end(encode_object_key_prefix::<Time>(prefix, &now, mem)?, mem);
+        let pairs = KVStoreClient.range(ctx, start, end, Order::Ascending, limit)?;
+        let mut expired = Vec::new_in(mem);
+        for pair in decode_kv_pairs(pairs)? {
+            let (key_bz, value_bz) = pair?;
+            expired.push(decode_entry::<(Time, K), V>(
+                &key_bz[prefix.len()..],
+                value_bz,
+                mem,
+            )?);
+            unsafe { KVStoreClient.delete(ctx, key_bz)? };
+        }
+        Ok(expired)
+    }
+}
+
+unsafe impl<K: KeyFieldValue, V: ObjectValue> StateObjectResource for TimeQueue<K, V> {
+    unsafe fn new(scope: &[u8], prefix: u8) -> Result<Self, InitializationError> {
+        let prefix = Prefix::new(scope, prefix)?;
+        Ok(Self {
+            _phantom: PhantomData,
+            prefix,
+        })
+    }
+
+    #[cfg(feature = "std")]
+    fn descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        Map::<(Time, K), V>::descriptor(allocator, collection_name, key_names, value_names)
+    }
+}
@@ -6,7 +6,7 @@ use crate::low_level::{dynamic_invoke_msg_packet, dynamic_invoke_query_packet};
 use crate::message::InitMessage;
 use crate::result::ClientResult;
 use ixc_core_macros::message_selector;
-use ixc_message_api::message::{Message, Request};
+use ixc_message_api::message::{Message, Request, Volatility};
 use ixc_message_api::AccountID;
 use ixc_schema::codec::Codec;
 
@@ -22,11 +22,54 @@ pub fn create_account<H: Handler>(
     Ok(<H as Service>::new_client(account_id))
 }
 
+/// Creates a new account for the specified handler with a migration admin.
+/// Only the admin can migrate the account, using [`migrate_account`],
+/// and the account can't migrate itself.
+pub fn create_account_with_admin<H: Handler>(
+    ctx: &mut Context,
+    init: H::Init<'_>,
+    admin: AccountID,
+) -> ClientResult<<H as Service>::Client> {
+    let cdc = <<H as Handler>::Init<'_> as InitMessage<'_>>::Codec::default();
+    let init_bz = cdc.encode_value(&init, ctx.memory_manager())?;
+
+    let message = Message::new(
+        ROOT_ACCOUNT,
+        Request::new3(
+            CREATE_WITH_ADMIN_SELECTOR,
+            <H as HandlerResources>::NAME.into(),
+            init_bz.into(),
+            admin.into(),
+        ),
+    );
+    let res = dynamic_invoke_msg_packet(ctx, &message, None)?;
+    let account_id = res.out1().expect_account_id()?;
+    Ok(<H as Service>::new_client(account_id))
+}
+
 /// Creates a new account for the named handler with opaque initialization data.
 pub fn create_account_raw(ctx: &mut Context, name: &str, init: &[u8]) -> ClientResult<AccountID> {
     do_create_account(ctx, name, init)
 }
 
+/// Creates a new account with a fixed ID for the named handler with opaque initialization data.
+/// Only the root account can create accounts with fixed IDs, which is useful for
+/// setting up system accounts at genesis, and the account must not already exist.
+pub fn create_account_raw_at(
+    ctx: &mut Context,
+    account_id: AccountID,
+    name: &str,
+    init: &[u8],
+) -> ClientResult<AccountID> {
+    let message = Message::new(
+        ROOT_ACCOUNT,
+        Request::new3(CREATE_SELECTOR, name.into(), init.into(), account_id.into()),
+    );
+    let res = dynamic_invoke_msg_packet(ctx, &message, None)?;
+    let id = res.out1().expect_account_id()?;
+    Ok(id)
+}
+
 /// Creates a new account for the named handler with opaque initialization data.
 fn do_create_account(ctx: &mut Context, name: &str, init: &[u8]) -> ClientResult<AccountID> {
     let message = Message::new(
@@ -42,14 +85,29 @@ fn do_create_account(ctx: &mut Context, name: &str, init: &[u8]) -> ClientResult
 pub fn get_handler_id<'a>(ctx: &Context<'a>, account_id: AccountID) -> ClientResult<&'a str> {
     let message = Message::new(
         ROOT_ACCOUNT,
-        Request::new1(GET_HANDLER_ID_SELECTOR, account_id.into()),
+        Request::new1(GET_HANDLER_ID_SELECTOR, account_id.into())
+            .with_volatility(Volatility::Query),
     );
     let res = dynamic_invoke_query_packet(ctx, &message, None)?;
     let handler_id = res.out1().expect_string()?;
     Ok(handler_id)
 }
 
+/// Gets the number of bytes stored by the account, counting both keys and values.
+/// This is always zero unless storage deposits are enabled.
+pub fn get_storage_usage(ctx: &Context, account_id: AccountID) -> ClientResult<u64> {
+    let message = Message::new(
+        ROOT_ACCOUNT,
+        Request::new1(GET_STORAGE_USAGE_SELECTOR, account_id.into())
+            .with_volatility(Volatility::Query),
+    );
+    let res = dynamic_invoke_query_packet(ctx, &message, None)?;
+    let usage = res.out1().expect_u64()?;
+    Ok(usage)
+}
+
 /// Migrates the account to the new handler with the specified ID.
+/// This fails with an unauthorized caller error if the account has an admin.
 pub fn migrate(ctx: &mut Context, new_handler_id: &str) -> ClientResult<()> {
     let msg = Message::new(
         ROOT_ACCOUNT,
@@ -59,7 +117,25 @@ pub fn migrate(ctx: &mut Context, new_handler_id: &str) -> ClientResult<()> {
     Ok(())
 }
 
+/// Migrates another account to the new handler with the specified ID.
+/// The caller must be the admin which was recorded when the account was created.
+pub fn migrate_account(
+    ctx: &mut Context,
+    account_id: AccountID,
+    new_handler_id: &str,
+) -> ClientResult<()> {
+    let msg = Message::new(
+        ROOT_ACCOUNT,
+        Request::new2(MIGRATE_SELECTOR, new_handler_id.into(), account_id.into()),
+    );
+    dynamic_invoke_msg_packet(ctx, &msg, None)?;
+    Ok(())
+}
+
 /// Self-destructs the account.
+/// This fails with an unauthorized caller error if self-destruct is disabled for the account's handler.
+/// If storage deposits are enabled, the deposit for all of the account's storage
+/// is refunded to the account which created it.
 ///
 /// # Safety
 /// This function is unsafe because it can be used to destroy the account and all its state.
@@ -71,8 +147,12 @@ pub unsafe fn self_destruct(ctx: &mut Context) -> ClientResult<()> {
 
 const CREATE_SELECTOR: u64 = message_selector!("ixc.account.v1.create");
 
+const CREATE_WITH_ADMIN_SELECTOR: u64 = message_selector!("ixc.account.v1.create_with_admin");
+
 const GET_HANDLER_ID_SELECTOR: u64 = message_selector!("ixc.account.v1.get_handler_id");
 
+const GET_STORAGE_USAGE_SELECTOR: u64 = message_selector!("ixc.account.v1.get_storage_usage");
+
 const MIGRATE_SELECTOR: u64 = message_selector!("ixc.account.v1.migrate");
 
 const SELF_DESTRUCT_SELECTOR: u64 = message_selector!("ixc.account.v1.self_destruct");
@@ -85,19 +165,3 @@ pub const ON_CREATE_SELECTOR: u64 = message_selector!("ixc.account.v1.on_create"
 
 /// The message selector for the on_migrate message.
 pub const ON_MIGRATE_SELECTOR: u64 = message_selector!("ixc.account.v1.on_migrate");
-
-// TODO:
-// // #[ixc_schema_macros::handler_api]
-// /// The API for converting between native addresses and account IDs.
-// /// Native addresses have both a byte representation and a string representation.
-// /// The mapping between addresses and account IDs is assumed to be stateful.
-// pub trait AddressAPI {
-//     /// Convert an account ID to a byte representation.
-//     fn to_bytes<'a>(&self, ctx: &'a Context, account_id: AccountID) -> crate::Result<&'a [u8]>;
-//     /// Convert a byte representation to an account ID.
-//     fn from_bytes<'a>(&self, ctx: &'a Context, address_bytes: &[u8]) -> crate::Result<AccountID>;
-//     /// Convert an account ID to a string representation.
-//     fn to_string<'a>(&self, ctx: &'a Context, account_id: AccountID) -> crate::Result<&'a str>;
-//     /// Convert a string representation to an account ID.
-//     fn from_string<'a>(&self, ctx: &'a Context, address_string: &str) -> crate::Result<AccountID>;
-// }
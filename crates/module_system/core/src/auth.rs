@@ -0,0 +1,14 @@
+//! Transaction authentication hooks for account handlers.
+pub use ixc_message_api::AUTHENTICATE_SELECTOR;
+
+/// The request passed to a handler's #[on_authenticate] function.
+pub struct AuthRequest<'a> {
+    /// The nonce of the transaction.
+    pub nonce: u64,
+    /// The opaque credentials attached to the transaction, such as signatures.
+    pub credentials: &'a [u8],
+    /// The canonical bytes of the transaction which the credentials should authenticate.
+    /// They include the ID of the chain, so verifying the credentials against them
+    /// also checks that the transaction was signed for this chain.
+    pub sign_bytes: &'a [u8],
+}
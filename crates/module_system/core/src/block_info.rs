@@ -0,0 +1,45 @@
+//! Information about the block which is currently being executed,
+//! served by the built-in block info account.
+use crate::context::Context;
+use crate::low_level::dynamic_invoke_query_packet;
+use crate::result::ClientResult;
+use ixc_core_macros::message_selector;
+use ixc_message_api::message::{Message, Request, Volatility};
+pub use ixc_message_api::BLOCK_INFO_ACCOUNT;
+use simple_time::Time;
+
+/// Gets the height of the current block.
+pub fn get_block_height(ctx: &Context) -> ClientResult<u64> {
+    let msg = Message::new(
+        BLOCK_INFO_ACCOUNT,
+        Request::new(GET_BLOCK_HEIGHT_SELECTOR).with_volatility(Volatility::Query),
+    );
+    let res = dynamic_invoke_query_packet(ctx, &msg, None)?;
+    Ok(res.out1().expect_u64()?)
+}
+
+/// Gets the time of the current block.
+pub fn get_block_time(ctx: &Context) -> ClientResult<Time> {
+    let msg = Message::new(
+        BLOCK_INFO_ACCOUNT,
+        Request::new(GET_BLOCK_TIME_SELECTOR).with_volatility(Volatility::Query),
+    );
+    let res = dynamic_invoke_query_packet(ctx, &msg, None)?;
+    Ok(Time::from_unix_nanos(res.out1().expect_u128()? as i128))
+}
+
+/// Gets the ID of the chain.
+pub fn get_chain_id<'a>(ctx: &Context<'a>) -> ClientResult<&'a str> {
+    let msg = Message::new(
+        BLOCK_INFO_ACCOUNT,
+        Request::new(GET_CHAIN_ID_SELECTOR).with_volatility(Volatility::Query),
+    );
+    let res = dynamic_invoke_query_packet(ctx, &msg, None)?;
+    Ok(res.out1().expect_string()?)
+}
+
+const GET_BLOCK_HEIGHT_SELECTOR: u64 = message_selector!("ixc.block.v1.get_block_height");
+
+const GET_BLOCK_TIME_SELECTOR: u64 = message_selector!("ixc.block.v1.get_block_time");
+
+const GET_CHAIN_ID_SELECTOR: u64 = message_selector!("ixc.block.v1.get_chain_id");
@@ -1,6 +1,8 @@
 #[cfg(feature = "std")]
 extern crate alloc;
 use crate::result::ClientResult;
+use crate::state::StateCache;
+use core::cell::RefCell;
 use ixc_message_api::code::{ErrorCode, SystemCode};
 use ixc_message_api::handler::HostBackend;
 use ixc_message_api::AccountID;
@@ -11,8 +13,10 @@ use ixc_schema::mem::MemoryManager;
 pub struct Context<'a> {
     account_id: AccountID,
     caller_id: AccountID,
-    backend: BackendHandle<'a>,
+    // the backend is in a cell so that the state cache can be flushed before nested queries
+    backend: RefCell<BackendHandle<'a>>,
     pub(crate) mem: &'a MemoryManager,
+    pub(crate) cache: RefCell<Option<StateCache<'a>>>,
 }
 
 enum BackendHandle<'a> {
@@ -33,7 +37,8 @@ impl<'a> Context<'a> {
             account_id: *account_id,
             caller_id: AccountID::EMPTY,
             mem,
-            backend: BackendHandle::Immutable(host_callbacks),
+            backend: RefCell::new(BackendHandle::Immutable(host_callbacks)),
+            cache: RefCell::new(None),
         }
     }
 
@@ -48,7 +53,8 @@ impl<'a> Context<'a> {
             account_id: *account_id,
             caller_id: *caller_id,
             mem,
-            backend: BackendHandle::Mut(host_callbacks),
+            backend: RefCell::new(BackendHandle::Mut(host_callbacks)),
+            cache: RefCell::new(None),
         }
     }
 
@@ -65,7 +71,8 @@ impl<'a> Context<'a> {
             account_id: *account_id,
             caller_id: *caller_id,
             mem,
-            backend: BackendHandle::Boxed(host_callbacks),
+            backend: RefCell::new(BackendHandle::Boxed(host_callbacks)),
+            cache: RefCell::new(None),
         }
     }
 
@@ -102,9 +109,27 @@ impl<'a> Context<'a> {
         self.mem
     }
 
+    /// Enables the state cache for the rest of the message, which caches the values
+    /// that are read and holds writes until the cache is flushed.
+    /// The cache is flushed before nested message calls and when the handler returns successfully,
+    /// and its writes are discarded if the handler returns an error.
+    /// See [`crate::state`] for details.
+    pub fn enable_state_cache(&mut self) {
+        let mem = self.mem;
+        self.cache
+            .get_mut()
+            .get_or_insert_with(|| StateCache::new(mem));
+    }
+
+    /// Writes the pending writes in the state cache, if it is enabled.
+    /// This only needs to be called directly by code which creates its own contexts, such as tests.
+    pub fn flush_state_cache(&mut self) -> ClientResult<()> {
+        self.flush_cache()
+    }
+
     /// Execute a closure directly on an immutable reference to the host backend.
     pub fn with_backend<R>(&self, f: impl FnOnce(&dyn HostBackend) -> R) -> R {
-        match &self.backend {
+        match &*self.backend.borrow() {
             BackendHandle::Mut(backend) => f(*backend),
             BackendHandle::Immutable(backend) => f(*backend),
             BackendHandle::Boxed(backend) => f(backend.as_ref()),
@@ -116,12 +141,27 @@ impl<'a> Context<'a> {
         &mut self,
         f: impl FnOnce(&mut dyn HostBackend) -> R,
     ) -> Result<R, ErrorCode> {
-        match self.backend {
-            BackendHandle::Mut(ref mut backend) => Ok(f(*backend)),
-            BackendHandle::Boxed(ref mut backend) => Ok(f(&mut **backend)),
+        self.with_shared_backend_mut(f)
+    }
+
+    /// Executes a closure on a mutable reference to the host backend without borrowing
+    /// the context mutably, which must only be used for writes that are already
+    /// visible to the handler, such as those in the state cache.
+    pub(crate) fn with_shared_backend_mut<R>(
+        &self,
+        f: impl FnOnce(&mut dyn HostBackend) -> R,
+    ) -> Result<R, ErrorCode> {
+        match &mut *self.backend.borrow_mut() {
+            BackendHandle::Mut(backend) => Ok(f(*backend)),
+            BackendHandle::Boxed(backend) => Ok(f(&mut **backend)),
             BackendHandle::Immutable(_) => {
                 Err(ErrorCode::SystemCode(SystemCode::VolatileAccessError))
             }
         }
     }
+
+    /// Returns true if the context can't be used to change state.
+    pub(crate) fn is_immutable(&self) -> bool {
+        matches!(&*self.backend.borrow(), BackendHandle::Immutable(_))
+    }
 }
@@ -0,0 +1,64 @@
+//! Scheduling messages for deferred execution outside the current call stack.
+use crate::account_api::ROOT_ACCOUNT;
+use crate::context::Context;
+use crate::error::convert_client_error;
+use crate::low_level::{dynamic_invoke_msg_packet, encode_message_packet};
+use crate::message::Message;
+use crate::result::ClientResult;
+use ixc_core_macros::message_selector;
+use ixc_message_api::encoding::encode_message;
+use ixc_message_api::message::Request;
+use ixc_message_api::AccountID;
+use simple_time::Time;
+
+/// When a deferred message should be executed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// At the end of the current transaction, if it succeeds.
+    EndOfTx,
+    /// At the end of the current block.
+    EndOfBlock,
+    /// At the end of the first block whose height is at least the given height.
+    AtHeight(u64),
+    /// At the end of the first block whose time is at least the given time.
+    AtTime(Time),
+}
+
+/// Schedules a message to be sent to the account when the trigger fires.
+/// The message will be sent with the calling account as the caller,
+/// in its own transaction so that its failure doesn't affect the current call.
+/// Deferred messages are fire-and-forget: their responses are discarded.
+pub fn defer<'b, M: Message<'b>>(
+    ctx: &mut Context,
+    account: AccountID,
+    message: M,
+    trigger: Trigger,
+) -> ClientResult<()> {
+    let packet = encode_message_packet(ctx.memory_manager(), account, message)
+        .map_err(convert_client_error)?;
+    defer_raw(ctx, &packet, trigger)
+}
+
+/// Schedules a raw message packet to be sent when the trigger fires.
+/// See [`defer`].
+pub fn defer_raw(
+    ctx: &mut Context,
+    message: &ixc_message_api::message::Message,
+    trigger: Trigger,
+) -> ClientResult<()> {
+    let (kind, value): (u64, u128) = match trigger {
+        Trigger::EndOfTx => (0, 0),
+        Trigger::EndOfBlock => (1, 0),
+        Trigger::AtHeight(height) => (2, height as u128),
+        Trigger::AtTime(time) => (3, time.unix_nanos() as u128),
+    };
+    let bz = unsafe { encode_message(message, ctx.memory_manager())? };
+    let msg = ixc_message_api::message::Message::new(
+        ROOT_ACCOUNT,
+        Request::new3(DEFER_SELECTOR, kind.into(), value.into(), bz.into()),
+    );
+    dynamic_invoke_msg_packet(ctx, &msg, None)?;
+    Ok(())
+}
+
+const DEFER_SELECTOR: u64 = message_selector!("ixc.deferred.v1.defer");
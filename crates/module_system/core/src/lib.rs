@@ -5,7 +5,10 @@
 extern crate alloc;
 
 pub mod account_api;
+pub mod auth;
+pub mod block_info;
 mod context;
+pub mod deferred;
 pub mod error;
 mod events;
 pub mod handler;
@@ -14,6 +17,8 @@ pub mod message;
 pub mod resource;
 pub mod result;
 pub mod routing;
+pub mod state;
+pub mod storage_deposit;
 mod unique_id;
 
 #[cfg(feature = "std")]
@@ -7,10 +7,11 @@ use crate::Context;
 use allocator_api2::alloc::Allocator;
 use ixc_core_macros::message_selector;
 use ixc_message_api::code::{ErrorCode, HandlerCode, SystemCode};
+use ixc_message_api::encoding::{decode_responses, encode_batch};
 use ixc_message_api::gas::GasTracker;
 use ixc_message_api::handler::InvokeParams;
-use ixc_message_api::message::{MessageSelector, Request, Response};
-use ixc_message_api::AccountID;
+use ixc_message_api::message::{MessageSelector, Request, Response, Volatility};
+use ixc_message_api::{AccountID, ROOT_ACCOUNT};
 use ixc_schema::binary::NativeBinaryCodec;
 use ixc_schema::codec::Codec;
 use ixc_schema::mem::MemoryManager;
@@ -61,7 +62,7 @@ pub fn dynamic_invoke_query_with_gas_tracker<'a, 'b, 'c, M: QueryMessage<'b>>(
     message: M,
     gas_tracker: Option<&'c GasTracker>,
 ) -> ClientResult<<M::Response<'a> as OptionalValue<'a>>::Value, M::Error> {
-    let packet = encode_message_packet(context.memory_manager(), account, message)?;
+    let packet = encode_query_packet(context.memory_manager(), account, message)?;
     let res = dynamic_invoke_query_packet(context, &packet, gas_tracker);
     decode_message_response::<M>(context, &res)
 }
@@ -72,6 +73,8 @@ pub fn dynamic_invoke_query_packet<'a>(
     msg: &ixc_message_api::message::Message,
     gas_tracker: Option<&GasTracker>,
 ) -> Result<Response<'a>, ErrorCode> {
+    // the query could read this account's state, so it needs to see any pending writes
+    ctx.flush_cache().map_err(|e| e.code)?;
     let invoke_params = InvokeParams::new(ctx.mem, gas_tracker);
     ctx.with_backend(|backend| backend.invoke_query(msg, &invoke_params))
 }
@@ -82,11 +85,110 @@ pub fn dynamic_invoke_msg_packet<'a>(
     msg: &ixc_message_api::message::Message,
     gas_limit: Option<&GasTracker>,
 ) -> Result<Response<'a>, ErrorCode> {
+    ctx.flush_cache().map_err(|e| e.code)?;
     let invoke_params = InvokeParams::new(ctx.mem, gas_limit);
-    ctx.with_backend_mut(|backend| backend.invoke_msg(msg, &invoke_params))?
+    let res = ctx.with_backend_mut(|backend| backend.invoke_msg(msg, &invoke_params))?;
+    // the message could have changed this account's state by calling back into it
+    if let Some(cache) = ctx.cache.get_mut() {
+        cache.clear();
+    }
+    res
+}
+
+/// Dynamically invokes a batch of message packets, which can be addressed to different accounts,
+/// atomically in a single transaction frame.
+/// Typed messages can be turned into packets with [`encode_message_packet`].
+/// If every message succeeds, their responses are returned in order,
+/// otherwise the state changes of all of them are rolled back
+/// and the index and error code of the first message which failed are returned.
+pub fn dynamic_invoke_batch<'a>(
+    ctx: &mut Context<'a>,
+    messages: &[ixc_message_api::message::Message],
+    gas_tracker: Option<&GasTracker>,
+) -> Result<BatchResponses<'a>, BatchError> {
+    let batch_error = |code| BatchError { index: None, code };
+    let batch = unsafe { encode_batch(messages, ctx.memory_manager()) }.map_err(batch_error)?;
+    let packet = ixc_message_api::message::Message::new(
+        ROOT_ACCOUNT,
+        Request::new1(BATCH_SELECTOR, batch.into()),
+    );
+    let res = dynamic_invoke_msg_packet(ctx, &packet, gas_tracker).map_err(batch_error)?;
+    if let Some(responses) = res.out1().as_slice() {
+        return BatchResponses::new(responses).map_err(batch_error);
+    }
+    // the batch was rolled back because one of its messages failed
+    let index = res.out1().expect_u64().map_err(batch_error)?;
+    let code = res.out2().expect_u64().map_err(batch_error)?;
+    Err(BatchError {
+        index: Some(index as usize),
+        code: ErrorCode::from(code as u16),
+    })
+}
+
+/// The responses of a batch of messages which all succeeded.
+pub struct BatchResponses<'a> {
+    responses: &'a [u8],
+    len: usize,
 }
 
-fn encode_message_packet<'a, 'b, M: MessageBase<'b>>(
+impl<'a> BatchResponses<'a> {
+    fn new(responses: &'a [u8]) -> Result<Self, ErrorCode> {
+        let mut len = 0;
+        for res in decode_responses(responses)? {
+            res?;
+            len += 1;
+        }
+        Ok(Self { responses, len })
+    }
+
+    /// Returns the number of responses, which is the number of messages in the batch.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the batch had no messages.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the raw response of the message at the index.
+    pub fn get(&self, index: usize) -> Option<Response<'a>> {
+        self.iter().nth(index)
+    }
+
+    /// Returns an iterator over the raw responses in the order of the messages.
+    pub fn iter(&self) -> impl Iterator<Item = Response<'a>> {
+        // the responses were validated when the batch completed
+        decode_responses(self.responses)
+            .into_iter()
+            .flatten()
+            .map_while(Result::ok)
+    }
+
+    /// Decodes the response of the message at the index as the response of the message type.
+    pub fn decode<'b, M: MessageBase<'b>>(
+        &self,
+        ctx: &Context<'a>,
+        index: usize,
+    ) -> ClientResult<<M::Response<'a> as OptionalValue<'a>>::Value, M::Error> {
+        let res = self
+            .get(index)
+            .ok_or(ErrorCode::SystemCode(SystemCode::EncodingError));
+        decode_message_response::<M>(ctx, &res)
+    }
+}
+
+/// An error returned when a batch of messages fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchError {
+    /// The index of the message which failed, or `None` if the batch couldn't be run at all.
+    pub index: Option<usize>,
+    /// The error code returned by the failed message or the account manager.
+    pub code: ErrorCode,
+}
+
+/// Encode a message struct into a message packet addressed to the account.
+pub fn encode_message_packet<'a, 'b, M: MessageBase<'b>>(
     mem: &'a MemoryManager,
     account: AccountID,
     message: M,
@@ -102,6 +204,20 @@ fn encode_message_packet<'a, 'b, M: MessageBase<'b>>(
     ))
 }
 
+/// Encode a query message struct into a query message packet addressed to the account.
+pub fn encode_query_packet<'a, 'b, M: QueryMessage<'b>>(
+    mem: &'a MemoryManager,
+    account: AccountID,
+    message: M,
+) -> ClientResult<ixc_message_api::message::Message<'a>, M::Error> {
+    let cdc = M::Codec::default();
+    let msg_body = cdc.encode_value(&message, mem)?;
+    Ok(ixc_message_api::message::Message::new(
+        account,
+        Request::new1(M::TYPE_SELECTOR, msg_body.into()).with_volatility(Volatility::Query),
+    ))
+}
+
 fn decode_message_response<'a, 'b, M: MessageBase<'b>>(
     context: &Context<'a>,
     res: &Result<Response<'a>, ErrorCode>,
@@ -120,6 +236,17 @@ fn decode_message_response<'a, 'b, M: MessageBase<'b>>(
     }
 }
 
+/// Flushes the state cache of the context if the handler returned successfully.
+/// Used after calling handlers in macros.
+pub fn flush_state_cache_on_success<R, E: HandlerCode + SchemaValue<'static>>(
+    ctx: &mut Context,
+    res: crate::Result<R, E>,
+) -> crate::Result<R, E> {
+    let value = res?;
+    ctx.flush_state_cache()?;
+    Ok(value)
+}
+
 /// Encodes the response to the out1 pointer of the message packet. Used for encoding the response of a message in macros.
 pub fn encode_response<'a, 'b, M: MessageBase<'a>>(
     cdc: &dyn Codec,
@@ -205,3 +332,4 @@ pub fn emit_event<'a, E: StructSchema + SchemaValue<'a>>(
 }
 
 const EMIT_EVENT_SELECTOR: MessageSelector = message_selector!("ixc.events.1.emit");
+const BATCH_SELECTOR: MessageSelector = message_selector!("ixc.account.v1.batch");
@@ -84,6 +84,8 @@ fn visit_message_base<'a, M: MessageBase<'a>, V: APISchemaVisitor<'a>>(
 ) -> MessageDescriptor<'static> {
     visitor.visit::<M::Type>();
     visitor.visit::<<M::Error as SchemaValue>::Type>();
+    // the response is referenced by the descriptor, so its type is part of the schema too
+    M::Response::visit_type(visitor);
     let mut desc: MessageDescriptor = MessageDescriptor::new(M::STRUCT_TYPE.name);
     desc.encoding = M::Codec::ENCODING;
     desc.response = M::Response::AS_FIELD;
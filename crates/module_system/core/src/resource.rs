@@ -68,6 +68,23 @@ pub unsafe trait StateObjectResource: Sized {
         key_names: &[&'a str],
         value_names: &[&'a str],
     ) -> StateObjectDescriptor<'a>;
+
+    #[cfg(feature = "std")]
+    /// Gets the descriptor for the state object with the supplied names
+    /// and the names of its indexes, which only state objects with indexes accept.
+    fn indexed_descriptor<'a>(
+        allocator: &'a dyn Allocator,
+        collection_name: &'a str,
+        key_names: &[&'a str],
+        value_names: &[&'a str],
+        index_names: &[&'a str],
+    ) -> StateObjectDescriptor<'a> {
+        if !index_names.is_empty() {
+            panic!("State object \"{}\" doesn't have indexes, but got index names {:?}. Only collections with indexes, such as IndexedMap, accept index(...) in #[state].",
+                collection_name, index_names);
+        }
+        Self::descriptor(allocator, collection_name, key_names, value_names)
+    }
 }
 
 /// An error that occurs during resource initialization.
@@ -82,15 +99,19 @@ pub enum InitializationError {
 }
 
 impl ResourceScope<'_> {
-    /// Resolves an account name to an account ID or returns a default account ID if provided.
+    /// Resolves an account name to an account ID or returns a default account ID if provided
+    /// and the name can't be resolved.
     pub fn resolve_account(
         &self,
         name: &str,
         default: Option<AccountID>,
     ) -> core::result::Result<AccountID, InitializationError> {
-        self.account_resolver
-            .map(|resolver| resolver.resolve(name))
-            .unwrap_or_else(|| default.ok_or(InitializationError::AccountNotFound))
+        match self.account_resolver.map(|resolver| resolver.resolve(name)) {
+            Some(Err(InitializationError::AccountNotFound)) | None => {
+                default.ok_or(InitializationError::AccountNotFound)
+            }
+            Some(res) => res,
+        }
     }
 }
 
@@ -106,21 +127,44 @@ pub trait ResourcesVisitor<'a>: TypeVisitor {
 
 /// Extract the state object descriptor for a state object.
 /// Used in macros to extract state object schemas.
+/// Any prefixes set by the descriptor for the state object or its indexes
+/// are relative to the state object's prefix.
 pub fn extract_state_object_descriptor<'a, R: StateObjectResource, V: ResourcesVisitor<'a>>(
     visitor: &mut V,
     prefix: u8,
     collection_name: &'a str,
     key_names: &'a [&'a str],
     value_names: &'a [&'a str],
+    index_names: &'a [&'a str],
 ) {
-    let mut state_object =
-        R::descriptor(visitor.allocator(), collection_name, key_names, value_names);
-    let mut prefix_vec = Vec::new_in(visitor.allocator());
-    prefix_vec.push(prefix);
-    state_object.prefix = List::Owned(prefix_vec);
+    let allocator = visitor.allocator();
+    let mut state_object = R::indexed_descriptor(
+        allocator,
+        collection_name,
+        key_names,
+        value_names,
+        index_names,
+    );
+    state_object.prefix = prepend_prefix(allocator, prefix, &state_object.prefix);
+    if !state_object.indexes.is_empty() {
+        let mut indexes = Vec::new_in(allocator);
+        for index in state_object.indexes.as_slice() {
+            let mut index = index.clone();
+            index.prefix = prepend_prefix(allocator, prefix, &index.prefix);
+            indexes.push(index);
+        }
+        state_object.indexes = List::Owned(indexes);
+    }
     visitor.visit_state_object(&state_object);
 }
 
+fn prepend_prefix<'a>(allocator: &'a dyn Allocator, prefix: u8, rest: &List<u8>) -> List<'a, u8> {
+    let mut prefix_vec = Vec::new_in(allocator);
+    prefix_vec.push(prefix);
+    prefix_vec.extend_from_slice(rest.as_slice());
+    List::Owned(prefix_vec)
+}
+
 /// Visit a client factory to extract the schema.
 /// The signature of this function is a bit of a hack around a possible bug in the quote crate.
 pub fn visit_client_factory<'a, S: Service, V: ResourcesVisitor<'a>>(
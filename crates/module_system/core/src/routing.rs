@@ -1,11 +1,14 @@
 //! Routing system for message packets.
 
+use crate::account_api::{ON_CREATE_SELECTOR, ON_MIGRATE_SELECTOR};
+use crate::storage_deposit::STORAGE_DEPOSIT_SELECTOR;
 use allocator_api2::alloc::Allocator;
-use ixc_message_api::code::SystemCode::MessageNotHandled;
+use ixc_message_api::code::SystemCode::{MessageNotHandled, VolatileAccessError};
 use ixc_message_api::error::HandlerError;
 use ixc_message_api::handler::HostBackend;
-use ixc_message_api::message::{Message, MessageSelector, Response};
+use ixc_message_api::message::{Message, MessageSelector, Response, Volatility};
 use ixc_message_api::AccountID;
+use ixc_message_api::{AUTHENTICATE_SELECTOR, REGISTER_ADDRESS_SELECTOR};
 
 /// A router for message packets.
 /// # Safety
@@ -22,8 +25,20 @@ where
 
     /// The system routes sorted by message selector.
     const SORTED_SYSTEM_ROUTES: &'static [Route<Self>];
+
+    /// The names of the messages and queries in the message and query routes by message selector.
+    const SELECTOR_NAMES: &'static [(MessageSelector, &'static str)];
 }
 
+/// The selectors reserved for system messages, which messages and queries must not collide with.
+pub const SYSTEM_SELECTORS: &[(MessageSelector, &str)] = &[
+    (ON_CREATE_SELECTOR, "ixc.account.v1.on_create"),
+    (ON_MIGRATE_SELECTOR, "ixc.account.v1.on_migrate"),
+    (AUTHENTICATE_SELECTOR, "ixc.auth.v1.authenticate"),
+    (STORAGE_DEPOSIT_SELECTOR, "ixc.storage.v1.deposit"),
+    (REGISTER_ADDRESS_SELECTOR, "ixc.address.v1.register"),
+];
+
 /// A route for a message packet.
 pub type Route<T> = (
     u64,
@@ -48,6 +63,7 @@ pub type QueryRoute<T> = (
 );
 
 /// Execute a message packet on a router.
+/// Query messages are routed to the query routes.
 pub fn exec_route<'a, R: Router + ?Sized>(
     rtr: &R,
     caller: &AccountID,
@@ -55,6 +71,9 @@ pub fn exec_route<'a, R: Router + ?Sized>(
     callbacks: &mut dyn HostBackend,
     allocator: &'a dyn Allocator,
 ) -> Result<Response<'a>, HandlerError> {
+    if req.request().volatility() == Volatility::Query {
+        return exec_query_route(rtr, req, callbacks, allocator);
+    }
     match find_route(R::SORTED_MSG_ROUTES, req.request().message_selector()) {
         Some(rt) => rt(rtr, caller, req, callbacks, allocator),
         None => Err(HandlerError::new(MessageNotHandled.into())),
@@ -62,18 +81,28 @@ pub fn exec_route<'a, R: Router + ?Sized>(
 }
 
 /// Execute a query message packet on a router.
+/// Volatile messages are rejected with a volatile access error.
 pub fn exec_query_route<'a, R: Router + ?Sized>(
     rtr: &R,
     req: &Message,
     callbacks: &dyn HostBackend,
     allocator: &'a dyn Allocator,
 ) -> Result<Response<'a>, HandlerError> {
+    ensure_query(req)?;
     match find_route(R::SORTED_QUERY_ROUTES, req.request().message_selector()) {
         Some(rt) => rt(rtr, req, callbacks, allocator),
         None => Err(HandlerError::new(MessageNotHandled.into())),
     }
 }
 
+/// Returns a volatile access error if the message isn't a query.
+pub fn ensure_query(req: &Message) -> Result<(), HandlerError> {
+    match req.request().volatility() {
+        Volatility::Query => Ok(()),
+        _ => Err(HandlerError::new(VolatileAccessError.into())),
+    }
+}
+
 /// Find a route for a message selector.
 pub fn find_route<R>(sorted_routes: &[(u64, R)], sel: MessageSelector) -> Option<&R> {
     let res = sorted_routes.binary_search_by_key(&sel, |(selector, _)| *selector);
@@ -114,9 +143,81 @@ const fn sort_routes_base<const N: usize, R: Copy>(mut arr: [(u64, R); N]) -> [(
             break;
         }
     }
+    let mut i = 1;
+    while i < N {
+        if arr[i - 1].0 == arr[i].0 {
+            panic!("duplicate message selector in route table");
+        }
+        i += 1;
+    }
     arr
 }
 
+/// Finds the names of the first two messages with the same selector across the tables.
+pub const fn find_selector_collision<'a>(
+    tables: &[&[(MessageSelector, &'a str)]],
+) -> Option<(&'a str, &'a str)> {
+    let mut i = 0;
+    while i < tables.len() {
+        let mut j = 0;
+        while j < tables[i].len() {
+            let (sel, name) = tables[i][j];
+            // compare against the rest of this table and all the following tables
+            let mut k = i;
+            let mut l = j + 1;
+            while k < tables.len() {
+                while l < tables[k].len() {
+                    if tables[k][l].0 == sel {
+                        return Some((name, tables[k][l].1));
+                    }
+                    l += 1;
+                }
+                k += 1;
+                l = 0;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Panics, at compile time when used in a constant, if two messages across the tables
+/// have the same selector, naming both of them.
+pub const fn check_selectors(tables: &[&[(MessageSelector, &str)]]) {
+    if let Some((a, b)) = find_selector_collision(tables) {
+        let mut buf = [0u8; 512];
+        let mut len = append_str(&mut buf, 0, "message selector collision between ");
+        len = append_str(&mut buf, len, a);
+        len = append_str(&mut buf, len, " and ");
+        len = append_str(&mut buf, len, b);
+        // SAFETY: append_str only copies whole UTF-8 characters
+        let msg = unsafe {
+            core::str::from_utf8_unchecked(core::slice::from_raw_parts(buf.as_ptr(), len))
+        };
+        panic!("{}", msg);
+    }
+}
+
+const fn append_str(buf: &mut [u8], mut len: usize, s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if len == buf.len() {
+            // don't cut the string in the middle of a character
+            while i > 0 && (bytes[i] & 0xC0) == 0x80 {
+                i -= 1;
+                len -= 1;
+            }
+            break;
+        }
+        buf[len] = bytes[i];
+        len += 1;
+        i += 1;
+    }
+    len
+}
+
 // Concatenates two arrays of routes.
 // pub const fn const_cat<T: ?Sized>(arr: &[Route<T>], arr2: &[Route<T>]) -> &'static [Route<T>] {
 //     const N: usize = arr.len() + arr2.len();
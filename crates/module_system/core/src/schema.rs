@@ -1,12 +1,20 @@
 //! Schema extraction and printing utilities.
 extern crate std;
+use crate::account_api::ROOT_ACCOUNT;
 use crate::handler::{APISchemaVisitor, Client, Handler};
+use crate::low_level::dynamic_invoke_query_packet;
 use crate::resource::ResourcesVisitor;
+use crate::result::ClientResult;
+use crate::Context;
 use alloc::string::{String, ToString};
 use allocator_api2::alloc::Allocator;
 use allocator_api2::vec::Vec;
+use ixc_core_macros::message_selector;
+use ixc_message_api::message::{Message, Param, Request, Volatility};
 use ixc_message_api::AccountID;
+use ixc_schema::binary::NativeBinaryCodec;
 use ixc_schema::client::ClientDescriptor;
+use ixc_schema::codec::{decode_value, Codec};
 use ixc_schema::handler::HandlerSchema;
 use ixc_schema::json;
 use ixc_schema::list::List;
@@ -110,6 +118,47 @@ pub fn extract_handler_schema<H: Handler>(
     Ok(res)
 }
 
+/// Extract the schema of the handler and encode it with the native binary codec,
+/// which is the format in which the runtime stores handler schemas.
+pub fn encode_handler_schema<H: Handler>(allocator: &dyn Allocator) -> Result<&[u8], String> {
+    let schema = extract_handler_schema::<H>(allocator)?;
+    NativeBinaryCodec
+        .encode_value(&schema, allocator)
+        .map_err(|e| e.to_string())
+}
+
+/// Gets the schema of a handler registered with the runtime by its handler ID.
+pub fn get_handler_schema<'a>(
+    ctx: &Context<'a>,
+    handler_id: &str,
+) -> ClientResult<HandlerSchema<'a>> {
+    query_schema(ctx, handler_id.into())
+}
+
+/// Gets the schema of the handler of the account from the runtime.
+pub fn get_account_schema<'a>(
+    ctx: &Context<'a>,
+    account_id: AccountID,
+) -> ClientResult<HandlerSchema<'a>> {
+    query_schema(ctx, account_id.into())
+}
+
+fn query_schema<'a>(ctx: &Context<'a>, handler: Param) -> ClientResult<HandlerSchema<'a>> {
+    let message = Message::new(
+        ROOT_ACCOUNT,
+        Request::new1(GET_SCHEMA_SELECTOR, handler).with_volatility(Volatility::Query),
+    );
+    let res = dynamic_invoke_query_packet(ctx, &message, None)?;
+    let schema = decode_value(
+        &NativeBinaryCodec,
+        res.out1().expect_bytes()?,
+        ctx.memory_manager(),
+    )?;
+    Ok(schema)
+}
+
+const GET_SCHEMA_SELECTOR: u64 = message_selector!("ixc.schema.v1.get");
+
 /// Dump the schema of the handler to stdout as JSON.
 pub fn print_handler_schema<H: Handler>() -> Result<(), String> {
     let mem = MemoryManager::new();
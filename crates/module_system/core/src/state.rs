@@ -0,0 +1,242 @@
+//! Low-level access to the key-value state of the account handling a message.
+//!
+//! If the message's state cache is enabled with [`Context::enable_state_cache`],
+//! values which are read are cached for the rest of the message and writes are held
+//! in the cache until it is flushed, so reading or writing a key several times
+//! only reads or writes it in storage once.
+//! The cache is flushed before nested message calls and when a handler returns successfully,
+//! so other accounts, and nested calls back into the same account, always see its writes.
+use crate::result::ClientResult;
+use crate::Context;
+use allocator_api2::vec::Vec;
+use ixc_core_macros::message_selector;
+use ixc_message_api::code::{ErrorCode, SystemCode};
+use ixc_message_api::encoding::{decode_kv_pairs, encode_kv_pairs, KVPair};
+use ixc_message_api::handler::InvokeParams;
+use ixc_message_api::message::{MessageSelector, Param, Request, Response};
+use ixc_schema::mem::MemoryManager;
+
+/// Gets the value at the key, if there is one.
+pub fn get<'a>(ctx: &Context<'a>, key: &[u8]) -> ClientResult<Option<&'a [u8]>> {
+    if let Some(cache) = ctx.cache.borrow().as_ref() {
+        if let Some(entry) = cache.get(key) {
+            return Ok(entry.value);
+        }
+    }
+    let res = query_state(ctx, &Request::new1(GET_SELECTOR, key.into()))?;
+    let value = res.out1().as_slice();
+    if let Some(cache) = ctx.cache.borrow_mut().as_mut() {
+        cache.insert(copy(ctx.mem, key), value, false);
+    }
+    Ok(value)
+}
+
+/// Sets the value at the key.
+pub fn set(ctx: &mut Context, key: &[u8], value: &[u8]) -> ClientResult<()> {
+    if ctx.cache.get_mut().is_some() {
+        let (key, value) = (copy(ctx.mem, key), copy(ctx.mem, value));
+        return ctx.write_cache(key, Some(value));
+    }
+    update_state(ctx, &Request::new2(SET_SELECTOR, key.into(), value.into()))?;
+    Ok(())
+}
+
+/// Deletes the value at the key.
+pub fn delete(ctx: &mut Context, key: &[u8]) -> ClientResult<()> {
+    if ctx.cache.get_mut().is_some() {
+        let key = copy(ctx.mem, key);
+        return ctx.write_cache(key, None);
+    }
+    update_state(ctx, &Request::new1(DELETE_SELECTOR, key.into()))?;
+    Ok(())
+}
+
+/// Gets up to `limit` key-value pairs with keys from `start` inclusive to `end` exclusive,
+/// in descending key order if `reverse` is true and ascending key order otherwise.
+/// The pairs are encoded with [`ixc_message_api::encoding::encode_kv_pairs`].
+pub fn range<'a>(
+    ctx: &Context<'a>,
+    start: &[u8],
+    end: Option<&[u8]>,
+    reverse: bool,
+    limit: usize,
+) -> ClientResult<&'a [u8]> {
+    let mem = ctx.mem;
+    let mut writes = Vec::new_in(mem);
+    if let Some(cache) = ctx.cache.borrow().as_ref() {
+        writes.extend(cache.writes_in(start, end));
+    }
+    if reverse {
+        writes.reverse();
+    }
+    // each pending write can hide at most one stored pair, so fetching that many more
+    // pairs than the limit is enough to fill the page after the writes are applied
+    let stored = query_range(ctx, start, end, reverse, limit + writes.len())?;
+    if writes.is_empty() {
+        return Ok(stored);
+    }
+    let before = |a: &[u8], b: &[u8]| if reverse { a > b } else { a < b };
+    let mut stored = decode_kv_pairs(stored)?.peekable();
+    let mut writes = writes.into_iter().peekable();
+    let mut pairs: Vec<KVPair, &MemoryManager> = Vec::new_in(mem);
+    while pairs.len() < limit {
+        let next_stored = match stored.peek() {
+            Some(Ok(pair)) => Some(*pair),
+            Some(Err(e)) => return Err((*e).into()),
+            None => None,
+        };
+        let (key, value) = match (next_stored, writes.peek()) {
+            (None, None) => break,
+            (Some(pair), Some((key, _))) if before(pair.0, key) => {
+                stored.next();
+                (pair.0, Some(pair.1))
+            }
+            (Some(pair), None) => {
+                stored.next();
+                (pair.0, Some(pair.1))
+            }
+            (next_stored, Some(&(key, value))) => {
+                // a pending write replaces the stored value with the same key
+                if next_stored.is_some_and(|pair| pair.0 == key) {
+                    stored.next();
+                }
+                writes.next();
+                (key, value)
+            }
+        };
+        if let Some(value) = value {
+            pairs.push((key, value));
+        }
+    }
+    Ok(unsafe { encode_kv_pairs(&pairs, mem)? })
+}
+
+/// The state cache of a message, which holds the values which have been read
+/// and the writes which haven't been flushed yet, sorted by key.
+pub(crate) struct StateCache<'a> {
+    entries: Vec<(&'a [u8], CacheEntry<'a>), &'a MemoryManager>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct CacheEntry<'a> {
+    value: Option<&'a [u8]>,
+    dirty: bool,
+}
+
+impl<'a> StateCache<'a> {
+    pub(crate) fn new(mem: &'a MemoryManager) -> Self {
+        Self {
+            entries: Vec::new_in(mem),
+        }
+    }
+
+    fn get(&self, key: &[u8]) -> Option<CacheEntry<'a>> {
+        let i = self.entries.binary_search_by(|(k, _)| (*k).cmp(key)).ok()?;
+        Some(self.entries[i].1)
+    }
+
+    fn insert(&mut self, key: &'a [u8], value: Option<&'a [u8]>, dirty: bool) {
+        let entry = CacheEntry { value, dirty };
+        match self.entries.binary_search_by(|(k, _)| (*k).cmp(key)) {
+            Ok(i) => self.entries[i].1 = entry,
+            Err(i) => self.entries.insert(i, (key, entry)),
+        }
+    }
+
+    /// Returns the pending writes with keys from `start` inclusive to `end` exclusive.
+    fn writes_in<'b>(
+        &'b self,
+        start: &'b [u8],
+        end: Option<&'b [u8]>,
+    ) -> impl Iterator<Item = (&'a [u8], Option<&'a [u8]>)> + 'b {
+        self.entries
+            .iter()
+            .filter(move |(key, entry)| {
+                entry.dirty && *key >= start && end.is_none_or(|end| *key < end)
+            })
+            .map(|(key, entry)| (*key, entry.value))
+    }
+
+    /// Removes all the entries, such as after a nested message call which could have
+    /// changed the state.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl<'a> Context<'a> {
+    fn write_cache(&mut self, key: &'a [u8], value: Option<&'a [u8]>) -> ClientResult<()> {
+        // fail on the write rather than on the flush if the state can't be written
+        if self.is_immutable() {
+            return Err(ErrorCode::SystemCode(SystemCode::VolatileAccessError).into());
+        }
+        if let Some(cache) = self.cache.get_mut() {
+            cache.insert(key, value, true);
+        }
+        Ok(())
+    }
+
+    /// Writes the pending writes in the state cache, if there are any.
+    pub(crate) fn flush_cache(&self) -> ClientResult<()> {
+        let mut cache = self.cache.borrow_mut();
+        let Some(cache) = cache.as_mut() else {
+            return Ok(());
+        };
+        let params = InvokeParams::new(self.mem, None);
+        for (key, entry) in cache.entries.iter_mut() {
+            if !entry.dirty {
+                continue;
+            }
+            let req = match entry.value {
+                Some(value) => Request::new2(SET_SELECTOR, (*key).into(), value.into()),
+                None => Request::new1(DELETE_SELECTOR, (*key).into()),
+            };
+            self.with_shared_backend_mut(|backend| backend.update_state(&req, &params))??;
+            entry.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+fn copy<'a>(mem: &'a MemoryManager, bz: &[u8]) -> &'a [u8] {
+    let mut copy = Vec::new_in(mem);
+    copy.extend_from_slice(bz);
+    copy.leak()
+}
+
+fn query_range<'a>(
+    ctx: &Context<'a>,
+    start: &[u8],
+    end: Option<&[u8]>,
+    reverse: bool,
+    limit: usize,
+) -> ClientResult<&'a [u8]> {
+    let selector = if reverse {
+        REVERSE_RANGE_SELECTOR
+    } else {
+        RANGE_SELECTOR
+    };
+    let end = match end {
+        Some(end) => end.into(),
+        None => Param::default(),
+    };
+    let req = Request::new3(selector, start.into(), end, (limit as u64).into());
+    let res = query_state(ctx, &req)?;
+    Ok(res.out1().expect_bytes()?)
+}
+
+fn update_state<'a>(ctx: &mut Context<'a>, req: &Request) -> Result<Response<'a>, ErrorCode> {
+    let invoke_params = InvokeParams::new(ctx.mem, None);
+    ctx.with_backend_mut(|backend| backend.update_state(req, &invoke_params))?
+}
+
+fn query_state<'a>(ctx: &Context<'a>, req: &Request) -> Result<Response<'a>, ErrorCode> {
+    let invoke_params = InvokeParams::new(ctx.mem, None);
+    ctx.with_backend(|backend| backend.query_state(req, &invoke_params))
+}
+
+const GET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.get");
+const SET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.set");
+const DELETE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.delete");
+const RANGE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.range");
+const REVERSE_RANGE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.reverse_range");
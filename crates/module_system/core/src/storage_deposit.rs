@@ -0,0 +1,18 @@
+//! Storage deposit hooks for bank-like handlers.
+use ixc_core_macros::message_selector;
+use ixc_message_api::AccountID;
+
+/// The message selector for the storage deposit system message,
+/// which the account manager sends to the configured storage deposit account
+/// whenever an account's storage usage grows or shrinks.
+pub const STORAGE_DEPOSIT_SELECTOR: u64 = message_selector!("ixc.storage.v1.deposit");
+
+/// The request passed to a handler's #[on_storage_deposit] function.
+pub struct StorageDepositRequest {
+    /// The account which pays the deposit or receives the refund.
+    pub account: AccountID,
+    /// The amount of the deposit or refund.
+    pub amount: u128,
+    /// Whether the deposit should be refunded to the account rather than charged.
+    pub refund: bool,
+}
@@ -0,0 +1,50 @@
+use crate::api_builder::APIBuilder;
+use crate::handler::{PublishedFnInfo, PublishedFnType};
+use manyhow::bail;
+use quote::quote;
+use syn::{FnArg, ImplItemFn};
+
+/// Checks the signature of a function annotated with #[on_register_address].
+pub(crate) fn check_on_register_address_fn(item_fn: &ImplItemFn) -> manyhow::Result<()> {
+    let fn_name = &item_fn.sig.ident;
+    if item_fn.sig.inputs.len() != 3 {
+        bail!("error with fn {}: expected exactly 3 arguments in on_register_address function: &self, &mut Context and AccountID", fn_name);
+    }
+    match &item_fn.sig.inputs[0] {
+        FnArg::Receiver(r) if r.mutability.is_none() => Ok(()),
+        _ => bail!(
+            "error with fn {}: the first argument of on_register_address function must be &self",
+            fn_name
+        ),
+    }
+}
+
+/// Generates the system route for the #[on_register_address] function, if there is one.
+pub(crate) fn build_on_register_address_handler(
+    builder: &mut APIBuilder,
+    published_fn_info: &[PublishedFnInfo],
+) -> manyhow::Result<()> {
+    let mut fn_names = published_fn_info
+        .iter()
+        .filter(|fn_info| matches!(fn_info.ty, PublishedFnType::OnRegisterAddress))
+        .map(|fn_info| &fn_info.signature.ident);
+    let Some(fn_name) = fn_names.next() else {
+        return Ok(());
+    };
+    if fn_names.next().is_some() {
+        bail!("only one function can be annotated with #[on_register_address]");
+    }
+    builder.system_routes.push(quote! {
+        (::ixc::message_api::REGISTER_ADDRESS_SELECTOR, | h: & Self, caller, packet, cb, a | {
+            unsafe {
+                let account = packet.request().in1().expect_account_id()?;
+                let mem =::ixc::schema::mem::MemoryManager::new();
+                let mut ctx =::ixc::core::Context::new_mut(&packet.target_account(), caller, cb, &mem);
+                let res = h.#fn_name(&mut ctx, account);
+                let res = ::ixc::core::low_level::flush_state_cache_on_success(&mut ctx, res);
+                ::ixc::core::low_level::encode_default_response(res)
+            }
+        })
+    });
+    Ok(())
+}
@@ -7,7 +7,9 @@ use manyhow::{bail, ensure};
 use proc_macro2::{Ident, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
 use syn::punctuated::Punctuated;
-use syn::{parse_quote, Item, Pat, PatType, PathArguments, ReturnType, Signature, Type};
+use syn::{
+    parse_quote, GenericArgument, Item, Pat, PatType, PathArguments, ReturnType, Signature, Type,
+};
 
 /// Builder for generating API implementation code.
 ///
@@ -20,6 +22,7 @@ pub(crate) struct APIBuilder {
     pub(crate) routes: Vec<TokenStream2>,
     pub(crate) query_routes: Vec<TokenStream2>,
     pub(crate) system_routes: Vec<TokenStream2>,
+    selector_names: Vec<TokenStream2>,
     pub(crate) client_signatures: Vec<Signature>,
     client_methods: Vec<TokenStream2>,
     pub(crate) create_msg_name: Option<Ident>,
@@ -109,8 +112,12 @@ impl APIBuilder {
         _handler_ty: &TokenStream2,
         publish_target: &PublishedFnInfo,
     ) -> manyhow::Result<()> {
-        if let PublishedFnType::OnMigrate { .. } = &publish_target.ty {
-            // we don't handle on_migrate functions here
+        if let PublishedFnType::OnMigrate { .. }
+        | PublishedFnType::OnAuthenticate
+        | PublishedFnType::OnStorageDeposit
+        | PublishedFnType::OnRegisterAddress = &publish_target.ty
+        {
+            // we don't handle on_migrate or the other system hook functions here
             return Ok(());
         }
 
@@ -202,7 +209,25 @@ impl APIBuilder {
                                             continue;
                                         }
                                     }
-                                    // note that we don't need to do anything special for most value types here
+                                    // value types with a lifetime parameter, such as PageRequest<'_>,
+                                    // borrow from the message like references do
+                                    for seg in path.path.segments.iter_mut() {
+                                        if let PathArguments::AngleBracketed(args) =
+                                            &mut seg.arguments
+                                        {
+                                            for arg in args.args.iter_mut() {
+                                                if let GenericArgument::Lifetime(lifetime) = arg {
+                                                    assert!(
+                                                        lifetime.ident == "a"
+                                                            || lifetime.ident == "_",
+                                                        "lifetime must be either '_ or called 'a"
+                                                    );
+                                                    msg_has_lifetimes = true;
+                                                    *lifetime = parse_quote!('a);
+                                                }
+                                            }
+                                        }
+                                    }
                                 }
                                 _ => {}
                             }
@@ -333,6 +358,11 @@ impl APIBuilder {
             } else {
                 quote! { caller, }
             };
+            let maybe_flush = if is_query {
+                quote! {}
+            } else {
+                quote! { let res = ::ixc::core::low_level::flush_state_cache_on_success(&mut ctx, res); }
+            };
             let route = quote! {
             ( < # msg_struct_name # opt_underscore_lifetime as::ixc::schema::structs::StructSchema>::TYPE_SELECTOR, |h: & Self, #maybe_caller packet, cb, allocator| {
                 unsafe {
@@ -343,10 +373,15 @@ impl APIBuilder {
                         .map_err(|e| ::ixc::message_api::error::HandlerError::new(::ixc::message_api::code::SystemCode::EncodingError.into()))?;
                     let # maybe_mut ctx = ::ixc::core::Context::# new_ctx(&packet.target_account(), #maybe_caller cb, &mem);
                     let res = h.# fn_name( & # maybe_mut ctx, # ( # fn_call_args) * );
+                    # maybe_flush
                     ::ixc::core::low_level::encode_response::< #msg_struct_name > ( &cdc, res, allocator )
                 }
             }),
             };
+            self.selector_names.push(quote! {
+                (< # msg_struct_name # opt_underscore_lifetime as::ixc::schema::structs::StructSchema>::TYPE_SELECTOR,
+                 < # msg_struct_name # opt_underscore_lifetime as::ixc::schema::structs::StructSchema>::STRUCT_TYPE.name),
+            });
             if is_query {
                 self.query_routes.push(route);
             } else {
@@ -379,6 +414,7 @@ impl APIBuilder {
                             .map_err(|e| ::ixc::message_api::error::HandlerError::new(::ixc::message_api::code::SystemCode::EncodingError.into()))?;
                         let mut ctx =::ixc::core::Context::new_mut(&packet.target_account(), caller, cb, &mem);
                         let res = h.# fn_name( & mut ctx, # (# fn_call_args) * );
+                        let res = ::ixc::core::low_level::flush_state_cache_on_success(&mut ctx, res);
                         ::ixc::core::low_level::encode_default_response(res)
                     }
                     }),}
@@ -395,6 +431,7 @@ impl APIBuilder {
     /// - Sorted message routes
     /// - Sorted query routes
     /// - Sorted system routes
+    /// - The names of the messages and queries by selector
     ///
     /// # Parameters
     /// - `target`: The type to implement Router for
@@ -402,6 +439,7 @@ impl APIBuilder {
         let routes = &self.routes;
         let query_routes = &self.query_routes;
         let system_routes = &self.system_routes;
+        let selector_names = &self.selector_names;
         push_item(
             &mut self.items,
             quote! {
@@ -420,6 +458,10 @@ impl APIBuilder {
                         &::ixc::core::routing::sort_routes([
                             #(#system_routes)*
                         ]);
+
+                    const SELECTOR_NAMES: &'static [(::ixc::message_api::message::MessageSelector, &'static str)] = &[
+                        #(#selector_names)*
+                    ];
                 }
             },
         )
@@ -1,5 +1,8 @@
+use crate::address::{build_on_register_address_handler, check_on_register_address_fn};
 use crate::api_builder::APIBuilder;
+use crate::auth::{build_on_authenticate_handler, check_on_authenticate_fn};
 use crate::migration::{build_on_migrate_handler, collect_on_migrate_info, OnMigrateInfo};
+use crate::storage_deposit::{build_on_storage_deposit_handler, check_on_storage_deposit_fn};
 use crate::util::{maybe_extract_attribute, push_item};
 use core::borrow::Borrow;
 use manyhow::{bail, manyhow};
@@ -38,6 +41,15 @@ pub(crate) fn handler(attr: TokenStream2, mut item: ItemMod) -> manyhow::Result<
     // handles for all on_migrate functions are generated here
     build_on_migrate_handler(&mut builder, &publish_fns)?;
 
+    // the handler for the on_authenticate function is generated here
+    build_on_authenticate_handler(&mut builder, &publish_fns)?;
+
+    // the handler for the on_storage_deposit function is generated here
+    build_on_storage_deposit_handler(&mut builder, &publish_fns)?;
+
+    // the handler for the on_register_address function is generated here
+    build_on_register_address_handler(&mut builder, &publish_fns)?;
+
     // the client struct and its trait implementation are generated here
     let client_ident = format_ident!("{}Client", handler);
     builder.define_client(&client_ident)?;
@@ -108,6 +120,25 @@ pub(crate) fn handler(attr: TokenStream2, mut item: ItemMod) -> manyhow::Result<
         });
     }
 
+    // messages and queries of the handler and its published traits must not share selectors
+    // with each other or with the system messages, which is checked at compile time
+    let mut selector_tables =
+        vec![quote! { <#handler as ::ixc::core::routing::Router>::SELECTOR_NAMES }];
+    for publish_trait in publish_traits.iter() {
+        let trait_ident = &publish_trait.ident;
+        selector_tables
+            .push(quote! { <dyn #trait_ident as ::ixc::core::routing::Router>::SELECTOR_NAMES });
+    }
+    push_item(
+        items,
+        quote! {
+            const _: () = ::ixc::core::routing::check_selectors(&[
+                #(#selector_tables,)*
+                ::ixc::core::routing::SYSTEM_SELECTORS,
+            ]);
+        },
+    )?;
+
     let not_handled = quote! {
         Err(::ixc::message_api::error::HandlerError::new(::ixc::message_api::code::SystemCode::MessageNotHandled.into()))
     };
@@ -117,6 +148,10 @@ pub(crate) fn handler(attr: TokenStream2, mut item: ItemMod) -> manyhow::Result<
         quote! {
             impl ::ixc::message_api::handler::RawHandler for #handler {
                 fn handle_msg<'a>(&self, caller: &::ixc::message_api::AccountID, message_packet: &::ixc::message_api::message::Message, callbacks: &mut dyn ::ixc::message_api::handler::HostBackend, allocator: &'a dyn ::ixc::message_api::handler::Allocator) -> ::core::result::Result<::ixc::message_api::message::Response<'a>, ::ixc::message_api::error::HandlerError> {
+                    // query messages are routed to the query handlers
+                    if message_packet.request().volatility() == ::ixc::message_api::message::Volatility::Query {
+                        return ::ixc::message_api::handler::RawHandler::handle_query(self, message_packet, callbacks, allocator)
+                    }
                     let sel = message_packet.request().message_selector();
                     if let Some(rt) = ::ixc::core::routing::find_route(<#handler as ::ixc::core::routing::Router>::SORTED_MSG_ROUTES, sel) {
                         return rt(self, caller, message_packet, callbacks, allocator)
@@ -128,6 +163,7 @@ pub(crate) fn handler(attr: TokenStream2, mut item: ItemMod) -> manyhow::Result<
                 }
 
                 fn handle_query<'a>(&self, message_packet: &::ixc::message_api::message::Message, callbacks: &dyn ::ixc::message_api::handler::HostBackend, allocator: &'a dyn ::ixc::message_api::handler::Allocator) -> ::core::result::Result<::ixc::message_api::message::Response<'a>, ::ixc::message_api::error::HandlerError> {
+                    ::ixc::core::routing::ensure_query(message_packet)?;
                     let sel = message_packet.request().message_selector();
                     if let Some(rt) = ::ixc::core::routing::find_route(<#handler as ::ixc::core::routing::Router>::SORTED_QUERY_ROUTES, sel) {
                         return rt(self, message_packet, callbacks, allocator)
@@ -222,16 +258,42 @@ fn collect_publish_targets(
                     let publish = maybe_extract_attribute(impl_fn)?;
                     // check if the function has the #[on_migrate] attribute
                     let on_migrate = maybe_extract_attribute(impl_fn)?;
+                    // check if the function has the #[on_authenticate] attribute
+                    let on_authenticate: Option<OnAuthenticateAttr> =
+                        maybe_extract_attribute(impl_fn)?;
+                    // check if the function has the #[on_storage_deposit] attribute
+                    let on_storage_deposit: Option<OnStorageDepositAttr> =
+                        maybe_extract_attribute(impl_fn)?;
+                    // check if the function has the #[on_register_address] attribute
+                    let on_register_address: Option<OnRegisterAddressAttr> =
+                        maybe_extract_attribute(impl_fn)?;
                     let attr_count = on_create.is_some() as usize
                         + publish.is_some() as usize
-                        + on_migrate.is_some() as usize;
+                        + on_migrate.is_some() as usize
+                        + on_authenticate.is_some() as usize
+                        + on_storage_deposit.is_some() as usize
+                        + on_register_address.is_some() as usize;
                     if attr_count > 1 {
-                        bail!("only one of #[on_create], #[publish], or #[on_migrate] can be attached to a function");
+                        bail!("only one of #[on_create], #[publish], #[on_migrate], #[on_authenticate], #[on_storage_deposit] or #[on_register_address] can be attached to a function");
                     }
                     // we define a publish attribute for the fn if it is annotated directly with #[publish] or if the impl block has #[publish]
-                    let publish = publish_all.clone().or(publish);
+                    // on_authenticate, on_storage_deposit and on_register_address fns are never published as regular messages
+                    let publish = if on_authenticate.is_none()
+                        && on_storage_deposit.is_none()
+                        && on_register_address.is_none()
+                    {
+                        publish_all.clone().or(publish)
+                    } else {
+                        None
+                    };
                     // if it's either a publish fn or an on_create fn
-                    if publish.is_some() || on_create.is_some() || on_migrate.is_some() {
+                    if publish.is_some()
+                        || on_create.is_some()
+                        || on_migrate.is_some()
+                        || on_authenticate.is_some()
+                        || on_storage_deposit.is_some()
+                        || on_register_address.is_some()
+                    {
                         let ty = if let Some(on_create) = on_create {
                             PublishedFnType::OnCreate { attr: on_create }
                         } else if let Some(publish) = publish {
@@ -242,6 +304,15 @@ fn collect_publish_targets(
                             PublishedFnType::OnMigrate(collect_on_migrate_info(
                                 impl_fn, on_migrate,
                             )?)
+                        } else if on_authenticate.is_some() {
+                            check_on_authenticate_fn(impl_fn)?;
+                            PublishedFnType::OnAuthenticate
+                        } else if on_storage_deposit.is_some() {
+                            check_on_storage_deposit_fn(impl_fn)?;
+                            PublishedFnType::OnStorageDeposit
+                        } else if on_register_address.is_some() {
+                            check_on_register_address_fn(impl_fn)?;
+                            PublishedFnType::OnRegisterAddress
                         } else {
                             unreachable!()
                         };
@@ -280,6 +351,21 @@ pub(crate) struct OnCreateAttr {
 #[deluxe(attributes(on_migrate))]
 pub(crate) struct OnMigrateAttr {}
 
+/// Represents the data in an #[on_authenticate] attribute.
+#[derive(deluxe::ExtractAttributes, Debug)]
+#[deluxe(attributes(on_authenticate))]
+pub(crate) struct OnAuthenticateAttr {}
+
+/// Represents the data in an #[on_storage_deposit] attribute.
+#[derive(deluxe::ExtractAttributes, Debug)]
+#[deluxe(attributes(on_storage_deposit))]
+pub(crate) struct OnStorageDepositAttr {}
+
+/// Represents the data in an #[on_register_address] attribute.
+#[derive(deluxe::ExtractAttributes, Debug)]
+#[deluxe(attributes(on_register_address))]
+pub(crate) struct OnRegisterAddressAttr {}
+
 /// Represents the data in an #[on_migrate] attribute.
 #[derive(deluxe::ExtractAttributes, Debug)]
 #[deluxe(attributes(from))]
@@ -303,6 +389,9 @@ pub(crate) enum PublishedFnType {
     Publish { attr: Option<PublishAttr> },
     OnCreate { attr: OnCreateAttr },
     OnMigrate(OnMigrateInfo),
+    OnAuthenticate,
+    OnStorageDeposit,
+    OnRegisterAddress,
 }
 
 /// Describes a trait that is implemented by a handler.
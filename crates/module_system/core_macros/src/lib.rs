@@ -2,12 +2,15 @@
 //! This is a macro utility crate for ixc_core.
 #![allow(unused)]
 
+mod address;
 mod api_builder;
+mod auth;
 mod handler;
 mod handler_api;
 mod message_selector;
 mod migration;
 mod resources;
+mod storage_deposit;
 mod util;
 
 //TODO remove
@@ -83,6 +86,53 @@ pub fn publish(_attr: TokenStream2, _item: TokenStream2) -> manyhow::Result<Toke
     bail!("the #[publish] attribute is being used in the wrong context, possibly #[handler] has not been applied to the enclosing module")
 }
 
+/// This attribute macro should be attached to the fn which is called to authenticate
+/// transactions sent by an account.
+///
+/// The function must take `&self`, `&mut Context` and `&AuthRequest` parameters
+/// and return `Result<()>`. It is called by the account manager before any of
+/// the transaction's messages are run and should verify the transaction's credentials
+/// against its sign bytes and check its nonce.
+/// Returning an error rejects the transaction.
+#[manyhow]
+#[proc_macro_attribute]
+pub fn on_authenticate(_attr: TokenStream2, _item: TokenStream2) -> manyhow::Result<TokenStream2> {
+    bail!("the #[on_authenticate] attribute is being used in the wrong context, possibly #[handler] has not been applied to the enclosing module")
+}
+
+/// This attribute macro should be attached to the fn which is called to charge or refund
+/// storage deposits when this handler is configured as the storage deposit account.
+///
+/// The function must take `&self`, `&mut Context` and `&StorageDepositRequest` parameters
+/// and return `Result<()>`. It is called by the account manager whenever an account's
+/// storage usage grows or shrinks and should move the deposit from or to the account.
+/// Returning an error, for instance because the account can't pay the deposit,
+/// fails the state update which required the deposit.
+#[manyhow]
+#[proc_macro_attribute]
+pub fn on_storage_deposit(
+    _attr: TokenStream2,
+    _item: TokenStream2,
+) -> manyhow::Result<TokenStream2> {
+    bail!("the #[on_storage_deposit] attribute is being used in the wrong context, possibly #[handler] has not been applied to the enclosing module")
+}
+
+/// This attribute macro should be attached to the fn which is called to register the address
+/// of each new account when this handler runs the system address registry account.
+///
+/// The function must take `&self`, `&mut Context` and `AccountID` parameters
+/// and return `Result<()>`. It is called by the account manager after an account's
+/// #[on_create] function has succeeded and should register the account's address.
+/// Returning an error fails the creation of the account.
+#[manyhow]
+#[proc_macro_attribute]
+pub fn on_register_address(
+    _attr: TokenStream2,
+    _item: TokenStream2,
+) -> manyhow::Result<TokenStream2> {
+    bail!("the #[on_register_address] attribute is being used in the wrong context, possibly #[handler] has not been applied to the enclosing module")
+}
+
 /// This attribute macro should be attached to the fn which is called when an account is created.
 #[manyhow]
 #[proc_macro_attribute]
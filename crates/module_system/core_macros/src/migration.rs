@@ -110,6 +110,7 @@ pub(crate) fn build_on_migrate_handler(
                             #(#cases)*
                             _ => return Err(::ixc::message_api::code::ErrorCode::SystemCode(::ixc::message_api::code::SystemCode::MessageNotHandled).into()),
                         };
+                        let res = ::ixc::core::low_level::flush_state_cache_on_success(&mut ctx, res);
                         ::ixc::core::low_level::encode_default_response(res)
                     }
                 })
@@ -38,23 +38,29 @@ pub(crate) fn derive_resources(input: DeriveInput) -> manyhow::Result<TokenStrea
             let value_names = state.value.iter().map(|s| {
                 quote! { stringify!(#s) }
             });
+            let index_names = state.index.iter().map(|s| {
+                quote! { stringify!(#s) }
+            });
             visit_state_objects.push(quote! {
                ::ixc::core::resource::extract_state_object_descriptor::<#ty, V>(visitor, #prefix,
                     stringify!(#field_name),
                     &[#(#key_names),*],
-                    &[#(#value_names),*]
+                    &[#(#value_names),*],
+                    &[#(#index_names),*]
                 );
             });
             // increment the automatic prefix
             prefix += 1;
             // TODO use the key and value attributes to populate the schema of the state object
         } else if let Some(client) = maybe_extract_attribute::<_, ClientAttr>(field)? {
-            // extract the account ID from the client attribute
-            // TODO read the account ID from the environment based on a name
+            // extract the default account ID from the client attribute
             let account_id = client.0;
-            // add the client field to the initializers
+            // add the client field to the initializers, resolving the account ID by the field name
+            // so that the environment can bind it to a different account
             field_inits.push(quote! {
-                #field_name: <#ty as ::ixc::core::handler::Client>::new(::ixc::message_api::AccountID::new(#account_id))
+                #field_name: <#ty as ::ixc::core::handler::Client>::new(
+                    scope.resolve_account(stringify!(#field_name), Some(::ixc::message_api::AccountID::new(#account_id)))?
+                )
             });
             visit_clients.push(quote! {
                 visitor.visit_client::<#ty>(stringify!(#field_name), &#account_id.into());
@@ -99,6 +105,8 @@ struct StateAttr {
     key: Vec<Ident>,
     #[deluxe(default)]
     value: Vec<Ident>,
+    #[deluxe(default)]
+    index: Vec<Ident>,
 }
 
 /// The data in a #[client] attribute.
@@ -0,0 +1,55 @@
+use crate::api_builder::APIBuilder;
+use crate::handler::{PublishedFnInfo, PublishedFnType};
+use manyhow::bail;
+use quote::quote;
+use syn::{FnArg, ImplItemFn};
+
+/// Checks the signature of a function annotated with #[on_storage_deposit].
+pub(crate) fn check_on_storage_deposit_fn(item_fn: &ImplItemFn) -> manyhow::Result<()> {
+    let fn_name = &item_fn.sig.ident;
+    if item_fn.sig.inputs.len() != 3 {
+        bail!("error with fn {}: expected exactly 3 arguments in on_storage_deposit function: &self, &mut Context and &StorageDepositRequest", fn_name);
+    }
+    match &item_fn.sig.inputs[0] {
+        FnArg::Receiver(r) if r.mutability.is_none() => Ok(()),
+        _ => bail!(
+            "error with fn {}: the first argument of on_storage_deposit function must be &self",
+            fn_name
+        ),
+    }
+}
+
+/// Generates the system route for the #[on_storage_deposit] function, if there is one.
+pub(crate) fn build_on_storage_deposit_handler(
+    builder: &mut APIBuilder,
+    published_fn_info: &[PublishedFnInfo],
+) -> manyhow::Result<()> {
+    let mut fn_names = published_fn_info
+        .iter()
+        .filter(|fn_info| matches!(fn_info.ty, PublishedFnType::OnStorageDeposit))
+        .map(|fn_info| &fn_info.signature.ident);
+    let Some(fn_name) = fn_names.next() else {
+        return Ok(());
+    };
+    if fn_names.next().is_some() {
+        bail!("only one function can be annotated with #[on_storage_deposit]");
+    }
+    builder.system_routes.push(quote! {
+        (::ixc::core::storage_deposit::STORAGE_DEPOSIT_SELECTOR, | h: & Self, caller, packet, cb, a | {
+            unsafe {
+                let request = packet.request();
+                let deposit_request = ::ixc::core::storage_deposit::StorageDepositRequest {
+                    account: request.in1().expect_account_id()?,
+                    amount: request.in2().expect_u128()?,
+                    refund: request.in3().expect_u64()? != 0,
+                };
+                let mem =::ixc::schema::mem::MemoryManager::new();
+                let mut ctx =::ixc::core::Context::new_mut(&packet.target_account(), caller, cb, &mem);
+                let res = h.#fn_name(&mut ctx, &deposit_request);
+                let res = ::ixc::core::low_level::flush_state_cache_on_success(&mut ctx, res);
+                ::ixc::core::low_level::encode_default_response(res)
+            }
+        })
+    });
+    Ok(())
+}
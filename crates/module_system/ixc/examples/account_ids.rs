@@ -0,0 +1,82 @@
+#![allow(missing_docs)]
+#[ixc::handler(Ephemeral)]
+pub mod ephemeral {
+    use ixc::*;
+    use ixc_core::account_api;
+
+    /// An account which can be deleted once it is no longer needed.
+    #[derive(Resources)]
+    pub struct Ephemeral {}
+
+    #[publish]
+    impl Ephemeral {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn delete(&self, ctx: &mut Context) -> Result<()> {
+            unsafe { Ok(account_api::self_destruct(ctx)?) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ephemeral::*;
+    use ixc::*;
+    use ixc_core::handler::Client;
+    use ixc_testing::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_tx_scoped_ids() {
+        let app = TestApp::default();
+        app.register_handler::<Ephemeral>().unwrap();
+        app.enable_tx_scoped_ids();
+        app.set_block_height(1);
+        let mut alice = app.new_client_context().unwrap();
+        let mut bob = app.new_client_context().unwrap();
+
+        // two transactions in the same block get IDs derived from their own positions
+        let a = create_account::<Ephemeral>(&mut alice, EphemeralCreate {}).unwrap();
+        let b = create_account::<Ephemeral>(&mut bob, EphemeralCreate {}).unwrap();
+        let (a_id, b_id) = (
+            u128::from(a.target_account()),
+            u128::from(b.target_account()),
+        );
+        assert_eq!(a_id >> 64, 2);
+        assert_eq!(b_id >> 64, 2);
+        assert_eq!((b_id >> 32) as u32, (a_id >> 32) as u32 + 1);
+
+        // as do transactions in a later block
+        app.set_block_height(2);
+        let c = create_account::<Ephemeral>(&mut alice, EphemeralCreate {}).unwrap();
+        let c_id = u128::from(c.target_account());
+        assert_eq!(c_id >> 64, 3);
+        assert_eq!((c_id >> 32) as u32, 0);
+
+        // the IDs of deleted accounts are never handed out again
+        let mut ids = BTreeSet::from([
+            alice.self_account_id(),
+            bob.self_account_id(),
+            a.target_account(),
+            b.target_account(),
+            c.target_account(),
+        ]);
+        assert_eq!(ids.len(), 5);
+        a.delete(&mut alice).unwrap();
+        b.delete(&mut bob).unwrap();
+        for _ in 0..3 {
+            let d = create_account::<Ephemeral>(&mut alice, EphemeralCreate {}).unwrap();
+            assert!(ids.insert(d.target_account()));
+        }
+        app.set_block_height(3);
+        let e = create_account::<Ephemeral>(&mut bob, EphemeralCreate {}).unwrap();
+        assert!(ids.insert(e.target_account()));
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<ephemeral::Ephemeral>().unwrap();
+}
@@ -0,0 +1,147 @@
+#![allow(missing_docs)]
+#[ixc::handler(Token)]
+pub mod token {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Token {
+        #[state(prefix = 1)]
+        owner: Item<AccountID>,
+        #[state(prefix = 2)]
+        supply: Item<u128>,
+        #[state(prefix = 3, key(account), value(balance))]
+        balances: Map<AccountID, u128>,
+    }
+
+    #[publish]
+    impl Token {
+        #[on_create]
+        pub fn create(&self, ctx: &mut Context) -> Result<()> {
+            let owner = ctx.caller();
+            self.owner.set(ctx, owner)?;
+            Ok(())
+        }
+
+        /// Mints tokens to the account and returns the new supply.
+        pub fn mint(&self, ctx: &mut Context, to: AccountID, amount: u128) -> Result<u128> {
+            ensure!(
+                ctx.caller() == self.owner.get(ctx)?,
+                "only the owner can mint"
+            );
+            let supply = self.supply.get(ctx)? + amount;
+            self.supply.set(ctx, supply)?;
+            let balance = self.balances.get(ctx, to)?.unwrap_or(0);
+            self.balances.set(ctx, to, balance + amount)?;
+            Ok(supply)
+        }
+
+        pub fn send(&self, ctx: &mut Context, to: AccountID, amount: u128) -> Result<()> {
+            let from = ctx.caller();
+            let from_balance = self.balances.get(ctx, from)?.unwrap_or(0);
+            ensure!(from_balance >= amount, "insufficient balance");
+            let to_balance = self.balances.get(ctx, to)?.unwrap_or(0);
+            self.balances.set(ctx, from, from_balance - amount)?;
+            self.balances.set(ctx, to, to_balance + amount)?;
+            Ok(())
+        }
+
+        pub fn balance(&self, ctx: &Context, account: AccountID) -> Result<u128> {
+            Ok(self.balances.get(ctx, account)?.unwrap_or(0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::token::*;
+    use ixc::{create_account, AccountID};
+    use ixc_core::handler::Client;
+    use ixc_core::low_level::{dynamic_invoke_batch, encode_message_packet};
+    use ixc_message_api::message::Message;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    fn mint(mem: &MemoryManager, token: AccountID, to: AccountID, amount: u128) -> Message<'_> {
+        encode_message_packet(mem, token, TokenMint { to, amount }).unwrap()
+    }
+
+    fn send(mem: &MemoryManager, token: AccountID, to: AccountID, amount: u128) -> Message<'_> {
+        encode_message_packet(mem, token, TokenSend { to, amount }).unwrap()
+    }
+
+    #[test]
+    fn test_batch() {
+        let app = TestApp::default();
+        app.register_handler::<Token>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let alice_id = alice.self_account_id();
+        let bob_id = app.new_client_context().unwrap().self_account_id();
+        let foo = create_account::<Token>(&mut alice, TokenCreate {}).unwrap();
+        let bar = create_account::<Token>(&mut alice, TokenCreate {}).unwrap();
+        let (foo_id, bar_id) = (foo.target_account(), bar.target_account());
+        let mem = alice.memory_manager();
+
+        // alice has no bar tokens, so the third message fails and the whole batch is rolled back
+        let messages = [
+            mint(mem, foo_id, alice_id, 100),
+            send(mem, foo_id, bob_id, 40),
+            send(mem, bar_id, bob_id, 10),
+        ];
+        let err = dynamic_invoke_batch(&mut alice, &messages, None)
+            .err()
+            .unwrap();
+        assert_eq!(err.index, Some(2));
+        assert_eq!(foo.balance(&alice, alice_id).unwrap(), 0);
+        assert_eq!(foo.balance(&alice, bob_id).unwrap(), 0);
+
+        // with bar minted first, every message succeeds
+        let messages = [
+            mint(mem, foo_id, alice_id, 100),
+            mint(mem, bar_id, alice_id, 10),
+            send(mem, foo_id, bob_id, 40),
+            send(mem, bar_id, bob_id, 10),
+        ];
+        let responses = dynamic_invoke_batch(&mut alice, &messages, None).unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses.decode::<TokenMint>(&alice, 0).unwrap(), 100);
+        assert_eq!(responses.decode::<TokenMint>(&alice, 1).unwrap(), 10);
+        assert!(responses.decode::<TokenMint>(&alice, 4).is_err());
+        assert_eq!(foo.balance(&alice, alice_id).unwrap(), 60);
+        assert_eq!(foo.balance(&alice, bob_id).unwrap(), 40);
+        assert_eq!(bar.balance(&alice, alice_id).unwrap(), 0);
+        assert_eq!(bar.balance(&alice, bob_id).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_test_app_batch() {
+        let app = TestApp::default();
+        app.register_handler::<Token>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let alice_id = alice.self_account_id();
+        let bob_id = app.new_client_context().unwrap().self_account_id();
+        let foo = create_account::<Token>(&mut alice, TokenCreate {}).unwrap();
+        let foo_id = foo.target_account();
+        let mem = alice.memory_manager();
+        let messages = [mint(mem, foo_id, bob_id, 5), send(mem, foo_id, alice_id, 5)];
+
+        // bob isn't the owner, so the first message fails
+        let res = app.invoke_batch(bob_id, &messages);
+        assert!(matches!(res, Err(TxError::MessageFailed { index: 0, .. })));
+
+        // alice can mint to bob, but then can't send bob's tokens
+        let res = app.invoke_batch(alice_id, &messages);
+        assert!(matches!(res, Err(TxError::MessageFailed { index: 1, .. })));
+        assert_eq!(foo.balance(&alice, bob_id).unwrap(), 0);
+
+        let responses = app.invoke_batch(alice_id, &messages[..1]).unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(foo.balance(&alice, bob_id).unwrap(), 5);
+        let responses = app.invoke_batch(bob_id, &messages[1..]).unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(foo.balance(&alice, alice_id).unwrap(), 5);
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<token::Token>().unwrap();
+}
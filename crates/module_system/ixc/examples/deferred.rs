@@ -0,0 +1,234 @@
+#![allow(missing_docs)]
+#[ixc::handler(Counter)]
+pub mod counter {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Counter {
+        #[state(prefix = 1)]
+        value: Item<u64>,
+        #[state(prefix = 2)]
+        last_caller: Item<AccountID>,
+    }
+
+    #[publish]
+    impl Counter {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn get(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.value.get(ctx)?)
+        }
+
+        pub fn last_caller(&self, ctx: &Context) -> Result<AccountID> {
+            Ok(self.last_caller.get(ctx)?)
+        }
+
+        pub fn inc(&self, ctx: &mut Context) -> Result<u64> {
+            ctx.consume_gas(100)?;
+            let value = self.value.get(ctx)? + 1;
+            self.value.set(ctx, value)?;
+            self.last_caller.set(ctx, ctx.caller())?;
+            Ok(value)
+        }
+
+        pub fn inc_and_fail(&self, ctx: &mut Context) -> Result<()> {
+            self.inc(ctx)?;
+            bail!("failed after incrementing")
+        }
+    }
+}
+
+#[ixc::handler(Scheduler)]
+pub mod scheduler {
+    use crate::counter::{CounterInc, CounterIncAndFail};
+    use ixc::*;
+    use ixc_core::deferred::{defer, Trigger};
+
+    #[derive(Resources)]
+    pub struct Scheduler {}
+
+    #[publish]
+    impl Scheduler {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn inc_at_end_of_tx(&self, ctx: &mut Context, counter: AccountID) -> Result<()> {
+            Ok(defer(ctx, counter, CounterInc {}, Trigger::EndOfTx)?)
+        }
+
+        pub fn inc_at_end_of_block(&self, ctx: &mut Context, counter: AccountID) -> Result<()> {
+            Ok(defer(ctx, counter, CounterInc {}, Trigger::EndOfBlock)?)
+        }
+
+        pub fn inc_at_height(
+            &self,
+            ctx: &mut Context,
+            counter: AccountID,
+            height: u64,
+        ) -> Result<()> {
+            Ok(defer(
+                ctx,
+                counter,
+                CounterInc {},
+                Trigger::AtHeight(height),
+            )?)
+        }
+
+        pub fn inc_at_time(&self, ctx: &mut Context, counter: AccountID, time: Time) -> Result<()> {
+            Ok(defer(ctx, counter, CounterInc {}, Trigger::AtTime(time))?)
+        }
+
+        pub fn fail_at_end_of_block(&self, ctx: &mut Context, counter: AccountID) -> Result<()> {
+            Ok(defer(
+                ctx,
+                counter,
+                CounterIncAndFail {},
+                Trigger::EndOfBlock,
+            )?)
+        }
+
+        /// Defers an increment and another call to this method to the end of the transaction,
+        /// so that there are always more messages deferred.
+        pub fn inc_and_repeat_at_end_of_tx(
+            &self,
+            ctx: &mut Context,
+            counter: AccountID,
+        ) -> Result<()> {
+            defer(ctx, counter, CounterInc {}, Trigger::EndOfTx)?;
+            let repeat = SchedulerIncAndRepeatAtEndOfTx { counter };
+            Ok(defer(ctx, ctx.self_account_id(), repeat, Trigger::EndOfTx)?)
+        }
+
+        /// Defers an increment and then fails, so the deferred message is discarded.
+        pub fn inc_at_end_of_tx_and_fail(
+            &self,
+            ctx: &mut Context,
+            counter: AccountID,
+        ) -> Result<()> {
+            defer(ctx, counter, CounterInc {}, Trigger::EndOfTx)?;
+            bail!("failed after deferring")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counter::*;
+    use super::scheduler::*;
+    use ixc::*;
+    use ixc_core::block_info::{get_block_height, get_block_time, get_chain_id};
+    use ixc_core::handler::Client;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_deferred() {
+        let app = TestApp::default();
+        app.register_handler::<Counter>().unwrap();
+        app.register_handler::<Scheduler>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let counter = create_account::<Counter>(&mut alice, CounterCreate {}).unwrap();
+        let scheduler = create_account::<Scheduler>(&mut alice, SchedulerCreate {}).unwrap();
+        let counter_id = counter.target_account();
+        let end_block = |height, secs, gas_budget| {
+            app.set_block_height(height);
+            app.set_block_time(Time::from_unix_secs(secs));
+            app.end_block(gas_budget).unwrap()
+        };
+
+        // end-of-tx messages run right after the message which deferred them,
+        // with the deferring account as the caller
+        assert_eq!(counter.get(&alice).unwrap(), 0);
+        scheduler.inc_at_end_of_tx(&mut alice, counter_id).unwrap();
+        assert_eq!(counter.get(&alice).unwrap(), 1);
+        assert_eq!(
+            counter.last_caller(&alice).unwrap(),
+            scheduler.target_account()
+        );
+
+        // messages deferred by a failed message are discarded with its other state changes
+        assert!(scheduler
+            .inc_at_end_of_tx_and_fail(&mut alice, counter_id)
+            .is_err());
+        assert_eq!(counter.get(&alice).unwrap(), 1);
+
+        // end-of-block messages run when the block ends
+        scheduler
+            .inc_at_end_of_block(&mut alice, counter_id)
+            .unwrap();
+        assert_eq!(counter.get(&alice).unwrap(), 1);
+        let res = end_block(1, 10, u64::MAX);
+        assert_eq!(res.executed, 1);
+        assert_eq!(res.pending, 0);
+        assert_eq!(counter.get(&alice).unwrap(), 2);
+
+        // scheduled messages run at the end of the first block which reaches their height or time
+        scheduler.inc_at_height(&mut alice, counter_id, 3).unwrap();
+        scheduler
+            .inc_at_time(&mut alice, counter_id, Time::from_unix_secs(30))
+            .unwrap();
+        let res = end_block(2, 20, u64::MAX);
+        assert_eq!(res.executed, 0);
+        let res = end_block(3, 25, u64::MAX);
+        assert_eq!(res.executed, 1);
+        assert_eq!(counter.get(&alice).unwrap(), 3);
+        let res = end_block(4, 40, u64::MAX);
+        assert_eq!(res.executed, 1);
+        assert_eq!(counter.get(&alice).unwrap(), 4);
+
+        // due messages which don't fit in the gas budget wait for the next block
+        for _ in 0..3 {
+            scheduler
+                .inc_at_end_of_block(&mut alice, counter_id)
+                .unwrap();
+        }
+        let res = end_block(5, 50, 250);
+        assert_eq!(res.executed, 2);
+        // the message which ran out of gas still used up the rest of the budget
+        assert_eq!(res.gas_consumed, 250);
+        assert_eq!(res.pending, 1);
+        assert_eq!(counter.get(&alice).unwrap(), 6);
+        let res = end_block(6, 60, 250);
+        assert_eq!(res.executed, 1);
+        assert_eq!(res.pending, 0);
+        assert_eq!(counter.get(&alice).unwrap(), 7);
+
+        // a failed deferred message is rolled back without affecting the others
+        scheduler
+            .fail_at_end_of_block(&mut alice, counter_id)
+            .unwrap();
+        scheduler
+            .inc_at_end_of_block(&mut alice, counter_id)
+            .unwrap();
+        let res = end_block(7, 70, u64::MAX);
+        assert_eq!(res.executed, 2);
+        assert_eq!(res.failed, 1);
+        assert_eq!(counter.get(&alice).unwrap(), 8);
+
+        // end-of-tx messages only run the messages queued before draining started,
+        // so the ones they defer are carried over to the end of the block
+        scheduler
+            .inc_and_repeat_at_end_of_tx(&mut alice, counter_id)
+            .unwrap();
+        assert_eq!(counter.get(&alice).unwrap(), 9);
+        let res = end_block(8, 80, u64::MAX);
+        assert_eq!(res.executed, 2);
+        // the repeated message drained the messages it deferred in its own transaction
+        assert_eq!(counter.get(&alice).unwrap(), 11);
+
+        // the block info account serves the block which was set up by the test app
+        app.set_chain_id("test-chain");
+        app.advance_block_height(1);
+        assert_eq!(get_block_height(&alice).unwrap(), 9);
+        assert_eq!(get_block_time(&alice).unwrap(), Time::from_unix_secs(80));
+        assert_eq!(get_chain_id(&alice).unwrap(), "test-chain");
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<scheduler::Scheduler>().unwrap();
+}
@@ -0,0 +1,238 @@
+#![allow(missing_docs)]
+#[ixc::handler(Logger)]
+pub mod logger {
+    use ixc::*;
+    use ixc_core::auth::AuthRequest;
+    use ixc_core::handler::Client;
+
+    #[derive(Resources)]
+    pub struct Logger {}
+
+    #[derive(SchemaValue, Clone, Default, Debug, Eq, PartialEq)]
+    #[non_exhaustive]
+    pub struct EventLogged {
+        pub value: u64,
+    }
+
+    #[publish]
+    impl Logger {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        /// Accepts every transaction so that loggers can send them in tests.
+        #[on_authenticate]
+        pub fn authenticate(&self, _ctx: &mut Context, _req: &AuthRequest) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn log(
+            &self,
+            ctx: &mut Context,
+            value: u64,
+            mut evt: EventBus<EventLogged>,
+        ) -> Result<()> {
+            evt.emit(ctx, &EventLogged { value })?;
+            Ok(())
+        }
+
+        /// Logs the value and then asks the other logger to log it too.
+        pub fn relay(
+            &self,
+            ctx: &mut Context,
+            to: AccountID,
+            value: u64,
+            mut evt: EventBus<EventLogged>,
+        ) -> Result<()> {
+            evt.emit(ctx, &EventLogged { value })?;
+            LoggerClient::new(to).log(ctx, value + 1)?;
+            Ok(())
+        }
+
+        pub fn log_and_fail(
+            &self,
+            ctx: &mut Context,
+            value: u64,
+            mut evt: EventBus<EventLogged>,
+        ) -> Result<()> {
+            evt.emit(ctx, &EventLogged { value })?;
+            bail!("failed after logging")
+        }
+    }
+}
+
+#[ixc::handler(Escrow)]
+pub mod escrow {
+    use ixc::core::storage_deposit::StorageDepositRequest;
+    use ixc::*;
+
+    /// A storage deposit account which accepts every deposit without keeping balances.
+    #[derive(Resources)]
+    pub struct Escrow {}
+
+    #[publish]
+    impl Escrow {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        #[on_storage_deposit]
+        pub fn on_storage_deposit(
+            &self,
+            _ctx: &mut Context,
+            _req: &StorageDepositRequest,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escrow::*;
+    use super::logger::*;
+    use ixc::create_account;
+    use ixc_core::handler::Client;
+    use ixc_core::low_level::encode_message_packet;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_event_positions() {
+        let app = TestApp::default();
+        app.register_handler::<Logger>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let alice_id = alice.self_account_id();
+        let foo = create_account::<Logger>(&mut alice, LoggerCreate {}).unwrap();
+        let bar = create_account::<Logger>(&mut alice, LoggerCreate {}).unwrap();
+        app.set_block_height(5);
+
+        // events record the block, the transaction and the depth of the call that emitted them
+        foo.relay(&mut alice, bar.target_account(), 1).unwrap();
+        let events: Vec<EventData> = app.last_message_events().collect();
+        assert_eq!(events.len(), 2);
+        let (outer, inner) = (events[0].metadata, events[1].metadata);
+        assert_eq!(events[0].sender, foo.target_account());
+        assert_eq!(events[1].sender, bar.target_account());
+        assert_eq!(outer.block_height, 5);
+        assert_eq!(outer.tx_index, 0);
+        assert_eq!(inner.tx_index, 0);
+        assert_eq!((outer.depth, inner.depth), (0, 1));
+        assert!(inner.sequence > outer.sequence);
+
+        // the next transaction in the block has the next index
+        foo.log(&mut alice, 2).unwrap();
+        let events: Vec<EventData> = app.last_message_events().collect();
+        assert_eq!(events[0].metadata.tx_index, 1);
+        assert!(events[0].metadata.sequence > inner.sequence);
+
+        // messages in a batch record their index within it
+        let mem = alice.memory_manager();
+        let messages = [
+            encode_message_packet(mem, foo.target_account(), LoggerLog { value: 3 }).unwrap(),
+            encode_message_packet(mem, bar.target_account(), LoggerLog { value: 4 }).unwrap(),
+        ];
+        app.invoke_batch(alice_id, &messages).unwrap();
+        let events: Vec<EventData> = app.last_message_events().collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].metadata.message_index, 0);
+        assert_eq!(events[1].metadata.message_index, 1);
+        assert_eq!(events[1].metadata.tx_index, 2);
+
+        // transaction indexes restart in a new block
+        app.advance_block_height(1);
+        foo.log(&mut alice, 5).unwrap();
+        let events: Vec<EventData> = app.last_message_events().collect();
+        assert_eq!(events[0].metadata.block_height, 6);
+        assert_eq!(events[0].metadata.tx_index, 0);
+    }
+
+    fn depths(app: &TestApp) -> Vec<u32> {
+        app.last_message_events()
+            .map(|e| e.metadata.depth)
+            .collect()
+    }
+
+    #[test]
+    fn test_event_depths() {
+        let app = TestApp::default();
+        app.register_handler::<Logger>().unwrap();
+        app.register_handler::<Escrow>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let alice_id = alice.self_account_id();
+        let foo = create_account::<Logger>(&mut alice, LoggerCreate {}).unwrap();
+        let bar = create_account::<Logger>(&mut alice, LoggerCreate {}).unwrap();
+        let mem = MemoryManager::new();
+        let relay = || {
+            encode_message_packet(
+                &mem,
+                foo.target_account(),
+                LoggerRelay {
+                    to: bar.target_account(),
+                    value: 1,
+                },
+            )
+            .unwrap()
+        };
+
+        // depths count calls, so a top-level message is at depth 0 however it is sent
+        foo.relay(&mut alice, bar.target_account(), 1).unwrap();
+        assert_eq!(depths(&app), vec![0, 1]);
+        app.invoke_batch(alice_id, &[relay()]).unwrap();
+        assert_eq!(depths(&app), vec![0, 1]);
+        let messages = [relay()];
+        let tx = Tx {
+            sender: foo.target_account(),
+            nonce: 0,
+            messages: &messages,
+            credentials: &[],
+        };
+        app.invoke_tx(&tx).unwrap();
+        assert_eq!(depths(&app), vec![0, 1]);
+
+        // and the frames storage deposits open around state updates don't change them
+        let escrow = create_account::<Escrow>(&mut alice, EscrowCreate {}).unwrap();
+        app.enable_storage_deposits(StorageDepositConfig {
+            deposit_account: escrow.target_account(),
+            deposit_per_byte: 1,
+        });
+        foo.relay(&mut alice, bar.target_account(), 1).unwrap();
+        assert_eq!(depths(&app), vec![0, 1]);
+        app.invoke_batch(alice_id, &[relay()]).unwrap();
+        assert_eq!(depths(&app), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_reverted_events() {
+        let app = TestApp::default();
+        app.register_handler::<Logger>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let foo = create_account::<Logger>(&mut alice, LoggerCreate {}).unwrap();
+
+        foo.log(&mut alice, 1).unwrap();
+        assert!(app.last_reverted_events().is_empty());
+        let logged = app.last_message_events().next().unwrap();
+
+        // events of a failed message are only kept in the reverted events
+        assert!(foo.log_and_fail(&mut alice, 2).is_err());
+        let reverted = app.last_reverted_events();
+        assert_eq!(
+            reverted.select::<EventLogged>(foo.target_account()),
+            vec![EventLogged { value: 2 }]
+        );
+        let reverted = reverted.clone().next().unwrap();
+        assert!(reverted.metadata.sequence > logged.metadata.sequence);
+
+        // and their sequence numbers aren't reused
+        foo.log(&mut alice, 3).unwrap();
+        let logged = app.last_message_events().next().unwrap();
+        assert!(logged.metadata.sequence > reverted.metadata.sequence);
+        assert!(app.last_reverted_events().is_empty());
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<logger::Logger>().unwrap();
+}
@@ -0,0 +1,214 @@
+#![allow(missing_docs)]
+#[ixc::handler(Counter)]
+pub mod counter {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Counter {
+        #[state]
+        value: Item<u64>,
+        #[state]
+        ticks: Sequence,
+    }
+
+    #[publish]
+    impl Counter {
+        #[on_create]
+        pub fn create(&self, ctx: &mut Context, start: u64) -> Result<()> {
+            self.value.set(ctx, start)?;
+            Ok(())
+        }
+
+        pub fn get(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.value.get(ctx)?)
+        }
+
+        pub fn inc(&self, ctx: &mut Context) -> Result<u64> {
+            let value = self.value.get(ctx)? + 1;
+            self.value.set(ctx, value)?;
+            Ok(value)
+        }
+
+        pub fn tick(&self, ctx: &mut Context) -> Result<u64> {
+            Ok(self.ticks.next(ctx)?)
+        }
+    }
+}
+
+#[ixc::handler(Reader)]
+pub mod reader {
+    use crate::counter::Counter;
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Reader {
+        // bound to the counter system account in genesis
+        #[client(1000)]
+        counter: <Counter as Service>::Client,
+    }
+
+    #[publish]
+    impl Reader {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn read(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.counter.get(ctx)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counter::*;
+    use super::reader::*;
+    use ixc::*;
+    use ixc_core::handler::Service;
+    use ixc_testing::*;
+
+    const GENESIS: &str = r#"{
+        "handlers": ["Counter", "Reader"],
+        "bindings": {"counter": "100"},
+        "accounts": [
+            {"id": "100", "handler": "Counter", "init": {"start": "5"}},
+            {"handler": "Reader"}
+        ]
+    }"#;
+
+    fn new_app() -> TestApp {
+        let app = TestApp::default();
+        app.register_handler::<Counter>().unwrap();
+        app.register_handler::<Reader>().unwrap();
+        app
+    }
+
+    #[test]
+    fn test_genesis() {
+        let app = new_app();
+        let genesis = Genesis::from_json(GENESIS).unwrap();
+        let res = app.load_genesis(&genesis).unwrap();
+        let counter_id = AccountID::new(100);
+        let reader_id = AccountID::new(65536);
+        assert_eq!(res.accounts.len(), 2);
+        assert_eq!(res.accounts[&counter_id], "Counter");
+        assert_eq!(res.accounts[&reader_id], "Reader");
+        assert_eq!(res.state_root, app.state_root());
+
+        // the reader's client is bound to the counter created with a fixed ID
+        let mut alice = app.new_client_context().unwrap();
+        let counter = <Counter as Service>::new_client(counter_id);
+        let reader = <Reader as Service>::new_client(reader_id);
+        assert_eq!(reader.read(&alice).unwrap(), 5);
+        counter.inc(&mut alice).unwrap();
+        assert_eq!(reader.read(&alice).unwrap(), 6);
+        assert_eq!(counter.tick(&mut alice).unwrap(), 0);
+        assert_eq!(counter.tick(&mut alice).unwrap(), 1);
+
+        // genesis can only be loaded into empty state
+        assert!(matches!(
+            app.load_genesis(&genesis),
+            Err(GenesisError::StateNotEmpty)
+        ));
+
+        // exported state can be imported into a new app
+        let exported = app.export_genesis().to_json();
+        let app2 = new_app();
+        let res = app2
+            .load_genesis(&Genesis::from_json(&exported).unwrap())
+            .unwrap();
+        assert_eq!(res.state_root, app.state_root());
+        assert_eq!(res.accounts[&counter_id], "Counter");
+        assert_eq!(res.accounts[&reader_id], "Reader");
+        let mut bob = app2.client_context_for(alice.self_account_id());
+        assert_eq!(reader.read(&bob).unwrap(), 6);
+        assert_eq!(counter.inc(&mut bob).unwrap(), 7);
+        // accumulators such as sequences are exported and imported too
+        assert_eq!(counter.tick(&mut bob).unwrap(), 2);
+        // account IDs continue from the imported state
+        let account = app2.new_client_account().unwrap();
+        assert_eq!(account, AccountID::new(65538));
+    }
+
+    #[test]
+    fn test_generated_ids_skip_fixed_ids() {
+        // the first ID the app's generator assigns is taken by a fixed account
+        let app = new_app();
+        let genesis = Genesis::from_json(
+            r#"{
+                "accounts": [
+                    {"id": "65536", "handler": "Counter", "init": {"start": "1"}},
+                    {"handler": "Counter", "init": {"start": "2"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let res = app.load_genesis(&genesis).unwrap();
+        let ids: Vec<AccountID> = res.accounts.keys().copied().collect();
+        assert_eq!(ids, vec![AccountID::new(65536), AccountID::new(65537)]);
+        let alice = app.new_client_context().unwrap();
+        let fixed = <Counter as Service>::new_client(AccountID::new(65536));
+        assert_eq!(fixed.get(&alice).unwrap(), 1);
+        assert_eq!(alice.self_account_id(), AccountID::new(65538));
+
+        // fixed IDs can't be used twice or take the ID of an earlier account
+        let app = new_app();
+        let genesis = Genesis::from_json(
+            r#"{
+                "accounts": [
+                    {"id": "100", "handler": "Counter", "init": {"start": "1"}},
+                    {"id": "100", "handler": "Counter", "init": {"start": "2"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            app.load_genesis(&genesis),
+            Err(GenesisError::DuplicateAccountID(_))
+        ));
+        let genesis = Genesis::from_json(
+            r#"{
+                "accounts": [
+                    {"handler": "Counter", "init": {"start": "1"}},
+                    {"id": "65536", "handler": "Counter", "state": {}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            app.load_genesis(&genesis),
+            Err(GenesisError::DuplicateAccountID(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_genesis() {
+        let app = new_app();
+        let genesis = Genesis::from_json(r#"{"handlers": ["Unknown"]}"#).unwrap();
+        assert!(matches!(
+            app.load_genesis(&genesis),
+            Err(GenesisError::HandlerNotRegistered(_))
+        ));
+        let genesis =
+            Genesis::from_json(r#"{"accounts": [{"handler": "Counter", "init": {"start": 5}}]}"#)
+                .unwrap();
+        assert!(matches!(
+            app.load_genesis(&genesis),
+            Err(GenesisError::InvalidInit(0))
+        ));
+        let genesis = Genesis::from_json(
+            r#"{"accounts": [{"id": "100", "accumulators": {"00": "not a number"}}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            app.load_genesis(&genesis),
+            Err(GenesisError::InvalidAccumulator(_))
+        ));
+        assert!(Genesis::from_json("{").is_err());
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<reader::Reader>().unwrap();
+}
@@ -0,0 +1,143 @@
+#![allow(missing_docs)]
+#[ixc::handler(Directory)]
+pub mod directory {
+    use ixc::*;
+
+    /// A directory of named entries, which can be looked up by their owner or their name.
+    #[derive(Resources)]
+    pub struct Directory {
+        #[state(prefix = 1, key(id), value(owner, name), index(by_owner, by_name))]
+        entries: IndexedMap<u64, (AccountID, Str), (MultiIndex<0>, UniqueIndex<1>)>,
+    }
+
+    #[publish]
+    impl Directory {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn register(&self, ctx: &mut Context, id: u64, name: &str) -> Result<()> {
+            ensure!(self.entries.get(ctx, id)?.is_none(), "id taken");
+            let owner = ctx.caller();
+            self.entries.set(ctx, id, (owner, name))?;
+            Ok(())
+        }
+
+        pub fn transfer(&self, ctx: &mut Context, id: u64, to: AccountID) -> Result<()> {
+            let (owner, name) = self.entries.get(ctx, id)?.ok_or(error!("not found"))?;
+            ensure!(owner == ctx.caller(), "not the owner");
+            self.entries.set(ctx, id, (to, name.to_string().as_str()))?;
+            Ok(())
+        }
+
+        pub fn remove(&self, ctx: &mut Context, id: u64) -> Result<()> {
+            self.entries.delete(ctx, id)?;
+            Ok(())
+        }
+
+        pub fn lookup(&self, ctx: &Context, name: &str) -> Result<Option<u64>> {
+            Ok(self.entries.index::<1>().get(ctx, name)?)
+        }
+
+        pub fn owned_by(&self, ctx: &Context, owner: AccountID) -> Result<Vec<u64>> {
+            let mut ids = Vec::new();
+            for entry in self.entries.index::<0>().iter(ctx, owner)? {
+                ids.push(entry?.1);
+            }
+            Ok(ids)
+        }
+
+        pub fn ids_in_range(
+            &self,
+            ctx: &Context,
+            start: u64,
+            end: u64,
+            descending: bool,
+        ) -> Result<Vec<u64>> {
+            let order = if descending {
+                Order::Descending
+            } else {
+                Order::Ascending
+            };
+            let mut ids = Vec::new();
+            for entry in self.entries.range(ctx, Some(&start), Some(&end), order)? {
+                ids.push(entry?.0);
+            }
+            Ok(ids)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::directory::*;
+    use ixc::create_account;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_indexed_map() {
+        let app = TestApp::default();
+        app.register_handler::<Directory>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let mut bob = app.new_client_context().unwrap();
+        let (alice_id, bob_id) = (alice.self_account_id(), bob.self_account_id());
+        let dir = create_account::<Directory>(&mut alice, DirectoryCreate {}).unwrap();
+
+        dir.register(&mut alice, 3, "foo").unwrap();
+        dir.register(&mut alice, 1, "bar").unwrap();
+        dir.register(&mut bob, 2, "baz").unwrap();
+        assert_eq!(dir.lookup(&alice, "foo").unwrap(), Some(3));
+        assert_eq!(dir.lookup(&alice, "baz").unwrap(), Some(2));
+        assert_eq!(dir.lookup(&alice, "qux").unwrap(), None);
+        assert_eq!(dir.owned_by(&alice, alice_id).unwrap(), vec![1, 3]);
+        assert_eq!(dir.owned_by(&alice, bob_id).unwrap(), vec![2]);
+
+        // names are unique, and a failed update doesn't touch the map or its indexes
+        assert!(dir.register(&mut bob, 4, "foo").is_err());
+        assert_eq!(dir.lookup(&alice, "foo").unwrap(), Some(3));
+        assert_eq!(dir.owned_by(&alice, bob_id).unwrap(), vec![2]);
+
+        // updating an entry moves its index entries
+        dir.transfer(&mut alice, 3, bob_id).unwrap();
+        assert_eq!(dir.owned_by(&alice, alice_id).unwrap(), vec![1]);
+        assert_eq!(dir.owned_by(&alice, bob_id).unwrap(), vec![2, 3]);
+        assert_eq!(dir.lookup(&alice, "foo").unwrap(), Some(3));
+
+        // deleting an entry removes its index entries, freeing up its name
+        dir.remove(&mut bob, 3).unwrap();
+        assert_eq!(dir.lookup(&alice, "foo").unwrap(), None);
+        assert_eq!(dir.owned_by(&alice, bob_id).unwrap(), vec![2]);
+        dir.register(&mut bob, 4, "foo").unwrap();
+        assert_eq!(dir.lookup(&alice, "foo").unwrap(), Some(4));
+    }
+
+    #[test]
+    fn test_iteration() {
+        let app = TestApp::default();
+        app.register_handler::<Directory>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let dir = create_account::<Directory>(&mut alice, DirectoryCreate {}).unwrap();
+
+        // enough entries to span several pages of storage reads
+        for id in 0..200u64 {
+            dir.register(&mut alice, id, &format!("name{}", id))
+                .unwrap();
+        }
+        let ids = dir.ids_in_range(&alice, 0, 1000, false).unwrap();
+        assert_eq!(ids, (0..200).collect::<Vec<_>>());
+        let ids = dir.ids_in_range(&alice, 0, 1000, true).unwrap();
+        assert_eq!(ids, (0..200).rev().collect::<Vec<_>>());
+        let ids = dir.ids_in_range(&alice, 10, 150, true).unwrap();
+        assert_eq!(ids, (10..150).rev().collect::<Vec<_>>());
+        assert!(dir.ids_in_range(&alice, 150, 10, false).unwrap().is_empty());
+        assert_eq!(
+            dir.owned_by(&alice, alice.self_account_id()).unwrap().len(),
+            200
+        );
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<directory::Directory>().unwrap();
+}
@@ -0,0 +1,135 @@
+#![allow(missing_docs)]
+#[ixc::handler(Counter)]
+pub mod counter {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Counter {
+        #[state]
+        value: Item<u64>,
+    }
+
+    #[publish]
+    impl Counter {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn get(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.value.get(ctx)?)
+        }
+
+        pub fn inc(&self, ctx: &mut Context) -> Result<u64> {
+            let value = self.value.get(ctx)? + 1;
+            self.value.set(ctx, value)?;
+            Ok(value)
+        }
+    }
+}
+
+pub mod interceptors {
+    use ixc::AccountID;
+    use ixc_message_api::code::{ErrorCode, SystemCode};
+    use ixc_message_api::message::{Request, Response};
+    use ixc_testing::MessageInterceptor;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Rejects all messages sent by a blocked account before they are executed.
+    pub struct Blocklist(pub AccountID);
+
+    impl MessageInterceptor for Blocklist {
+        fn before_invoke(
+            &self,
+            caller: &AccountID,
+            _target: &AccountID,
+            _request: &Request,
+        ) -> Result<(), SystemCode> {
+            if *caller == self.0 {
+                return Err(SystemCode::UnauthorizedCallerAccess);
+            }
+            Ok(())
+        }
+    }
+
+    /// Limits the number of successful calls to an account,
+    /// rejecting (and thus rolling back) any calls which exceed the limit.
+    pub struct CallLimit {
+        pub target: AccountID,
+        pub limit: u64,
+        pub calls: Rc<Cell<u64>>,
+    }
+
+    impl MessageInterceptor for CallLimit {
+        fn after_invoke(
+            &self,
+            _caller: &AccountID,
+            target: &AccountID,
+            _request: &Request,
+            result: &Result<Response, ErrorCode>,
+        ) -> Result<(), SystemCode> {
+            if *target != self.target || result.is_err() {
+                return Ok(());
+            }
+            let calls = self.calls.get() + 1;
+            if calls > self.limit {
+                return Err(SystemCode::UnauthorizedCallerAccess);
+            }
+            self.calls.set(calls);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counter::*;
+    use super::interceptors::*;
+    use ixc_core::handler::Client;
+    use ixc_message_api::code::{ErrorCode, SystemCode};
+    use ixc_testing::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_interceptors() {
+        let app = TestApp::default();
+        app.register_handler::<Counter>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let mut bob = app.new_client_context().unwrap();
+        let counter = create_account::<Counter>(&mut alice, CounterCreate {}).unwrap();
+
+        let calls = Rc::new(Cell::new(0));
+        app.add_interceptor(Blocklist(bob.self_account_id()));
+        app.add_interceptor(CallLimit {
+            target: counter.target_account(),
+            limit: 2,
+            calls: calls.clone(),
+        });
+
+        // bob is blocked before the message is ever executed
+        let res = counter.inc(&mut bob);
+        assert_eq!(
+            res.unwrap_err().code,
+            ErrorCode::SystemCode(SystemCode::UnauthorizedCallerAccess)
+        );
+        assert_eq!(calls.get(), 0);
+
+        assert_eq!(counter.inc(&mut alice).unwrap(), 1);
+        assert_eq!(counter.inc(&mut alice).unwrap(), 2);
+
+        // the third call succeeds in the handler but is rejected afterward and rolled back
+        let res = counter.inc(&mut alice);
+        assert_eq!(
+            res.unwrap_err().code,
+            ErrorCode::SystemCode(SystemCode::UnauthorizedCallerAccess)
+        );
+        assert_eq!(counter.get(&alice).unwrap(), 2);
+        assert_eq!(calls.get(), 2);
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<counter::Counter>().unwrap();
+}
@@ -0,0 +1,135 @@
+#![allow(missing_docs)]
+#[ixc::handler(AllowList)]
+pub mod allow_list {
+    use ixc::*;
+
+    /// An allow-list of member IDs, which the admin can also block for specific denoms.
+    #[derive(Resources)]
+    pub struct AllowList {
+        #[state(prefix = 1)]
+        admin: Item<AccountID>,
+        #[state(prefix = 2, key(member))]
+        allowed: KeySet<u64>,
+        #[state(prefix = 3, key(denom, member))]
+        blocked: KeySet<(Str, u64)>,
+    }
+
+    #[publish]
+    impl AllowList {
+        #[on_create]
+        pub fn create(&self, ctx: &mut Context) -> Result<()> {
+            let admin = ctx.caller();
+            self.admin.set(ctx, admin)?;
+            Ok(())
+        }
+
+        pub fn allow(&self, ctx: &mut Context, member: u64) -> Result<()> {
+            ensure!(ctx.caller() == self.admin.get(ctx)?, "unauthorized");
+            self.allowed.insert(ctx, member)?;
+            Ok(())
+        }
+
+        pub fn disallow(&self, ctx: &mut Context, member: u64) -> Result<()> {
+            ensure!(ctx.caller() == self.admin.get(ctx)?, "unauthorized");
+            self.allowed.remove(ctx, member)?;
+            Ok(())
+        }
+
+        pub fn block(&self, ctx: &mut Context, denom: &str, member: u64) -> Result<()> {
+            ensure!(ctx.caller() == self.admin.get(ctx)?, "unauthorized");
+            self.blocked.insert(ctx, (denom, member))?;
+            Ok(())
+        }
+
+        pub fn is_allowed(&self, ctx: &Context, member: u64) -> Result<bool> {
+            Ok(self.allowed.contains(ctx, member)?)
+        }
+
+        pub fn allowed_members(&self, ctx: &Context, descending: bool) -> Result<Vec<u64>> {
+            let order = if descending {
+                Order::Descending
+            } else {
+                Order::Ascending
+            };
+            let mut members = Vec::new();
+            for member in self.allowed.range(ctx, None, None, order)? {
+                members.push(member?);
+            }
+            Ok(members)
+        }
+
+        pub fn blocked_members(&self, ctx: &Context, denom: &str) -> Result<Vec<u64>> {
+            let mut members = Vec::new();
+            for key in self.blocked.iter_prefix(ctx, denom, Order::Ascending)? {
+                members.push(key?.1);
+            }
+            Ok(members)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::allow_list::*;
+    use ixc::create_account;
+    use ixc_core::schema::extract_handler_schema;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_key_set() {
+        let app = TestApp::default();
+        app.register_handler::<AllowList>().unwrap();
+        let mut admin = app.new_client_context().unwrap();
+        let mut bob = app.new_client_context().unwrap();
+        let list = create_account::<AllowList>(&mut admin, AllowListCreate {}).unwrap();
+        let (a, b, c) = (30, 10, 20);
+
+        assert!(!list.is_allowed(&admin, a).unwrap());
+        list.allow(&mut admin, a).unwrap();
+        list.allow(&mut admin, b).unwrap();
+        list.allow(&mut admin, c).unwrap();
+        // inserting a key twice is a no-op
+        list.allow(&mut admin, a).unwrap();
+        assert!(list.allow(&mut bob, 40).is_err());
+        assert!(list.is_allowed(&admin, a).unwrap());
+        assert!(!list.is_allowed(&admin, 40).unwrap());
+        assert_eq!(list.allowed_members(&admin, false).unwrap(), vec![b, c, a]);
+        assert_eq!(list.allowed_members(&admin, true).unwrap(), vec![a, c, b]);
+
+        list.disallow(&mut admin, c).unwrap();
+        assert!(!list.is_allowed(&admin, c).unwrap());
+        assert_eq!(list.allowed_members(&admin, false).unwrap(), vec![b, a]);
+
+        list.block(&mut admin, "foo", a).unwrap();
+        list.block(&mut admin, "foo", b).unwrap();
+        list.block(&mut admin, "bar", c).unwrap();
+        assert_eq!(list.blocked_members(&admin, "foo").unwrap(), vec![b, a]);
+        assert_eq!(list.blocked_members(&admin, "bar").unwrap(), vec![c]);
+        assert!(list.blocked_members(&admin, "baz").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_key_set_descriptor() {
+        let mem = MemoryManager::new();
+        let schema = extract_handler_schema::<AllowList>(&mem).unwrap();
+        let blocked = schema
+            .state_objects
+            .as_slice()
+            .iter()
+            .find(|s| s.name == "blocked")
+            .unwrap();
+        let key_names: Vec<_> = blocked
+            .key_fields
+            .as_slice()
+            .iter()
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(key_names, vec!["denom", "member"]);
+        assert!(blocked.value_fields.is_empty());
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<allow_list::AllowList>().unwrap();
+}
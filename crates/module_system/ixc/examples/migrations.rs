@@ -29,6 +29,12 @@ mod handler1 {
             ensure!(ctx.caller() == self.owner.get(ctx)?, "unauthorized caller");
             Ok(account_api::migrate(ctx, new_handler_id)?)
         }
+
+        #[publish]
+        pub fn destroy(&self, ctx: &mut Context) -> Result<()> {
+            ensure!(ctx.caller() == self.owner.get(ctx)?, "unauthorized caller");
+            unsafe { Ok(account_api::self_destruct(ctx)?) }
+        }
     }
 }
 
@@ -125,10 +131,18 @@ mod tests {
     use crate::handler2::Handler2;
     use crate::handler3::Handler3;
     use ixc::*;
-    use ixc_core::account_api::get_handler_id;
+    use ixc_core::account_api::{
+        create_account_with_admin, get_handler_id, migrate_account, ON_CREATE_SELECTOR,
+        ON_MIGRATE_SELECTOR, ROOT_ACCOUNT,
+    };
     use ixc_core::handler::{Client, HandlerResources};
+    use ixc_core::low_level::dynamic_invoke_msg_packet;
+    use ixc_message_api::code::{ErrorCode, SystemCode};
+    use ixc_message_api::message::{Message, Request};
     use ixc_testing::*;
 
+    const UNAUTHORIZED: ErrorCode = ErrorCode::SystemCode(SystemCode::UnauthorizedCallerAccess);
+
     #[test]
     fn test_migration() {
         let test_app = TestApp::default();
@@ -145,6 +159,21 @@ mod tests {
         let cur = foo.get(&bob).unwrap();
         assert_eq!(cur, 1);
 
+        // simulating a migration reports its state changes without committing them
+        let sim = test_app.simulate(bob.self_account_id(), |ctx| {
+            foo.migrate(ctx, Handler2::NAME)
+        });
+        sim.result.unwrap();
+        assert!(sim.state_changes.iter().any(|change| matches!(
+            change,
+            StateChange::Set { account, value, .. }
+                if *account == ROOT_ACCOUNT && value.as_slice() == Handler2::NAME.as_bytes()
+        )));
+        assert_eq!(
+            get_handler_id(&bob, foo.target_account()).unwrap(),
+            Handler1::NAME
+        );
+
         foo.migrate(&mut bob, Handler2::NAME).unwrap();
         assert_eq!(
             get_handler_id(&bob, foo.target_account()).unwrap(),
@@ -181,6 +210,64 @@ mod tests {
         let cur = bar.get(&bob).unwrap();
         assert_eq!(cur, 3);
     }
+
+    #[test]
+    fn test_migration_admin() {
+        let test_app = TestApp::default();
+        test_app.register_handler::<Handler1>().unwrap();
+        test_app.register_handler::<Handler2>().unwrap();
+
+        let mut bob = test_app.new_client_context().unwrap();
+        let mut admin = test_app.new_client_context().unwrap();
+        let foo = create_account_with_admin::<Handler1>(
+            &mut bob,
+            Handler1Create {},
+            admin.self_account_id(),
+        )
+        .unwrap();
+
+        // an account with an admin can't migrate itself
+        assert!(foo.migrate(&mut bob, Handler2::NAME).is_err());
+        assert_eq!(
+            get_handler_id(&bob, foo.target_account()).unwrap(),
+            Handler1::NAME
+        );
+
+        // and only its admin can migrate it
+        let res = migrate_account(&mut bob, foo.target_account(), Handler2::NAME);
+        assert_eq!(res.unwrap_err().code, UNAUTHORIZED);
+        migrate_account(&mut admin, foo.target_account(), Handler2::NAME).unwrap();
+        let foo = Handler2::new_client(foo.target_account());
+        assert_eq!(foo.get(&bob).unwrap(), 2);
+
+        // accounts without an admin can only be migrated by themselves
+        let bar = create_account::<Handler1>(&mut bob, Handler1Create {}).unwrap();
+        let res = migrate_account(&mut admin, bar.target_account(), Handler2::NAME);
+        assert_eq!(res.unwrap_err().code, UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_protected_routes() {
+        let test_app = TestApp::default();
+        test_app.register_handler::<Handler1>().unwrap();
+
+        let mut bob = test_app.new_client_context().unwrap();
+        let foo = create_account::<Handler1>(&mut bob, Handler1Create {}).unwrap();
+
+        // system routes can only be called by the account manager
+        for selector in [ON_CREATE_SELECTOR, ON_MIGRATE_SELECTOR] {
+            let msg = Message::new(foo.target_account(), Request::new(selector));
+            let res = dynamic_invoke_msg_packet(&mut bob, &msg, None);
+            assert_eq!(res.err(), Some(UNAUTHORIZED));
+        }
+
+        // self-destruct can be disabled for a handler
+        test_app.disable_self_destruct::<Handler1>();
+        assert!(foo.destroy(&mut bob).is_err());
+        assert_eq!(foo.get(&bob).unwrap(), 1);
+        let bar = create_account::<Handler1>(&mut bob, Handler1Create {}).unwrap();
+        assert!(bar.destroy(&mut bob).is_err());
+    }
 }
 
 fn main() {}
@@ -0,0 +1,113 @@
+#![allow(missing_docs)]
+#[ixc::handler(Tags)]
+pub mod tags {
+    use ixc::*;
+
+    /// Items tagged with any number of tags.
+    #[derive(Resources)]
+    pub struct Tags {
+        #[state(prefix = 1, key(tag), value(item))]
+        items: MultiMap<Str, u64>,
+    }
+
+    #[publish]
+    impl Tags {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn tag(&self, ctx: &mut Context, tag: &str, item: u64) -> Result<()> {
+            self.items.insert(ctx, tag, item)?;
+            Ok(())
+        }
+
+        pub fn untag(&self, ctx: &mut Context, tag: &str, item: u64) -> Result<()> {
+            ensure!(self.items.contains(ctx, tag, item)?, "item not tagged");
+            self.items.remove(ctx, tag, item)?;
+            Ok(())
+        }
+
+        pub fn is_tagged(&self, ctx: &Context, tag: &str, item: u64) -> Result<bool> {
+            Ok(self.items.contains(ctx, tag, item)?)
+        }
+
+        pub fn items(&self, ctx: &Context, tag: &str, descending: bool) -> Result<Vec<u64>> {
+            let order = if descending {
+                Order::Descending
+            } else {
+                Order::Ascending
+            };
+            let mut items = Vec::new();
+            for item in self.items.iter_values(ctx, tag, order)? {
+                items.push(item?);
+            }
+            Ok(items)
+        }
+
+        /// Returns the number of tagged items across all tags.
+        pub fn count(&self, ctx: &Context) -> Result<u64> {
+            let mut count = 0;
+            for entry in self.items.iter(ctx)? {
+                entry?;
+                count += 1;
+            }
+            Ok(count)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tags::*;
+    use ixc::create_account;
+    use ixc_core::schema::extract_handler_schema;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_multi_map() {
+        let app = TestApp::default();
+        app.register_handler::<Tags>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let tags = create_account::<Tags>(&mut alice, TagsCreate {}).unwrap();
+
+        for item in [3, 1, 2] {
+            tags.tag(&mut alice, "red", item).unwrap();
+        }
+        // inserting a value twice keeps a single copy
+        tags.tag(&mut alice, "red", 1).unwrap();
+        // tags which start with another tag, or contain zero bytes, don't share values
+        tags.tag(&mut alice, "re", 7).unwrap();
+        tags.tag(&mut alice, "redder", 8).unwrap();
+        tags.tag(&mut alice, "red\0", 9).unwrap();
+        assert_eq!(tags.items(&alice, "red", false).unwrap(), vec![1, 2, 3]);
+        assert_eq!(tags.items(&alice, "red", true).unwrap(), vec![3, 2, 1]);
+        assert_eq!(tags.items(&alice, "re", false).unwrap(), vec![7]);
+        assert_eq!(tags.items(&alice, "red\0", false).unwrap(), vec![9]);
+        assert!(tags.items(&alice, "blue", false).unwrap().is_empty());
+        assert_eq!(tags.count(&alice).unwrap(), 6);
+
+        assert!(tags.is_tagged(&alice, "red", 2).unwrap());
+        tags.untag(&mut alice, "red", 2).unwrap();
+        assert!(!tags.is_tagged(&alice, "red", 2).unwrap());
+        assert!(tags.untag(&mut alice, "red", 2).is_err());
+        assert_eq!(tags.items(&alice, "red", false).unwrap(), vec![1, 3]);
+        assert_eq!(tags.count(&alice).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_multi_map_descriptor() {
+        let mem = MemoryManager::new();
+        let schema = extract_handler_schema::<Tags>(&mem).unwrap();
+        let items = &schema.state_objects.as_slice()[0];
+        assert_eq!(items.name, "items");
+        let key_names: Vec<&str> = items.key_fields.as_slice().iter().map(|f| f.name).collect();
+        assert_eq!(key_names, vec!["tag", "item"]);
+        assert!(items.value_fields.as_slice().is_empty());
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<tags::Tags>().unwrap();
+}
@@ -0,0 +1,182 @@
+#![allow(missing_docs)]
+#[ixc::handler(Registry)]
+pub mod registry {
+    use ixc::*;
+
+    /// Names registered by ID, with their owners.
+    #[derive(Resources)]
+    pub struct Registry {
+        #[state(prefix = 1, key(id), value(owner))]
+        owners: Map<u64, AccountID>,
+    }
+
+    #[derive(SchemaValue, Clone, Debug, Default, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub struct Registration {
+        pub id: u64,
+        pub owner: AccountID,
+    }
+
+    #[publish]
+    impl Registry {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn register(&self, ctx: &mut Context, id: u64) -> Result<()> {
+            ensure!(!self.owners.has(ctx, id)?, "already registered");
+            let owner = ctx.caller();
+            self.owners.set(ctx, id, owner)?;
+            Ok(())
+        }
+
+        pub fn unregister(&self, ctx: &mut Context, id: u64) -> Result<()> {
+            self.owners.delete(ctx, id)?;
+            Ok(())
+        }
+
+        pub fn ids(
+            &self,
+            ctx: &Context,
+            req: PageRequest<'_>,
+            descending: bool,
+        ) -> Result<PageResponse<u64>> {
+            let order = if descending {
+                Order::Descending
+            } else {
+                Order::Ascending
+            };
+            Ok(self.owners.page_keys(ctx, &req, order)?)
+        }
+
+        pub fn owners(
+            &self,
+            ctx: &Context,
+            req: PageRequest<'_>,
+        ) -> Result<PageResponse<AccountID>> {
+            Ok(self.owners.page_values(ctx, &req, Order::Ascending)?)
+        }
+
+        pub fn registrations(
+            &self,
+            ctx: &Context,
+            req: PageRequest<'_>,
+        ) -> Result<PageResponse<Registration>> {
+            Ok(self
+                .owners
+                .page(ctx, &req, Order::Ascending, |id, owner| Registration {
+                    id,
+                    owner,
+                })?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::registry::*;
+    use ixc::{create_account, PageRequest};
+    use ixc_core::schema::extract_handler_schema;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_schema::page::MAX_PAGE_LIMIT;
+    use ixc_schema::schema::SchemaType;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_pagination() {
+        let app = TestApp::default();
+        app.register_handler::<Registry>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let registry = create_account::<Registry>(&mut alice, RegistryCreate {}).unwrap();
+        for id in [5, 1, 4, 2, 3] {
+            registry.register(&mut alice, id).unwrap();
+        }
+
+        let page = registry.ids(&alice, PageRequest::new(2), false).unwrap();
+        assert_eq!(page.items, vec![1, 2]);
+        let cursor = page.next_cursor.unwrap();
+        // entries changed between pages don't make the next page skip or repeat entries
+        registry.unregister(&mut alice, 1).unwrap();
+        registry.register(&mut alice, 0).unwrap();
+        let page = registry
+            .ids(&alice, PageRequest::after(&cursor, 2), false)
+            .unwrap();
+        assert_eq!(page.items, vec![3, 4]);
+        let cursor = page.next_cursor.unwrap();
+        let page = registry
+            .ids(&alice, PageRequest::after(&cursor, 2), false)
+            .unwrap();
+        assert_eq!(page.items, vec![5]);
+        assert_eq!(page.next_cursor, None);
+
+        let page = registry.ids(&alice, PageRequest::new(3), true).unwrap();
+        assert_eq!(page.items, vec![5, 4, 3]);
+        let cursor = page.next_cursor.unwrap();
+        let page = registry
+            .ids(&alice, PageRequest::after(&cursor, 3), true)
+            .unwrap();
+        assert_eq!(page.items, vec![2, 0]);
+        assert_eq!(page.next_cursor, None);
+
+        // a limit of zero uses the default limit, which covers all the entries
+        let page = registry.ids(&alice, PageRequest::new(0), false).unwrap();
+        assert_eq!(page.items, vec![0, 2, 3, 4, 5]);
+        assert_eq!(page.next_cursor, None);
+
+        let owner = alice.self_account_id();
+        let page = registry.owners(&alice, PageRequest::new(2)).unwrap();
+        assert_eq!(page.items, vec![owner, owner]);
+        let page = registry.registrations(&alice, PageRequest::new(1)).unwrap();
+        assert_eq!(page.items, vec![Registration { id: 0, owner }]);
+
+        // cursors from other collections are rejected
+        assert!(registry
+            .ids(&alice, PageRequest::after(&[2, 0], 2), false)
+            .is_err());
+    }
+
+    #[test]
+    fn test_page_limit_is_bounded() {
+        let app = TestApp::default();
+        app.register_handler::<Registry>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let registry = create_account::<Registry>(&mut alice, RegistryCreate {}).unwrap();
+        for id in 0..=MAX_PAGE_LIMIT as u64 {
+            registry.register(&mut alice, id).unwrap();
+        }
+
+        // a huge limit returns at most a full page and a cursor for the rest
+        let page = registry
+            .ids(&alice, PageRequest::new(u32::MAX), false)
+            .unwrap();
+        assert_eq!(page.items.len(), MAX_PAGE_LIMIT as usize);
+        let cursor = page.next_cursor.unwrap();
+        let page = registry
+            .ids(&alice, PageRequest::after(&cursor, u32::MAX), false)
+            .unwrap();
+        assert_eq!(page.items, vec![MAX_PAGE_LIMIT as u64]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_page_schema() {
+        let mem = MemoryManager::new();
+        let schema = extract_handler_schema::<Registry>(&mem).unwrap();
+        let mut names: Vec<&str> = schema
+            .types
+            .as_slice()
+            .iter()
+            .filter_map(|t| match t {
+                SchemaType::Struct(s) if s.name.ends_with("Page") => Some(s.name),
+                _ => None,
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["AccountIDPage", "RegistrationPage", "U64Page"]);
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<registry::Registry>().unwrap();
+}
@@ -0,0 +1,158 @@
+#![allow(missing_docs)]
+#[ixc::handler(JobBoard)]
+pub mod job_board {
+    use ixc::*;
+
+    /// A board of pending job IDs, processed in order, and a history of recent job IDs.
+    #[derive(Resources)]
+    pub struct JobBoard {
+        #[state(prefix = 1, value(job))]
+        pending: Queue<u64>,
+        #[state(prefix = 2)]
+        history: Deque<u64>,
+    }
+
+    #[publish]
+    impl JobBoard {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn submit(&self, ctx: &mut Context, job: u64) -> Result<()> {
+            self.pending.push_back(ctx, job)?;
+            Ok(())
+        }
+
+        /// Processes the next pending job, adding it to the front of the history.
+        pub fn process(&self, ctx: &mut Context) -> Result<Option<u64>> {
+            let job = self.pending.pop_front(ctx)?;
+            if let Some(job) = job {
+                self.history.push_front(ctx, job)?;
+            }
+            Ok(job)
+        }
+
+        /// Drops the oldest job from the history.
+        pub fn forget(&self, ctx: &mut Context) -> Result<Option<u64>> {
+            Ok(self.history.pop_back(ctx)?)
+        }
+
+        pub fn next_job(&self, ctx: &Context) -> Result<Option<u64>> {
+            Ok(self.pending.peek(ctx)?)
+        }
+
+        pub fn oldest_job(&self, ctx: &Context) -> Result<Option<u64>> {
+            Ok(self.history.peek_back(ctx)?)
+        }
+
+        pub fn pending_jobs(&self, ctx: &Context, limit: u32) -> Result<Vec<u64>> {
+            let mut jobs = Vec::new();
+            for job in self.pending.iter(ctx)?.take(limit as usize) {
+                jobs.push(job?);
+            }
+            Ok(jobs)
+        }
+
+        pub fn history(&self, ctx: &Context, oldest_first: bool) -> Result<Vec<u64>> {
+            let order = if oldest_first {
+                Order::Descending
+            } else {
+                Order::Ascending
+            };
+            let mut jobs = Vec::new();
+            for job in self.history.iter(ctx, order)? {
+                jobs.push(job?);
+            }
+            Ok(jobs)
+        }
+
+        pub fn pending_count(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.pending.len(ctx)?)
+        }
+
+        pub fn history_count(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.history.len(ctx)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::job_board::*;
+    use ixc::create_account;
+    use ixc_core::schema::extract_handler_schema;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_queue_and_deque() {
+        let app = TestApp::default();
+        app.register_handler::<JobBoard>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let board = create_account::<JobBoard>(&mut alice, JobBoardCreate {}).unwrap();
+
+        assert_eq!(board.next_job(&alice).unwrap(), None);
+        assert_eq!(board.process(&mut alice).unwrap(), None);
+        for job in [3, 1, 2, 5] {
+            board.submit(&mut alice, job).unwrap();
+        }
+        assert_eq!(board.pending_count(&alice).unwrap(), 4);
+        assert_eq!(board.history_count(&alice).unwrap(), 0);
+        assert_eq!(board.next_job(&alice).unwrap(), Some(3));
+        assert_eq!(board.pending_jobs(&alice, 10).unwrap(), vec![3, 1, 2, 5]);
+        assert_eq!(board.pending_jobs(&alice, 2).unwrap(), vec![3, 1]);
+
+        assert_eq!(board.process(&mut alice).unwrap(), Some(3));
+        assert_eq!(board.process(&mut alice).unwrap(), Some(1));
+        assert_eq!(board.process(&mut alice).unwrap(), Some(2));
+        assert_eq!(board.pending_count(&alice).unwrap(), 1);
+        assert_eq!(board.history_count(&alice).unwrap(), 3);
+        assert_eq!(board.pending_jobs(&alice, 10).unwrap(), vec![5]);
+        // the history grows at the front, below its starting position
+        assert_eq!(board.history(&alice, false).unwrap(), vec![2, 1, 3]);
+        assert_eq!(board.history(&alice, true).unwrap(), vec![3, 1, 2]);
+        assert_eq!(board.oldest_job(&alice).unwrap(), Some(3));
+
+        assert_eq!(board.forget(&mut alice).unwrap(), Some(3));
+        assert_eq!(board.forget(&mut alice).unwrap(), Some(1));
+        assert_eq!(board.oldest_job(&alice).unwrap(), Some(2));
+        assert_eq!(board.forget(&mut alice).unwrap(), Some(2));
+        assert_eq!(board.forget(&mut alice).unwrap(), None);
+        assert_eq!(board.pending_count(&alice).unwrap(), 1);
+        assert_eq!(board.history_count(&alice).unwrap(), 0);
+
+        // queues can be reused after they're drained
+        assert_eq!(board.process(&mut alice).unwrap(), Some(5));
+        assert_eq!(board.process(&mut alice).unwrap(), None);
+        board.submit(&mut alice, 7).unwrap();
+        assert_eq!(board.pending_jobs(&alice, 10).unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn test_queue_descriptor() {
+        let mem = MemoryManager::new();
+        let schema = extract_handler_schema::<JobBoard>(&mem).unwrap();
+        let pending = schema
+            .state_objects
+            .as_slice()
+            .iter()
+            .find(|s| s.name == "pending")
+            .unwrap();
+        assert_eq!(pending.prefix.as_slice(), &[1, 1]);
+        assert_eq!(pending.key_fields.as_slice()[0].name, "position");
+        assert_eq!(pending.value_fields.as_slice()[0].name, "job");
+        let history = schema
+            .state_objects
+            .as_slice()
+            .iter()
+            .find(|s| s.name == "history")
+            .unwrap();
+        assert_eq!(history.prefix.as_slice(), &[2, 1]);
+        assert_eq!(history.value_fields.as_slice()[0].name, "history");
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<job_board::JobBoard>().unwrap();
+}
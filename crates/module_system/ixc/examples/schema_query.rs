@@ -0,0 +1,103 @@
+#![allow(missing_docs)]
+#[ixc::handler(Registry)]
+pub mod registry {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Registry {
+        #[state(prefix = 1, key(name), value(owner))]
+        names: Map<Str, AccountID>,
+    }
+
+    #[derive(SchemaValue, Clone, Default, Debug, Eq, PartialEq)]
+    #[non_exhaustive]
+    pub struct EventRegistered<'a> {
+        pub name: &'a str,
+        pub owner: AccountID,
+    }
+
+    #[publish]
+    impl Registry {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn register<'a>(
+            &self,
+            ctx: &mut Context,
+            name: &'a str,
+            mut evt: EventBus<EventRegistered<'a>>,
+        ) -> Result<()> {
+            ensure!(self.names.get(ctx, name)?.is_none(), "name taken");
+            let owner = ctx.caller();
+            self.names.set(ctx, name, owner)?;
+            evt.emit(ctx, &EventRegistered { name, owner })?;
+            Ok(())
+        }
+
+        pub fn owner(&self, ctx: &Context, name: &str) -> Result<Option<AccountID>> {
+            Ok(self.names.get(ctx, name)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::registry::*;
+    use ixc::{create_account, AccountID};
+    use ixc_core::handler::Client;
+    use ixc_core::schema::{extract_handler_schema, get_account_schema, get_handler_schema};
+    use ixc_message_api::code::{ErrorCode, SystemCode};
+    use ixc_schema::handler::HandlerSchema;
+    use ixc_schema::list::List;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    fn request_types<'a>(schema: &HandlerSchema<'a>) -> Vec<&'a str> {
+        let messages = match &schema.messages {
+            List::Empty => &[][..],
+            List::Borrowed(messages) => messages,
+            List::Owned(messages) => messages.as_slice(),
+        };
+        let mut types: Vec<_> = messages.iter().map(|m| m.request_type).collect();
+        types.sort();
+        types
+    }
+
+    #[test]
+    fn test_schema_query() {
+        let app = TestApp::default();
+        app.register_handler::<Registry>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let registry = create_account::<Registry>(&mut alice, RegistryCreate {}).unwrap();
+        let mem = MemoryManager::new();
+        let expected = extract_handler_schema::<Registry>(&mem).unwrap();
+
+        // the schema can be looked up by handler ID or through an account using the handler
+        for schema in [
+            get_handler_schema(&alice, "Registry").unwrap(),
+            get_account_schema(&alice, registry.target_account()).unwrap(),
+        ] {
+            assert_eq!(request_types(&schema), request_types(&expected));
+            assert!(request_types(&schema).contains(&"RegistryRegister"));
+            assert_eq!(schema.types.len(), expected.types.len());
+            assert_eq!(schema.state_objects.len(), 1);
+        }
+
+        let res = get_handler_schema(&alice, "unknown");
+        assert_eq!(
+            res.err().unwrap().code,
+            ErrorCode::SystemCode(SystemCode::HandlerNotFound)
+        );
+        let res = get_account_schema(&alice, AccountID::new(1_000_000));
+        assert_eq!(
+            res.err().unwrap().code,
+            ErrorCode::SystemCode(SystemCode::AccountNotFound)
+        );
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<registry::Registry>().unwrap();
+}
@@ -0,0 +1,132 @@
+#![allow(missing_docs)]
+#[ixc::handler(OrderBook)]
+pub mod order_book {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct OrderBook {
+        #[state(prefix = 1)]
+        next_order_id: Sequence,
+        #[state(prefix = 2, key(id), value(owner))]
+        orders: Map<u64, AccountID>,
+    }
+
+    #[publish]
+    impl OrderBook {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        /// Places an order and returns its ID.
+        pub fn place(&self, ctx: &mut Context) -> Result<u64> {
+            let id = self.next_order_id.next(ctx)?;
+            let owner = ctx.caller();
+            self.orders.set(ctx, id, owner)?;
+            Ok(id)
+        }
+
+        pub fn next_id(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.next_order_id.peek(ctx)?)
+        }
+
+        pub fn reset_ids(&self, ctx: &mut Context, next: u64) -> Result<()> {
+            self.next_order_id.set(ctx, next)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::order_book::*;
+    use ixc::create_account;
+    use ixc_core::handler::Client;
+    use ixc_core::schema::extract_handler_schema;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_sequence() {
+        let app = TestApp::default();
+        app.register_handler::<OrderBook>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let book = create_account::<OrderBook>(&mut alice, OrderBookCreate {}).unwrap();
+
+        assert_eq!(book.next_id(&alice).unwrap(), 0);
+        assert_eq!(book.place(&mut alice).unwrap(), 0);
+        assert_eq!(book.place(&mut alice).unwrap(), 1);
+        assert_eq!(book.next_id(&alice).unwrap(), 2);
+
+        // the sequence can be moved forward and back
+        book.reset_ids(&mut alice, 100).unwrap();
+        assert_eq!(book.place(&mut alice).unwrap(), 100);
+        book.reset_ids(&mut alice, 5).unwrap();
+        assert_eq!(book.place(&mut alice).unwrap(), 5);
+
+        // but fails instead of wrapping around
+        book.reset_ids(&mut alice, u64::MAX - 1).unwrap();
+        assert_eq!(book.place(&mut alice).unwrap(), u64::MAX - 1);
+        assert!(book.place(&mut alice).is_err());
+        assert_eq!(book.next_id(&alice).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn test_simulated_sequence() {
+        let app = TestApp::default();
+        app.register_handler::<OrderBook>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let book = create_account::<OrderBook>(&mut alice, OrderBookCreate {}).unwrap();
+        let book_id = book.target_account();
+        book.place(&mut alice).unwrap();
+
+        // increments are reported as accumulator adds to the sequence's key
+        let sim = app.simulate(alice.self_account_id(), |ctx| book.place(ctx));
+        assert_eq!(sim.result.unwrap(), 1);
+        assert_eq!(
+            sim.state_changes[0],
+            StateChange::AccumulatorAdd {
+                account: book_id,
+                key: vec![1],
+                amount: 1,
+            }
+        );
+
+        // and moving the sequence back is reported as a subtraction
+        let sim = app.simulate(alice.self_account_id(), |ctx| book.reset_ids(ctx, 0));
+        sim.result.unwrap();
+        assert_eq!(
+            sim.state_changes,
+            vec![StateChange::AccumulatorSafeSub {
+                account: book_id,
+                key: vec![1],
+                amount: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sequence_descriptor() {
+        let mem = MemoryManager::new();
+        let schema = extract_handler_schema::<OrderBook>(&mem).unwrap();
+        let desc = schema
+            .state_objects
+            .as_slice()
+            .iter()
+            .find(|s| s.name == "next_order_id")
+            .unwrap();
+        assert!(desc.is_sequence);
+        assert!(desc.is_accumulator);
+        let orders = schema
+            .state_objects
+            .as_slice()
+            .iter()
+            .find(|s| s.name == "orders")
+            .unwrap();
+        assert!(!orders.is_sequence);
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<order_book::OrderBook>().unwrap();
+}
@@ -2,6 +2,10 @@
 #[ixc::handler(SimpleAsset)]
 pub mod simple_asset {
     use ixc::*;
+    use ixc_core::error::ClientError;
+    use ixc_core::result::ClientResult;
+    use ixc_message_api::code::ErrorCode;
+    use num_enum::{IntoPrimitive, TryFromPrimitive};
 
     #[derive(Resources)]
     pub struct SimpleAsset {
@@ -10,6 +14,18 @@ pub mod simple_asset {
 
         #[state(key(account), value(balance))]
         balances: Map<AccountID, u128>,
+
+        #[state]
+        burned: Item<u128>,
+    }
+
+    /// An error that can occur when sending.
+    #[derive(Default, Debug, Clone, TryFromPrimitive, IntoPrimitive, SchemaValue, Copy)]
+    #[repr(u8)]
+    #[non_exhaustive]
+    pub enum SendError {
+        #[default]
+        InsufficientBalance,
     }
 
     impl SimpleAsset {
@@ -23,20 +39,50 @@ pub mod simple_asset {
 
         #[publish]
         pub fn get_balance(&self, ctx: &Context, account: AccountID) -> Result<u128> {
-            let res = self.balances.get(ctx, account)?;
-            Ok(res.unwrap_or(0))
+            Ok(self.balances.get_or_default(ctx, account)?)
+        }
+
+        #[publish]
+        pub fn has_balance(&self, ctx: &Context, account: AccountID) -> Result<bool> {
+            Ok(self.balances.has(ctx, account)?)
         }
 
         #[publish]
         pub fn send(&self, ctx: &mut Context, amount: u128, to: AccountID) -> Result<()> {
             let from = ctx.caller();
-            let from_balance = self.balances.get(ctx, from)?.unwrap_or(0);
-            ensure!(from_balance >= amount, "insufficient balance");
-            let to_balance = self.balances.get(ctx, to)?.unwrap_or(0);
-            self.balances.set(ctx, from, from_balance - amount)?;
-            self.balances.set(ctx, to, to_balance + amount)?;
+            self.balances
+                .update(ctx, from, |balance| -> ClientResult<_, SendError> {
+                    match balance.unwrap_or(0).checked_sub(amount) {
+                        None => Err(ClientError::new(ErrorCode::HandlerCode(
+                            SendError::InsufficientBalance,
+                        ))),
+                        // empty balances are deleted
+                        Some(0) => Ok(None),
+                        Some(balance) => Ok(Some(balance)),
+                    }
+                })?;
+            self.balances
+                .update(ctx, to, |balance| -> ClientResult<_> {
+                    Ok(Some(balance.unwrap_or(0) + amount))
+                })?;
             Ok(())
         }
+
+        /// Burns the caller's whole balance and returns the amount burned.
+        #[publish]
+        pub fn burn_all(&self, ctx: &mut Context) -> Result<u128> {
+            let from = ctx.caller();
+            let amount = self.balances.take(ctx, from)?.unwrap_or(0);
+            self.burned.update(ctx, |burned| -> ClientResult<_> {
+                Ok(Some(burned + amount))
+            })?;
+            Ok(amount)
+        }
+
+        #[publish]
+        pub fn get_burned(&self, ctx: &Context) -> Result<u128> {
+            Ok(self.burned.get(ctx)?)
+        }
     }
 }
 
@@ -75,6 +121,41 @@ mod tests {
             .unwrap();
         assert_eq!(bob_balance, 50);
     }
+
+    #[test]
+    fn test_update_and_take() {
+        let app = TestApp::default();
+        app.register_handler::<SimpleAsset>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let mut bob = app.new_client_context().unwrap();
+        let (alice_id, bob_id) = (alice.self_account_id(), bob.self_account_id());
+        let asset_client = create_account::<SimpleAsset>(
+            &mut alice,
+            SimpleAssetInit {
+                initial_balance: 100,
+            },
+        )
+        .unwrap();
+        assert!(!asset_client.has_balance(&bob, bob_id).unwrap());
+
+        // an error from the update closure leaves both balances unchanged
+        assert!(asset_client.send(&mut alice, 101, bob_id).is_err());
+        assert_eq!(asset_client.get_balance(&alice, alice_id).unwrap(), 100);
+        assert!(!asset_client.has_balance(&bob, bob_id).unwrap());
+
+        // sending the whole balance deletes it
+        asset_client.send(&mut alice, 100, bob_id).unwrap();
+        assert!(!asset_client.has_balance(&alice, alice_id).unwrap());
+        assert_eq!(asset_client.get_balance(&alice, alice_id).unwrap(), 0);
+        assert_eq!(asset_client.get_balance(&bob, bob_id).unwrap(), 100);
+
+        asset_client.send(&mut bob, 30, alice_id).unwrap();
+        assert_eq!(asset_client.burn_all(&mut bob).unwrap(), 70);
+        assert!(!asset_client.has_balance(&bob, bob_id).unwrap());
+        assert_eq!(asset_client.burn_all(&mut bob).unwrap(), 0);
+        assert_eq!(asset_client.burn_all(&mut alice).unwrap(), 30);
+        assert_eq!(asset_client.get_burned(&alice).unwrap(), 100);
+    }
 }
 
 fn main() {
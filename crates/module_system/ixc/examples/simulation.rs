@@ -0,0 +1,122 @@
+#![allow(missing_docs)]
+#[ixc::handler(Counter)]
+pub mod counter {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Counter {
+        #[state]
+        value: Item<u64>,
+    }
+
+    #[derive(SchemaValue, Clone, Default, Debug, Eq, PartialEq)]
+    #[non_exhaustive]
+    pub struct EventIncremented {
+        pub value: u64,
+    }
+
+    #[publish]
+    impl Counter {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn get(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.value.get(ctx)?)
+        }
+
+        pub fn inc(&self, ctx: &mut Context, mut evt: EventBus<EventIncremented>) -> Result<u64> {
+            ctx.consume_gas(100)?;
+            let value = self.value.get(ctx)? + 1;
+            self.value.set(ctx, value)?;
+            evt.emit(ctx, &EventIncremented { value })?;
+            Ok(value)
+        }
+    }
+}
+
+#[ixc::handler(Factory)]
+pub mod factory {
+    use crate::counter::{Counter, CounterCreate};
+    use ixc::*;
+    use ixc_core::account_api::create_account;
+    use ixc_core::handler::Client;
+
+    #[derive(Resources)]
+    pub struct Factory {}
+
+    #[publish]
+    impl Factory {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        /// Creates a new counter and increments it twice.
+        pub fn spawn(&self, ctx: &mut Context) -> Result<AccountID> {
+            let counter = create_account::<Counter>(ctx, CounterCreate {})?;
+            counter.inc(ctx)?;
+            counter.inc(ctx)?;
+            Ok(counter.target_account())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counter::*;
+    use super::factory::*;
+    use ixc::*;
+    use ixc_core::account_api::{create_account_raw, get_handler_id};
+    use ixc_testing::*;
+
+    #[test]
+    fn test_simulation() {
+        let app = TestApp::default();
+        app.register_handler::<Counter>().unwrap();
+        app.register_handler::<Factory>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let factory = create_account::<Factory>(&mut alice, FactoryCreate {}).unwrap();
+
+        // simulate a message which creates an account and calls it
+        app.set_block_height(7);
+        let sim = app.simulate(alice.self_account_id(), |ctx| factory.spawn(ctx));
+        let simulated_counter = sim.result.unwrap();
+        assert_eq!(sim.gas_consumed, 200);
+        assert_eq!(
+            sim.events.select::<EventIncremented>(simulated_counter),
+            vec![EventIncremented { value: 1 }, EventIncremented { value: 2 }]
+        );
+        assert!(sim.state_changes.contains(&StateChange::CreateAccount {
+            account: simulated_counter
+        }));
+
+        // simulated events carry the metadata recorded where they were emitted
+        let events: Vec<EventData> = sim.events.clone().collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].metadata.block_height, 7);
+        assert!(events[1].metadata.sequence > events[0].metadata.sequence);
+
+        // nothing was committed
+        assert!(get_handler_id(&alice, simulated_counter).is_err());
+
+        // running the message for real creates the same account
+        let counter = factory.spawn(&mut alice).unwrap();
+        assert_eq!(counter, simulated_counter);
+        let counter = Counter::new_client(counter);
+        assert_eq!(counter.get(&alice).unwrap(), 2);
+
+        // failed messages report no state changes or events
+        let sim = app.simulate(alice.self_account_id(), |ctx| {
+            create_account_raw(ctx, "unknown", &[])
+        });
+        assert!(sim.result.is_err());
+        assert!(sim.state_changes.is_empty());
+        assert!(sim.events.is_empty());
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<factory::Factory>().unwrap();
+}
@@ -0,0 +1,153 @@
+#![allow(missing_docs)]
+#[ixc::handler(Tally)]
+pub mod tally {
+    use super::relay::RelayClient;
+    use ixc::*;
+    use ixc_core::handler::Client;
+    use ixc_core::result::ClientResult;
+
+    /// Counts votes per option, with the state cache enabled for every message which writes.
+    #[derive(Resources)]
+    pub struct Tally {
+        #[state(prefix = 1, key(option), value(votes))]
+        votes: Map<u64, u64>,
+    }
+
+    #[publish]
+    impl Tally {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        /// Adds each of the votes, reading and writing each option only once in storage.
+        pub fn vote(&self, ctx: &mut Context, options: &[u64]) -> Result<()> {
+            ctx.enable_state_cache();
+            for option in options {
+                self.votes.update(ctx, option, |votes| -> ClientResult<_> {
+                    Ok(Some(votes.unwrap_or(0) + 1))
+                })?;
+            }
+            Ok(())
+        }
+
+        /// Adds the votes and then fails, which must not change any state.
+        pub fn vote_and_fail(&self, ctx: &mut Context, options: &[u64]) -> Result<()> {
+            self.vote(ctx, options)?;
+            bail!("failed after voting")
+        }
+
+        /// Moves all the votes of an option to another one and returns the remaining options
+        /// without flushing the cache in between.
+        pub fn merge(&self, ctx: &mut Context, from: u64, to: u64) -> Result<Vec<u64>> {
+            ctx.enable_state_cache();
+            let moved = self.votes.take(ctx, from)?.unwrap_or(0);
+            self.votes.update(ctx, to, |votes| -> ClientResult<_> {
+                Ok(Some(votes.unwrap_or(0) + moved))
+            })?;
+            let mut options = Vec::new();
+            for entry in self.votes.iter(ctx)? {
+                options.push(entry?.0);
+            }
+            Ok(options)
+        }
+
+        /// Votes for an option and then has the relay vote for it on this tally too,
+        /// returning the votes this tally sees afterwards.
+        pub fn vote_via_relay(
+            &self,
+            ctx: &mut Context,
+            option: u64,
+            relay: AccountID,
+        ) -> Result<u64> {
+            self.vote(ctx, &[option])?;
+            let votes = self.votes(ctx, option)?;
+            let tally = ctx.self_account_id();
+            let seen_by_relay = RelayClient::new(relay).vote_and_count(ctx, tally, option)?;
+            ensure!(seen_by_relay == votes, "relay didn't see the pending vote");
+            self.votes(ctx, option)
+        }
+
+        pub fn votes(&self, ctx: &Context, option: u64) -> Result<u64> {
+            Ok(self.votes.get_or_default(ctx, option)?)
+        }
+    }
+}
+
+#[ixc::handler(Relay)]
+pub mod relay {
+    use ixc::*;
+    use ixc_core::handler::Client;
+
+    #[derive(Resources)]
+    pub struct Relay {}
+
+    #[publish]
+    impl Relay {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        /// Votes for an option on the tally and returns the votes it had before.
+        pub fn vote_and_count(
+            &self,
+            ctx: &mut Context,
+            tally: AccountID,
+            option: u64,
+        ) -> Result<u64> {
+            let tally = super::tally::TallyClient::new(tally);
+            let before = tally.votes(ctx, option)?;
+            tally.vote(ctx, &[option])?;
+            let after = tally.votes(ctx, option)?;
+            ensure!(after == before + 1, "vote not counted");
+            Ok(before)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::relay::*;
+    use super::tally::*;
+    use ixc::create_account;
+    use ixc_core::handler::Client;
+    use ixc_testing::*;
+
+    #[test]
+    fn test_state_cache() {
+        let app = TestApp::default();
+        app.register_handler::<Tally>().unwrap();
+        app.register_handler::<Relay>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let tally = create_account::<Tally>(&mut alice, TallyCreate {}).unwrap();
+
+        // repeated writes to the same key are combined and flushed when the message succeeds
+        tally.vote(&mut alice, &[1, 2, 1, 1, 3]).unwrap();
+        assert_eq!(tally.votes(&alice, 1).unwrap(), 3);
+        assert_eq!(tally.votes(&alice, 2).unwrap(), 1);
+
+        // pending writes are discarded when the message fails
+        assert!(tally.vote_and_fail(&mut alice, &[1, 2]).is_err());
+        assert_eq!(tally.votes(&alice, 1).unwrap(), 3);
+        assert_eq!(tally.votes(&alice, 2).unwrap(), 1);
+
+        // iteration sees pending writes and deletes
+        assert_eq!(tally.merge(&mut alice, 1, 4).unwrap(), vec![2, 3, 4]);
+        assert_eq!(tally.votes(&alice, 1).unwrap(), 0);
+        assert_eq!(tally.votes(&alice, 4).unwrap(), 3);
+
+        // nested calls back into the account see its pending writes,
+        // and it sees their writes afterwards
+        let relay = create_account::<Relay>(&mut alice, RelayCreate {}).unwrap();
+        let votes = tally
+            .vote_via_relay(&mut alice, 2, relay.target_account())
+            .unwrap();
+        assert_eq!(votes, 3);
+        assert_eq!(tally.votes(&alice, 2).unwrap(), 3);
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<tally::Tally>().unwrap();
+}
@@ -0,0 +1,215 @@
+#![allow(missing_docs)]
+#[ixc::handler(DepositBank)]
+pub mod deposit_bank {
+    use ixc::core::storage_deposit::StorageDepositRequest;
+    use ixc::*;
+
+    /// A bank-like handler which holds storage deposits in escrow.
+    #[derive(Resources)]
+    pub struct DepositBank {
+        #[state(prefix = 1, key(account), value(amount))]
+        balances: AccumulatorMap<AccountID>,
+        #[state(prefix = 2)]
+        escrow: Accumulator,
+    }
+
+    #[publish]
+    impl DepositBank {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn mint(&self, ctx: &mut Context, to: AccountID, amount: u128) -> Result<()> {
+            self.balances.add(ctx, to, amount)?;
+            Ok(())
+        }
+
+        pub fn balance(&self, ctx: &Context, account: AccountID) -> Result<u128> {
+            Ok(self.balances.get(ctx, account)?)
+        }
+
+        pub fn escrowed(&self, ctx: &Context) -> Result<u128> {
+            Ok(self.escrow.get(ctx)?)
+        }
+
+        #[on_storage_deposit]
+        pub fn on_storage_deposit(
+            &self,
+            ctx: &mut Context,
+            req: &StorageDepositRequest,
+        ) -> Result<()> {
+            if req.refund {
+                self.escrow.safe_sub(ctx, req.amount)?;
+                self.balances.add(ctx, req.account, req.amount)?;
+            } else {
+                self.balances.safe_sub(ctx, req.account, req.amount)?;
+                self.escrow.add(ctx, req.amount)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[ixc::handler(Notes)]
+pub mod notes {
+    use ixc::*;
+    use ixc_core::account_api;
+
+    #[derive(Resources)]
+    pub struct Notes {
+        #[state(prefix = 1, key(id), value(text))]
+        notes: Map<u64, Str>,
+    }
+
+    #[publish]
+    impl Notes {
+        #[on_create]
+        pub fn create(&self, ctx: &mut Context, first_note: &str) -> Result<()> {
+            self.notes.set(ctx, 0, first_note)?;
+            Ok(())
+        }
+
+        pub fn add(&self, ctx: &mut Context, id: u64, text: &str) -> Result<()> {
+            self.notes.set(ctx, id, text)?;
+            Ok(())
+        }
+
+        pub fn remove(&self, ctx: &mut Context, id: u64) -> Result<()> {
+            self.notes.delete(ctx, id)?;
+            Ok(())
+        }
+
+        pub fn destroy(&self, ctx: &mut Context) -> Result<()> {
+            unsafe { Ok(account_api::self_destruct(ctx)?) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::deposit_bank::*;
+    use super::notes::*;
+    use ixc::create_account;
+    use ixc_core::account_api::get_storage_usage;
+    use ixc_core::handler::Client;
+    use ixc_core::low_level::dynamic_invoke_msg_packet;
+    use ixc_core::storage_deposit::STORAGE_DEPOSIT_SELECTOR;
+    use ixc_message_api::code::{ErrorCode, SystemCode};
+    use ixc_message_api::message::{Message, Request};
+    use ixc_testing::*;
+
+    const DEPOSIT_PER_BYTE: u128 = 10;
+
+    #[test]
+    fn test_storage_deposits() {
+        let app = TestApp::default();
+        app.register_handler::<DepositBank>().unwrap();
+        app.register_handler::<Notes>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let alice_id = alice.self_account_id();
+        let mut bob = app.new_client_context().unwrap();
+        let bob_id = bob.self_account_id();
+        let bank = create_account::<DepositBank>(&mut alice, DepositBankCreate {}).unwrap();
+        app.enable_storage_deposits(StorageDepositConfig {
+            deposit_account: bank.target_account(),
+            deposit_per_byte: DEPOSIT_PER_BYTE,
+        });
+        bank.mint(&mut alice, alice_id, 10_000).unwrap();
+
+        // the creator pays the deposit for the new account's initial state
+        let notes = create_account::<Notes>(
+            &mut alice,
+            NotesCreate {
+                first_note: "hello",
+            },
+        )
+        .unwrap();
+        let notes_id = notes.target_account();
+        let initial_usage = get_storage_usage(&alice, notes_id).unwrap() as u128;
+        assert!(initial_usage > 0);
+        let initial_deposit = initial_usage * DEPOSIT_PER_BYTE;
+        assert_eq!(
+            bank.balance(&alice, alice_id).unwrap(),
+            10_000 - initial_deposit
+        );
+        assert_eq!(bank.escrowed(&alice).unwrap(), initial_deposit);
+
+        // afterwards, accounts pay for their own storage and fail if they can't
+        assert!(notes.add(&mut alice, 1, "world").is_err());
+        assert_eq!(
+            get_storage_usage(&alice, notes_id).unwrap() as u128,
+            initial_usage
+        );
+        bank.mint(&mut alice, notes_id, 1_000).unwrap();
+        notes.add(&mut alice, 1, "world").unwrap();
+        let usage = get_storage_usage(&alice, notes_id).unwrap() as u128;
+        assert!(usage > initial_usage);
+        assert_eq!(
+            bank.balance(&alice, notes_id).unwrap(),
+            1_000 - (usage - initial_usage) * DEPOSIT_PER_BYTE
+        );
+
+        // overwriting a value is only charged for the bytes it adds
+        notes.add(&mut alice, 1, "world!").unwrap();
+        assert_eq!(
+            get_storage_usage(&alice, notes_id).unwrap() as u128,
+            usage + 1
+        );
+        assert_eq!(
+            bank.balance(&alice, notes_id).unwrap(),
+            1_000 - (usage + 1 - initial_usage) * DEPOSIT_PER_BYTE
+        );
+
+        // deleting keys refunds their deposit
+        notes.remove(&mut alice, 1).unwrap();
+        assert_eq!(
+            get_storage_usage(&alice, notes_id).unwrap() as u128,
+            initial_usage
+        );
+        assert_eq!(bank.balance(&alice, notes_id).unwrap(), 1_000);
+        assert_eq!(bank.escrowed(&alice).unwrap(), initial_deposit);
+
+        // self-destructing refunds the deposit for all of the account's storage to its creator,
+        // not to whoever triggered it
+        notes.destroy(&mut bob).unwrap();
+        assert_eq!(get_storage_usage(&alice, notes_id).unwrap(), 0);
+        assert_eq!(bank.balance(&alice, alice_id).unwrap(), 10_000);
+        assert_eq!(bank.balance(&alice, bob_id).unwrap(), 0);
+        assert_eq!(bank.escrowed(&alice).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_deposit_hook_is_protected() {
+        let app = TestApp::default();
+        app.register_handler::<DepositBank>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let alice_id = alice.self_account_id();
+        let bank = create_account::<DepositBank>(&mut alice, DepositBankCreate {}).unwrap();
+        app.enable_storage_deposits(StorageDepositConfig {
+            deposit_account: bank.target_account(),
+            deposit_per_byte: DEPOSIT_PER_BYTE,
+        });
+
+        // only the account manager can ask for a refund
+        let refund = Message::new(
+            bank.target_account(),
+            Request::new3(
+                STORAGE_DEPOSIT_SELECTOR,
+                alice_id.into(),
+                1_000u128.into(),
+                1u64.into(),
+            ),
+        );
+        let res = dynamic_invoke_msg_packet(&mut alice, &refund, None);
+        assert_eq!(
+            res.err(),
+            Some(ErrorCode::SystemCode(SystemCode::UnauthorizedCallerAccess))
+        );
+        assert_eq!(bank.balance(&alice, alice_id).unwrap(), 0);
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<deposit_bank::DepositBank>().unwrap();
+}
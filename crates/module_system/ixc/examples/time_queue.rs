@@ -0,0 +1,128 @@
+#![allow(missing_docs)]
+#[ixc::handler(AuctionHouse)]
+pub mod auction_house {
+    use ixc::*;
+    use ixc_core::block_info::get_block_time;
+
+    /// Auctions which close at their deadlines, with their reserve prices.
+    #[derive(Resources)]
+    pub struct AuctionHouse {
+        #[state(prefix = 1, key(deadline, auction), value(reserve))]
+        open: TimeQueue<u64, u128>,
+        #[state(prefix = 2, key(auction), value(reserve))]
+        closed: Map<u64, u128>,
+    }
+
+    #[publish]
+    impl AuctionHouse {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn open(
+            &self,
+            ctx: &mut Context,
+            deadline: Time,
+            auction: u64,
+            reserve: u128,
+        ) -> Result<()> {
+            self.open.insert(ctx, deadline, auction, reserve)?;
+            Ok(())
+        }
+
+        pub fn cancel(&self, ctx: &mut Context, deadline: Time, auction: u64) -> Result<()> {
+            ensure!(
+                self.open.get(ctx, deadline, auction)?.is_some(),
+                "auction not found"
+            );
+            self.open.remove(ctx, deadline, auction)?;
+            Ok(())
+        }
+
+        /// Closes up to `limit` auctions whose deadlines have passed, returning their IDs.
+        pub fn close_expired(&self, ctx: &mut Context, limit: u32) -> Result<Vec<u64>> {
+            let now = get_block_time(ctx)?;
+            let expired = self.open.pop_expired(ctx, now, limit as usize)?;
+            let mut closed = Vec::new();
+            for ((_, auction), reserve) in expired {
+                self.closed.set(ctx, auction, reserve)?;
+                closed.push(auction);
+            }
+            Ok(closed)
+        }
+
+        /// Returns the IDs of the open auctions with deadlines before `before`, soonest first.
+        pub fn closing_before(&self, ctx: &Context, before: Time) -> Result<Vec<u64>> {
+            let mut auctions = Vec::new();
+            for entry in self.open.range(ctx, None, Some(before), Order::Ascending)? {
+                let ((_, auction), _) = entry?;
+                auctions.push(auction);
+            }
+            Ok(auctions)
+        }
+
+        pub fn open_auctions(&self, ctx: &Context) -> Result<Vec<u64>> {
+            let mut auctions = Vec::new();
+            for entry in self.open.iter(ctx)? {
+                let ((_, auction), _) = entry?;
+                auctions.push(auction);
+            }
+            Ok(auctions)
+        }
+
+        pub fn closed_reserve(&self, ctx: &Context, auction: u64) -> Result<Option<u128>> {
+            Ok(self.closed.get(ctx, auction)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::auction_house::*;
+    use ixc::{create_account, Time};
+    use ixc_testing::*;
+
+    #[test]
+    fn test_time_queue() {
+        let app = TestApp::default();
+        app.register_handler::<AuctionHouse>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let house = create_account::<AuctionHouse>(&mut alice, AuctionHouseCreate {}).unwrap();
+        let at = Time::from_unix_secs;
+
+        // times before the unix epoch are ordered correctly too
+        house.open(&mut alice, at(30), 1, 100).unwrap();
+        house.open(&mut alice, at(10), 2, 200).unwrap();
+        house.open(&mut alice, at(20), 3, 300).unwrap();
+        house.open(&mut alice, at(10), 4, 400).unwrap();
+        house.open(&mut alice, at(-5), 5, 500).unwrap();
+        assert_eq!(house.open_auctions(&alice).unwrap(), vec![5, 2, 4, 3, 1]);
+        assert_eq!(house.closing_before(&alice, at(20)).unwrap(), vec![5, 2, 4]);
+
+        house.cancel(&mut alice, at(20), 3).unwrap();
+        assert!(house.cancel(&mut alice, at(20), 3).is_err());
+        assert!(house.cancel(&mut alice, at(30), 2).is_err());
+        assert_eq!(house.open_auctions(&alice).unwrap(), vec![5, 2, 4, 1]);
+
+        app.set_block_time(at(0));
+        assert_eq!(house.close_expired(&mut alice, 10).unwrap(), vec![5]);
+        assert_eq!(house.closed_reserve(&alice, 5).unwrap(), Some(500));
+
+        // entries expire at their exact time and the limit bounds how many are closed
+        app.set_block_time(at(10));
+        assert_eq!(house.close_expired(&mut alice, 1).unwrap(), vec![2]);
+        assert_eq!(house.close_expired(&mut alice, 10).unwrap(), vec![4]);
+        assert!(house.close_expired(&mut alice, 10).unwrap().is_empty());
+        assert_eq!(house.closed_reserve(&alice, 1).unwrap(), None);
+
+        app.set_block_time(at(100));
+        assert_eq!(house.close_expired(&mut alice, 10).unwrap(), vec![1]);
+        assert!(house.open_auctions(&alice).unwrap().is_empty());
+        assert_eq!(house.closed_reserve(&alice, 1).unwrap(), Some(100));
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<auction_house::AuctionHouse>().unwrap();
+}
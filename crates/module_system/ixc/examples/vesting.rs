@@ -3,6 +3,7 @@
 #[ixc::handler(FixedVesting)]
 mod vesting {
     use ixc::*;
+    use ixc_core::block_info::get_block_time;
     use ixc_core::handler::{Client, Service};
     use mockall::automock;
     use num_enum::{IntoPrimitive, TryFromPrimitive};
@@ -18,8 +19,6 @@ mod vesting {
         pub(crate) unlock_time: Item<Time>,
         #[client(65536)]
         bank_client: <dyn BankAPI as Service>::Client,
-        #[client(65537)]
-        block_client: <dyn BlockInfoAPI as Service>::Client,
     }
 
     impl FixedVesting {
@@ -43,7 +42,7 @@ mod vesting {
             ctx: &mut Context,
             eb: &mut EventBus<UnlockEvent>,
         ) -> Result<(), UnlockError> {
-            if self.unlock_time.get(ctx)? > self.block_client.get_block_time(ctx)? {
+            if self.unlock_time.get(ctx)? > get_block_time(ctx)? {
                 bail!(UnlockError::NotTimeYet);
             }
             if let Some(amount) = self.amount.get(ctx)? {
@@ -98,12 +97,6 @@ mod vesting {
         fn on_receive(&self, ctx: &mut Context, from: AccountID, amount: &[Coin]) -> Result<()>;
     }
 
-    #[handler_api]
-    #[automock]
-    pub trait BlockInfoAPI {
-        fn get_block_time<'a>(&self, ctx: &Context<'a>) -> Result<Time>;
-    }
-
     #[publish]
     impl ReceiveHook for FixedVesting {
         fn on_receive(&self, ctx: &mut Context, _from: AccountID, amount: &[Coin]) -> Result<()> {
@@ -164,8 +157,6 @@ mod tests {
     use ixc_message_api::code::SystemCode::AccountNotFound;
     use ixc_testing::*;
     use simple_time::{Duration, Time};
-    use std::ops::{AddAssign, SubAssign};
-    use std::sync::{Arc, RwLock};
 
     #[test]
     fn test_unlock() {
@@ -192,23 +183,9 @@ mod tests {
             .unwrap();
         let mut bank_ctx = app.client_context_for(bank_id);
 
-        // initialize block info mock
-        let mut block_mock = MockBlockInfoAPI::new();
-        let cur_time = Arc::new(RwLock::new(Time::default()));
-        let cur_time_copy = cur_time.clone();
-        block_mock
-            .expect_get_block_time()
-            .returning(move |_| Ok(cur_time_copy.read().unwrap().clone()));
-        let block_info_id = app
-            .add_mock(MockHandler::of::<dyn BlockInfoAPI>(Box::new(block_mock)))
-            .unwrap();
-
         // register vesting account handler
-        app.register_handler_with_bindings::<FixedVesting>(&[
-            ("bank", bank_id),
-            ("block_info", block_info_id),
-        ])
-        .unwrap();
+        app.register_handler_with_bindings::<FixedVesting>(&[("bank_client", bank_id)])
+            .unwrap();
 
         // initialize the vesting account
         let beneficiary = app.new_client_account().unwrap();
@@ -223,7 +200,7 @@ mod tests {
         .unwrap();
 
         // try to unlock before the initial deposit but after the unlock time (we're time traveling)
-        cur_time.write().unwrap().add_assign(Duration::DAY * 6);
+        app.advance_block_time(Duration::DAY * 6);
         let res = vesting_acct.unlock(&mut root);
         assert!(res.is_err());
         assert_eq!(
@@ -258,12 +235,12 @@ mod tests {
         });
 
         // try unlocking before the unlock time
-        cur_time.write().unwrap().sub_assign(Duration::DAY * 6);
+        app.set_block_time(Time::default());
         let res = vesting_acct.unlock(&mut root);
         assert!(res.is_err());
         assert_eq!(res.unwrap_err().code, HandlerCode(UnlockError::NotTimeYet));
         // try unlocking after the unlock time
-        cur_time.write().unwrap().add_assign(Duration::DAY * 6);
+        app.advance_block_time(Duration::DAY * 6);
         vesting_acct.unlock(&mut root).unwrap();
         // TODO check for unlock event
         // since the unlock succeeded, if we try to unlock again we should get account not found,
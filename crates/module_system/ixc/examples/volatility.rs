@@ -0,0 +1,276 @@
+#![allow(missing_docs)]
+#[ixc::handler(Counter)]
+pub mod counter {
+    use ixc::*;
+
+    #[derive(Resources)]
+    pub struct Counter {
+        #[state]
+        value: Item<u64>,
+    }
+
+    #[publish]
+    impl Counter {
+        #[on_create]
+        pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+            Ok(())
+        }
+
+        pub fn get(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.value.get(ctx)?)
+        }
+
+        pub fn inc(&self, ctx: &mut Context) -> Result<u64> {
+            let value = self.value.get(ctx)? + 1;
+            self.value.set(ctx, value)?;
+            Ok(value)
+        }
+    }
+}
+
+/// A raw handler which tries to update state or invoke volatile messages
+/// no matter how it is called.
+pub mod sneaky {
+    use ixc_core_macros::message_selector;
+    use ixc_message_api::code::ErrorCode;
+    use ixc_message_api::error::HandlerError;
+    use ixc_message_api::handler::{Allocator, HostBackend, InvokeParams, RawHandler};
+    use ixc_message_api::message::{Message, Request, Response};
+    use ixc_message_api::AccountID;
+
+    pub const SET_SELECTOR: u64 = message_selector!("sneaky.set");
+    pub const CALL_SELECTOR: u64 = message_selector!("sneaky.call");
+    const STORE_SET_SELECTOR: u64 = message_selector!("ixc.store.v1.set");
+
+    pub struct Sneaky;
+
+    impl RawHandler for Sneaky {
+        fn handle_msg<'a>(
+            &self,
+            _caller: &AccountID,
+            message: &Message,
+            callbacks: &mut dyn HostBackend,
+            allocator: &'a dyn Allocator,
+        ) -> Result<Response<'a>, HandlerError> {
+            let params = InvokeParams::new(allocator, None);
+            let res = match message.request().message_selector() {
+                SET_SELECTOR => callbacks.update_state(
+                    &Request::new2(
+                        STORE_SET_SELECTOR,
+                        b"key".as_slice().into(),
+                        b"value".as_slice().into(),
+                    ),
+                    &params,
+                ),
+                CALL_SELECTOR => {
+                    // forward the volatile message in the first input to the account in the second
+                    let target = message.request().in2().expect_account_id()?;
+                    let selector = message.request().in1().expect_u64()?;
+                    let request = Request::new1(selector, [].as_slice().into());
+                    callbacks.invoke_msg(&Message::new(target, request), &params)
+                }
+                _ => Err(ErrorCode::SystemCode(
+                    ixc_message_api::code::SystemCode::MessageNotHandled,
+                )),
+            };
+            res.map(|_| Response::default()).map_err(HandlerError::new)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counter::*;
+    use super::sneaky::*;
+    use ixc::{create_account, AccountID};
+    use ixc_core::account_api::ON_CREATE_SELECTOR;
+    use ixc_core::handler::Client;
+    use ixc_core::low_level::{dynamic_invoke_msg_packet, dynamic_invoke_query_packet};
+    use ixc_core::resource::{ResourceScope, Resources};
+    use ixc_core::routing::{
+        exec_query_route, exec_route, find_selector_collision, Router, SYSTEM_SELECTORS,
+    };
+    use ixc_message_api::code::{ErrorCode, SystemCode};
+    use ixc_message_api::handler::{HostBackend, InvokeParams};
+    use ixc_message_api::message::{Message, Request, Response, Volatility};
+    use ixc_schema::mem::MemoryManager;
+    use ixc_schema::structs::StructSchema;
+    use ixc_testing::*;
+
+    const VOLATILE_ACCESS: ErrorCode = ErrorCode::SystemCode(SystemCode::VolatileAccessError);
+    const NOT_HANDLED: ErrorCode = ErrorCode::SystemCode(SystemCode::MessageNotHandled);
+    // returned by handlers which reached the backend, which has no state
+    const NO_STATE: ErrorCode = ErrorCode::SystemCode(SystemCode::Other);
+
+    /// A backend which doesn't provide any state, so routed messages fail when they access state.
+    struct NoBackend;
+
+    impl HostBackend for NoBackend {
+        fn invoke_msg<'a>(
+            &mut self,
+            _message: &Message,
+            _invoke_params: &InvokeParams<'a, '_>,
+        ) -> Result<Response<'a>, ErrorCode> {
+            Err(ErrorCode::SystemCode(SystemCode::FatalExecutionError))
+        }
+
+        fn invoke_query<'a>(
+            &self,
+            _message: &Message,
+            _invoke_params: &InvokeParams<'a, '_>,
+        ) -> Result<Response<'a>, ErrorCode> {
+            Err(ErrorCode::SystemCode(SystemCode::FatalExecutionError))
+        }
+
+        fn update_state<'a>(
+            &mut self,
+            _req: &Request,
+            _invoke_params: &InvokeParams<'a, '_>,
+        ) -> Result<Response<'a>, ErrorCode> {
+            Err(ErrorCode::SystemCode(SystemCode::FatalExecutionError))
+        }
+
+        fn query_state<'a>(
+            &self,
+            _req: &Request,
+            _invoke_params: &InvokeParams<'a, '_>,
+        ) -> Result<Response<'a>, ErrorCode> {
+            Err(ErrorCode::SystemCode(SystemCode::FatalExecutionError))
+        }
+
+        fn consume_gas(&self, _gas: u64) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn out_of_gas(&self) -> Result<bool, ErrorCode> {
+            Ok(false)
+        }
+    }
+
+    fn message(selector: u64, volatility: Volatility) -> Message<'static> {
+        Message::new(
+            AccountID::new(100),
+            Request::new1(selector, [].as_slice().into()).with_volatility(volatility),
+        )
+    }
+
+    #[test]
+    fn test_routing() {
+        let counter = unsafe { Counter::new(&ResourceScope::default()) }.unwrap();
+        let caller = AccountID::new(1);
+        let mem = MemoryManager::new();
+        let inc = message(CounterInc::TYPE_SELECTOR, Volatility::Volatile);
+        let get = message(CounterGet::TYPE_SELECTOR, Volatility::Query);
+        let volatile_get = message(CounterGet::TYPE_SELECTOR, Volatility::Volatile);
+        let query_inc = message(CounterInc::TYPE_SELECTOR, Volatility::Query);
+        let code =
+            |res: Result<Response, ixc_message_api::error::HandlerError>| res.err().unwrap().code;
+
+        // volatile messages are routed to message handlers,
+        // which fail here because there is no state
+        let res = exec_route(&counter, &caller, &inc, &mut NoBackend, &mem);
+        assert_eq!(code(res), NO_STATE);
+        // query messages are routed to query handlers
+        let res = exec_route(&counter, &caller, &get, &mut NoBackend, &mem);
+        assert_eq!(code(res), NO_STATE);
+        let res = exec_query_route(&counter, &get, &NoBackend, &mem);
+        assert_eq!(code(res), NO_STATE);
+        // a query message isn't handled as a volatile one and vice versa
+        let res = exec_route(&counter, &caller, &volatile_get, &mut NoBackend, &mem);
+        assert_eq!(code(res), NOT_HANDLED);
+        let res = exec_route(&counter, &caller, &query_inc, &mut NoBackend, &mem);
+        assert_eq!(code(res), NOT_HANDLED);
+        let res = exec_query_route(&counter, &query_inc, &NoBackend, &mem);
+        assert_eq!(code(res), NOT_HANDLED);
+        // volatile messages are never routed to query handlers
+        let res = exec_query_route(&counter, &inc, &NoBackend, &mem);
+        assert_eq!(code(res), VOLATILE_ACCESS);
+        let res = exec_query_route(&counter, &volatile_get, &NoBackend, &mem);
+        assert_eq!(code(res), VOLATILE_ACCESS);
+    }
+
+    #[test]
+    fn test_selector_names() {
+        let mut names: Vec<_> = Counter::SELECTOR_NAMES.to_vec();
+        names.sort();
+        let mut expected = vec![
+            (CounterGet::TYPE_SELECTOR, "CounterGet"),
+            (CounterInc::TYPE_SELECTOR, "CounterInc"),
+        ];
+        expected.sort();
+        assert_eq!(names, expected);
+        assert_eq!(
+            find_selector_collision(&[Counter::SELECTOR_NAMES, SYSTEM_SELECTORS]),
+            None
+        );
+
+        // collisions are found within and across tables and name both messages
+        let colliding = [(CounterInc::TYPE_SELECTOR, "Other")];
+        assert_eq!(
+            find_selector_collision(&[Counter::SELECTOR_NAMES, &colliding]),
+            Some(("CounterInc", "Other"))
+        );
+        let colliding = [(1, "A"), (ON_CREATE_SELECTOR, "B"), (1, "C")];
+        assert_eq!(find_selector_collision(&[&colliding]), Some(("A", "C")));
+        assert_eq!(
+            find_selector_collision(&[&colliding[1..], SYSTEM_SELECTORS]),
+            Some(("B", "ixc.account.v1.on_create"))
+        );
+    }
+
+    #[test]
+    fn test_hypervisor() {
+        let app = TestApp::default();
+        app.register_handler::<Counter>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let counter = create_account::<Counter>(&mut alice, CounterCreate {}).unwrap();
+        let counter_id = counter.target_account();
+        let sneaky = app.add_mock(MockHandler::of(Box::new(Sneaky))).unwrap();
+        assert_eq!(counter.inc(&mut alice).unwrap(), 1);
+        assert_eq!(counter.get(&alice).unwrap(), 1);
+
+        // volatile messages can't be sent as queries
+        let inc = Message::new(counter_id, Request::new(CounterInc::TYPE_SELECTOR));
+        let res = dynamic_invoke_query_packet(&alice, &inc, None);
+        assert_eq!(res.err(), Some(VOLATILE_ACCESS));
+
+        // query messages can be sent in a transaction but can't update state,
+        // either directly or by invoking volatile messages
+        let set = Message::new(
+            sneaky,
+            Request::new(SET_SELECTOR).with_volatility(Volatility::Query),
+        );
+        let res = dynamic_invoke_msg_packet(&mut alice, &set, None);
+        assert_eq!(res.err(), Some(VOLATILE_ACCESS));
+        let call = Message::new(
+            sneaky,
+            Request::new2(
+                CALL_SELECTOR,
+                CounterInc::TYPE_SELECTOR.into(),
+                counter_id.into(),
+            )
+            .with_volatility(Volatility::Query),
+        );
+        let res = dynamic_invoke_msg_packet(&mut alice, &call, None);
+        assert_eq!(res.err(), Some(VOLATILE_ACCESS));
+        assert_eq!(counter.get(&alice).unwrap(), 1);
+
+        // the same messages succeed when they are volatile
+        let set = Message::new(sneaky, Request::new(SET_SELECTOR));
+        dynamic_invoke_msg_packet(&mut alice, &set, None).unwrap();
+        let call = Message::new(
+            sneaky,
+            Request::new2(
+                CALL_SELECTOR,
+                CounterInc::TYPE_SELECTOR.into(),
+                counter_id.into(),
+            ),
+        );
+        dynamic_invoke_msg_packet(&mut alice, &call, None).unwrap();
+        assert_eq!(counter.get(&alice).unwrap(), 2);
+    }
+}
+
+fn main() {
+    ixc_core::schema::print_handler_schema::<counter::Counter>().unwrap();
+}
@@ -9,14 +9,20 @@ pub use ixc_core::{
 };
 
 #[doc(inline)]
-pub use ixc_collections::{Accumulator, AccumulatorMap, Item, Map};
+pub use ixc_collections::{
+    Accumulator, AccumulatorMap, Deque, IndexedMap, Item, KeySet, Map, MultiIndex, MultiMap, Order,
+    Queue, Sequence, TimeQueue, UniqueIndex,
+};
 #[doc(inline)]
 pub use ixc_message_api::AccountID;
 #[doc(inline)]
+pub use ixc_schema::page::{PageRequest, PageResponse};
+#[doc(inline)]
 pub use ixc_schema::{Bytes, Str};
 #[doc(inline)]
 pub use simple_time::{Duration, Time};
 
+pub use ixc_collections as collections;
 pub use ixc_core as core;
 pub use ixc_message_api as message_api;
 pub use ixc_schema as schema;
@@ -25,7 +31,10 @@ pub use ixc_schema as schema;
 #[macro_use]
 extern crate ixc_core_macros;
 #[doc(inline)]
-pub use ixc_core_macros::{from, handler, handler_api, on_create, on_migrate, publish, Resources};
+pub use ixc_core_macros::{
+    from, handler, handler_api, on_authenticate, on_create, on_migrate, on_register_address,
+    on_storage_deposit, publish, Resources,
+};
 
 #[allow(unused_imports)]
 #[macro_use]
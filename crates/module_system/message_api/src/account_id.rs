@@ -28,7 +28,7 @@ impl AccountID {
 
     /// Returns the account ID as a big-endian byte array.
     pub fn to_bytes(&self) -> [u8; 16] {
-        self.0.to_le_bytes()
+        self.0.to_be_bytes()
     }
 }
 
@@ -46,7 +46,7 @@ impl From<u128> for AccountID {
 
 impl From<[u8; 16]> for AccountID {
     fn from(value: [u8; 16]) -> Self {
-        AccountID::new(u128::from_le_bytes(value))
+        AccountID::new(u128::from_be_bytes(value))
     }
 }
 
@@ -0,0 +1,364 @@
+//! A canonical binary encoding of message packets,
+//! used when messages need to be stored or signed.
+//!
+//! A message is encoded as its 16 byte target account ID, its 8 byte message selector,
+//! a one byte volatility flag which is 1 for queries
+//! and then each of its three input parameters, with all integers encoded as big-endian.
+//! Each parameter is a one byte type tag followed by its value,
+//! where slices and strings are prefixed with their length as a u32.
+//!
+//! A batch of messages is encoded as the number of messages as a u32 followed by each message,
+//! and a batch of responses likewise as their number followed by each response's two output parameters.
+//!
+//! The key-value pairs returned by a range query on storage are encoded as their number
+//! followed by each key and value, both prefixed with their length as a u32.
+use crate::code::{ErrorCode, SystemCode};
+use crate::message::{Message, Param, Request, Response, Volatility};
+use crate::AccountID;
+use allocator_api2::alloc::Allocator;
+use core::alloc::Layout;
+
+const EMPTY: u8 = 0;
+const SLICE: u8 = 1;
+const STRING: u8 = 2;
+const U128: u8 = 3;
+const U64: u8 = 4;
+const ACCOUNT_ID: u8 = 5;
+
+/// Writes the encoding of the message to the sink in chunks.
+pub fn write_message(message: &Message, sink: &mut dyn FnMut(&[u8])) {
+    sink(&u128::from(message.target_account()).to_be_bytes());
+    let request = message.request();
+    sink(&request.message_selector().to_be_bytes());
+    sink(&[(request.volatility() == Volatility::Query) as u8]);
+    write_param(&request.in1(), sink);
+    write_param(&request.in2(), sink);
+    write_param(&request.in3(), sink);
+}
+
+fn write_param(param: &Param, sink: &mut dyn FnMut(&[u8])) {
+    if let Some(slice) = param.as_slice() {
+        sink(&[SLICE]);
+        sink(&(slice.len() as u32).to_be_bytes());
+        sink(slice);
+    } else if let Some(string) = param.as_string() {
+        sink(&[STRING]);
+        sink(&(string.len() as u32).to_be_bytes());
+        sink(string.as_bytes());
+    } else if let Some(x) = param.as_u128() {
+        sink(&[U128]);
+        sink(&x.to_be_bytes());
+    } else if let Ok(x) = param.expect_u64() {
+        sink(&[U64]);
+        sink(&x.to_be_bytes());
+    } else if let Some(account_id) = param.as_account_id() {
+        sink(&[ACCOUNT_ID]);
+        sink(&u128::from(account_id).to_be_bytes());
+    } else {
+        sink(&[EMPTY]);
+    }
+}
+
+/// Returns the length of the encoding of the message.
+pub fn encoded_len(message: &Message) -> usize {
+    let mut len = 0;
+    write_message(message, &mut |bz| len += bz.len());
+    len
+}
+
+/// Encodes the message into memory allocated by the allocator.
+///
+/// # Safety
+/// See [`crate::alloc_util::copy_bytes`].
+pub unsafe fn encode_message<'a>(
+    message: &Message,
+    allocator: &'a dyn Allocator,
+) -> Result<&'a [u8], ErrorCode> {
+    encode_with(allocator, &mut |sink| write_message(message, sink))
+}
+
+/// Decodes a message which borrows its slice and string parameters from the encoded bytes.
+pub fn decode_message(bz: &[u8]) -> Result<Message<'_>, ErrorCode> {
+    let mut reader = bz;
+    let message = read_message(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(ErrorCode::SystemCode(SystemCode::EncodingError));
+    }
+    Ok(message)
+}
+
+/// Encodes a batch of messages into memory allocated by the allocator.
+///
+/// # Safety
+/// See [`crate::alloc_util::copy_bytes`].
+pub unsafe fn encode_batch<'a>(
+    messages: &[Message],
+    allocator: &'a dyn Allocator,
+) -> Result<&'a [u8], ErrorCode> {
+    encode_with(allocator, &mut |sink| {
+        sink(&(messages.len() as u32).to_be_bytes());
+        for message in messages {
+            write_message(message, sink);
+        }
+    })
+}
+
+/// Decodes a batch of messages encoded with [`encode_batch`],
+/// returning an iterator over the messages.
+pub fn decode_batch(bz: &[u8]) -> Result<Decoder<'_, Message<'_>>, ErrorCode> {
+    Decoder::new(bz, read_message)
+}
+
+/// Encodes a batch of responses into memory allocated by the allocator.
+///
+/// # Safety
+/// See [`crate::alloc_util::copy_bytes`].
+pub unsafe fn encode_responses<'a>(
+    responses: &[Response],
+    allocator: &'a dyn Allocator,
+) -> Result<&'a [u8], ErrorCode> {
+    encode_with(allocator, &mut |sink| {
+        sink(&(responses.len() as u32).to_be_bytes());
+        for response in responses {
+            write_param(&response.out1(), sink);
+            write_param(&response.out2(), sink);
+        }
+    })
+}
+
+/// Decodes a batch of responses encoded with [`encode_responses`],
+/// returning an iterator over the responses.
+pub fn decode_responses(bz: &[u8]) -> Result<Decoder<'_, Response<'_>>, ErrorCode> {
+    Decoder::new(bz, |reader| {
+        let out1 = read_param(reader)?;
+        let out2 = read_param(reader)?;
+        Ok(Response::new2(out1, out2))
+    })
+}
+
+/// A key-value pair from storage.
+pub type KVPair<'a> = (&'a [u8], &'a [u8]);
+
+/// Encodes key-value pairs into memory allocated by the allocator.
+///
+/// # Safety
+/// See [`crate::alloc_util::copy_bytes`].
+pub unsafe fn encode_kv_pairs<'a>(
+    pairs: &[KVPair],
+    allocator: &'a dyn Allocator,
+) -> Result<&'a [u8], ErrorCode> {
+    encode_with(allocator, &mut |sink| {
+        sink(&(pairs.len() as u32).to_be_bytes());
+        for (key, value) in pairs {
+            sink(&(key.len() as u32).to_be_bytes());
+            sink(key);
+            sink(&(value.len() as u32).to_be_bytes());
+            sink(value);
+        }
+    })
+}
+
+/// Decodes key-value pairs encoded with [`encode_kv_pairs`],
+/// returning an iterator over the pairs.
+pub fn decode_kv_pairs(bz: &[u8]) -> Result<Decoder<'_, KVPair<'_>>, ErrorCode> {
+    Decoder::new(bz, |reader| Ok((read_slice(reader)?, read_slice(reader)?)))
+}
+
+/// An iterator over the items of an encoded batch.
+/// Decoding fails if there are fewer or more items than the batch's length says.
+pub struct Decoder<'a, T> {
+    reader: &'a [u8],
+    remaining: u32,
+    read: ReadFn<'a, T>,
+}
+
+type Sink<'a> = &'a mut dyn FnMut(&[u8]);
+
+type ReadFn<'a, T> = fn(&mut &'a [u8]) -> Result<T, ErrorCode>;
+
+impl<'a, T> Decoder<'a, T> {
+    fn new(mut reader: &'a [u8], read: ReadFn<'a, T>) -> Result<Self, ErrorCode> {
+        let remaining = u32::from_be_bytes(read_array(&mut reader)?);
+        Ok(Self {
+            reader,
+            remaining,
+            read,
+        })
+    }
+
+    /// Returns the number of items which haven't been decoded yet.
+    pub fn remaining(&self) -> usize {
+        self.remaining as usize
+    }
+}
+
+impl<T> Iterator for Decoder<'_, T> {
+    type Item = Result<T, ErrorCode>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            if self.reader.is_empty() {
+                return None;
+            }
+            // trailing bytes after the last item
+            self.reader = &[];
+            return Some(Err(ErrorCode::SystemCode(SystemCode::EncodingError)));
+        }
+        self.remaining -= 1;
+        let res = (self.read)(&mut self.reader);
+        if res.is_err() {
+            // stop after the first error
+            self.remaining = 0;
+            self.reader = &[];
+        }
+        Some(res)
+    }
+}
+
+unsafe fn encode_with<'a>(
+    allocator: &'a dyn Allocator,
+    write: &mut dyn FnMut(Sink),
+) -> Result<&'a [u8], ErrorCode> {
+    let mut len = 0;
+    write(&mut |bz| len += bz.len());
+    let buf = allocator
+        .allocate(Layout::from_size_align_unchecked(len, 1))
+        .map_err(|_| ErrorCode::SystemCode(SystemCode::FatalExecutionError))?;
+    let buf = &mut *buf.as_ptr();
+    let mut offset = 0;
+    write(&mut |bz| {
+        buf[offset..offset + bz.len()].copy_from_slice(bz);
+        offset += bz.len();
+    });
+    Ok(buf)
+}
+
+fn read_message<'a>(reader: &mut &'a [u8]) -> Result<Message<'a>, ErrorCode> {
+    let target_account = AccountID::new(u128::from_be_bytes(read_array(reader)?));
+    let selector = u64::from_be_bytes(read_array(reader)?);
+    let volatility = match read_array(reader)? {
+        [0] => Volatility::Volatile,
+        [1] => Volatility::Query,
+        _ => return Err(ErrorCode::SystemCode(SystemCode::EncodingError)),
+    };
+    let in1 = read_param(reader)?;
+    let in2 = read_param(reader)?;
+    let in3 = read_param(reader)?;
+    Ok(Message::new(
+        target_account,
+        Request::new3(selector, in1, in2, in3).with_volatility(volatility),
+    ))
+}
+
+fn read_param<'a>(reader: &mut &'a [u8]) -> Result<Param<'a>, ErrorCode> {
+    let [tag] = read_array(reader)?;
+    match tag {
+        EMPTY => Ok(Param::default()),
+        SLICE => Ok(read_slice(reader)?.into()),
+        STRING => {
+            let string = core::str::from_utf8(read_slice(reader)?)
+                .map_err(|_| ErrorCode::SystemCode(SystemCode::EncodingError))?;
+            Ok(string.into())
+        }
+        U128 => Ok(u128::from_be_bytes(read_array(reader)?).into()),
+        U64 => Ok(u64::from_be_bytes(read_array(reader)?).into()),
+        ACCOUNT_ID => Ok(AccountID::new(u128::from_be_bytes(read_array(reader)?)).into()),
+        _ => Err(ErrorCode::SystemCode(SystemCode::EncodingError)),
+    }
+}
+
+fn read_slice<'a>(reader: &mut &'a [u8]) -> Result<&'a [u8], ErrorCode> {
+    let len = u32::from_be_bytes(read_array(reader)?) as usize;
+    read_bytes(reader, len)
+}
+
+fn read_array<const N: usize>(reader: &mut &[u8]) -> Result<[u8; N], ErrorCode> {
+    let bz = read_bytes(reader, N)?;
+    Ok(bz.try_into().unwrap())
+}
+
+fn read_bytes<'a>(reader: &mut &'a [u8], len: usize) -> Result<&'a [u8], ErrorCode> {
+    if reader.len() < len {
+        return Err(ErrorCode::SystemCode(SystemCode::EncodingError));
+    }
+    let (bz, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok(bz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use allocator_api2::alloc::Global;
+
+    #[test]
+    fn test_round_trip() {
+        let message = Message::new(
+            AccountID::new(2),
+            Request::new3(7, b"foo".as_slice().into(), "bar".into(), 5u64.into())
+                .with_volatility(Volatility::Query),
+        );
+        let bz = unsafe { encode_message(&message, &Global) }.unwrap();
+        assert_eq!(bz.len(), encoded_len(&message));
+        let decoded = decode_message(bz).unwrap();
+        assert_eq!(decoded.target_account(), AccountID::new(2));
+        let request = decoded.request();
+        assert_eq!(request.message_selector(), 7);
+        assert_eq!(request.volatility(), Volatility::Query);
+        assert_eq!(request.in1().expect_bytes().unwrap(), b"foo");
+        assert_eq!(request.in2().expect_string().unwrap(), "bar");
+        assert_eq!(request.in3().expect_u64().unwrap(), 5);
+
+        // truncated and extended encodings are rejected
+        assert!(decode_message(&bz[..bz.len() - 1]).is_err());
+        let mut extended = bz.to_vec();
+        extended.push(0);
+        assert!(decode_message(&extended).is_err());
+    }
+
+    #[test]
+    fn test_batch_round_trip() {
+        let messages = [
+            Message::new(AccountID::new(2), Request::new1(7, "foo".into())),
+            Message::new(AccountID::new(3), Request::new(8)),
+        ];
+        let bz = unsafe { encode_batch(&messages, &Global) }.unwrap();
+        let mut decoded = decode_batch(bz).unwrap();
+        assert_eq!(decoded.remaining(), 2);
+        let first = decoded.next().unwrap().unwrap();
+        assert_eq!(first.target_account(), AccountID::new(2));
+        assert_eq!(first.request().in1().expect_string().unwrap(), "foo");
+        let second = decoded.next().unwrap().unwrap();
+        assert_eq!(second.request().message_selector(), 8);
+        assert!(decoded.next().is_none());
+
+        // truncated and extended batches are rejected
+        assert!(decode_batch(&bz[..bz.len() - 1])
+            .unwrap()
+            .any(|res| res.is_err()));
+        let mut extended = bz.to_vec();
+        extended.push(0);
+        assert!(decode_batch(&extended).unwrap().any(|res| res.is_err()));
+
+        let responses = [Response::new1(5u64.into()), Response::default()];
+        let bz = unsafe { encode_responses(&responses, &Global) }.unwrap();
+        let mut decoded = decode_responses(bz).unwrap();
+        let first = decoded.next().unwrap().unwrap();
+        assert_eq!(first.out1().expect_u64().unwrap(), 5);
+        assert!(decoded.next().unwrap().unwrap().out1().is_empty());
+        assert!(decoded.next().is_none());
+    }
+
+    #[test]
+    fn test_kv_pairs_round_trip() {
+        let pairs: [(&[u8], &[u8]); 2] = [(b"a", b"foo"), (b"bc", b"")];
+        let bz = unsafe { encode_kv_pairs(&pairs, &Global) }.unwrap();
+        let mut decoded = decode_kv_pairs(bz).unwrap();
+        assert_eq!(decoded.next().unwrap().unwrap(), pairs[0]);
+        assert_eq!(decoded.next().unwrap().unwrap(), pairs[1]);
+        assert!(decoded.next().is_none());
+        assert!(decode_kv_pairs(&bz[..bz.len() - 1])
+            .unwrap()
+            .any(|res| res.is_err()));
+    }
+}
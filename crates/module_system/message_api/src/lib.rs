@@ -6,6 +6,7 @@
 mod account_id;
 pub mod alloc_util;
 pub mod code;
+pub mod encoding;
 pub mod error;
 pub mod gas;
 pub mod handler;
@@ -15,3 +16,23 @@ pub use account_id::AccountID;
 
 /// The root system account ID.
 pub const ROOT_ACCOUNT: AccountID = AccountID::new(1);
+/// The built-in system account which provides information about the current block.
+pub const BLOCK_INFO_ACCOUNT: AccountID = AccountID::new(2);
+/// The system account which maps the native addresses of accounts to account IDs.
+/// Unlike the block info account it is backed by a handler, so it must be created
+/// with this ID at genesis.
+pub const ADDRESS_REGISTRY_ACCOUNT: AccountID = AccountID::new(3);
+
+/// The selector of the system message which the account manager sends to the
+/// [`ADDRESS_REGISTRY_ACCOUNT`], if it exists, after an account is created
+/// so that the registry can register the new account's address.
+/// The request input is the ID of the new account.
+pub const REGISTER_ADDRESS_SELECTOR: message::MessageSelector =
+    ixc_core_macros::message_selector!("ixc.address.v1.register");
+
+/// The selector of the system message which the account manager sends to the sender account
+/// of a transaction to authenticate it before any of its messages are run.
+/// The request inputs are the transaction nonce, the transaction credentials
+/// and the transaction's sign bytes, in that order.
+pub const AUTHENTICATE_SELECTOR: message::MessageSelector =
+    ixc_core_macros::message_selector!("ixc.auth.v1.authenticate");
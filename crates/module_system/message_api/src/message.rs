@@ -25,6 +25,18 @@ pub struct Request<'a> {
     inputs_values: [ParamValue<'a>; 3], // size 16 * 3, aligned to 16 bytes
     message_selector: MessageSelector,  // size 8, aligned to 8 bytes
     inputs_types: [ParamType; 3],       // size 3, aligned to 1 byte
+    volatility: Volatility,             // size 1, aligned to 1 byte
+}
+
+/// The volatility of a request, which the hypervisor enforces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Volatility {
+    /// The request can update state.
+    #[default]
+    Volatile,
+    /// The request can only read state and can't invoke volatile requests.
+    Query,
 }
 
 /// A message response.
@@ -104,6 +116,7 @@ impl<'a> Request<'a> {
             message_selector,
             inputs_values: Default::default(),
             inputs_types: Default::default(),
+            volatility: Volatility::Volatile,
         }
     }
 
@@ -113,6 +126,7 @@ impl<'a> Request<'a> {
             message_selector,
             inputs_values: [in1.value, ParamValue::default(), ParamValue::default()],
             inputs_types: [in1.typ, ParamType::Empty, ParamType::Empty],
+            volatility: Volatility::Volatile,
         }
     }
 
@@ -122,6 +136,7 @@ impl<'a> Request<'a> {
             message_selector,
             inputs_values: [in1.value, in2.value, ParamValue::default()],
             inputs_types: [in1.typ, in2.typ, ParamType::Empty],
+            volatility: Volatility::Volatile,
         }
     }
 
@@ -136,14 +151,26 @@ impl<'a> Request<'a> {
             message_selector,
             inputs_values: [in1.value, in2.value, in3.value],
             inputs_types: [in1.typ, in2.typ, in3.typ],
+            volatility: Volatility::Volatile,
         }
     }
 
+    /// Sets the volatility of the request.
+    pub fn with_volatility(mut self, volatility: Volatility) -> Self {
+        self.volatility = volatility;
+        self
+    }
+
     /// Get the message selector.
     pub fn message_selector(&self) -> MessageSelector {
         self.message_selector
     }
 
+    /// Get the volatility of the request.
+    pub fn volatility(&self) -> Volatility {
+        self.volatility
+    }
+
     /// Get the first input parameter.
     pub fn in1(&self) -> Param<'a> {
         Param {
@@ -310,9 +310,10 @@ impl<'b, 'a: 'b> crate::decoder::Decoder<'a> for InnerDecoder<'b, 'a> {
     fn decode_enum_variant(
         &mut self,
         visitor: &mut dyn EnumDecodeVisitor<'a>,
-        enum_type: &EnumType,
+        _enum_type: &EnumType,
     ) -> Result<(), DecodeError> {
-        self.outer.decode_enum_variant(visitor, enum_type)
+        let discriminant = self.decode_i32()?;
+        visitor.decode_variant(discriminant, self)
     }
 }
 
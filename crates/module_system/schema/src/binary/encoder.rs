@@ -378,8 +378,11 @@ impl<'b, 'a: 'b, W: Writer> crate::encoder::Encoder for InnerEncoder<'a, 'b, W>
         enum_type: &EnumType,
         value: Option<&dyn ValueCodec>,
     ) -> Result<(), EncodeError> {
-        self.outer
-            .encode_enum_variant(discriminant, enum_type, value)
+        // the value is nested, so it must be length-prefixed like any other nested value
+        if let Some(value) = value {
+            value.encode(self)?;
+        }
+        self.encode_i32(discriminant)
     }
 }
 
@@ -493,8 +496,11 @@ impl crate::encoder::Encoder for InnerEncodeSizer<'_> {
         enum_type: &EnumType,
         value: Option<&dyn ValueCodec>,
     ) -> Result<(), EncodeError> {
-        self.outer
-            .encode_enum_variant(discriminant, enum_type, value)
+        // the value is nested, so it must be length-prefixed like any other nested value
+        if let Some(value) = value {
+            value.encode(self)?;
+        }
+        self.encode_i32(discriminant)
     }
 }
 
@@ -26,6 +26,7 @@ pub mod kind;
 pub mod list;
 pub mod mem;
 pub mod message;
+pub mod page;
 pub mod schema;
 pub mod structs;
 pub mod types;
@@ -119,7 +119,7 @@ impl<'a> ValueCodec<'a> for List<'a, u8> {
 
     fn encode(&self, encoder: &mut dyn Encoder) -> Result<(), EncodeError> {
         match self {
-            List::Empty => Ok(()),
+            List::Empty => encoder.encode_bytes(&[]),
             List::Borrowed(bytes) => encoder.encode_bytes(bytes),
             List::Owned(v) => encoder.encode_bytes(v.as_slice()),
         }
@@ -148,7 +148,7 @@ where
 
     fn encode(&self, encoder: &mut dyn Encoder) -> Result<(), EncodeError> {
         match self {
-            List::Empty => Ok(()),
+            List::Empty => encoder.encode_list(&<&[V]>::default()),
             List::Borrowed(v) => encoder.encode_list(v),
             List::Owned(v) => encoder.encode_list(&v.as_slice()),
         }
@@ -180,4 +180,13 @@ impl<V: Clone> List<'_, V> {
             List::Owned(v) => v.is_empty(),
         }
     }
+
+    /// Return the elements of the list as a slice.
+    pub fn as_slice(&self) -> &[V] {
+        match self {
+            List::Empty => &[],
+            List::Borrowed(v) => v,
+            List::Owned(v) => v.as_slice(),
+        }
+    }
 }
@@ -0,0 +1,319 @@
+//! Standard types for paginated queries.
+//!
+//! A query which returns a list takes a [`PageRequest`] and returns a [`PageResponse`].
+//! The response's cursor is opaque to clients, which just pass it back in the next request
+//! to continue where the previous page ended.
+use crate::value::SchemaValue;
+use ixc_schema_macros::{type_selector, SchemaValue};
+
+/// The number of items returned when a page request doesn't specify a limit.
+pub const DEFAULT_PAGE_LIMIT: u32 = 100;
+
+/// The largest number of items a single page can return.
+/// Requests with a higher limit are clamped to it.
+pub const MAX_PAGE_LIMIT: u32 = 1000;
+
+/// A request for a page of a list.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, SchemaValue, Default)]
+#[sealed]
+pub struct PageRequest<'a> {
+    /// The cursor returned with the previous page, or `None` to request the first page.
+    pub cursor: Option<&'a [u8]>,
+    /// The maximum number of items to return, or zero to use [`DEFAULT_PAGE_LIMIT`].
+    /// Limits above [`MAX_PAGE_LIMIT`] are clamped to it.
+    pub limit: u32,
+}
+
+impl<'a> PageRequest<'a> {
+    /// Create a request for the first page with the given limit.
+    pub const fn new(limit: u32) -> Self {
+        Self {
+            cursor: None,
+            limit,
+        }
+    }
+
+    /// Create a request for the page after the one which returned `cursor`.
+    pub const fn after(cursor: &'a [u8], limit: u32) -> Self {
+        Self {
+            cursor: Some(cursor),
+            limit,
+        }
+    }
+
+    /// The maximum number of items to return.
+    pub const fn limit(&self) -> usize {
+        if self.limit == 0 {
+            DEFAULT_PAGE_LIMIT as usize
+        } else if self.limit > MAX_PAGE_LIMIT {
+            MAX_PAGE_LIMIT as usize
+        } else {
+            self.limit as usize
+        }
+    }
+}
+
+/// Types which can be the items of a [`PageResponse`].
+/// The schema of each page type is a struct with its own name, so that a handler can return
+/// pages of different item types. This is implemented when deriving [`SchemaValue`]
+/// for types without a lifetime parameter.
+pub trait PageItem: for<'a> SchemaValue<'a> {
+    /// The name of the struct type of pages of this item type.
+    const PAGE_NAME: &'static str;
+    /// The type selector of pages of this item type, which is derived from [`Self::PAGE_NAME`]
+    /// in the same way as the type selectors of other structs.
+    const PAGE_TYPE_SELECTOR: u64;
+}
+
+macro_rules! impl_page_item {
+    ($($ty:ty => $name:literal),* $(,)?) => {
+        $(impl PageItem for $ty {
+            const PAGE_NAME: &'static str = $name;
+            const PAGE_TYPE_SELECTOR: u64 = type_selector!($name);
+        })*
+    };
+}
+
+impl_page_item!(
+    u16 => "U16Page",
+    u32 => "U32Page",
+    u64 => "U64Page",
+    u128 => "U128Page",
+    i8 => "I8Page",
+    i16 => "I16Page",
+    i32 => "I32Page",
+    i64 => "I64Page",
+    i128 => "I128Page",
+    bool => "BoolPage",
+    ixc_message_api::AccountID => "AccountIDPage",
+    simple_time::Time => "TimePage",
+    simple_time::Duration => "DurationPage",
+);
+
+#[cfg(feature = "std")]
+impl_page_item!(
+    alloc::string::String => "StringPage",
+    alloc::vec::Vec<u8> => "BytesPage",
+);
+
+#[cfg(feature = "std")]
+pub use response::PageResponse;
+
+#[cfg(feature = "std")]
+mod response {
+    use super::PageItem;
+    use crate::decoder::{DecodeError, Decoder};
+    use crate::encoder::{EncodeError, Encoder};
+    use crate::structs::{StructDecodeVisitor, StructEncodeVisitor, StructSchema, StructType};
+    use crate::types::{to_field, ListElementType, ListT, StructT, TypeVisitor};
+    use crate::value::{SchemaValue, ValueCodec};
+    use alloc::vec::Vec;
+
+    /// A page of a list, which is returned for a [`PageRequest`](super::PageRequest).
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    #[non_exhaustive]
+    pub struct PageResponse<T> {
+        /// The items in the page.
+        pub items: Vec<T>,
+        /// The cursor to request the next page with, or `None` if this is the last page.
+        pub next_cursor: Option<Vec<u8>>,
+    }
+
+    impl<T> PageResponse<T> {
+        /// Create a page with the given items and cursor for the next page.
+        pub fn new(items: Vec<T>, next_cursor: Option<Vec<u8>>) -> Self {
+            Self { items, next_cursor }
+        }
+    }
+
+    impl<T> Default for PageResponse<T> {
+        fn default() -> Self {
+            Self {
+                items: Vec::new(),
+                next_cursor: None,
+            }
+        }
+    }
+
+    // these are written out rather than derived because the derive macro doesn't support type
+    // parameters, and because each item type's pages need a different struct name
+
+    #[allow(private_bounds)]
+    unsafe impl<T> StructSchema for PageResponse<T>
+    where
+        T: PageItem,
+        <T as SchemaValue<'static>>::Type: ListElementType,
+    {
+        const STRUCT_TYPE: StructType<'static> = StructType::new(
+            T::PAGE_NAME,
+            &[
+                to_field::<ListT<<T as SchemaValue<'static>>::Type>>().with_name("items"),
+                to_field::<<Option<Vec<u8>> as SchemaValue<'static>>::Type>()
+                    .with_name("next_cursor"),
+            ],
+            false,
+        );
+
+        const TYPE_SELECTOR: u64 = T::PAGE_TYPE_SELECTOR;
+
+        fn visit_field_types<V: TypeVisitor>(visitor: &mut V) {
+            visitor.visit::<ListT<<T as SchemaValue<'static>>::Type>>();
+            visitor.visit::<<Option<Vec<u8>> as SchemaValue<'static>>::Type>();
+        }
+    }
+
+    #[allow(private_bounds)]
+    unsafe impl<T> StructEncodeVisitor for PageResponse<T>
+    where
+        T: PageItem,
+        <T as SchemaValue<'static>>::Type: ListElementType,
+    {
+        fn encode_field(&self, index: usize, encoder: &mut dyn Encoder) -> Result<(), EncodeError> {
+            match index {
+                0 => encoder.encode_list(&self.items),
+                1 => self.next_cursor.encode(encoder),
+                _ => Err(EncodeError::UnknownError),
+            }
+        }
+    }
+
+    #[allow(private_bounds)]
+    unsafe impl<'a, T> StructDecodeVisitor<'a> for PageResponse<T>
+    where
+        T: PageItem,
+        <T as SchemaValue<'static>>::Type: ListElementType,
+    {
+        fn decode_field(
+            &mut self,
+            index: usize,
+            decoder: &mut dyn Decoder<'a>,
+        ) -> Result<(), DecodeError> {
+            match index {
+                0 => decoder.decode_list(&mut self.items),
+                1 => {
+                    let mut cursor = Vec::new();
+                    self.next_cursor = if decoder.decode_option(&mut cursor)? {
+                        Some(cursor)
+                    } else {
+                        None
+                    };
+                    Ok(())
+                }
+                _ => Err(DecodeError::UnknownField),
+            }
+        }
+    }
+
+    #[allow(private_bounds)]
+    impl<'a, T> ValueCodec<'a> for PageResponse<T>
+    where
+        T: PageItem,
+        <T as SchemaValue<'static>>::Type: ListElementType,
+    {
+        fn decode(&mut self, decoder: &mut dyn Decoder<'a>) -> Result<(), DecodeError> {
+            decoder.decode_struct(self, &Self::STRUCT_TYPE)
+        }
+
+        fn encode(&self, encoder: &mut dyn Encoder) -> Result<(), EncodeError> {
+            encoder.encode_struct(self, &Self::STRUCT_TYPE)
+        }
+    }
+
+    #[allow(private_bounds)]
+    impl<'a, T> SchemaValue<'a> for PageResponse<T>
+    where
+        T: PageItem,
+        <T as SchemaValue<'static>>::Type: ListElementType,
+    {
+        type Type = StructT<Self>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PageItem, PageRequest, PageResponse, MAX_PAGE_LIMIT};
+    use crate::binary::NativeBinaryCodec;
+    use crate::codec::{decode_value, Codec};
+    use crate::json::{decode_value as decode_json, encode_value as encode_json};
+    use crate::mem::MemoryManager;
+    use crate::structs::StructSchema;
+    use ixc_message_api::AccountID;
+    use ixc_schema_macros::SchemaValue;
+
+    extern crate std;
+
+    #[test]
+    fn test_page_roundtrip() {
+        let mem = MemoryManager::new();
+        let page = PageResponse::new(
+            std::vec![AccountID::new(1), AccountID::new(2)],
+            Some(std::vec![0, 1, 0xFF]),
+        );
+        let bz = NativeBinaryCodec.encode_value(&page, &mem).unwrap();
+        let decoded =
+            decode_value::<PageResponse<AccountID>>(&NativeBinaryCodec, bz, &mem).unwrap();
+        assert_eq!(decoded, page);
+
+        let last = PageResponse::<u64>::new(std::vec![7], None);
+        let bz = NativeBinaryCodec.encode_value(&last, &mem).unwrap();
+        let decoded = decode_value::<PageResponse<u64>>(&NativeBinaryCodec, bz, &mem).unwrap();
+        assert_eq!(decoded, last);
+    }
+
+    #[test]
+    fn test_page_json() {
+        let mem = MemoryManager::new();
+        let page = PageResponse::<u64>::new(std::vec![1, 2], Some(std::vec![0, 1, 0xFF]));
+        let mut writer = allocator_api2::vec![];
+        encode_json(&page, &mut writer).unwrap();
+        let json = std::str::from_utf8(&writer).unwrap();
+        // the cursor is rendered as base64
+        assert_eq!(json, r#"{"items":["1","2"],"next_cursor":"AAH/"}"#);
+        assert_eq!(decode_json::<PageResponse<u64>>(json, &mem).unwrap(), page);
+
+        let req = PageRequest::after(&[0, 1, 0xFF], 10);
+        let mut writer = allocator_api2::vec![];
+        encode_json(&req, &mut writer).unwrap();
+        let json = std::str::from_utf8(&writer).unwrap();
+        assert_eq!(json, r#"{"cursor":"AAH/","limit":10}"#);
+        assert_eq!(decode_json::<PageRequest>(json, &mem).unwrap(), req);
+    }
+
+    #[test]
+    fn test_page_names() {
+        assert_eq!(PageResponse::<u64>::STRUCT_TYPE.name, "U64Page");
+        assert_eq!(PageResponse::<AccountID>::STRUCT_TYPE.name, "AccountIDPage");
+        assert_ne!(
+            PageResponse::<u64>::TYPE_SELECTOR,
+            PageResponse::<AccountID>::TYPE_SELECTOR
+        );
+        assert_eq!(PageRequest::<'_>::new(0).limit(), 100);
+
+        // page type selectors are computed like those of derived structs with the same name
+        #[derive(SchemaValue, Default)]
+        #[sealed]
+        struct U64Page {}
+        assert_eq!(PageResponse::<u64>::TYPE_SELECTOR, U64Page::TYPE_SELECTOR);
+        #[derive(SchemaValue, Default)]
+        #[sealed]
+        struct Item {}
+        #[derive(SchemaValue, Default)]
+        #[sealed]
+        struct ItemPage {}
+        assert_eq!(
+            <Item as PageItem>::PAGE_TYPE_SELECTOR,
+            ItemPage::TYPE_SELECTOR
+        );
+    }
+
+    #[test]
+    fn test_page_limit() {
+        assert_eq!(PageRequest::new(0).limit(), 100);
+        assert_eq!(PageRequest::new(7).limit(), 7);
+        assert_eq!(
+            PageRequest::new(MAX_PAGE_LIMIT).limit(),
+            MAX_PAGE_LIMIT as usize
+        );
+        assert_eq!(PageRequest::new(u32::MAX).limit(), MAX_PAGE_LIMIT as usize);
+    }
+}
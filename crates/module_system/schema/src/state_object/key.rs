@@ -22,6 +22,37 @@ pub fn encode_object_key<'b, K: ObjectKey>(
     Ok(writer.finish())
 }
 
+/// Encode the first segment of a multipart object key with the given prefix.
+/// The result is a prefix of the encoding of every key whose first segment is `key`,
+/// so it can be used to iterate over all of those keys.
+pub fn encode_object_key_prefix<'b, A: KeyFieldValue>(
+    prefix: &[u8],
+    key: &A::In<'_>,
+    writer_factory: &'b dyn Allocator,
+) -> Result<&'b [u8], EncodeError> {
+    let out_size = A::out_size(key) + prefix.len();
+    let mut writer = writer_factory.new_reverse(out_size)?;
+    A::encode(key, &mut writer)?;
+    writer.write(prefix)?;
+    Ok(writer.finish())
+}
+
+/// Encode a two-part object key from its parts with the given prefix,
+/// which is the same as encoding the tuple `(a, b)` as an object key.
+pub fn encode_object_key_pair<'b, A: KeyFieldValue, B: KeyFieldValue>(
+    prefix: &[u8],
+    a: &A::In<'_>,
+    b: &B::In<'_>,
+    writer_factory: &'b dyn Allocator,
+) -> Result<&'b [u8], EncodeError> {
+    let out_size = A::out_size(a) + B::out_size_terminal(b) + prefix.len();
+    let mut writer = writer_factory.new_reverse(out_size)?;
+    B::encode_terminal(b, &mut writer)?;
+    A::encode(a, &mut writer)?;
+    writer.write(prefix)?;
+    Ok(writer.finish())
+}
+
 /// Decode an object key. This function assumes that the input has already had any prefix stripped.
 pub fn decode_object_key<'a, K: ObjectKey>(
     input: &'a [u8],
@@ -98,7 +129,7 @@ impl<A: KeyFieldValue> ObjectKey for (A,) {
     }
 
     fn out_size(key: &Self::In<'_>) -> usize {
-        A::out_size_terminal(&key.0)
+        A::out_size(&key.0)
     }
 }
 
@@ -178,3 +209,102 @@ impl<A: KeyFieldValue, B: KeyFieldValue, C: KeyFieldValue, D: KeyFieldValue> Obj
             + D::out_size_terminal(&key.3)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_object_key, encode_object_key};
+    use crate::mem::MemoryManager;
+    use crate::{Bytes, Str};
+    use ixc_message_api::AccountID;
+    use proptest::prelude::*;
+    use simple_time::{Duration, Time};
+
+    /// Checks that the encodings of two keys are ordered like the keys and decode to the keys.
+    macro_rules! check {
+        ($key:ty, $a:expr, $b:expr) => {{
+            let (a, b) = ($a, $b);
+            let mem = MemoryManager::new();
+            let a_bz = encode_object_key::<$key>(&[], &a, &mem).unwrap();
+            let b_bz = encode_object_key::<$key>(&[], &b, &mem).unwrap();
+            prop_assert_eq!(a_bz.cmp(b_bz), a.cmp(&b));
+            prop_assert_eq!(decode_object_key::<$key>(a_bz, &mem).unwrap(), a);
+            prop_assert_eq!(decode_object_key::<$key>(b_bz, &mem).unwrap(), b);
+        }};
+    }
+
+    proptest! {
+        #[test]
+        fn test_unsigned_order(a: (u8, u16, u32, u64, u128), b: (u8, u16, u32, u64, u128)) {
+            check!(u8, a.0, b.0);
+            check!(u16, a.1, b.1);
+            check!(u32, a.2, b.2);
+            check!(u64, a.3, b.3);
+            check!(u128, a.4, b.4);
+        }
+
+        #[test]
+        fn test_signed_order(a: (i8, i16, i32, i64, i128), b: (i8, i16, i32, i64, i128)) {
+            check!(i8, a.0, b.0);
+            check!(i16, a.1, b.1);
+            check!(i32, a.2, b.2);
+            check!(i64, a.3, b.3);
+            check!(i128, a.4, b.4);
+        }
+
+        #[test]
+        fn test_bool_order(a: bool, b: bool) {
+            check!(bool, a, b);
+        }
+
+        #[test]
+        fn test_time_order(a: i128, b: i128) {
+            check!(Time, Time::from_unix_nanos(a), Time::from_unix_nanos(b));
+            check!(Duration, Duration::from_nanos(a), Duration::from_nanos(b));
+        }
+
+        #[test]
+        fn test_account_id_order(a: u128, b: u128) {
+            check!(AccountID, AccountID::new(a), AccountID::new(b));
+        }
+
+        #[test]
+        fn test_string_order(a: String, b: String) {
+            check!(Str, a.as_str(), b.as_str());
+        }
+
+        #[test]
+        fn test_bytes_order(a: Vec<u8>, b: Vec<u8>) {
+            check!(Bytes, a.as_slice(), b.as_slice());
+        }
+
+        #[test]
+        fn test_option_order(a: Option<u64>, b: Option<u64>) {
+            check!(Option<u64>, a, b);
+        }
+
+        #[test]
+        fn test_string_tuple_order(a: (String, u64), b: (String, u64)) {
+            check!((Str, u64), (a.0.as_str(), a.1), (b.0.as_str(), b.1));
+        }
+
+        #[test]
+        fn test_bytes_tuple_order(
+            // small alphabets make shared prefixes and zero bytes likely
+            a in (prop::collection::vec(0u8..3, 0..4), "[\0ab]{0,3}"),
+            b in (prop::collection::vec(0u8..3, 0..4), "[\0ab]{0,3}"),
+        ) {
+            check!((Bytes, Str), (a.0.as_slice(), a.1.as_str()), (b.0.as_slice(), b.1.as_str()));
+            check!((Bytes, Str, u8), (a.0.as_slice(), a.1.as_str(), 1), (b.0.as_slice(), b.1.as_str(), 1));
+            check!((Bytes,), (a.0.as_slice(),), (b.0.as_slice(),));
+        }
+
+        #[test]
+        fn test_optional_tuple_order(
+            a in (any::<i64>(), prop::option::of("[\0ab]{0,3}")),
+            b in (any::<i64>(), prop::option::of("[\0ab]{0,3}")),
+        ) {
+            check!((i64, Option<Str>), (a.0, a.1.as_deref()), (b.0, b.1.as_deref()));
+            check!((Option<Str>, i64), (a.1.as_deref(), a.0), (b.1.as_deref(), b.0));
+        }
+    }
+}
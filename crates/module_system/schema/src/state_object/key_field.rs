@@ -5,8 +5,25 @@ use crate::encoder::EncodeError;
 use crate::mem::MemoryManager;
 use crate::state_object::value_field::{Bytes, ObjectFieldValue};
 use crate::Str;
+use allocator_api2::vec::Vec;
 
 /// This trait is implemented for types that can be used as key fields in state objects.
+///
+/// Key fields are encoded so that the byte order of encoded keys is the same as the order
+/// of the values they encode, which lets collections iterate over ranges of keys in order:
+/// - unsigned integers are encoded as big-endian bytes
+/// - signed integers, [`simple_time::Time`] and [`simple_time::Duration`] are encoded
+///   as big-endian bytes with the sign bit flipped, so that negative values come first
+/// - `bool` is encoded as a single 0 or 1 byte
+/// - [`ixc_message_api::AccountID`] is encoded as its ID in big-endian bytes
+/// - strings and bytes are encoded as is when they are the last field of a key, and otherwise
+///   with each 0x00 byte escaped as 0x00 0xFF and terminated by 0x00 0x00,
+///   so that a string sorts before any longer string it is a prefix of
+/// - `Option` is encoded as a 0 byte for `None` and a 1 byte followed by the value for `Some`,
+///   so that `None` comes first
+///
+/// Keys with multiple fields are encoded as the concatenation of their fields,
+/// so they are ordered by their first field, then their second field and so on.
 pub trait KeyFieldValue: ObjectFieldValue {
     /// Encode the key segment as a non-terminal segment.
     fn encode(key: &Self::In<'_>, writer: &mut ReverseSliceWriter) -> Result<(), EncodeError>;
@@ -40,6 +57,9 @@ pub trait KeyFieldValue: ObjectFieldValue {
     fn out_size_terminal(key: &Self::In<'_>) -> usize {
         Self::out_size(key)
     }
+
+    /// Borrow a decoded key segment as the type used to encode it.
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a>;
 }
 
 impl KeyFieldValue for u8 {
@@ -57,6 +77,10 @@ impl KeyFieldValue for u8 {
     fn out_size(key: &Self::In<'_>) -> usize {
         1
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for u16 {
@@ -75,6 +99,10 @@ impl KeyFieldValue for u16 {
     fn out_size(key: &Self::In<'_>) -> usize {
         2
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for u32 {
@@ -93,6 +121,10 @@ impl KeyFieldValue for u32 {
     fn out_size(key: &Self::In<'_>) -> usize {
         4
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for u64 {
@@ -111,6 +143,10 @@ impl KeyFieldValue for u64 {
     fn out_size(key: &Self::In<'_>) -> usize {
         8
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for u128 {
@@ -129,6 +165,10 @@ impl KeyFieldValue for u128 {
     fn out_size(key: &Self::In<'_>) -> usize {
         16
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for i8 {
@@ -150,6 +190,10 @@ impl KeyFieldValue for i8 {
     fn out_size(key: &Self::In<'_>) -> usize {
         1
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for i16 {
@@ -171,6 +215,10 @@ impl KeyFieldValue for i16 {
     fn out_size(key: &Self::In<'_>) -> usize {
         2
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for i32 {
@@ -190,6 +238,10 @@ impl KeyFieldValue for i32 {
     fn out_size(key: &Self::In<'_>) -> usize {
         4
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for i64 {
@@ -209,6 +261,10 @@ impl KeyFieldValue for i64 {
     fn out_size(key: &Self::In<'_>) -> usize {
         8
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for i128 {
@@ -228,6 +284,10 @@ impl KeyFieldValue for i128 {
     fn out_size(key: &Self::In<'_>) -> usize {
         16
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for bool {
@@ -245,6 +305,10 @@ impl KeyFieldValue for bool {
     fn out_size(key: &Self::In<'_>) -> usize {
         1
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for simple_time::Time {
@@ -262,7 +326,11 @@ impl KeyFieldValue for simple_time::Time {
     }
 
     fn out_size(key: &Self::In<'_>) -> usize {
-        12
+        16
+    }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
     }
 }
 
@@ -281,7 +349,11 @@ impl KeyFieldValue for simple_time::Duration {
     }
 
     fn out_size(key: &Self::In<'_>) -> usize {
-        12
+        16
+    }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
     }
 }
 
@@ -295,7 +367,7 @@ impl KeyFieldValue for ixc_message_api::AccountID {
         reader: &mut &'a [u8],
         _memory_manager: &'a MemoryManager,
     ) -> Result<Self::Out<'a>, DecodeError> {
-        let bz = reader.read_bytes(8)?;
+        let bz = reader.read_bytes(16)?;
         Ok(ixc_message_api::AccountID::new(u128::from_be_bytes(
             bz.try_into().unwrap(),
         )))
@@ -304,20 +376,22 @@ impl KeyFieldValue for ixc_message_api::AccountID {
     fn out_size(_key: &Self::In<'_>) -> usize {
         16
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for Str {
     fn encode(key: &Self::In<'_>, writer: &mut ReverseSliceWriter) -> Result<(), EncodeError> {
-        // write null terminator
-        writer.write(&[0])?;
-        writer.write(key.as_bytes())
+        encode_escaped(key.as_bytes(), writer)
     }
 
     fn encode_terminal(
         key: &Self::In<'_>,
         writer: &mut ReverseSliceWriter,
     ) -> Result<(), EncodeError> {
-        // no null terminator needed
+        // no terminator needed
         writer.write(key.as_bytes())
     }
 
@@ -325,13 +399,8 @@ impl KeyFieldValue for Str {
         reader: &mut &'a [u8],
         memory_manager: &'a MemoryManager,
     ) -> Result<Self::Out<'a>, DecodeError> {
-        let mut i = 0;
-        while reader[i] != 0 {
-            i += 1;
-        }
-        let s = core::str::from_utf8(&reader[..i]).map_err(|_| DecodeError::InvalidUtf8)?;
-        *reader = &reader[i + 1..];
-        Ok(s)
+        let bz = decode_escaped(reader, memory_manager)?;
+        core::str::from_utf8(bz).map_err(|_| DecodeError::InvalidUtf8)
     }
 
     fn decode_terminal<'a>(
@@ -344,19 +413,21 @@ impl KeyFieldValue for Str {
     }
 
     fn out_size(key: &Self::In<'_>) -> usize {
-        key.len() + 1
+        escaped_size(key.as_bytes())
     }
 
     fn out_size_terminal(key: &Self::In<'_>) -> usize {
         key.len()
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
 }
 
 impl KeyFieldValue for Bytes {
     fn encode(key: &Self::In<'_>, writer: &mut ReverseSliceWriter) -> Result<(), EncodeError> {
-        writer.write(key)?;
-        // write length
-        writer.write(&(key.len() as u32).to_be_bytes())
+        encode_escaped(key, writer)
     }
 
     fn encode_terminal(
@@ -370,22 +441,148 @@ impl KeyFieldValue for Bytes {
         reader: &mut &'a [u8],
         memory_manager: &'a MemoryManager,
     ) -> Result<Self::Out<'a>, DecodeError> {
-        let len = u32::from_be_bytes(reader.read_bytes(4)?.try_into().unwrap()) as usize;
-        let key = reader.read_bytes(len)?;
-        Ok(key)
+        decode_escaped(reader, memory_manager)
     }
 
     fn decode_terminal<'a>(
         reader: &mut &'a [u8],
         memory_manager: &'a MemoryManager,
     ) -> Result<Self::Out<'a>, DecodeError> {
-        Ok(reader)
+        let key = *reader;
+        *reader = &[];
+        Ok(key)
     }
 
     fn out_size(key: &Self::In<'_>) -> usize {
-        key.len() + 4
+        escaped_size(key)
     }
+
     fn out_size_terminal(key: &Self::In<'_>) -> usize {
         key.len()
     }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
+}
+
+impl<V> KeyFieldValue for Option<V>
+where
+    V: KeyFieldValue,
+    for<'a> V: ObjectFieldValue<Out<'a> = <V as ObjectFieldValue>::In<'a>>,
+{
+    fn encode(key: &Self::In<'_>, writer: &mut ReverseSliceWriter) -> Result<(), EncodeError> {
+        match key {
+            None => writer.write(&[0]),
+            Some(key) => {
+                V::encode(key, writer)?;
+                writer.write(&[1])
+            }
+        }
+    }
+
+    fn encode_terminal(
+        key: &Self::In<'_>,
+        writer: &mut ReverseSliceWriter,
+    ) -> Result<(), EncodeError> {
+        match key {
+            None => writer.write(&[0]),
+            Some(key) => {
+                V::encode_terminal(key, writer)?;
+                writer.write(&[1])
+            }
+        }
+    }
+
+    fn decode<'a>(
+        reader: &mut &'a [u8],
+        memory_manager: &'a MemoryManager,
+    ) -> Result<Self::Out<'a>, DecodeError> {
+        match reader.read_bytes(1)?[0] {
+            0 => Ok(None),
+            1 => Ok(Some(V::decode(reader, memory_manager)?)),
+            _ => Err(DecodeError::InvalidData),
+        }
+    }
+
+    fn decode_terminal<'a>(
+        reader: &mut &'a [u8],
+        memory_manager: &'a MemoryManager,
+    ) -> Result<Self::Out<'a>, DecodeError> {
+        match reader.read_bytes(1)?[0] {
+            0 => Ok(None),
+            1 => Ok(Some(V::decode_terminal(reader, memory_manager)?)),
+            _ => Err(DecodeError::InvalidData),
+        }
+    }
+
+    fn out_size(key: &Self::In<'_>) -> usize {
+        1 + key.as_ref().map_or(0, |key| V::out_size(key))
+    }
+
+    fn out_size_terminal(key: &Self::In<'_>) -> usize {
+        1 + key.as_ref().map_or(0, |key| V::out_size_terminal(key))
+    }
+
+    fn out_as_in<'b, 'a>(out: &'b Self::Out<'a>) -> &'b Self::In<'a> {
+        out
+    }
+}
+
+/// Encodes a non-terminal byte string so that it can't be a prefix of another encoded string
+/// and the order of encoded strings is the same as the order of the strings:
+/// each 0x00 byte is escaped as 0x00 0xFF and the string is terminated by 0x00 0x00.
+fn encode_escaped(bz: &[u8], writer: &mut ReverseSliceWriter) -> Result<(), EncodeError> {
+    writer.write(&[0, 0])?;
+    // the writer writes backwards, so the chunks between 0x00 bytes are written in reverse
+    let mut chunks = bz.split(|b| *b == 0).rev();
+    if let Some(last) = chunks.next() {
+        writer.write(last)?;
+    }
+    for chunk in chunks {
+        writer.write(&[0, 0xFF])?;
+        writer.write(chunk)?;
+    }
+    Ok(())
+}
+
+fn escaped_size(bz: &[u8]) -> usize {
+    bz.len() + bz.iter().filter(|b| **b == 0).count() + 2
+}
+
+/// Decodes a byte string encoded with [`encode_escaped`], which is only copied
+/// if it had any escaped 0x00 bytes.
+fn decode_escaped<'a>(
+    reader: &mut &'a [u8],
+    memory_manager: &'a MemoryManager,
+) -> Result<&'a [u8], DecodeError> {
+    let input = *reader;
+    let mut escapes = 0;
+    let mut i = 0;
+    let end = loop {
+        match input.get(i..i + 2) {
+            None => return Err(DecodeError::OutOfData),
+            Some([0, 0]) => break i,
+            Some([0, 0xFF]) => {
+                escapes += 1;
+                i += 2;
+            }
+            Some([0, _]) => return Err(DecodeError::InvalidData),
+            Some(_) => i += 1,
+        }
+    };
+    *reader = &input[end + 2..];
+    if escapes == 0 {
+        return Ok(&input[..end]);
+    }
+    let mut bz = Vec::with_capacity_in(end - escapes, memory_manager);
+    let mut escaped = input[..end].iter();
+    while let Some(b) = escaped.next() {
+        bz.push(*b);
+        if *b == 0 {
+            // skip the 0xFF after an escaped 0x00
+            escaped.next();
+        }
+    }
+    Ok(bz.leak())
 }
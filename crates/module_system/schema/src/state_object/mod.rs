@@ -11,10 +11,13 @@ use crate::encoding::Encoding;
 use crate::field::Field;
 use crate::list::List;
 use ixc_schema_macros::SchemaValue;
-pub use key::{decode_object_key, encode_object_key, ObjectKey};
+pub use key::{
+    decode_object_key, encode_object_key, encode_object_key_pair, encode_object_key_prefix,
+    ObjectKey,
+};
 pub use key_field::KeyFieldValue;
 pub use prefix::PrefixKey;
-pub use value::{decode_object_value, encode_object_value, ObjectValue};
+pub use value::{decode_object_value, encode_object_value, ObjectValue, ValueField};
 pub use value_field::{Bytes, ObjectFieldValue, Str};
 
 /// A type representing objects stored in key-value store state.
@@ -40,4 +43,25 @@ pub struct StateObjectDescriptor<'a> {
     /// which is the first part of its key.
     /// The first part of the key must be an account ID if this is true.
     pub is_account_scoped: bool,
+    /// Indicates that the state object is a sequence, a counter which only increases
+    /// unless it is explicitly set. Sequences are also accumulators.
+    pub is_sequence: bool,
+    /// The secondary indexes of the state object which are updated along with it.
+    pub indexes: List<'a, IndexDescriptor<'a>>,
+}
+
+/// A secondary index of a state object.
+/// Each entry of the index has a key made of the indexed fields followed by
+/// the primary key of the object it refers to, and an empty value.
+#[derive(Debug, Clone, Eq, PartialEq, SchemaValue, Default)]
+#[non_exhaustive]
+pub struct IndexDescriptor<'a> {
+    /// The name of the index.
+    pub name: &'a str,
+    /// The bytes prefix of the index.
+    pub prefix: List<'a, u8>,
+    /// The indexed fields followed by the primary key fields.
+    pub key_fields: List<'a, Field<'a>>,
+    /// Whether each combination of the indexed fields may only refer to a single object.
+    pub unique: bool,
 }
@@ -7,6 +7,7 @@ use crate::encoder::{EncodeError, Encoder};
 use crate::fields::FieldTypes;
 use crate::mem::MemoryManager;
 use crate::state_object::field_types::unnamed_struct_type;
+use crate::state_object::key_field::KeyFieldValue;
 use crate::state_object::value_field::ObjectFieldValue;
 use crate::structs::StructType;
 use crate::value::SchemaValue;
@@ -200,3 +201,157 @@ impl<A: ObjectFieldValue, B: ObjectFieldValue, C: ObjectFieldValue, D: ObjectFie
         ))
     }
 }
+
+/// This trait is implemented for object values whose field at position `I`
+/// can be used as a key field, for instance to index the objects by that field.
+pub trait ValueField<const I: usize>: ObjectValue {
+    /// The type of the field.
+    type Field: KeyFieldValue;
+
+    /// Borrow the field from the value.
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b <Self::Field as ObjectFieldValue>::In<'a>;
+
+    /// Borrow the field from a decoded value.
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>)
+        -> &'b <Self::Field as ObjectFieldValue>::In<'a>;
+}
+
+impl<A: KeyFieldValue> ValueField<0> for A {
+    type Field = A;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b A::In<'a> {
+        value
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b A::In<'a> {
+        A::out_as_in(value)
+    }
+}
+
+impl<A: KeyFieldValue> ValueField<0> for (A,) {
+    type Field = A;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b A::In<'a> {
+        &value.0
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b A::In<'a> {
+        A::out_as_in(&value.0)
+    }
+}
+
+impl<A: KeyFieldValue, B: ObjectFieldValue> ValueField<0> for (A, B) {
+    type Field = A;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b A::In<'a> {
+        &value.0
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b A::In<'a> {
+        A::out_as_in(&value.0)
+    }
+}
+
+impl<A: ObjectFieldValue, B: KeyFieldValue> ValueField<1> for (A, B) {
+    type Field = B;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b B::In<'a> {
+        &value.1
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b B::In<'a> {
+        B::out_as_in(&value.1)
+    }
+}
+
+impl<A: KeyFieldValue, B: ObjectFieldValue, C: ObjectFieldValue> ValueField<0> for (A, B, C) {
+    type Field = A;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b A::In<'a> {
+        &value.0
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b A::In<'a> {
+        A::out_as_in(&value.0)
+    }
+}
+
+impl<A: ObjectFieldValue, B: KeyFieldValue, C: ObjectFieldValue> ValueField<1> for (A, B, C) {
+    type Field = B;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b B::In<'a> {
+        &value.1
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b B::In<'a> {
+        B::out_as_in(&value.1)
+    }
+}
+
+impl<A: ObjectFieldValue, B: ObjectFieldValue, C: KeyFieldValue> ValueField<2> for (A, B, C) {
+    type Field = C;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b C::In<'a> {
+        &value.2
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b C::In<'a> {
+        C::out_as_in(&value.2)
+    }
+}
+
+impl<A: KeyFieldValue, B: ObjectFieldValue, C: ObjectFieldValue, D: ObjectFieldValue> ValueField<0>
+    for (A, B, C, D)
+{
+    type Field = A;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b A::In<'a> {
+        &value.0
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b A::In<'a> {
+        A::out_as_in(&value.0)
+    }
+}
+
+impl<A: ObjectFieldValue, B: KeyFieldValue, C: ObjectFieldValue, D: ObjectFieldValue> ValueField<1>
+    for (A, B, C, D)
+{
+    type Field = B;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b B::In<'a> {
+        &value.1
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b B::In<'a> {
+        B::out_as_in(&value.1)
+    }
+}
+
+impl<A: ObjectFieldValue, B: ObjectFieldValue, C: KeyFieldValue, D: ObjectFieldValue> ValueField<2>
+    for (A, B, C, D)
+{
+    type Field = C;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b C::In<'a> {
+        &value.2
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b C::In<'a> {
+        C::out_as_in(&value.2)
+    }
+}
+
+impl<A: ObjectFieldValue, B: ObjectFieldValue, C: ObjectFieldValue, D: KeyFieldValue> ValueField<3>
+    for (A, B, C, D)
+{
+    type Field = D;
+
+    fn field<'b, 'a>(value: &'b Self::In<'a>) -> &'b D::In<'a> {
+        &value.3
+    }
+
+    fn field_out<'b, 'a>(value: &'b Self::Out<'a>) -> &'b D::In<'a> {
+        D::out_as_in(&value.3)
+    }
+}
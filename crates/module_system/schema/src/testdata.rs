@@ -18,6 +18,8 @@ pub(crate) struct ABitOfEverything {
     pub(crate) op: Option<Prims>,
     pub(crate) e: TestEnum,
     pub(crate) ef: TestEnumWithFields,
+    pub(crate) lef: Vec<TestEnumWithFields>,
+    pub(crate) oef: Option<TestEnumWithFields>,
 }
 
 #[derive(SchemaValue, Default, Debug, Eq, PartialEq, Arbitrary)]
@@ -472,3 +472,4 @@ impl<'a> ListElementValue<'a> for &'a [u8] {}
 impl ListElementValue<'_> for alloc::vec::Vec<u8> {}
 impl ListElementValue<'_> for simple_time::Time {}
 impl ListElementValue<'_> for simple_time::Duration {}
+impl ListElementValue<'_> for ixc_message_api::AccountID {}
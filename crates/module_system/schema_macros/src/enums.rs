@@ -1,4 +1,4 @@
-use crate::util::{extract_generics, is_sealed, mk_ixc_schema_path, GenericInfo};
+use crate::util::{extract_generics, is_sealed, mk_ixc_schema_path, page_item_impl, GenericInfo};
 use deluxe::ParseAttributes;
 use manyhow::bail;
 use proc_macro2::{Ident, TokenStream as TokenStream2};
@@ -22,6 +22,7 @@ pub(crate) fn derive_enum_schema(
     } = extract_generics(input)?;
 
     let is_sealed = is_sealed(input)?;
+    let page_item = page_item_impl(input);
 
     // extract repr attribute
     let mut repr = "i32";
@@ -191,6 +192,8 @@ pub(crate) fn derive_enum_schema(
             type In< #lifetime2 > = #enum_name #ty_generics2;
             type Out< #lifetime2 > = #enum_name #ty_generics2;
         }
+
+        #page_item
     };
     Ok(res)
 }
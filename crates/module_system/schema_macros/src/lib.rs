@@ -7,10 +7,11 @@ mod structs;
 mod util;
 
 use crate::enums::derive_enum_schema;
+use crate::mesage_selector::type_selector_from_str;
 use crate::structs::derive_struct_schema;
 use manyhow::{bail, manyhow};
 use proc_macro2::TokenStream as TokenStream2;
-use syn::Data;
+use syn::{Data, LitStr};
 
 /// This derives a struct or enum codec.
 /// The struct or enum must implement Default.
@@ -25,3 +26,11 @@ pub fn derive_schema_value(input: syn::DeriveInput) -> manyhow::Result<TokenStre
         _ => bail!("only know how to derive SchemaValue for structs"),
     }
 }
+
+/// Computes the type selector of a struct with the given name,
+/// which is the same as the type selector of a derived struct with that name.
+#[manyhow]
+#[proc_macro]
+pub fn type_selector(name: LitStr) -> manyhow::Result<TokenStream2> {
+    Ok(type_selector_from_str(&name.value()))
+}
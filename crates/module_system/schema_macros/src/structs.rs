@@ -1,5 +1,5 @@
 use crate::mesage_selector::type_selector_from_str;
-use crate::util::{extract_generics, is_sealed, mk_ixc_schema_path, GenericInfo};
+use crate::util::{extract_generics, is_sealed, mk_ixc_schema_path, page_item_impl, GenericInfo};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::DataStruct;
@@ -21,6 +21,7 @@ pub(crate) fn derive_struct_schema(
     } = extract_generics(input)?;
 
     let sealed = is_sealed(input)?;
+    let page_item = page_item_impl(input);
 
     let visit_field_types = str.fields.iter().map(|field| {
         let field_type = &field.ty;
@@ -105,5 +106,7 @@ pub(crate) fn derive_struct_schema(
             type In< #lifetime2 > = #struct_name #ty_generics2;
             type Out< #lifetime2 > = #struct_name #ty_generics2;
         }
+
+        #page_item
     })
 }
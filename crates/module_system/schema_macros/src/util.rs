@@ -1,3 +1,4 @@
+use crate::mesage_selector::type_selector_from_str;
 use manyhow::bail;
 use proc_macro2::TokenStream as TokenStream2;
 use proc_macro2::{Ident, Span, TokenStream};
@@ -76,3 +77,20 @@ pub(crate) struct GenericInfo<'a> {
     pub(crate) where_clause: Option<&'a syn::WhereClause>,
     pub(crate) ty_generics: TypeGenerics<'a>,
 }
+
+// types without a lifetime parameter can be the items of a page response,
+// whose schema struct is named after the item type
+pub(crate) fn page_item_impl(input: &syn::DeriveInput) -> TokenStream2 {
+    if input.generics.params.iter().next().is_some() {
+        return quote! {};
+    }
+    let ixc_schema_path = mk_ixc_schema_path();
+    let name = &input.ident;
+    let page_type_selector = type_selector_from_str(&format!("{}Page", name));
+    quote! {
+        impl #ixc_schema_path::page::PageItem for #name {
+            const PAGE_NAME: &'static str = concat!(stringify!(#name), "Page");
+            const PAGE_TYPE_SELECTOR: u64 = #page_type_selector;
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! Declarative genesis files for bootstrapping a [`TestApp`] from JSON.
+use crate::TestApp;
+use ixc_account_manager::native_vm::NativeVM;
+use ixc_account_manager::AccountManager;
+use ixc_core::account_api::{
+    create_account_raw, create_account_raw_at, get_handler_id, ROOT_ACCOUNT,
+};
+use ixc_core::handler::Handler;
+use ixc_core::message::InitMessage;
+use ixc_core::resource::{InitializationError, ResourceScope};
+use ixc_message_api::handler::RawHandler;
+use ixc_message_api::AccountID;
+use ixc_schema::codec::Codec;
+use ixc_schema::mem::MemoryManager;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// A genesis file which describes the initial accounts and state of an app.
+///
+/// Accounts are set up in order and each account is either created by running its handler's
+/// `on_create` with an `Init` message, or imported directly from its exported storage.
+/// [`TestApp::export_genesis`] produces a genesis file which imports the app's current state.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Genesis {
+    /// The IDs of the handlers which must be registered with the app.
+    #[serde(default)]
+    pub handlers: Vec<String>,
+    /// Client bindings from client names to account IDs which are provided to every handler.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, String>,
+    /// The accounts to set up, in order.
+    #[serde(default)]
+    pub accounts: Vec<GenesisAccount>,
+}
+
+/// An account in a [`Genesis`] file.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct GenesisAccount {
+    /// The account ID as a decimal string.
+    /// It is required for imported accounts and it fixes the ID of created accounts,
+    /// which is useful for system services.
+    /// Each ID can only be used once, and the app's ID generator skips IDs which are taken.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The ID of the account's handler.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handler: Option<String>,
+    /// The handler's `Init` message encoded as JSON, which defaults to an empty object.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init: Option<serde_json::Value>,
+    /// The account's storage as hex-encoded keys and values,
+    /// in which case the account is imported instead of created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<BTreeMap<String, String>>,
+    /// The values of the account's accumulators, such as sequences,
+    /// by hex-encoded key as decimal strings, which are imported along with its storage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accumulators: Option<BTreeMap<String, String>>,
+}
+
+/// The result of loading a [`Genesis`] file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenesisResult {
+    /// A hash of the app's state after genesis.
+    pub state_root: [u8; 32],
+    /// The handler IDs of the accounts which were set up.
+    pub accounts: BTreeMap<AccountID, String>,
+}
+
+/// An error loading a [`Genesis`] file.
+#[derive(Debug, Error)]
+pub enum GenesisError {
+    /// The genesis file isn't valid JSON.
+    #[error("invalid genesis JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// Genesis can only be loaded into an app with empty state.
+    #[error("the app state is not empty")]
+    StateNotEmpty,
+    /// A handler in the genesis file isn't registered with the app.
+    #[error("handler {0} is not registered")]
+    HandlerNotRegistered(String),
+    /// An account ID isn't a valid decimal integer.
+    #[error("invalid account ID {0}")]
+    InvalidAccountID(String),
+    /// A state key or value isn't valid hex.
+    #[error("invalid hex {0}")]
+    InvalidHex(String),
+    /// An accumulator value isn't a valid decimal integer.
+    #[error("invalid accumulator value {0}")]
+    InvalidAccumulator(String),
+    /// The account at the index has neither a handler nor state.
+    #[error("account {0} must have a handler or state")]
+    MissingHandler(usize),
+    /// The imported account at the index doesn't have an ID.
+    #[error("imported account {0} must have an ID")]
+    MissingAccountID(usize),
+    /// More than one account in the genesis file has the ID,
+    /// or a fixed ID was already assigned to an account created earlier in the file.
+    #[error("duplicate account ID {0}")]
+    DuplicateAccountID(String),
+    /// The init message of the account at the index couldn't be encoded.
+    #[error("invalid init message for account {0}")]
+    InvalidInit(usize),
+    /// A handler couldn't be instantiated with the client bindings.
+    #[error("failed to bind clients for handler {0}: {1:?}")]
+    Binding(String, InitializationError),
+    /// The account at the index couldn't be created or imported.
+    #[error("failed to set up account {index}: {message}")]
+    Account {
+        /// The index of the account.
+        index: usize,
+        /// The error message.
+        message: String,
+    },
+}
+
+impl Genesis {
+    /// Parses a genesis file from JSON.
+    pub fn from_json(json: &str) -> Result<Self, GenesisError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Encodes the genesis file as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// Instantiates registered handlers and encodes their init messages for genesis.
+#[derive(Clone, Copy)]
+pub(crate) struct HandlerFactory {
+    new_handler: fn(&ResourceScope) -> Result<Box<dyn RawHandler>, InitializationError>,
+    encode_init: for<'a> fn(&'a str, &'a MemoryManager) -> Option<&'a [u8]>,
+}
+
+impl HandlerFactory {
+    pub(crate) fn of<H: Handler>() -> Self {
+        Self {
+            new_handler: new_handler::<H>,
+            encode_init: encode_init::<H>,
+        }
+    }
+}
+
+fn new_handler<H: Handler>(
+    scope: &ResourceScope,
+) -> Result<Box<dyn RawHandler>, InitializationError> {
+    Ok(Box::new(unsafe { H::new(scope)? }))
+}
+
+fn encode_init<'a, H: Handler>(json: &'a str, mem: &'a MemoryManager) -> Option<&'a [u8]> {
+    let init = ixc_schema::json::decode_value::<H::Init<'a>>(json, mem).ok()?;
+    let cdc = <H::Init<'a> as InitMessage<'a>>::Codec::default();
+    cdc.encode_value(&init, mem).ok()
+}
+
+fn parse_account_id(id: &str) -> Result<AccountID, GenesisError> {
+    id.parse::<u128>()
+        .map(AccountID::new)
+        .map_err(|_| GenesisError::InvalidAccountID(id.to_string()))
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>, GenesisError> {
+    hex::decode(s).map_err(|_| GenesisError::InvalidHex(s.to_string()))
+}
+
+fn parse_accumulator(s: &str) -> Result<u128, GenesisError> {
+    s.parse::<u128>()
+        .map_err(|_| GenesisError::InvalidAccumulator(s.to_string()))
+}
+
+impl<V: NativeVM + 'static> TestApp<V> {
+    /// Loads a genesis file into an app with empty state.
+    /// Any client bindings are applied to every registered handler,
+    /// then the accounts are created or imported in order.
+    pub fn load_genesis(&self, genesis: &Genesis) -> Result<GenesisResult, GenesisError> {
+        let bindings = genesis
+            .bindings
+            .iter()
+            .map(|(name, id)| Ok((name.as_str(), parse_account_id(id)?)))
+            .collect::<Result<BTreeMap<&str, AccountID>, GenesisError>>()?;
+        let mut fixed_ids = BTreeSet::new();
+        for id in genesis.accounts.iter().filter_map(|a| a.id.as_deref()) {
+            if !fixed_ids.insert(parse_account_id(id)?) {
+                return Err(GenesisError::DuplicateAccountID(id.to_string()));
+            }
+        }
+        {
+            let mut backend = self.backend.lock().unwrap();
+            if !backend.state.is_empty() {
+                return Err(GenesisError::StateNotEmpty);
+            }
+            for handler in &genesis.handlers {
+                if !backend.handler_factories.contains_key(handler) {
+                    return Err(GenesisError::HandlerNotRegistered(handler.clone()));
+                }
+            }
+            // re-instantiate the registered handlers so that their clients use the bindings
+            if !bindings.is_empty() {
+                let scope = ResourceScope {
+                    account_resolver: Some(&bindings),
+                    ..Default::default()
+                };
+                let factories: Vec<(String, HandlerFactory)> = backend
+                    .handler_factories
+                    .iter()
+                    .map(|(name, factory)| (name.clone(), *factory))
+                    .collect();
+                for (name, factory) in factories {
+                    let handler = (factory.new_handler)(&scope)
+                        .map_err(|err| GenesisError::Binding(name.clone(), err))?;
+                    backend.vm.register_handler(&name, handler);
+                }
+            }
+            backend.bindings = genesis.bindings.clone();
+        }
+
+        let mut root = self.client_context_for(ROOT_ACCOUNT);
+        let mut accounts = BTreeMap::new();
+        for (index, account) in genesis.accounts.iter().enumerate() {
+            let id = account.id.as_deref().map(parse_account_id).transpose()?;
+            // a fixed ID can't take over an account which was created earlier in the file
+            if let Some(id) = id.filter(|id| accounts.contains_key(id)) {
+                return Err(GenesisError::DuplicateAccountID(u128::from(id).to_string()));
+            }
+            if account.state.is_some() || account.accumulators.is_some() {
+                let id = id.ok_or(GenesisError::MissingAccountID(index))?;
+                let state = account
+                    .state
+                    .iter()
+                    .flatten()
+                    .map(|(key, value)| Ok((parse_hex(key)?, parse_hex(value)?)))
+                    .collect::<Result<Vec<_>, GenesisError>>()?;
+                let accumulators = account
+                    .accumulators
+                    .iter()
+                    .flatten()
+                    .map(|(key, value)| Ok((parse_hex(key)?, parse_accumulator(value)?)))
+                    .collect::<Result<Vec<_>, GenesisError>>()?;
+                self.import_account(id, account.handler.as_deref(), &state, &accumulators)
+                    .map_err(|message| GenesisError::Account { index, message })?;
+                if let Some(handler) = &account.handler {
+                    accounts.insert(id, handler.clone());
+                }
+            } else {
+                let handler = account
+                    .handler
+                    .as_ref()
+                    .ok_or(GenesisError::MissingHandler(index))?;
+                let factory = self
+                    .backend
+                    .lock()
+                    .unwrap()
+                    .handler_factories
+                    .get(handler)
+                    .copied()
+                    .ok_or_else(|| GenesisError::HandlerNotRegistered(handler.clone()))?;
+                let init_json = account
+                    .init
+                    .as_ref()
+                    .map_or_else(|| "{}".to_string(), |init| init.to_string());
+                let init = (factory.encode_init)(&init_json, &self.mem)
+                    .ok_or(GenesisError::InvalidInit(index))?;
+                let res = match id {
+                    Some(id) => create_account_raw_at(&mut root, id, handler, init),
+                    None => create_account_raw(&mut root, handler, init),
+                };
+                let id = res.map_err(|err| GenesisError::Account {
+                    index,
+                    message: err.to_string(),
+                })?;
+                accounts.insert(id, handler.clone());
+            }
+        }
+
+        Ok(GenesisResult {
+            state_root: self.state_root(),
+            accounts,
+        })
+    }
+
+    /// Exports the app's current state as a genesis file which imports every account's storage
+    /// and accumulators.
+    pub fn export_genesis(&self) -> Genesis {
+        let (accounts, bindings) = {
+            let backend = self.backend.lock().unwrap();
+            (backend.state.accounts(), backend.bindings.clone())
+        };
+        let root = self.client_context_for(ROOT_ACCOUNT);
+        let mut handlers = vec![];
+        let accounts = accounts
+            .into_iter()
+            .map(|(id, state, accumulators)| {
+                // system accounts such as the root account don't have handlers
+                let handler = get_handler_id(&root, id).ok().map(|h| h.to_string());
+                if let Some(handler) = &handler {
+                    if !handlers.contains(handler) {
+                        handlers.push(handler.clone());
+                    }
+                }
+                GenesisAccount {
+                    id: Some(u128::from(id).to_string()),
+                    handler,
+                    init: None,
+                    state: Some(
+                        state
+                            .iter()
+                            .map(|(key, value)| (hex::encode(key), hex::encode(value)))
+                            .collect(),
+                    ),
+                    accumulators: (!accumulators.is_empty()).then(|| {
+                        accumulators
+                            .iter()
+                            .map(|(key, value)| (hex::encode(key), value.to_string()))
+                            .collect()
+                    }),
+                }
+            })
+            .collect();
+        handlers.sort();
+        Genesis {
+            handlers,
+            bindings,
+            accounts,
+        }
+    }
+
+    /// Returns a hash of the app's committed state.
+    pub fn state_root(&self) -> [u8; 32] {
+        self.backend.lock().unwrap().state.state_root()
+    }
+
+    fn import_account(
+        &self,
+        account: AccountID,
+        handler_id: Option<&str>,
+        state: &[(Vec<u8>, Vec<u8>)],
+        accumulators: &[(Vec<u8>, u128)],
+    ) -> Result<(), String> {
+        let mut backend = self.backend.lock().unwrap();
+        let mut store_tx = backend.new_transaction();
+        let mut state_handler = backend.state_handler(&mut store_tx);
+        let account_manager: AccountManager<V> = AccountManager::new(&backend.vm);
+        let state: Vec<(&[u8], &[u8])> = state
+            .iter()
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+            .collect();
+        let accumulators: Vec<(&[u8], u128)> = accumulators
+            .iter()
+            .map(|(key, value)| (key.as_slice(), *value))
+            .collect();
+        account_manager
+            .import_account(
+                &mut state_handler,
+                account,
+                handler_id,
+                &state,
+                &accumulators,
+                &self.mem,
+            )
+            .map_err(|code| format!("{:?}", code))?;
+        backend
+            .state
+            .commit(store_tx)
+            .map_err(|_| "failed to commit".to_string())?;
+        Ok(())
+    }
+}
@@ -1,20 +1,34 @@
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
 
+mod genesis;
 mod store;
 
 use crate::default_account::{DefaultAccount, DefaultAccountCreate};
+use crate::genesis::HandlerFactory;
+pub use crate::genesis::{Genesis, GenesisAccount, GenesisError, GenesisResult};
 use crate::store::VersionedMultiStore;
 use allocator_api2::alloc::Allocator;
+use ixc::{Duration, Time};
+use ixc_account_manager::block_info::BlockInfo;
+pub use ixc_account_manager::deferred::DrainResult;
+pub use ixc_account_manager::events::EventMetadata;
 use ixc_account_manager::gas::GasMeter;
-use ixc_account_manager::id_generator::IncrementingIDGenerator;
+use ixc_account_manager::id_generator::{
+    IDGenerator, IncrementingIDGenerator, Store, TxContext, TxScopedIDGenerator,
+};
+pub use ixc_account_manager::interceptor::MessageInterceptor;
 use ixc_account_manager::native_vm::{NativeVM, NativeVMImpl};
+pub use ixc_account_manager::simulation::StateChange;
 use ixc_account_manager::state_handler::std::StdStateHandler;
+pub use ixc_account_manager::state_handler::std::StorageDepositConfig;
 use ixc_account_manager::state_handler::StateHandler;
+pub use ixc_account_manager::tx::{Tx, TxError};
 use ixc_account_manager::AccountManager;
 #[doc(hidden)]
 pub use ixc_core::account_api::create_account;
-use ixc_core::account_api::{create_account_raw, ROOT_ACCOUNT};
-use ixc_core::handler::{Client, Handler, HandlerClient};
+use ixc_core::account_api::{create_account_raw, create_account_raw_at, ROOT_ACCOUNT};
+use ixc_core::handler::{Client, Handler, HandlerClient, Service};
+use ixc_core::message::InitMessage;
 use ixc_core::resource::{InitializationError, ResourceScope, Resources};
 use ixc_core::result::ClientResult;
 use ixc_core::Context;
@@ -25,11 +39,11 @@ use ixc_message_api::handler::{HostBackend, InvokeParams, RawHandler};
 use ixc_message_api::message::{Message, Request, Response};
 use ixc_message_api::AccountID;
 use ixc_schema::binary::NativeBinaryCodec;
-use ixc_schema::codec::decode_value;
+use ixc_schema::codec::{decode_value, Codec};
 use ixc_schema::mem::MemoryManager;
 use ixc_schema::structs::StructSchema;
 use ixc_schema::SchemaValue;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::collections::BTreeMap;
 use std::fmt::{Debug, Formatter};
 use std::rc::Rc;
@@ -64,6 +78,10 @@ impl<V: NativeVM + 'static> TestApp<V> {
                 .vm
                 .register_handler(H::NAME, Box::new(H::new(&scope)?));
         }
+        register_schema::<H, V>(&mut backend.vm)?;
+        backend
+            .handler_factories
+            .insert(H::NAME.to_string(), HandlerFactory::of::<H>());
         Ok(())
     }
 
@@ -82,6 +100,10 @@ impl<V: NativeVM + 'static> TestApp<V> {
                 .vm
                 .register_handler(H::NAME, Box::new(H::new(&scope)?));
         }
+        register_schema::<H, V>(&mut backend.vm)?;
+        backend
+            .handler_factories
+            .insert(H::NAME.to_string(), HandlerFactory::of::<H>());
         Ok(())
     }
 
@@ -98,16 +120,60 @@ impl<V: NativeVM + 'static> TestApp<V> {
         Ok(self.client_context_for(account_id))
     }
 
+    /// Creates an account with a fixed ID from the root account,
+    /// which is how system accounts backed by handlers are set up.
+    pub fn create_account_at<H: Handler>(
+        &self,
+        account_id: AccountID,
+        init: H::Init<'_>,
+    ) -> ClientResult<<H as Service>::Client> {
+        let mut root = self.client_context_for(ROOT_ACCOUNT);
+        let cdc = <<H as Handler>::Init<'_> as InitMessage<'_>>::Codec::default();
+        let init_bz = cdc.encode_value(&init, &self.mem)?;
+        let account_id = create_account_raw_at(&mut root, account_id, H::NAME, init_bz)?;
+        Ok(<H as Service>::new_client(account_id))
+    }
+
     /// Creates a new client for the given account.
     pub fn client_context_for(&self, account_id: AccountID) -> Context {
         let backend = BackendWrapper {
             account: account_id,
             backend: self.backend.clone(),
+            simulation: None,
         };
         let ctx = Context::new_boxed(&account_id, &account_id, Box::new(backend), &self.mem);
         ctx
     }
 
+    /// Simulates the messages sent by the provided function from the given caller account
+    /// without committing any of their effects.
+    /// Each message is simulated against the latest committed state with a very high gas limit,
+    /// and the gas consumed, events emitted and state changes made by all the messages are
+    /// reported together with the function's return value.
+    pub fn simulate<F, R>(&self, caller: AccountID, f: F) -> Simulation<'_, R>
+    where
+        F: FnOnce(&mut Context) -> R,
+    {
+        let log = Rc::new(RefCell::new(SimulationLog::default()));
+        let backend = BackendWrapper {
+            account: caller,
+            backend: self.backend.clone(),
+            simulation: Some(log.clone()),
+        };
+        let mut ctx = Context::new_boxed(&caller, &caller, Box::new(backend), &self.mem);
+        let result = f(&mut ctx);
+        let log = log.take();
+        Simulation {
+            result,
+            gas_consumed: log.gas_consumed,
+            events: EventLog {
+                mem: &self.mem,
+                events: log.events,
+            },
+            state_changes: log.state_changes,
+        }
+    }
+
     /// Adds a mock account handler to the test harness, instantiates it as an account and returns the account ID.
     pub fn add_mock(&self, mock: MockHandler) -> ClientResult<AccountID> {
         let mut root = self.client_context_for(ROOT_ACCOUNT);
@@ -138,6 +204,188 @@ impl<V: NativeVM + 'static> TestApp<V> {
         f(&h, &mut ctx)
     }
 
+    /// Adds a message interceptor to the test harness which will be run around every message
+    /// after any previously added interceptors.
+    pub fn add_interceptor<I: MessageInterceptor + 'static>(&self, interceptor: I) {
+        let mut backend = self.backend.lock().unwrap();
+        backend.interceptors.push(Box::new(interceptor));
+    }
+
+    /// Executes a transaction, authenticating its sender before running its messages.
+    /// State changes made while authenticating the sender, such as nonce increments,
+    /// are committed even if one of the messages fails.
+    pub fn invoke_tx(&self, tx: &Tx) -> Result<Vec<Response<'_>>, TxError> {
+        let mut backend = self.backend.lock().unwrap();
+        let mut store_tx = backend.new_transaction();
+        let mut state = backend.state_handler(&mut store_tx);
+        let interceptors: Vec<&dyn MessageInterceptor> =
+            backend.interceptors.iter().map(|i| i.as_ref()).collect();
+        let account_manager: AccountManager<V> =
+            AccountManager::with_interceptors(&backend.vm, &interceptors)
+                .with_tx_context(backend.tx_context());
+        let res = account_manager.invoke_tx(
+            &mut state,
+            &backend.id_gen,
+            tx,
+            &InvokeParams::new(&self.mem, None),
+        );
+        backend.finish_tx(&mut store_tx);
+        if let Err(TxError::Unauthenticated(_) | TxError::Fatal(_)) = res {
+            return res;
+        }
+        let events = backend
+            .state
+            .commit(store_tx)
+            .map_err(|_| TxError::Fatal(ErrorCode::SystemCode(FatalExecutionError)))?;
+        backend.last_events = events;
+        res
+    }
+
+    /// Runs the messages atomically with the sender as the caller of every message
+    /// and returns their responses in order.
+    /// If one of the messages fails, the state changes of all of them are rolled back
+    /// and the index of the failing message is reported.
+    pub fn invoke_batch(
+        &self,
+        sender: AccountID,
+        messages: &[Message],
+    ) -> Result<Vec<Response<'_>>, TxError> {
+        let mut backend = self.backend.lock().unwrap();
+        let mut store_tx = backend.new_transaction();
+        let mut state = backend.state_handler(&mut store_tx);
+        let interceptors: Vec<&dyn MessageInterceptor> =
+            backend.interceptors.iter().map(|i| i.as_ref()).collect();
+        let account_manager: AccountManager<V> =
+            AccountManager::with_interceptors(&backend.vm, &interceptors)
+                .with_tx_context(backend.tx_context());
+        let res = account_manager.invoke_batch(
+            &mut state,
+            &backend.id_gen,
+            sender,
+            messages,
+            &InvokeParams::new(&self.mem, None),
+        );
+        backend.finish_tx(&mut store_tx);
+        if let Err(TxError::Fatal(_)) = res {
+            return res;
+        }
+        let events = backend
+            .state
+            .commit(store_tx)
+            .map_err(|_| TxError::Fatal(ErrorCode::SystemCode(FatalExecutionError)))?;
+        backend.last_events = events;
+        res
+    }
+
+    /// Disables self-destruct for all accounts using the handler.
+    pub fn disable_self_destruct<H: Handler>(&self) {
+        let mut backend = self.backend.lock().unwrap();
+        let mut store_tx = backend.new_transaction();
+        let mut state = backend.state_handler(&mut store_tx);
+        let account_manager: AccountManager<V> = AccountManager::new(&backend.vm);
+        account_manager
+            .set_self_destruct_disabled(&mut state, H::NAME, true)
+            .expect("failed to disable self-destruct");
+        backend
+            .state
+            .commit(store_tx)
+            .expect("failed to commit self-destruct config");
+    }
+
+    /// Requires accounts to pay a deposit for the bytes they store, which is charged and
+    /// refunded by the #[on_storage_deposit] function of the configured deposit account.
+    pub fn enable_storage_deposits(&self, config: StorageDepositConfig) {
+        self.backend.lock().unwrap().storage_deposits = Some(config);
+    }
+
+    /// Derives the IDs of new accounts from the block height and transaction index of the
+    /// transaction which creates them, using a [`TxScopedIDGenerator`], instead of incrementing
+    /// a global counter.
+    pub fn enable_tx_scoped_ids(&self) {
+        self.backend.lock().unwrap().id_gen = TestIDGenerator::TxScoped(Default::default());
+    }
+
+    /// Sets the height of the current block which is served by the block info account.
+    pub fn set_block_height(&self, height: u64) {
+        self.update_block(|block| block.height = height);
+    }
+
+    /// Sets the time of the current block which is served by the block info account.
+    pub fn set_block_time(&self, time: Time) {
+        self.update_block(|block| block.time = time);
+    }
+
+    /// Sets the chain ID which is served by the block info account.
+    pub fn set_chain_id(&self, chain_id: &str) {
+        self.update_block(|block| block.chain_id = chain_id.to_string());
+    }
+
+    /// Advances the height of the current block by the given number of blocks.
+    pub fn advance_block_height(&self, blocks: u64) {
+        self.update_block(|block| block.height += blocks);
+    }
+
+    /// Advances the time of the current block by the given duration.
+    pub fn advance_block_time(&self, duration: Duration) {
+        self.update_block(|block| block.time = block.time.add(duration));
+    }
+
+    /// Returns the height of the current block.
+    pub fn block_height(&self) -> u64 {
+        self.backend.lock().unwrap().block.height
+    }
+
+    /// Returns the time of the current block.
+    pub fn block_time(&self) -> Time {
+        self.backend.lock().unwrap().block.time
+    }
+
+    fn update_block(&self, f: impl FnOnce(&mut TestBlock)) {
+        let mut backend = self.backend.lock().unwrap();
+        let height = backend.block.height;
+        f(&mut backend.block);
+        if backend.block.height != height {
+            backend.block.tx_index = 0;
+        }
+        let mut store_tx = backend.new_transaction();
+        let mut state = backend.state_handler(&mut store_tx);
+        let account_manager: AccountManager<V> = AccountManager::new(&backend.vm);
+        let block_info = BlockInfo {
+            height: backend.block.height,
+            time_unix_nanos: backend.block.time.unix_nanos(),
+            chain_id: &backend.block.chain_id,
+        };
+        account_manager
+            .begin_block(&mut state, &block_info)
+            .expect("failed to set block info");
+        backend
+            .state
+            .commit(store_tx)
+            .expect("failed to commit block info");
+    }
+
+    /// Ends the current block by running the deferred messages which are due
+    /// at the current block height and time, using at most the given gas budget.
+    /// Due messages which don't fit in the budget remain queued for the next block.
+    pub fn end_block(&self, gas_budget: u64) -> Result<DrainResult, ErrorCode> {
+        let mut backend = self.backend.lock().unwrap();
+        let mut store_tx = backend.new_transaction();
+        let mut state = backend.state_handler(&mut store_tx);
+        let interceptors: Vec<&dyn MessageInterceptor> =
+            backend.interceptors.iter().map(|i| i.as_ref()).collect();
+        let account_manager: AccountManager<V> =
+            AccountManager::with_interceptors(&backend.vm, &interceptors);
+        let res =
+            account_manager.drain_deferred(&mut state, &backend.id_gen, gas_budget, &self.mem)?;
+        backend.last_reverted_events = store_tx.take_reverted_events();
+        let events = backend
+            .state
+            .commit(store_tx)
+            .map_err(|_| ErrorCode::SystemCode(FatalExecutionError))?;
+        backend.last_events = events;
+        Ok(res)
+    }
+
     /// Get the events emitted during the last message execution.
     pub fn last_message_events(&self) -> EventLog {
         let backend = self.backend.lock().unwrap();
@@ -146,19 +394,141 @@ impl<V: NativeVM + 'static> TestApp<V> {
             events: backend.last_events.clone(),
         }
     }
+
+    /// Get the events which were emitted and then rolled back during the last message execution,
+    /// including those of the message itself if it failed.
+    pub fn last_reverted_events(&self) -> EventLog<'_> {
+        let backend = self.backend.lock().unwrap();
+        EventLog {
+            mem: &self.mem,
+            events: backend.last_reverted_events.clone(),
+        }
+    }
+}
+
+/// Stores the handler's schema in the VM so that it can be queried at runtime.
+fn register_schema<H: Handler, V: NativeVM>(vm: &mut V) -> Result<(), InitializationError> {
+    let mem = MemoryManager::new();
+    let schema = ixc_core::schema::encode_handler_schema::<H>(&mem)
+        .map_err(|_| InitializationError::Other)?;
+    vm.register_handler_schema(H::NAME, schema);
+    Ok(())
 }
 
 #[derive(Default)]
 struct Backend<V> {
     vm: V,
     state: VersionedMultiStore,
-    id_gen: IncrementingIDGenerator,
+    id_gen: TestIDGenerator,
     last_events: imbl::Vector<EventData>,
+    last_reverted_events: imbl::Vector<EventData>,
+    interceptors: Vec<Box<dyn MessageInterceptor>>,
+    block: TestBlock,
+    handler_factories: BTreeMap<String, HandlerFactory>,
+    bindings: BTreeMap<String, String>,
+    storage_deposits: Option<StorageDepositConfig>,
+}
+
+impl<V> Backend<V> {
+    /// Wraps the store transaction in a state handler with the configured storage deposits.
+    fn state_handler<'a>(&self, tx: &'a mut store::Tx) -> StdStateHandler<'a, store::Tx> {
+        let state = StdStateHandler::new(tx, Default::default());
+        match self.storage_deposits {
+            Some(config) => state.with_storage_deposits(config),
+            None => state,
+        }
+    }
+
+    /// Returns the position of the next transaction in the current block.
+    fn tx_context(&self) -> TxContext {
+        TxContext {
+            block_height: self.block.height,
+            tx_index: self.block.tx_index,
+        }
+    }
+
+    /// Starts a store transaction whose events record the current block height and transaction index.
+    fn new_transaction(&self) -> store::Tx {
+        let mut tx = self.state.new_transaction();
+        tx.set_event_position(self.block.height, self.block.tx_index);
+        tx
+    }
+
+    /// Records the reverted events of a top-level transaction, whether or not it succeeded,
+    /// and moves on to the next transaction index in the block.
+    fn finish_tx(&mut self, tx: &mut store::Tx) {
+        self.last_reverted_events = tx.take_reverted_events();
+        self.block.tx_index += 1;
+    }
+}
+
+/// The ID generator of the test app.
+enum TestIDGenerator {
+    Incrementing(IncrementingIDGenerator),
+    TxScoped(TxScopedIDGenerator),
+}
+
+impl Default for TestIDGenerator {
+    fn default() -> Self {
+        TestIDGenerator::Incrementing(Default::default())
+    }
+}
+
+impl IDGenerator for TestIDGenerator {
+    fn new_account_id<S: Store>(&self, store: &mut S) -> Result<AccountID, ErrorCode> {
+        match self {
+            TestIDGenerator::Incrementing(gen) => gen.new_account_id(store),
+            TestIDGenerator::TxScoped(gen) => gen.new_account_id(store),
+        }
+    }
+
+    fn new_unique_id<S: Store>(&self, store: &mut S) -> Result<u128, ErrorCode> {
+        match self {
+            TestIDGenerator::Incrementing(gen) => gen.new_unique_id(store),
+            TestIDGenerator::TxScoped(gen) => gen.new_unique_id(store),
+        }
+    }
+
+    fn begin_tx(&self, ctx: TxContext) {
+        match self {
+            TestIDGenerator::Incrementing(gen) => gen.begin_tx(ctx),
+            TestIDGenerator::TxScoped(gen) => gen.begin_tx(ctx),
+        }
+    }
+}
+
+#[derive(Default)]
+struct TestBlock {
+    height: u64,
+    time: Time,
+    chain_id: String,
+    // the index of the next transaction in the block
+    tx_index: u32,
 }
 
 struct BackendWrapper<V> {
     account: AccountID,
     backend: Rc<Mutex<Backend<V>>>,
+    simulation: Option<Rc<RefCell<SimulationLog>>>,
+}
+
+#[derive(Default)]
+struct SimulationLog {
+    gas_consumed: u64,
+    events: imbl::Vector<EventData>,
+    state_changes: Vec<StateChange>,
+}
+
+/// The result of simulating messages with [`TestApp::simulate`].
+pub struct Simulation<'a, R> {
+    /// The value returned by the simulated function.
+    pub result: R,
+    /// The total gas consumed by the simulated messages.
+    pub gas_consumed: u64,
+    /// The events the simulated messages would have emitted.
+    pub events: EventLog<'a>,
+    /// The state changes the simulated messages would have made.
+    pub state_changes: Vec<StateChange>,
 }
 
 impl<V: ixc_vm_api::VM> HostBackend for BackendWrapper<V> {
@@ -168,16 +538,41 @@ impl<V: ixc_vm_api::VM> HostBackend for BackendWrapper<V> {
         invoke_params: &InvokeParams<'a, '_>,
     ) -> Result<Response<'a>, ErrorCode> {
         let mut backend = self.backend.lock().unwrap();
-        let mut tx = backend.state.new_transaction();
-        let mut state = StdStateHandler::new(&mut tx, Default::default());
-        let account_manager: AccountManager<V> = AccountManager::new(&backend.vm);
+        let mut tx = backend.new_transaction();
+        let mut state = backend.state_handler(&mut tx);
+        let interceptors: Vec<&dyn MessageInterceptor> =
+            backend.interceptors.iter().map(|i| i.as_ref()).collect();
+        let account_manager: AccountManager<V> =
+            AccountManager::with_interceptors(&backend.vm, &interceptors)
+                .with_tx_context(backend.tx_context());
+        if let Some(simulation) = &self.simulation {
+            let res = account_manager.simulate_msg(
+                &mut state,
+                &backend.id_gen,
+                self.account,
+                message,
+                invoke_params.allocator,
+            )?;
+            let mut log = simulation.borrow_mut();
+            log.gas_consumed += res.gas_consumed;
+            log.events.extend(res.events.into_iter().map(|e| EventData {
+                sender: e.sender,
+                type_selector: e.type_selector,
+                data: e.data,
+                metadata: e.metadata,
+            }));
+            log.state_changes.extend(res.state_changes);
+            return res.response;
+        }
         let res = account_manager.invoke_msg(
             &mut state,
             &backend.id_gen,
             self.account,
             message,
             invoke_params,
-        )?;
+        );
+        backend.finish_tx(&mut tx);
+        let res = res?;
         let events = backend
             .state
             .commit(tx)
@@ -193,8 +588,8 @@ impl<V: ixc_vm_api::VM> HostBackend for BackendWrapper<V> {
     ) -> Result<Response<'a>, ErrorCode> {
         // TODO add a read only state handler impl for query
         let backend = self.backend.lock().unwrap();
-        let mut tx = backend.state.new_transaction();
-        let state = StdStateHandler::new(&mut tx, Default::default());
+        let mut tx = backend.new_transaction();
+        let state = backend.state_handler(&mut tx);
         let account_manager: AccountManager<V> = AccountManager::new(&backend.vm);
         account_manager.invoke_query(&state, message, invoke_params)
     }
@@ -205,14 +600,18 @@ impl<V: ixc_vm_api::VM> HostBackend for BackendWrapper<V> {
         invoke_params: &InvokeParams<'a, '_>,
     ) -> Result<Response<'a>, ErrorCode> {
         let mut backend = self.backend.lock().unwrap();
-        let mut tx = backend.state.new_transaction();
-        let mut state = StdStateHandler::new(&mut tx, Default::default());
+        let mut tx = backend.new_transaction();
+        let mut state = backend.state_handler(&mut tx);
         let res = state.handle_exec(
             self.account,
             req,
             &GasMeter::unlimited(),
             invoke_params.allocator,
         )?;
+        if self.simulation.is_some() {
+            // state updates are never committed when simulating
+            return Ok(res);
+        }
         backend
             .state
             .commit(tx)
@@ -226,8 +625,8 @@ impl<V: ixc_vm_api::VM> HostBackend for BackendWrapper<V> {
         invoke_params: &InvokeParams<'a, '_>,
     ) -> Result<Response<'a>, ErrorCode> {
         let backend = self.backend.lock().unwrap();
-        let mut tx = backend.state.new_transaction();
-        let state = StdStateHandler::new(&mut tx, Default::default());
+        let mut tx = backend.new_transaction();
+        let state = backend.state_handler(&mut tx);
         state.handle_query(
             self.account,
             req,
@@ -420,6 +819,8 @@ pub struct EventData {
     pub type_selector: u64,
     /// The event data.
     pub data: Vec<u8>,
+    /// Where the event was emitted.
+    pub metadata: EventMetadata,
 }
 
 impl EventData {
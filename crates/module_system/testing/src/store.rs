@@ -2,18 +2,31 @@
 use crate::EventData;
 use allocator_api2::alloc::Allocator;
 use imbl::{HashMap, OrdMap, Vector};
+use ixc_account_manager::events::EventMetadata;
 use ixc_account_manager::state_handler::std::StdStateManager;
 use ixc_account_manager::state_handler::StateHandler;
 use ixc_core_macros::message_selector;
 use ixc_message_api::code::{ErrorCode, SystemCode};
+use ixc_message_api::encoding::encode_kv_pairs;
 use ixc_message_api::{alloc_util, AccountID};
+use sha2::{Digest, Sha256};
 use std::alloc::Layout;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::ops::Bound;
+use std::rc::Rc;
 use thiserror::Error;
 
+/// The key-value pairs in an account's storage.
+pub type AccountState = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// The keys and values of an account's accumulators.
+pub type AccumulatorState = Vec<(Vec<u8>, u128)>;
+
 #[derive(Default, Clone)]
 pub struct VersionedMultiStore {
     versions: Vector<MultiStore>,
+    // shared with transactions so that the sequence numbers of reverted events aren't reused
+    event_sequence: Rc<Cell<u64>>,
 }
 
 impl VersionedMultiStore {
@@ -24,9 +37,73 @@ impl VersionedMultiStore {
                 store: latest,
                 events: Default::default(),
             }],
+            block_height: 0,
+            tx_index: 0,
+            message_index: 0,
+            event_sequence: self.event_sequence.clone(),
+            reverted_events: Default::default(),
+        }
+    }
+
+    /// Returns true if nothing has been committed to any account's storage.
+    pub fn is_empty(&self) -> bool {
+        match self.versions.last() {
+            Some(latest) => latest
+                .stores
+                .values()
+                .all(|store| store.kv_store.is_empty() && store.accumulators.is_empty()),
+            None => true,
         }
     }
 
+    /// Returns the committed storage and accumulators of every account ordered by account ID.
+    pub fn accounts(&self) -> Vec<(AccountID, AccountState, AccumulatorState)> {
+        let Some(latest) = self.versions.last() else {
+            return vec![];
+        };
+        let mut accounts: Vec<_> = latest
+            .stores
+            .iter()
+            .map(|(account, store)| {
+                let state = store
+                    .kv_store
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                let accumulators = store
+                    .accumulators
+                    .iter()
+                    .map(|(k, v)| (k.clone(), *v))
+                    .collect();
+                (*account, state, accumulators)
+            })
+            .collect();
+        accounts.sort_by_key(|(account, _, _)| *account);
+        accounts
+    }
+
+    /// Returns a SHA-256 hash of the committed storage and accumulators of every account.
+    pub fn state_root(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for (account, state, accumulators) in self.accounts() {
+            hasher.update(u128::from(account).to_be_bytes());
+            hasher.update((state.len() as u64).to_be_bytes());
+            for (key, value) in state {
+                hasher.update((key.len() as u32).to_be_bytes());
+                hasher.update(&key);
+                hasher.update((value.len() as u32).to_be_bytes());
+                hasher.update(&value);
+            }
+            hasher.update((accumulators.len() as u64).to_be_bytes());
+            for (key, value) in accumulators {
+                hasher.update((key.len() as u32).to_be_bytes());
+                hasher.update(&key);
+                hasher.update(value.to_be_bytes());
+            }
+        }
+        hasher.finalize().into()
+    }
+
     pub fn commit(&mut self, tx: Tx) -> Result<Vector<EventData>, ()> {
         if tx.call_stack.len() != 1 {
             return Err(());
@@ -46,13 +123,30 @@ pub struct MultiStore {
 #[derive(Default, Clone, Debug)]
 pub struct Store {
     kv_store: OrdMap<Vec<u8>, Vec<u8>>,
+    accumulators: OrdMap<Vec<u8>, u128>,
 }
 
 pub struct Tx {
     call_stack: Vec<Frame>,
+    block_height: u64,
+    tx_index: u32,
+    message_index: u32,
+    event_sequence: Rc<Cell<u64>>,
+    reverted_events: Vector<EventData>,
 }
 
 impl Tx {
+    /// Sets the block height and transaction index recorded with the events this transaction emits.
+    pub fn set_event_position(&mut self, block_height: u64, tx_index: u32) {
+        self.block_height = block_height;
+        self.tx_index = tx_index;
+    }
+
+    /// Takes the events emitted by frames which were rolled back, in the order they were emitted.
+    pub fn take_reverted_events(&mut self) -> Vector<EventData> {
+        std::mem::take(&mut self.reverted_events)
+    }
+
     fn current_frame(&self) -> Result<&Frame, ErrorCode> {
         self.call_stack
             .last()
@@ -88,6 +182,24 @@ impl StdStateManager for Tx {
         }
     }
 
+    fn kv_value_len(
+        &self,
+        account_id: AccountID,
+        scope: Option<AccountID>,
+        key: &[u8],
+    ) -> Result<Option<usize>, ErrorCode> {
+        if scope.is_some() {
+            todo!("scoped kv_value_len")
+        }
+        Ok(self
+            .current_frame()?
+            .store
+            .stores
+            .get(&account_id)
+            .and_then(|store| store.kv_store.get(key))
+            .map(|value| value.len()))
+    }
+
     fn kv_set(
         &mut self,
         account_id: AccountID,
@@ -119,17 +231,65 @@ impl StdStateManager for Tx {
             todo!("scoped kv_delete")
         }
         let multistore = &mut self.current_frame_mut()?.store;
-        multistore.stores.remove(&account_id);
+        if let Some(store) = multistore.stores.get_mut(&account_id) {
+            store.kv_store.remove(key);
+        }
         Ok(())
     }
 
+    fn kv_range<'a>(
+        &self,
+        account_id: AccountID,
+        scope: Option<AccountID>,
+        start: &[u8],
+        end: Option<&[u8]>,
+        reverse: bool,
+        limit: usize,
+        allocator: &'a dyn Allocator,
+    ) -> Result<&'a [u8], ErrorCode> {
+        // scoped ranges aren't supported by the test store
+        if scope.is_some() {
+            return Err(ErrorCode::SystemCode(SystemCode::MessageNotHandled));
+        }
+        let mut pairs = vec![];
+        if let Some(store) = self.current_frame()?.store.stores.get(&account_id) {
+            let end = match end {
+                Some(end) if end <= start => return unsafe { encode_kv_pairs(&[], allocator) },
+                Some(end) => Bound::Excluded(end),
+                None => Bound::Unbounded,
+            };
+            let range = store
+                .kv_store
+                .range::<_, [u8]>((Bound::Included(start), end));
+            if reverse {
+                pairs.extend(range.rev().take(limit));
+            } else {
+                pairs.extend(range.take(limit));
+            }
+        }
+        let pairs: Vec<(&[u8], &[u8])> = pairs
+            .into_iter()
+            .map(|(k, v)| (k.as_slice(), v.as_slice()))
+            .collect();
+        unsafe { encode_kv_pairs(&pairs, allocator) }
+    }
+
     fn accumulator_get(
         &self,
         account_id: AccountID,
         scope: Option<AccountID>,
         key: &[u8],
     ) -> Result<u128, ErrorCode> {
-        todo!("accumulator_get")
+        if scope.is_some() {
+            todo!("scoped accumulator_get")
+        }
+        Ok(self
+            .current_frame()?
+            .store
+            .stores
+            .get(&account_id)
+            .and_then(|store| store.accumulators.get(key).copied())
+            .unwrap_or_default())
     }
 
     fn accumulator_add(
@@ -138,8 +298,16 @@ impl StdStateManager for Tx {
         scope: Option<AccountID>,
         key: &[u8],
         value: u128,
-    ) -> Result<(), ErrorCode> {
-        todo!("accumulator_add")
+    ) -> Result<u128, ErrorCode> {
+        if scope.is_some() {
+            todo!("scoped accumulator_add")
+        }
+        let multistore = &mut self.current_frame_mut()?.store;
+        let store = multistore.stores.entry(account_id).or_default();
+        let current = store.accumulators.get(key).copied().unwrap_or_default();
+        let new_value = current.saturating_add(value);
+        store.accumulators.insert(key.to_vec(), new_value);
+        Ok(new_value)
     }
 
     fn accumulator_safe_sub(
@@ -149,7 +317,19 @@ impl StdStateManager for Tx {
         key: &[u8],
         value: u128,
     ) -> Result<bool, ErrorCode> {
-        todo!("accumulator_safe_sub")
+        if scope.is_some() {
+            todo!("scoped accumulator_safe_sub")
+        }
+        let multistore = &mut self.current_frame_mut()?.store;
+        let store = multistore.stores.entry(account_id).or_default();
+        let current = store.accumulators.get(key).copied().unwrap_or_default();
+        match current.checked_sub(value) {
+            Some(new_value) => {
+                store.accumulators.insert(key.to_vec(), new_value);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 
     fn begin_tx(&mut self) -> Result<(), ErrorCode> {
@@ -168,13 +348,15 @@ impl StdStateManager for Tx {
         self.call_stack.pop();
         let next_frame = self.current_frame_mut()?;
         next_frame.store = new_multi_store;
-        next_frame.events = events;
+        next_frame.events.append(events);
         Ok(())
     }
 
     fn rollback_tx(&mut self) -> Result<(), ErrorCode> {
-        // when we rollback we simply pop the current frame
-        self.call_stack.pop();
+        // when we rollback we pop the current frame, keeping its events for debugging
+        if let Some(frame) = self.call_stack.pop() {
+            self.reverted_events.append(frame.events);
+        }
         Ok(())
     }
 
@@ -190,17 +372,38 @@ impl StdStateManager for Tx {
         Ok(())
     }
 
+    fn begin_message(&mut self, index: u32) -> Result<(), ErrorCode> {
+        self.message_index = index;
+        Ok(())
+    }
+
+    fn last_event_metadata(&self) -> Option<EventMetadata> {
+        let frame = self.current_frame().ok()?;
+        frame.events.last().map(|event| event.metadata)
+    }
+
     fn emit_event(
         &mut self,
         sender: AccountID,
         type_selector: u64,
         data: &[u8],
+        depth: u32,
     ) -> Result<(), ErrorCode> {
+        let sequence = self.event_sequence.get();
+        self.event_sequence.set(sequence + 1);
+        let metadata = EventMetadata {
+            block_height: self.block_height,
+            tx_index: self.tx_index,
+            message_index: self.message_index,
+            depth,
+            sequence,
+        };
         let mut current_frame = self.current_frame_mut()?;
         current_frame.events.push_back(EventData {
             sender,
             type_selector,
             data: data.to_vec(),
+            metadata,
         });
         Ok(())
     }
@@ -0,0 +1,122 @@
+//! String encodings of address bytes in the supported address spaces.
+use bech32::{Bech32, Hrp};
+use ixc_message_api::AccountID;
+use sha3::{Digest, Keccak256};
+
+/// The length of EVM-style addresses in bytes.
+pub(crate) const EVM_ADDRESS_LEN: usize = 20;
+
+/// Derives the address bytes of an account from its ID,
+/// which are the last 20 bytes of the Keccak-256 hash of the big-endian ID.
+/// They are valid in every supported address space.
+pub(crate) fn derive_address(account: AccountID) -> Vec<u8> {
+    let hash = Keccak256::digest(account.to_bytes());
+    hash[hash.len() - EVM_ADDRESS_LEN..].to_vec()
+}
+
+/// Returns true if the prefix is a valid bech32 human-readable part.
+pub(crate) fn is_valid_bech32_prefix(prefix: &str) -> bool {
+    Hrp::parse(prefix).is_ok()
+}
+
+/// Encodes the address bytes as a bech32 string with the given human-readable prefix.
+pub(crate) fn encode_bech32(prefix: &str, address: &[u8]) -> Option<String> {
+    let hrp = Hrp::parse(prefix).ok()?;
+    bech32::encode::<Bech32>(hrp, address).ok()
+}
+
+/// Decodes a bech32 string, checking that it has the expected human-readable prefix.
+pub(crate) fn decode_bech32(prefix: &str, address: &str) -> Option<Vec<u8>> {
+    let hrp = Hrp::parse(prefix).ok()?;
+    let (decoded_hrp, bytes) = bech32::decode(address).ok()?;
+    (decoded_hrp == hrp).then_some(bytes)
+}
+
+/// Encodes 20 address bytes as an EIP-55 checksummed hex string.
+pub(crate) fn encode_evm(address: &[u8]) -> Option<String> {
+    if address.len() != EVM_ADDRESS_LEN {
+        return None;
+    }
+    let hex: String = address.iter().map(|b| format!("{:02x}", b)).collect();
+    let hash = Keccak256::digest(hex.as_bytes());
+    let mut res = String::with_capacity(2 + hex.len());
+    res.push_str("0x");
+    for (i, c) in hex.chars().enumerate() {
+        // letters are uppercased if the corresponding nibble of the hash is 8 or higher
+        let nibble = if i % 2 == 0 {
+            hash[i / 2] >> 4
+        } else {
+            hash[i / 2] & 0xf
+        };
+        if nibble >= 8 {
+            res.push(c.to_ascii_uppercase());
+        } else {
+            res.push(c);
+        }
+    }
+    Some(res)
+}
+
+/// Decodes a 0x-prefixed hex string into 20 address bytes.
+/// All lowercase and all uppercase strings are accepted,
+/// but mixed case strings must have a valid EIP-55 checksum.
+pub(crate) fn decode_evm(address: &str) -> Option<Vec<u8>> {
+    let hex = address.strip_prefix("0x")?;
+    if hex.len() != 2 * EVM_ADDRESS_LEN || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bytes = (0..EVM_ADDRESS_LEN)
+        .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let has_lower = hex.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = hex.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper && encode_evm(&bytes)? != address {
+        return None;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evm() {
+        // test vector from EIP-55
+        let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let bytes = decode_evm(checksummed).unwrap();
+        assert_eq!(encode_evm(&bytes).unwrap(), checksummed);
+        assert_eq!(decode_evm(&checksummed.to_lowercase()).unwrap(), bytes);
+        // a bad checksum is rejected
+        assert!(decode_evm("0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_none());
+        // so are bad lengths and missing prefixes
+        assert!(decode_evm("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA").is_none());
+        assert!(decode_evm("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_none());
+        assert!(encode_evm(&[1; 32]).is_none());
+    }
+
+    #[test]
+    fn test_derive_address() {
+        let address = derive_address(AccountID::new(65536));
+        assert_eq!(address.len(), EVM_ADDRESS_LEN);
+        assert_eq!(derive_address(AccountID::new(65536)), address);
+        assert_ne!(derive_address(AccountID::new(65537)), address);
+        assert!(encode_evm(&address).is_some());
+        assert!(encode_bech32("cosmos", &address).is_some());
+    }
+
+    #[test]
+    fn test_bech32() {
+        let bytes = [7u8; 20];
+        let address = encode_bech32("cosmos", &bytes).unwrap();
+        assert!(address.starts_with("cosmos1"));
+        assert_eq!(decode_bech32("cosmos", &address).unwrap(), bytes);
+        assert_eq!(
+            decode_bech32("cosmos", &address.to_uppercase()).unwrap(),
+            bytes
+        );
+        // the prefix must match
+        assert!(decode_bech32("osmo", &address).is_none());
+        assert!(!is_valid_bech32_prefix(""));
+    }
+}
@@ -0,0 +1,323 @@
+//! Address registry module which maps native addresses to account IDs.
+#![allow(missing_docs)] //TODO remove when docs are added to macros
+#![allow(clippy::wrong_self_convention)]
+mod encoding;
+
+pub use ixc_message_api::ADDRESS_REGISTRY_ACCOUNT;
+
+/// A registry of the native addresses of accounts in a single address space.
+/// The registry of the chain's native address space is a system account
+/// which is created at [`ADDRESS_REGISTRY_ACCOUNT`] by the root account at genesis,
+/// and which registers the derived address of every account created after it.
+#[ixc::handler(AddressRegistry)]
+pub mod address_registry {
+    use crate::encoding::*;
+    use ixc::*;
+    use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+    /// A registry of the native addresses of accounts in a single address space.
+    #[derive(Resources)]
+    pub struct AddressRegistry {
+        /// The address space of the registry.
+        #[state(prefix = 1)]
+        space: Item<u8>,
+        /// The human-readable prefix of bech32 addresses.
+        #[state(prefix = 2)]
+        bech32_prefix: Item<Str>,
+        /// The account which can register addresses for other accounts.
+        #[state(prefix = 3)]
+        admin: Item<AccountID>,
+        /// The address bytes of each registered account.
+        #[state(prefix = 4, key(account), value(address))]
+        addresses: Map<AccountID, Bytes>,
+        /// The account each registered address points to.
+        #[state(prefix = 5, key(address), value(account))]
+        accounts: Map<Bytes, AccountID>,
+    }
+
+    /// The kinds of address spaces supported by the registry.
+    #[derive(
+        Clone, Copy, Debug, Default, PartialEq, Eq, IntoPrimitive, TryFromPrimitive, SchemaValue,
+    )]
+    #[repr(u8)]
+    #[non_exhaustive]
+    pub enum AddressSpace {
+        /// Cosmos-style bech32 addresses with a human-readable prefix.
+        #[default]
+        Bech32,
+        /// 20-byte EVM-style addresses which are rendered as EIP-55 checksummed hex.
+        Evm,
+    }
+
+    /// The API for converting between native addresses and account IDs.
+    /// Native addresses have both a byte representation and a string representation.
+    /// The mapping between addresses and account IDs is assumed to be stateful.
+    #[handler_api]
+    pub trait AddressAPI {
+        /// Convert an account ID to a byte representation.
+        fn to_bytes(&self, ctx: &Context, account_id: AccountID) -> Result<Vec<u8>>;
+        /// Convert a byte representation to an account ID.
+        fn from_bytes(&self, ctx: &Context, address_bytes: &[u8]) -> Result<AccountID>;
+        /// Convert an account ID to a string representation.
+        fn to_string(&self, ctx: &Context, account_id: AccountID) -> Result<String>;
+        /// Convert a string representation to an account ID.
+        fn from_string(&self, ctx: &Context, address_string: &str) -> Result<AccountID>;
+    }
+
+    #[publish]
+    impl AddressRegistry {
+        /// Creates a registry for the address space.
+        /// The prefix is only used by bech32 address spaces and must be empty otherwise.
+        /// The caller becomes the registry's admin.
+        #[on_create]
+        pub fn create(
+            &self,
+            ctx: &mut Context,
+            space: AddressSpace,
+            bech32_prefix: &str,
+        ) -> Result<()> {
+            match space {
+                AddressSpace::Bech32 => {
+                    ensure!(
+                        is_valid_bech32_prefix(bech32_prefix),
+                        "invalid bech32 prefix"
+                    )
+                }
+                _ => ensure!(bech32_prefix.is_empty(), "unexpected bech32 prefix"),
+            }
+            self.space.set(ctx, u8::from(space))?;
+            self.bech32_prefix.set(ctx, bech32_prefix)?;
+            self.admin.set(ctx, ctx.caller())?;
+            Ok(())
+        }
+
+        /// Registers the address derived from the calling account's ID,
+        /// so that accounts can't claim addresses which belong to other accounts.
+        /// Accounts are registered with the system registry when they are created,
+        /// so this is only needed for other registries and for accounts created before it.
+        pub fn register(&self, ctx: &mut Context) -> Result<()> {
+            let account = ctx.caller();
+            self.do_register(ctx, account, &derive_address(account))
+        }
+
+        /// Registers the derived address of an account which was just created,
+        /// unless its #[on_create] function already registered an address.
+        /// The account manager only calls this on the system registry.
+        #[on_register_address]
+        pub fn register_created(&self, ctx: &mut Context, account: AccountID) -> Result<()> {
+            if self.addresses.get(ctx, account)?.is_some() {
+                return Ok(());
+            }
+            self.do_register(ctx, account, &derive_address(account))
+        }
+
+        /// Registers an address which isn't derived from the account's ID,
+        /// such as one derived from its public key.
+        /// Only the admin, which is the root account for the system registry, can call this.
+        pub fn register_for(
+            &self,
+            ctx: &mut Context,
+            account: AccountID,
+            address: &[u8],
+        ) -> Result<()> {
+            ensure!(self.admin.get(ctx)? == ctx.caller(), "not authorized");
+            self.do_register(ctx, account, address)
+        }
+    }
+
+    impl AddressRegistry {
+        fn do_register(&self, ctx: &mut Context, account: AccountID, address: &[u8]) -> Result<()> {
+            // make sure the address is valid in this address space
+            self.encode_string(ctx, address)?;
+            ensure!(
+                self.addresses.get(ctx, account)?.is_none(),
+                "account already has an address"
+            );
+            ensure!(
+                self.accounts.get(ctx, address)?.is_none(),
+                "address already registered"
+            );
+            self.addresses.set(ctx, account, address)?;
+            self.accounts.set(ctx, address, account)?;
+            Ok(())
+        }
+
+        fn encode_string(&self, ctx: &Context, address: &[u8]) -> Result<String> {
+            let encoded = match self.address_space(ctx)? {
+                AddressSpace::Bech32 => encode_bech32(self.bech32_prefix.get(ctx)?, address),
+                AddressSpace::Evm => encode_evm(address),
+            };
+            encoded.ok_or(error!("invalid address"))
+        }
+
+        fn address_space(&self, ctx: &Context) -> Result<AddressSpace> {
+            AddressSpace::try_from(self.space.get(ctx)?)
+                .map_err(|_| error!("unknown address space"))
+        }
+    }
+
+    #[publish]
+    impl AddressAPI for AddressRegistry {
+        fn to_bytes(&self, ctx: &Context, account_id: AccountID) -> Result<Vec<u8>> {
+            let address = self
+                .addresses
+                .get(ctx, account_id)?
+                .ok_or(error!("account has no address"))?;
+            Ok(address.to_vec())
+        }
+
+        fn from_bytes(&self, ctx: &Context, address_bytes: &[u8]) -> Result<AccountID> {
+            self.accounts
+                .get(ctx, address_bytes)?
+                .ok_or(error!("address not registered"))
+        }
+
+        fn to_string(&self, ctx: &Context, account_id: AccountID) -> Result<String> {
+            let address = self.to_bytes(ctx, account_id)?;
+            self.encode_string(ctx, &address)
+        }
+
+        fn from_string(&self, ctx: &Context, address_string: &str) -> Result<AccountID> {
+            let address = match self.address_space(ctx)? {
+                AddressSpace::Bech32 => decode_bech32(self.bech32_prefix.get(ctx)?, address_string),
+                AddressSpace::Evm => decode_evm(address_string),
+            }
+            .ok_or(error!("invalid address"))?;
+            self.from_bytes(ctx, &address)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::address_registry::*;
+    use super::ADDRESS_REGISTRY_ACCOUNT;
+    use crate::encoding::derive_address;
+    use ixc::*;
+    use ixc_core::account_api::ROOT_ACCOUNT;
+    use ixc_core::handler::Client;
+    use ixc_testing::*;
+
+    #[ixc::handler(Wallet)]
+    pub mod wallet {
+        use super::super::address_registry::AddressRegistry;
+        use super::super::ADDRESS_REGISTRY_ACCOUNT;
+        use ixc::*;
+
+        #[derive(Resources)]
+        pub struct Wallet {}
+
+        #[publish]
+        impl Wallet {
+            /// Registers the wallet's address itself when it is created.
+            #[on_create]
+            pub fn create(&self, ctx: &mut Context) -> Result<()> {
+                AddressRegistry::new_client(ADDRESS_REGISTRY_ACCOUNT).register(ctx)?;
+                Ok(())
+            }
+        }
+    }
+    use wallet::*;
+
+    #[test]
+    fn test_address_registry() {
+        let app = TestApp::default();
+        app.register_handler::<AddressRegistry>().unwrap();
+        app.register_handler::<Wallet>().unwrap();
+        // the native address space is served by the system registry
+        let cosmos = app
+            .create_account_at::<AddressRegistry>(
+                ADDRESS_REGISTRY_ACCOUNT,
+                AddressRegistryCreate {
+                    space: AddressSpace::Bech32,
+                    bech32_prefix: "cosmos",
+                },
+            )
+            .unwrap();
+        assert_eq!(cosmos.target_account(), ADDRESS_REGISTRY_ACCOUNT);
+        let mut root = app.client_context_for(ROOT_ACCOUNT);
+        let evm = create_account::<AddressRegistry>(
+            &mut root,
+            AddressRegistryCreate {
+                space: AddressSpace::Evm,
+                bech32_prefix: "",
+            },
+        )
+        .unwrap();
+
+        // accounts created after the system registry have their derived address registered,
+        // unless they register it themselves when they are created, as the wallet does
+        let mut alice = app.new_client_context().unwrap();
+        let wallet = create_account::<Wallet>(&mut alice, WalletCreate {})
+            .unwrap()
+            .target_account();
+        let wallet_bytes = derive_address(wallet);
+        let address = cosmos.to_string(&alice, wallet).unwrap();
+        assert!(address.starts_with("cosmos1"));
+        assert_eq!(cosmos.from_string(&alice, &address).unwrap(), wallet);
+        assert_eq!(cosmos.to_bytes(&alice, wallet).unwrap(), wallet_bytes);
+        assert_eq!(cosmos.from_bytes(&alice, &wallet_bytes).unwrap(), wallet);
+
+        // the admin can register an address which isn't derived from the account ID
+        let evm_address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let evm_bytes = [
+            0x5a, 0xae, 0xb6, 0x05, 0x3f, 0x3e, 0x94, 0xc9, 0xb9, 0xa0, 0x9f, 0x33, 0x66, 0x94,
+            0x35, 0xe7, 0xef, 0x1b, 0xea, 0xed,
+        ];
+        evm.register_for(&mut root, wallet, &evm_bytes).unwrap();
+        assert_eq!(evm.to_string(&alice, wallet).unwrap(), evm_address);
+        assert_eq!(evm.from_string(&alice, evm_address).unwrap(), wallet);
+
+        // but nobody else can, so addresses of other accounts can't be claimed
+        let bob = app.new_client_account().unwrap();
+        assert_eq!(cosmos.to_bytes(&alice, bob).unwrap(), derive_address(bob));
+        assert!(evm.register_for(&mut alice, bob, &[2; 20]).is_err());
+        assert!(cosmos.register_for(&mut alice, bob, &[2; 20]).is_err());
+        // addresses must be valid in the address space
+        assert!(evm.register_for(&mut root, bob, &[2; 32]).is_err());
+        // and can't be registered twice
+        assert!(cosmos.register_for(&mut root, bob, &wallet_bytes).is_err());
+        // nor can an account register a second address
+        let alice_id = alice.self_account_id();
+        assert_eq!(
+            cosmos.to_bytes(&alice, alice_id).unwrap(),
+            derive_address(alice_id)
+        );
+        assert!(cosmos.register(&mut alice).is_err());
+        // accounts register themselves with other registries
+        evm.register(&mut alice).unwrap();
+        assert_eq!(
+            evm.to_bytes(&alice, alice_id).unwrap(),
+            derive_address(alice_id)
+        );
+        assert!(evm.register(&mut alice).is_err());
+        assert!(evm.register_for(&mut root, alice_id, &[4; 20]).is_err());
+
+        // unknown addresses aren't found
+        assert!(evm.from_bytes(&alice, &[9; 20]).is_err());
+        assert!(cosmos.from_string(&alice, evm_address).is_err());
+        assert!(evm.to_string(&alice, bob).is_err());
+    }
+
+    #[test]
+    fn test_genesis_system_registry() {
+        let app = TestApp::default();
+        app.register_handler::<AddressRegistry>().unwrap();
+        let genesis = Genesis::from_json(
+            r#"{
+                "handlers": ["AddressRegistry"],
+                "accounts": [{
+                    "id": "3",
+                    "handler": "AddressRegistry",
+                    "init": {"space": "Evm", "bech32_prefix": ""}
+                }]
+            }"#,
+        )
+        .unwrap();
+        app.load_genesis(&genesis).unwrap();
+        let registry = AddressRegistry::new_client(ADDRESS_REGISTRY_ACCOUNT);
+        let alice = app.new_client_context().unwrap();
+        let address = registry.to_string(&alice, alice.self_account_id()).unwrap();
+        assert!(address.starts_with("0x"));
+    }
+}
@@ -24,16 +24,16 @@ pub mod bank {
         #[state(prefix = 4)]
         global_send_hook: Item<AccountID>,
         /// The denom admins.
-        #[state(prefix = 5)]
+        #[state(prefix = 5, key(denom), value(admin))]
         denom_admins: Map<Str, AccountID>,
         /// The denom send hooks.
-        #[state(prefix = 6)]
+        #[state(prefix = 6, key(denom), value(hook))]
         denom_send_hooks: Map<Str, AccountID>,
         /// The denom burn hooks.
-        #[state(prefix = 6)]
+        #[state(prefix = 6, key(denom), value(hook))]
         denom_burn_hooks: Map<Str, AccountID>,
         /// The denom recieve hooks.
-        #[state(prefix = 7)]
+        #[state(prefix = 7, key(account), value(hook))]
         denom_recieve_hooks: Map<AccountID, AccountID>,
     }
 
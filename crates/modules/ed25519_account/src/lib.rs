@@ -0,0 +1,193 @@
+//! Default account handler which authenticates transactions with a single ed25519 key.
+#![allow(missing_docs)] //TODO remove when docs are added to macros
+/// An account controlled by a single ed25519 key.
+#[ixc::handler(Ed25519Account)]
+pub mod ed25519_account {
+    use ed25519_dalek::{Signature, VerifyingKey};
+    use ixc::*;
+    use ixc_core::auth::AuthRequest;
+
+    /// An account which authenticates transactions by verifying an ed25519 signature
+    /// over their sign bytes and enforcing sequential nonces.
+    #[derive(Resources)]
+    pub struct Ed25519Account {
+        /// The ed25519 public key of the account.
+        #[state(prefix = 1)]
+        pub_key: Item<Bytes>,
+        /// The nonce of the next transaction the account will accept.
+        #[state(prefix = 2)]
+        nonce: Item<u64>,
+    }
+
+    #[publish]
+    impl Ed25519Account {
+        /// Creates the account with the given 32 byte ed25519 public key.
+        #[on_create]
+        pub fn create(&self, ctx: &mut Context, pub_key: &[u8]) -> Result<()> {
+            VerifyingKey::try_from(pub_key).map_err(|_| error!("invalid public key"))?;
+            self.pub_key.set(ctx, pub_key)?;
+            Ok(())
+        }
+
+        /// Authenticates a transaction sent by the account.
+        /// The nonce must match the account's current nonce,
+        /// which is incremented once the signature has been verified.
+        #[on_authenticate]
+        pub fn authenticate(&self, ctx: &mut Context, req: &AuthRequest) -> Result<()> {
+            let nonce = self.nonce.get(ctx)?;
+            ensure!(req.nonce == nonce, "invalid nonce");
+            // the account can't send any more transactions once its nonces are used up
+            let next_nonce = nonce.checked_add(1).ok_or(error!("nonce exhausted"))?;
+            let pub_key = self.pub_key.get(ctx)?;
+            let pub_key =
+                VerifyingKey::try_from(pub_key).map_err(|_| error!("invalid public key"))?;
+            let signature =
+                Signature::from_slice(req.credentials).map_err(|_| error!("invalid signature"))?;
+            pub_key
+                .verify_strict(req.sign_bytes, &signature)
+                .map_err(|_| error!("signature verification failed"))?;
+            self.nonce.set(ctx, next_nonce)?;
+            Ok(())
+        }
+
+        /// Returns the nonce of the next transaction the account will accept.
+        pub fn get_nonce(&self, ctx: &Context) -> Result<u64> {
+            Ok(self.nonce.get(ctx)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ed25519_account::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use ixc::*;
+    use ixc_core::handler::Client;
+    use ixc_core::low_level::encode_message_packet;
+    use ixc_schema::mem::MemoryManager;
+    use ixc_testing::*;
+
+    #[ixc::handler(Counter)]
+    pub mod counter {
+        use ixc::*;
+
+        #[derive(Resources)]
+        pub struct Counter {
+            #[state]
+            value: Item<u64>,
+        }
+
+        #[publish]
+        impl Counter {
+            #[on_create]
+            pub fn create(&self, _ctx: &mut Context) -> Result<()> {
+                Ok(())
+            }
+
+            pub fn get(&self, ctx: &Context) -> Result<u64> {
+                Ok(self.value.get(ctx)?)
+            }
+
+            pub fn inc(&self, ctx: &mut Context) -> Result<u64> {
+                let value = self.value.get(ctx)? + 1;
+                self.value.set(ctx, value)?;
+                Ok(value)
+            }
+        }
+    }
+    use counter::*;
+
+    const CHAIN_ID: &str = "test-chain";
+
+    fn sign<'a>(key: &SigningKey, tx: Tx<'a>, sig: &'a mut Vec<u8>) -> Tx<'a> {
+        sign_for(CHAIN_ID, key, tx, sig)
+    }
+
+    fn sign_for<'a>(chain_id: &str, key: &SigningKey, tx: Tx<'a>, sig: &'a mut Vec<u8>) -> Tx<'a> {
+        *sig = key.sign(&tx.sign_bytes(chain_id)).to_bytes().to_vec();
+        Tx {
+            credentials: sig,
+            ..tx
+        }
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let app = TestApp::default();
+        app.set_chain_id(CHAIN_ID);
+        app.register_handler::<Ed25519Account>().unwrap();
+        app.register_handler::<Counter>().unwrap();
+        let mut alice = app.new_client_context().unwrap();
+        let counter = create_account::<Counter>(&mut alice, CounterCreate {}).unwrap();
+
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let pub_key = key.verifying_key().to_bytes();
+        let account = create_account::<Ed25519Account>(
+            &mut alice,
+            Ed25519AccountCreate { pub_key: &pub_key },
+        )
+        .unwrap();
+        let sender = account.target_account();
+
+        let mem = MemoryManager::new();
+        let inc = || encode_message_packet(&mem, counter.target_account(), CounterInc {}).unwrap();
+        let messages = [inc(), inc()];
+        let unsigned = Tx {
+            sender,
+            nonce: 0,
+            messages: &messages,
+            credentials: &[],
+        };
+
+        // a valid signature with the current nonce runs all the messages
+        let mut sig = vec![];
+        let res = app.invoke_tx(&sign(&key, unsigned, &mut sig)).unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(counter.get(&alice).unwrap(), 2);
+        assert_eq!(account.get_nonce(&alice).unwrap(), 1);
+
+        // replaying the transaction is rejected
+        let res = app.invoke_tx(&sign(&key, unsigned, &mut sig));
+        assert!(matches!(res, Err(TxError::Unauthenticated(_))));
+
+        // so is a transaction signed for another chain
+        let tx = Tx {
+            nonce: 1,
+            ..unsigned
+        };
+        let res = app.invoke_tx(&sign_for("other-chain", &key, tx, &mut sig));
+        assert!(matches!(res, Err(TxError::Unauthenticated(_))));
+
+        // and a transaction signed by another key
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        let tx = Tx {
+            nonce: 1,
+            ..unsigned
+        };
+        let res = app.invoke_tx(&sign(&other, tx, &mut sig));
+        assert!(matches!(res, Err(TxError::Unauthenticated(_))));
+        assert_eq!(counter.get(&alice).unwrap(), 2);
+        assert_eq!(account.get_nonce(&alice).unwrap(), 1);
+
+        // a failing message rolls back the messages but still consumes the nonce
+        let bad = encode_message_packet(&mem, sender, CounterInc {}).unwrap();
+        let messages = [inc(), bad];
+        let tx = Tx {
+            nonce: 1,
+            messages: &messages,
+            ..unsigned
+        };
+        let res = app.invoke_tx(&sign(&key, tx, &mut sig));
+        assert!(matches!(res, Err(TxError::MessageFailed { index: 1, .. })));
+        assert_eq!(counter.get(&alice).unwrap(), 2);
+        assert_eq!(account.get_nonce(&alice).unwrap(), 2);
+
+        // accounts without an authentication hook can't send transactions
+        let tx = Tx {
+            sender: alice.self_account_id(),
+            ..unsigned
+        };
+        let res = app.invoke_tx(&tx);
+        assert!(matches!(res, Err(TxError::Unauthenticated(_))));
+    }
+}
@@ -0,0 +1,118 @@
+//! The built-in block info account which serves the current block's height, time and chain ID.
+use crate::gas::GasMeter;
+use crate::state_handler::StateHandler;
+use crate::AccountManager;
+use allocator_api2::alloc::Allocator;
+use ixc_core_macros::message_selector;
+use ixc_message_api::code::ErrorCode;
+use ixc_message_api::code::ErrorCode::SystemCode;
+use ixc_message_api::code::SystemCode::{EncodingError, MessageNotHandled};
+use ixc_message_api::message::{MessageSelector, Request, Response};
+use ixc_message_api::BLOCK_INFO_ACCOUNT;
+use ixc_vm_api::VM;
+
+/// The selector of the block info query which returns the block height as a u64.
+const GET_BLOCK_HEIGHT_SELECTOR: MessageSelector =
+    message_selector!("ixc.block.v1.get_block_height");
+/// The selector of the block info query which returns the block time
+/// as a u128 containing the signed number of nanoseconds since the Unix epoch.
+const GET_BLOCK_TIME_SELECTOR: MessageSelector = message_selector!("ixc.block.v1.get_block_time");
+/// The selector of the block info query which returns the chain ID as a string.
+const GET_CHAIN_ID_SELECTOR: MessageSelector = message_selector!("ixc.block.v1.get_chain_id");
+
+const HEIGHT_KEY: &[u8] = b"h";
+const TIME_KEY: &[u8] = b"t";
+const CHAIN_ID_KEY: &[u8] = b"c";
+
+/// Information about the block which is currently being executed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInfo<'a> {
+    /// The block height.
+    pub height: u64,
+    /// The block time as the number of nanoseconds since the Unix epoch.
+    pub time_unix_nanos: i128,
+    /// The ID of the chain.
+    pub chain_id: &'a str,
+}
+
+impl<CM: VM, const CALL_STACK_LIMIT: usize> AccountManager<'_, CM, CALL_STACK_LIMIT> {
+    /// Sets up the block info account for a new block.
+    /// This should be called by the block executor before any messages in the block are run.
+    pub fn begin_block<ST: StateHandler>(
+        &self,
+        state_handler: &mut ST,
+        block_info: &BlockInfo,
+    ) -> Result<(), ErrorCode> {
+        let gas = GasMeter::unlimited();
+        state_handler.kv_set(
+            BLOCK_INFO_ACCOUNT,
+            HEIGHT_KEY,
+            &block_info.height.to_be_bytes(),
+            &gas,
+        )?;
+        state_handler.kv_set(
+            BLOCK_INFO_ACCOUNT,
+            TIME_KEY,
+            &block_info.time_unix_nanos.to_be_bytes(),
+            &gas,
+        )?;
+        state_handler.kv_set(
+            BLOCK_INFO_ACCOUNT,
+            CHAIN_ID_KEY,
+            block_info.chain_id.as_bytes(),
+            &gas,
+        )
+    }
+
+    /// Returns the information about the current block, which is empty
+    /// if [`AccountManager::begin_block`] was never called.
+    pub fn block_info<'b, ST: StateHandler>(
+        &self,
+        state_handler: &ST,
+        allocator: &'b dyn Allocator,
+    ) -> Result<BlockInfo<'b>, ErrorCode> {
+        get_block_info(state_handler, &GasMeter::unlimited(), allocator)
+    }
+}
+
+/// Reads the current block info from the block info account's storage.
+pub(crate) fn get_block_info<'b, ST: StateHandler>(
+    state_handler: &ST,
+    gas: &GasMeter,
+    allocator: &'b dyn Allocator,
+) -> Result<BlockInfo<'b>, ErrorCode> {
+    let get = |key| state_handler.kv_get(BLOCK_INFO_ACCOUNT, key, gas, allocator);
+    let height = match get(HEIGHT_KEY)? {
+        Some(bz) => u64::from_be_bytes(bz.try_into().map_err(|_| SystemCode(EncodingError))?),
+        None => 0,
+    };
+    let time_unix_nanos = match get(TIME_KEY)? {
+        Some(bz) => i128::from_be_bytes(bz.try_into().map_err(|_| SystemCode(EncodingError))?),
+        None => 0,
+    };
+    let chain_id = match get(CHAIN_ID_KEY)? {
+        Some(bz) => core::str::from_utf8(bz).map_err(|_| SystemCode(EncodingError))?,
+        None => "",
+    };
+    Ok(BlockInfo {
+        height,
+        time_unix_nanos,
+        chain_id,
+    })
+}
+
+/// Handles a query sent to the block info account.
+pub(crate) fn handle_block_info_query<'b, ST: StateHandler>(
+    state_handler: &ST,
+    req: &Request,
+    gas: &GasMeter,
+    allocator: &'b dyn Allocator,
+) -> Result<Response<'b>, ErrorCode> {
+    let block_info = get_block_info(state_handler, gas, allocator)?;
+    match req.message_selector() {
+        GET_BLOCK_HEIGHT_SELECTOR => Ok(Response::new1(block_info.height.into())),
+        GET_BLOCK_TIME_SELECTOR => Ok(Response::new1((block_info.time_unix_nanos as u128).into())),
+        GET_CHAIN_ID_SELECTOR => Ok(Response::new1(block_info.chain_id.into())),
+        _ => Err(SystemCode(MessageNotHandled)),
+    }
+}
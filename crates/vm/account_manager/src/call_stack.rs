@@ -47,6 +47,12 @@ impl<const CALL_STACK_LIMIT: usize> CallStack<CALL_STACK_LIMIT> {
             ))
     }
 
+    /// The depth of the active call, which is 0 for a message sent by the account
+    /// the call stack was created for and increases by 1 for each nested call.
+    pub(crate) fn depth(&self) -> usize {
+        self.call_stack.borrow().len().saturating_sub(2)
+    }
+
     pub(crate) fn active_account(&self) -> Result<AccountID, ErrorCode> {
         self.call_stack
             .borrow()
@@ -0,0 +1,433 @@
+//! Deferred messages which are scheduled to run outside the call stack that enqueued them.
+//!
+//! Deferred messages are stored in the root account's storage.
+//! Messages deferred to the end of a transaction or block are kept in FIFO queues,
+//! while messages scheduled for a block height or time are kept in min-heaps
+//! ordered by their trigger and moved to the end-of-block queue once they are due.
+use crate::block_info::get_block_info;
+use crate::gas::GasMeter;
+use crate::id_generator::{IDGenerator, TxContext};
+use crate::state_handler::StateHandler;
+use crate::AccountManager;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use ixc_core_macros::message_selector;
+use ixc_message_api::code::ErrorCode;
+use ixc_message_api::code::SystemCode::{EncodingError, OutOfGas};
+use ixc_message_api::encoding::decode_message;
+use ixc_message_api::gas::GasTracker;
+use ixc_message_api::handler::InvokeParams;
+use ixc_message_api::message::MessageSelector;
+use ixc_message_api::{AccountID, ROOT_ACCOUNT};
+use ixc_vm_api::VM;
+
+/// The selector of the root account message which defers a message.
+/// The inputs are the trigger kind as a u64, the trigger value as a u128
+/// and the encoded message.
+pub(crate) const DEFER_SELECTOR: MessageSelector = message_selector!("ixc.deferred.v1.defer");
+
+const TRIGGER_END_OF_TX: u64 = 0;
+const TRIGGER_END_OF_BLOCK: u64 = 1;
+const TRIGGER_AT_HEIGHT: u64 = 2;
+const TRIGGER_AT_TIME: u64 = 3;
+
+const END_OF_TX_QUEUE: u8 = b'x';
+const END_OF_BLOCK_QUEUE: u8 = b'b';
+const HEIGHT_HEAP: u8 = b'h';
+const TIME_HEAP: u8 = b't';
+
+/// The result of draining deferred messages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DrainResult {
+    /// The number of deferred messages which were executed, including failed ones.
+    pub executed: u64,
+    /// The number of executed messages which failed and were rolled back.
+    pub failed: u64,
+    /// The gas consumed by the executed messages.
+    pub gas_consumed: u64,
+    /// The number of due messages which are still queued because the gas budget ran out.
+    pub pending: u64,
+}
+
+/// Provides access to the deferred message queues in the root account's storage.
+pub(crate) struct DeferredStore<'a, ST: StateHandler> {
+    state_handler: &'a mut ST,
+    gas: &'a GasMeter,
+    allocator: &'a dyn Allocator,
+}
+
+impl<'a, ST: StateHandler> DeferredStore<'a, ST> {
+    pub(crate) fn new(
+        state_handler: &'a mut ST,
+        gas: &'a GasMeter,
+        allocator: &'a dyn Allocator,
+    ) -> Self {
+        Self {
+            state_handler,
+            gas,
+            allocator,
+        }
+    }
+
+    /// Stores a deferred message sent by the caller and schedules it with the trigger.
+    pub(crate) fn defer(
+        &mut self,
+        caller: AccountID,
+        trigger_kind: u64,
+        trigger_value: u128,
+        message: &[u8],
+    ) -> Result<(), ErrorCode> {
+        // make sure the message can be decoded before we store it
+        decode_message(message)?;
+        let seq = self.get_u64(b"d:s")?;
+        self.set_u64(b"d:s", seq + 1)?;
+        let mut entry = Vec::with_capacity(16 + message.len());
+        entry.extend_from_slice(&u128::from(caller).to_be_bytes());
+        entry.extend_from_slice(message);
+        self.set(&entry_key(seq), &entry)?;
+        match trigger_kind {
+            TRIGGER_END_OF_TX => self.queue_push(END_OF_TX_QUEUE, seq),
+            TRIGGER_END_OF_BLOCK => self.queue_push(END_OF_BLOCK_QUEUE, seq),
+            TRIGGER_AT_HEIGHT => self.heap_push(HEIGHT_HEAP, heap_item(trigger_value, seq)),
+            // flip the sign bit of the time so that times are ordered as unsigned integers
+            TRIGGER_AT_TIME => {
+                self.heap_push(TIME_HEAP, heap_item(trigger_value ^ (1 << 127), seq))
+            }
+            _ => Err(ErrorCode::SystemCode(EncodingError)),
+        }
+    }
+
+    /// Moves the messages which are due at the block height and time to the end-of-block queue.
+    fn schedule_due(&mut self, height: u64, time_unix_nanos: i128) -> Result<(), ErrorCode> {
+        let time = (time_unix_nanos as u128) ^ (1 << 127);
+        for (heap, due) in [(HEIGHT_HEAP, height as u128), (TIME_HEAP, time)] {
+            while let Some(item) = self.heap_peek(heap)? {
+                let (trigger, seq) = split_heap_item(&item);
+                if trigger > due {
+                    break;
+                }
+                self.heap_pop(heap)?;
+                self.queue_push(END_OF_BLOCK_QUEUE, seq)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the caller and encoded message of the deferred message.
+    fn entry(&self, seq: u64) -> Result<Option<(AccountID, &'a [u8])>, ErrorCode> {
+        let entry =
+            self.state_handler
+                .kv_get(ROOT_ACCOUNT, &entry_key(seq), self.gas, self.allocator)?;
+        match entry {
+            Some(entry) if entry.len() >= 16 => {
+                let caller = u128::from_be_bytes(entry[..16].try_into().unwrap());
+                Ok(Some((AccountID::new(caller), &entry[16..])))
+            }
+            Some(_) => Err(ErrorCode::SystemCode(EncodingError)),
+            None => Ok(None),
+        }
+    }
+
+    fn queue_push(&mut self, queue: u8, seq: u64) -> Result<(), ErrorCode> {
+        let tail = self.get_u64(&queue_key(queue, b't'))?;
+        self.set(&queue_item_key(queue, tail), &seq.to_be_bytes())?;
+        self.set_u64(&queue_key(queue, b't'), tail + 1)
+    }
+
+    fn queue_peek(&self, queue: u8) -> Result<Option<u64>, ErrorCode> {
+        let head = self.get_u64(&queue_key(queue, b'h'))?;
+        if head == self.get_u64(&queue_key(queue, b't'))? {
+            return Ok(None);
+        }
+        self.get(&queue_item_key(queue, head))?
+            .map(decode_u64)
+            .transpose()
+    }
+
+    /// Removes the head of the queue and returns its sequence number.
+    fn queue_pop(&mut self, queue: u8) -> Result<Option<u64>, ErrorCode> {
+        let head = self.get_u64(&queue_key(queue, b'h'))?;
+        let seq = self.queue_peek(queue)?;
+        if seq.is_some() {
+            self.delete(&queue_item_key(queue, head))?;
+            self.set_u64(&queue_key(queue, b'h'), head + 1)?;
+        }
+        Ok(seq)
+    }
+
+    fn queue_len(&self, queue: u8) -> Result<u64, ErrorCode> {
+        Ok(self.get_u64(&queue_key(queue, b't'))? - self.get_u64(&queue_key(queue, b'h'))?)
+    }
+
+    fn heap_push(&mut self, heap: u8, item: [u8; 24]) -> Result<(), ErrorCode> {
+        let len = self.get_u64(&heap_len_key(heap))?;
+        self.set_u64(&heap_len_key(heap), len + 1)?;
+        // sift the new item up
+        let mut i = len;
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            let parent_item = self.heap_get(heap, parent)?;
+            if parent_item <= item {
+                break;
+            }
+            self.set(&heap_item_key(heap, i), &parent_item)?;
+            i = parent;
+        }
+        self.set(&heap_item_key(heap, i), &item)
+    }
+
+    fn heap_peek(&self, heap: u8) -> Result<Option<[u8; 24]>, ErrorCode> {
+        if self.get_u64(&heap_len_key(heap))? == 0 {
+            return Ok(None);
+        }
+        self.heap_get(heap, 0).map(Some)
+    }
+
+    fn heap_pop(&mut self, heap: u8) -> Result<(), ErrorCode> {
+        let len = self.get_u64(&heap_len_key(heap))?;
+        if len == 0 {
+            return Ok(());
+        }
+        let len = len - 1;
+        let last = self.heap_get(heap, len)?;
+        self.delete(&heap_item_key(heap, len))?;
+        self.set_u64(&heap_len_key(heap), len)?;
+        if len == 0 {
+            return Ok(());
+        }
+        // sift the last item down from the root
+        let mut i = 0;
+        loop {
+            let mut smallest = i;
+            let mut smallest_item = last;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < len {
+                    let child_item = self.heap_get(heap, child)?;
+                    if child_item < smallest_item {
+                        smallest = child;
+                        smallest_item = child_item;
+                    }
+                }
+            }
+            if smallest == i {
+                break;
+            }
+            self.set(&heap_item_key(heap, i), &smallest_item)?;
+            i = smallest;
+        }
+        self.set(&heap_item_key(heap, i), &last)
+    }
+
+    fn heap_get(&self, heap: u8, i: u64) -> Result<[u8; 24], ErrorCode> {
+        self.get(&heap_item_key(heap, i))?
+            .and_then(|item| item.try_into().ok())
+            .ok_or(ErrorCode::SystemCode(EncodingError))
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<&'a [u8]>, ErrorCode> {
+        self.state_handler
+            .kv_get(ROOT_ACCOUNT, key, self.gas, self.allocator)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) -> Result<(), ErrorCode> {
+        self.state_handler
+            .kv_set(ROOT_ACCOUNT, key, value, self.gas)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> Result<(), ErrorCode> {
+        self.state_handler.kv_delete(ROOT_ACCOUNT, key, self.gas)
+    }
+
+    fn get_u64(&self, key: &[u8]) -> Result<u64, ErrorCode> {
+        Ok(self.get(key)?.map(decode_u64).transpose()?.unwrap_or(0))
+    }
+
+    fn set_u64(&mut self, key: &[u8], value: u64) -> Result<(), ErrorCode> {
+        self.set(key, &value.to_be_bytes())
+    }
+}
+
+fn decode_u64(bz: &[u8]) -> Result<u64, ErrorCode> {
+    Ok(u64::from_be_bytes(
+        bz.try_into()
+            .map_err(|_| ErrorCode::SystemCode(EncodingError))?,
+    ))
+}
+
+fn entry_key(seq: u64) -> Vec<u8> {
+    let mut key = Vec::from(b"d:e".as_slice());
+    key.extend_from_slice(&seq.to_be_bytes());
+    key
+}
+
+fn queue_key(queue: u8, field: u8) -> [u8; 5] {
+    [b'd', b':', b'q', queue, field]
+}
+
+fn queue_item_key(queue: u8, index: u64) -> Vec<u8> {
+    let mut key = Vec::from(queue_key(queue, b'i').as_slice());
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
+
+fn heap_len_key(heap: u8) -> [u8; 5] {
+    [b'd', b':', b'p', heap, b'n']
+}
+
+fn heap_item_key(heap: u8, index: u64) -> Vec<u8> {
+    let mut key = Vec::from([b'd', b':', b'p', heap, b'i'].as_slice());
+    key.extend_from_slice(&index.to_be_bytes());
+    key
+}
+
+fn heap_item(trigger: u128, seq: u64) -> [u8; 24] {
+    let mut item = [0u8; 24];
+    item[..16].copy_from_slice(&trigger.to_be_bytes());
+    item[16..].copy_from_slice(&seq.to_be_bytes());
+    item
+}
+
+fn split_heap_item(item: &[u8; 24]) -> (u128, u64) {
+    (
+        u128::from_be_bytes(item[..16].try_into().unwrap()),
+        u64::from_be_bytes(item[16..].try_into().unwrap()),
+    )
+}
+
+impl<CM: VM, const CALL_STACK_LIMIT: usize> AccountManager<'_, CM, CALL_STACK_LIMIT> {
+    /// Drains the messages deferred to the end of the block, including messages scheduled
+    /// for a block height or time which are due at the current block's height and time.
+    /// Each message is run in its own transaction with the account which deferred it as the caller.
+    /// Draining stops once the gas budget is exhausted and any remaining due messages
+    /// are left queued for the next block.
+    /// Messages deferred to the end of the block while draining are also left for the next block.
+    /// This should be called by the block executor after all the transactions in the block are run.
+    pub fn drain_deferred<ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        gas_budget: u64,
+        allocator: &dyn Allocator,
+    ) -> Result<DrainResult, ErrorCode> {
+        let gas = GasMeter::unlimited();
+        let block_info = get_block_info(state_handler, &gas, allocator)?;
+        id_generator.begin_tx(TxContext {
+            block_height: block_info.height,
+            tx_index: TxContext::END_BLOCK_INDEX,
+        });
+        let mut store = DeferredStore::new(state_handler, &gas, allocator);
+        store.schedule_due(block_info.height, block_info.time_unix_nanos)?;
+        let due = store.queue_len(END_OF_BLOCK_QUEUE)?;
+        let gas_tracker = GasTracker::limited(gas_budget);
+        let mut res = self.drain_queue(
+            state_handler,
+            id_generator,
+            END_OF_BLOCK_QUEUE,
+            due,
+            &gas_tracker,
+            allocator,
+        )?;
+        res.pending = due - res.executed;
+        Ok(res)
+    }
+
+    /// Drains the messages deferred to the end of the current transaction.
+    /// Only the messages which were queued when draining starts are run, so that
+    /// messages which keep deferring more messages can't run forever.
+    /// Messages which they defer and messages which can't run because the transaction
+    /// ran out of gas are carried over to the end of the block.
+    pub(crate) fn drain_end_of_tx<ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        gas_tracker: &GasTracker,
+        allocator: &dyn Allocator,
+    ) -> Result<(), ErrorCode> {
+        let gas = GasMeter::unlimited();
+        let queued =
+            DeferredStore::new(state_handler, &gas, allocator).queue_len(END_OF_TX_QUEUE)?;
+        self.drain_queue(
+            state_handler,
+            id_generator,
+            END_OF_TX_QUEUE,
+            queued,
+            gas_tracker,
+            allocator,
+        )?;
+        let mut store = DeferredStore::new(state_handler, &gas, allocator);
+        while let Some(seq) = store.queue_pop(END_OF_TX_QUEUE)? {
+            store.queue_push(END_OF_BLOCK_QUEUE, seq)?;
+        }
+        Ok(())
+    }
+
+    fn drain_queue<ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        queue: u8,
+        max: u64,
+        gas_tracker: &GasTracker,
+        allocator: &dyn Allocator,
+    ) -> Result<DrainResult, ErrorCode> {
+        // bookkeeping of the queues isn't metered
+        let gas = GasMeter::unlimited();
+        let mut res = DrainResult::default();
+        while res.executed < max {
+            let store = DeferredStore::new(state_handler, &gas, allocator);
+            let Some(seq) = store.queue_peek(queue)? else {
+                break;
+            };
+            let gas_left = gas_tracker
+                .limit
+                .map(|limit| limit.saturating_sub(gas_tracker.consumed.get()));
+            if gas_left == Some(0) {
+                break;
+            }
+            // copy the message out of storage so that the state handler can be borrowed again
+            let entry = store
+                .entry(seq)?
+                .map(|(caller, message)| (caller, message.to_vec()));
+            let message_tracker = GasTracker::new(gas_left);
+            let msg_res = match entry {
+                Some((caller, message)) => decode_message(&message).and_then(|message| {
+                    let invoke_params = InvokeParams::new(allocator, Some(&message_tracker));
+                    self.invoke_msg_in_tx(
+                        state_handler,
+                        id_generator,
+                        caller,
+                        &message,
+                        &invoke_params,
+                        queue != END_OF_TX_QUEUE,
+                    )
+                    .map(|_| ())
+                }),
+                None => Err(ErrorCode::SystemCode(EncodingError)),
+            };
+            // gas consumed by a message which ran out of gas is capped at what it was given
+            let consumed = gas_left.map_or(message_tracker.consumed.get(), |gas_left| {
+                gas_left.min(message_tracker.consumed.get())
+            });
+            gas_tracker
+                .consumed
+                .set(gas_tracker.consumed.get() + consumed);
+            res.gas_consumed += consumed;
+            // a message which ran out of the remaining budget is retried with a fresh budget,
+            // unless it already had the whole budget to itself
+            if msg_res == Err(ErrorCode::SystemCode(OutOfGas))
+                && gas_left.is_some()
+                && res.executed > 0
+            {
+                break;
+            }
+            let mut store = DeferredStore::new(state_handler, &gas, allocator);
+            store.queue_pop(queue)?;
+            store.delete(&entry_key(seq))?;
+            res.executed += 1;
+            if msg_res.is_err() {
+                res.failed += 1;
+            }
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,18 @@
+//! Positional metadata which state managers record with emitted events.
+
+/// The position at which an event was emitted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EventMetadata {
+    /// The height of the block in which the event was emitted.
+    pub block_height: u64,
+    /// The index of the transaction within its block.
+    pub tx_index: u32,
+    /// The index of the message within its transaction.
+    pub message_index: u32,
+    /// The depth of the call which emitted the event, starting at 0 for a top-level message
+    /// and increasing by 1 for each nested call.
+    pub depth: u32,
+    /// A sequence number which orders every emitted event, including reverted ones.
+    /// Sequence numbers are never reused, so committed events may have gaps between them.
+    pub sequence: u64,
+}
@@ -1,24 +1,36 @@
+use crate::block_info::get_block_info;
 use crate::call_stack::CallStack;
+use crate::deferred::{DeferredStore, DEFER_SELECTOR};
 use crate::gas_stack::GasStack;
 use crate::id_generator::IDGenerator;
+use crate::interceptor::{run_after_invoke, run_before_invoke};
 use crate::query_ctx::QueryContext;
 use crate::state_handler::{
-    destroy_account_data, get_account_handler_id, init_next_account, set_handler_id, StateHandler,
+    destroy_account_data, get_account_admin, get_account_handler_id, get_deposit_payer,
+    init_account, init_next_account, is_self_destruct_disabled, set_account_admin,
+    set_deposit_payer, set_handler_id, StateHandler, StorageDeposit, STORAGE_DEPOSIT_SELECTOR,
 };
+use crate::tx::{Tx, TxError};
 use crate::wrapper::ExecContextWrapper;
 use crate::{AccountManager, ReadOnlyStoreWrapper};
+use alloc::vec::Vec;
 use allocator_api2::alloc::Allocator;
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use ixc_core_macros::message_selector;
 use ixc_message_api::code::ErrorCode;
 use ixc_message_api::code::ErrorCode::SystemCode;
 use ixc_message_api::code::SystemCode::{
     AccountNotFound, FatalExecutionError, HandlerNotFound, InvalidHandler, MessageNotHandled,
+    UnauthorizedCallerAccess, VolatileAccessError,
 };
+use ixc_message_api::encoding::{decode_batch, encode_responses};
 use ixc_message_api::gas::GasTracker;
 use ixc_message_api::handler::{HostBackend, InvokeParams};
-use ixc_message_api::message::{Message, Request, Response};
-use ixc_message_api::{AccountID, ROOT_ACCOUNT};
+use ixc_message_api::message::{Message, Request, Response, Volatility};
+use ixc_message_api::{
+    AccountID, ADDRESS_REGISTRY_ACCOUNT, AUTHENTICATE_SELECTOR, REGISTER_ADDRESS_SELECTOR,
+    ROOT_ACCOUNT,
+};
 use ixc_vm_api::VM;
 
 pub(crate) struct ExecContext<
@@ -33,6 +45,11 @@ pub(crate) struct ExecContext<
     id_generator: &'a IDG,
     call_stack: CallStack<CALL_STACK_LIMIT>,
     gas_stack: GasStack<CALL_STACK_LIMIT>,
+    // whether a query message is currently executing, in which case state can't be updated
+    readonly: Cell<bool>,
+    // the account whose on_create is currently running and the account creating it,
+    // which pays the storage deposits for the new account's initial state
+    creating: Cell<Option<(AccountID, AccountID)>>,
 }
 
 impl<'a, CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
@@ -51,6 +68,8 @@ impl<'a, CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usi
             id_generator,
             call_stack: CallStack::new(account),
             gas_stack: GasStack::new(gas_tracker.and_then(|g| g.limit)),
+            readonly: Cell::new(false),
+            creating: Cell::new(None),
         }
     }
 }
@@ -64,6 +83,123 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
         message: &Message,
         invoke_params: &InvokeParams<'a, '_>,
     ) -> Result<Response<'a>, ErrorCode> {
+        self.invoke_in_frame(message, invoke_params, false)
+    }
+
+    /// Invokes a system message, such as authentication, on the target account's handler.
+    /// System messages can only be sent by the account manager itself.
+    pub(crate) fn do_invoke_system_msg<'a>(
+        &self,
+        message: &Message,
+        invoke_params: &InvokeParams<'a, '_>,
+    ) -> Result<Response<'a>, ErrorCode> {
+        self.invoke_in_frame(message, invoke_params, true)
+    }
+
+    /// Authenticates the transaction's sender and then runs all of its messages atomically.
+    pub(crate) fn do_invoke_tx<'a>(
+        &self,
+        tx: &Tx,
+        invoke_params: &InvokeParams<'a, '_>,
+    ) -> Result<Vec<Response<'a>>, TxError> {
+        let gas_scope = self
+            .gas_stack
+            .push(invoke_params.gas_tracker)
+            .map_err(TxError::Fatal)?;
+        // nested calls share the transaction's gas limit
+        let nested_params = InvokeParams::new(invoke_params.allocator, None);
+
+        // authenticate the sender by calling its handler's authenticate system message
+        // with sign bytes which are bound to the current chain
+        let block_info = get_block_info(
+            *self.state_handler.borrow(),
+            self.gas_stack.meter(),
+            invoke_params.allocator,
+        )
+        .map_err(TxError::Fatal)?;
+        let sign_bytes = tx.sign_bytes(block_info.chain_id);
+        let auth = Message::new(
+            tx.sender,
+            Request::new3(
+                AUTHENTICATE_SELECTOR,
+                tx.nonce.into(),
+                tx.credentials.into(),
+                sign_bytes.as_slice().into(),
+            ),
+        );
+        self.do_invoke_system_msg(&auth, &nested_params)
+            .map_err(|code| match code {
+                // accounts which don't handle authentication can't send transactions
+                SystemCode(MessageNotHandled) => {
+                    TxError::Unauthenticated(SystemCode(UnauthorizedCallerAccess))
+                }
+                code => TxError::Unauthenticated(code),
+            })?;
+
+        let responses = self.do_invoke_batch(tx.messages, &nested_params, true)?;
+
+        gas_scope.pop();
+        Ok(responses)
+    }
+
+    /// Runs the messages in order in a single transaction frame so that they succeed or fail together.
+    /// The currently active account is the caller of every message.
+    /// The state handler is told the index of each message when the batch is top-level.
+    pub(crate) fn do_invoke_batch<'a>(
+        &self,
+        messages: &[Message],
+        invoke_params: &InvokeParams<'a, '_>,
+        top_level: bool,
+    ) -> Result<Vec<Response<'a>>, TxError> {
+        let gas_scope = self
+            .gas_stack
+            .push(invoke_params.gas_tracker)
+            .map_err(TxError::Fatal)?;
+        // the messages share the batch's gas limit
+        let nested_params = InvokeParams::new(invoke_params.allocator, None);
+        self.state_handler
+            .borrow_mut()
+            .begin_tx(self.gas_stack.meter())
+            .map_err(TxError::Fatal)?;
+        let mut responses = Vec::with_capacity(messages.len());
+        for (index, message) in messages.iter().enumerate() {
+            if top_level {
+                self.state_handler
+                    .borrow_mut()
+                    .begin_message(index as u32)
+                    .map_err(TxError::Fatal)?;
+            }
+            match self.do_invoke_msg(message, &nested_params) {
+                Ok(res) => responses.push(res),
+                Err(code) => {
+                    self.state_handler
+                        .borrow_mut()
+                        .rollback_tx(self.gas_stack.meter())
+                        .map_err(TxError::Fatal)?;
+                    return Err(TxError::MessageFailed { index, code });
+                }
+            }
+        }
+        self.state_handler
+            .borrow_mut()
+            .commit_tx(self.gas_stack.meter())
+            .map_err(TxError::Fatal)?;
+        gas_scope.pop();
+        Ok(responses)
+    }
+
+    fn invoke_in_frame<'a>(
+        &self,
+        message: &Message,
+        invoke_params: &InvokeParams<'a, '_>,
+        system: bool,
+    ) -> Result<Response<'a>, ErrorCode> {
+        // query messages can't invoke volatile messages
+        let is_query = message.request().volatility() == Volatility::Query;
+        if self.readonly.get() && !is_query {
+            return Err(SystemCode(VolatileAccessError));
+        }
+
         let gas_scope = self.gas_stack.push(invoke_params.gas_tracker)?;
         let target_account = message.target_account();
         let allocator = invoke_params.allocator;
@@ -74,41 +210,18 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
             .begin_tx(self.gas_stack.meter())
             .map_err(|_| SystemCode(InvalidHandler))?;
 
-        let res = if target_account == ROOT_ACCOUNT {
-            // if the target account is the root account, we can just run the system message
-            self.handle_system_message(message.request(), allocator)
-        } else {
-            // push onto the call stack when we're calling a non-system account
-            let call_scope = self.call_stack.push(target_account)?;
+        // the caller of this message is the currently active account
+        let caller = self.call_stack.active_account()?;
+        let interceptors = self.account_manager.interceptors;
+        let request = message.request();
 
-            // find the account's handler ID
-            let handler_id = get_account_handler_id(
-                *self.state_handler.borrow(),
-                target_account,
-                self.gas_stack.meter(),
-                allocator,
-            )?
-            .ok_or(SystemCode(AccountNotFound))?;
-
-            // run the handler
-            let handler = self.account_manager.code_manager.resolve_handler(
-                &ReadOnlyStoreWrapper::wrap(
-                    *self.state_handler.borrow(),
-                    self.gas_stack.meter(),
-                    allocator,
-                ),
-                handler_id,
-                allocator,
-            )?;
-            let caller = self.call_stack.caller()?;
-            let mut wrapper = ExecContextWrapper::new(self);
-            let res = handler.handle_msg(&caller, message, &mut wrapper, allocator);
-
-            // pop the call stack
-            call_scope.pop();
-
-            res.map_err(|e| e.code)
-        };
+        // run the interceptors and the message within the transaction frame,
+        // with state updates disabled if the message is a query
+        let was_readonly = self.readonly.replace(self.readonly.get() || is_query);
+        let res = run_before_invoke(interceptors, &caller, &target_account, request)
+            .and_then(|_| self.dispatch_msg(message, allocator, system));
+        let res = run_after_invoke(interceptors, &caller, &target_account, request, res);
+        self.readonly.set(was_readonly);
 
         // commit or rollback the transaction
         if res.is_ok() {
@@ -127,6 +240,58 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
         res
     }
 
+    fn dispatch_msg<'a>(
+        &self,
+        message: &Message,
+        allocator: &'a dyn Allocator,
+        system: bool,
+    ) -> Result<Response<'a>, ErrorCode> {
+        let target_account = message.target_account();
+        if target_account == ROOT_ACCOUNT && !system {
+            // if the target account is the root account, we can just run the system message
+            return self.handle_system_message(message.request(), allocator);
+        }
+        if !system && is_system_route(message.request().message_selector()) {
+            // system routes can only be called by the account manager itself
+            return Err(SystemCode(UnauthorizedCallerAccess));
+        }
+
+        // push onto the call stack when we're calling a non-system account
+        let call_scope = self.call_stack.push(target_account)?;
+
+        // find the account's handler ID
+        let handler_id = get_account_handler_id(
+            *self.state_handler.borrow(),
+            target_account,
+            self.gas_stack.meter(),
+            allocator,
+        )?
+        .ok_or(SystemCode(AccountNotFound))?;
+
+        // run the handler
+        let handler = self.account_manager.code_manager.resolve_handler(
+            &ReadOnlyStoreWrapper::wrap(
+                *self.state_handler.borrow(),
+                self.gas_stack.meter(),
+                allocator,
+            ),
+            handler_id,
+            allocator,
+        )?;
+        let caller = self.call_stack.caller()?;
+        let mut wrapper = ExecContextWrapper::new(self);
+        let res = if system {
+            handler.handle_system(&caller, message, &mut wrapper, allocator)
+        } else {
+            handler.handle_msg(&caller, message, &mut wrapper, allocator)
+        };
+
+        // pop the call stack
+        call_scope.pop();
+
+        res.map_err(|e| e.code)
+    }
+
     pub(crate) fn do_invoke_query<'a>(
         &self,
         message: &Message,
@@ -151,18 +316,102 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
         req: &Request,
         invoke_params: &InvokeParams<'a, '_>,
     ) -> Result<Response<'a>, ErrorCode> {
+        if self.readonly.get() {
+            return Err(SystemCode(VolatileAccessError));
+        }
         let gas_scope = self.gas_stack.push(invoke_params.gas_tracker)?;
         let active_account = self.call_stack.active_account()?;
+        // events are stamped with the depth of the call which emitted them,
+        // which the state handler can't tell from its transaction frames
+        let emit_req;
+        let req = if req.message_selector() == EMIT_EVENT_SELECTOR {
+            let depth = self.call_stack.depth() as u64;
+            emit_req = Request::new3(EMIT_EVENT_SELECTOR, req.in1(), req.in2(), depth.into());
+            &emit_req
+        } else {
+            req
+        };
+        let deposits_enabled = self
+            .state_handler
+            .borrow()
+            .storage_deposit_account()
+            .is_some();
+        if !deposits_enabled {
+            let res = self.state_handler.borrow_mut().handle_exec(
+                active_account,
+                req,
+                self.gas_stack.meter(),
+                invoke_params.allocator,
+            );
+            gas_scope.pop();
+            return res;
+        }
+
+        // the update and its deposit succeed or fail together
+        self.state_handler
+            .borrow_mut()
+            .begin_tx(self.gas_stack.meter())?;
         let res = self.state_handler.borrow_mut().handle_exec(
             active_account,
             req,
             self.gas_stack.meter(),
             invoke_params.allocator,
         );
+        let res = res.and_then(|res| {
+            // accounts which are being created have their deposits paid by their creator
+            let payer = match self.creating.get() {
+                Some((account, creator)) if account == active_account => creator,
+                _ => active_account,
+            };
+            self.settle_storage_deposit(active_account, payer, invoke_params.allocator)?;
+            Ok(res)
+        });
+        if res.is_ok() {
+            self.state_handler
+                .borrow_mut()
+                .commit_tx(self.gas_stack.meter())?;
+        } else {
+            self.state_handler
+                .borrow_mut()
+                .rollback_tx(self.gas_stack.meter())?;
+        }
         gas_scope.pop();
         res
     }
 
+    /// Charges the payer or refunds it for the changes to the account's storage
+    /// by sending a storage deposit system message to the storage deposit account.
+    fn settle_storage_deposit(
+        &self,
+        account: AccountID,
+        payer: AccountID,
+        allocator: &dyn Allocator,
+    ) -> Result<(), ErrorCode> {
+        let mut state_handler = self.state_handler.borrow_mut();
+        let Some(deposit) = state_handler.take_storage_deposit(account)? else {
+            return Ok(());
+        };
+        let deposit_account = state_handler
+            .storage_deposit_account()
+            .ok_or(SystemCode(FatalExecutionError))?;
+        drop(state_handler);
+        let (amount, refund) = match deposit {
+            StorageDeposit::Charge(amount) => (amount, 0u64),
+            StorageDeposit::Refund(amount) => (amount, 1u64),
+        };
+        let message = Message::new(
+            deposit_account,
+            Request::new3(
+                STORAGE_DEPOSIT_SELECTOR,
+                payer.into(),
+                amount.into(),
+                refund.into(),
+            ),
+        );
+        self.do_invoke_system_msg(&message, &InvokeParams::new(allocator, None))?;
+        Ok(())
+    }
+
     pub(crate) fn do_query_state<'a>(
         &self,
         req: &Request,
@@ -199,12 +448,18 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
     ) -> Result<Response<'a>, ErrorCode> {
         unsafe {
             match request.message_selector() {
-                CREATE_SELECTOR => self.handle_create(request, allocator),
+                CREATE_SELECTOR => self.handle_create(request, allocator, false),
+                CREATE_WITH_ADMIN_SELECTOR => self.handle_create(request, allocator, true),
                 MIGRATE_SELECTOR => self.handle_migrate(request, allocator),
                 SELF_DESTRUCT_SELECTOR => {
-                    self.handle_self_destruct()?;
+                    self.handle_self_destruct(allocator)?;
+                    Ok(Default::default())
+                }
+                DEFER_SELECTOR => {
+                    self.handle_defer(request, allocator)?;
                     Ok(Default::default())
                 }
+                BATCH_SELECTOR => self.handle_batch(request, allocator),
                 _ => Err(SystemCode(MessageNotHandled)),
             }
         }
@@ -214,10 +469,17 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
         &self,
         req: &Request,
         allocator: &'a dyn Allocator,
+        with_admin: bool,
     ) -> Result<Response<'a>, ErrorCode> {
         // get the input data
         let handler_id = req.in1().expect_string()?;
         let init_data = req.in2().expect_bytes()?;
+        // the third input is either the migration admin or a fixed account ID
+        let (admin, fixed_id) = if with_admin {
+            (Some(req.in3().expect_account_id()?), None)
+        } else {
+            (None, req.in3().as_account_id())
+        };
 
         // resolve the handler ID and retrieve the VM
         let handler_id = self
@@ -234,15 +496,61 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
             )?
             .ok_or(SystemCode(HandlerNotFound))?;
 
-        // get the next account ID and initialize the account storage
-        let id = init_next_account(
-            self.id_generator,
+        let id = if let Some(id) = fixed_id {
+            // only the root account can create accounts with a fixed ID,
+            // for instance to set up system accounts at genesis
+            if self.call_stack.active_account()? != ROOT_ACCOUNT {
+                return Err(SystemCode(UnauthorizedCallerAccess));
+            }
+            // and it can't replace an existing account
+            if get_account_handler_id(
+                *self.state_handler.borrow(),
+                id,
+                self.gas_stack.meter(),
+                allocator,
+            )?
+            .is_some()
+            {
+                return Err(SystemCode(UnauthorizedCallerAccess));
+            }
+            init_account(
+                *self.state_handler.borrow_mut(),
+                id,
+                handler_id,
+                self.gas_stack.meter(),
+            )
+            .map_err(|_| SystemCode(InvalidHandler))?;
+            id
+        } else {
+            // get the next account ID and initialize the account storage
+            init_next_account(
+                self.id_generator,
+                *self.state_handler.borrow_mut(),
+                handler_id,
+                allocator,
+                self.gas_stack.meter(),
+            )
+            .map_err(|_| SystemCode(InvalidHandler))?
+        };
+
+        // record the admin before on_create runs so that it applies to the whole account lifecycle
+        if let Some(admin) = admin {
+            set_account_admin(
+                *self.state_handler.borrow_mut(),
+                id,
+                admin,
+                self.gas_stack.meter(),
+            )?;
+        }
+
+        // the creator pays the deposit for the new account's initial state,
+        // so it is also refunded when the account self-destructs
+        set_deposit_payer(
             *self.state_handler.borrow_mut(),
-            handler_id,
-            allocator,
+            id,
+            self.call_stack.active_account()?,
             self.gas_stack.meter(),
-        )
-        .map_err(|_| SystemCode(InvalidHandler))?;
+        )?;
 
         // create a packet for calling on_create
         let on_create = Message::new(id, Request::new1(ON_CREATE_SELECTOR, init_data.into()));
@@ -262,6 +570,7 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
         let call_scope = self.call_stack.push(id)?;
 
         let caller = self.call_stack.caller()?;
+        let was_creating = self.creating.replace(Some((id, caller)));
         let res = handler
             .handle_system(
                 &caller,
@@ -270,6 +579,7 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
                 allocator,
             )
             .map_err(|e| e.code);
+        self.creating.set(was_creating);
 
         // pop the frame
         call_scope.pop();
@@ -281,11 +591,37 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
             Err(_) => false,
         };
 
-        if is_ok {
-            // the result is ID of the newly created account, which is the first input
-            Ok(Response::new1(id.into()))
-        } else {
-            res
+        if !is_ok {
+            return res;
+        }
+
+        self.register_address(id, allocator)?;
+
+        // the result is ID of the newly created account, which is the first input
+        Ok(Response::new1(id.into()))
+    }
+
+    /// Asks the system address registry, if it has been created, to register the address
+    /// of the new account.
+    fn register_address(&self, id: AccountID, allocator: &dyn Allocator) -> Result<(), ErrorCode> {
+        if get_account_handler_id(
+            *self.state_handler.borrow(),
+            ADDRESS_REGISTRY_ACCOUNT,
+            self.gas_stack.meter(),
+            allocator,
+        )?
+        .is_none()
+        {
+            return Ok(());
+        }
+        let message = Message::new(
+            ADDRESS_REGISTRY_ACCOUNT,
+            Request::new1(REGISTER_ADDRESS_SELECTOR, id.into()),
+        );
+        match self.do_invoke_system_msg(&message, &InvokeParams::new(allocator, None)) {
+            // we accept the case where the registry doesn't register addresses on creation
+            Ok(_) | Err(SystemCode(MessageNotHandled)) => Ok(()),
+            Err(code) => Err(code),
         }
     }
 
@@ -297,11 +633,25 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
         // get the input data
         let active_account = self.call_stack.active_account()?;
         let new_handler_id = req.in1().expect_string()?;
+        // an account migrates itself unless its admin specifies which account to migrate
+        let account = req.in2().as_account_id().unwrap_or(active_account);
+
+        // if the account has an admin, only the admin can migrate it,
+        // otherwise only the account itself can
+        let admin = get_account_admin(
+            *self.state_handler.borrow(),
+            account,
+            self.gas_stack.meter(),
+            allocator,
+        )?;
+        if admin.unwrap_or(account) != active_account {
+            return Err(SystemCode(UnauthorizedCallerAccess));
+        }
 
         // get the old handler id
         let old_handler_id = get_account_handler_id(
             *self.state_handler.borrow(),
-            active_account,
+            account,
             self.gas_stack.meter(),
             allocator,
         )?
@@ -325,7 +675,7 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
         // update the handler ID
         set_handler_id(
             *self.state_handler.borrow_mut(),
-            active_account,
+            account,
             new_handler_id,
             self.gas_stack.meter(),
         )
@@ -333,7 +683,7 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
 
         // create a packet for calling on_create
         let on_migrate = Message::new(
-            active_account,
+            account,
             Request::new1(ON_MIGRATE_SELECTOR, old_handler_id.into()),
         );
 
@@ -348,30 +698,118 @@ impl<CM: VM, ST: StateHandler, IDG: IDGenerator, const CALL_STACK_LIMIT: usize>
             allocator,
         )?;
 
+        // when an admin migrates another account, on_migrate runs in that account's frame
+        let call_scope = if account != active_account {
+            Some(self.call_stack.push(account)?)
+        } else {
+            None
+        };
+
         // execute the on-migrate packet with the system message handler
-        handler
+        let res = handler
             .handle_system(
                 &active_account,
                 &on_migrate,
                 &mut ExecContextWrapper::new(self),
                 allocator,
             )
-            .map_err(|e| e.code)
+            .map_err(|e| e.code);
+
+        if let Some(call_scope) = call_scope {
+            call_scope.pop();
+        }
+
+        res
+    }
+
+    fn handle_defer(&self, req: &Request, allocator: &dyn Allocator) -> Result<(), ErrorCode> {
+        // the deferred message will be sent by the account which deferred it
+        let caller = self.call_stack.active_account()?;
+        let trigger_kind = req.in1().expect_u64()?;
+        let trigger_value = req.in2().expect_u128()?;
+        let message = req.in3().expect_bytes()?;
+        let mut state_handler = self.state_handler.borrow_mut();
+        DeferredStore::new(*state_handler, self.gas_stack.meter(), allocator).defer(
+            caller,
+            trigger_kind,
+            trigger_value,
+            message,
+        )
     }
 
-    unsafe fn handle_self_destruct(&self) -> Result<(), ErrorCode> {
+    /// Runs a batch of messages atomically on behalf of the active account.
+    /// If one of the messages fails, the response holds its index and error code
+    /// rather than the encoded responses of all the messages.
+    unsafe fn handle_batch<'a>(
+        &self,
+        req: &Request,
+        allocator: &'a dyn Allocator,
+    ) -> Result<Response<'a>, ErrorCode> {
+        let messages = decode_batch(req.in1().expect_bytes()?)?.collect::<Result<Vec<_>, _>>()?;
+        match self.do_invoke_batch(&messages, &InvokeParams::new(allocator, None), false) {
+            Ok(responses) => Ok(Response::new1(
+                encode_responses(&responses, allocator)?.into(),
+            )),
+            Err(TxError::MessageFailed { index, code }) => {
+                let code: u16 = code.into();
+                Ok(Response::new2((index as u64).into(), (code as u64).into()))
+            }
+            Err(TxError::Unauthenticated(code) | TxError::Fatal(code)) => Err(code),
+        }
+    }
+
+    unsafe fn handle_self_destruct(&self, allocator: &dyn Allocator) -> Result<(), ErrorCode> {
+        let account = self.call_stack.active_account()?;
+        let handler_id = get_account_handler_id(
+            *self.state_handler.borrow(),
+            account,
+            self.gas_stack.meter(),
+            allocator,
+        )?
+        .ok_or(SystemCode(AccountNotFound))?;
+        let payer = get_deposit_payer(
+            *self.state_handler.borrow(),
+            account,
+            self.gas_stack.meter(),
+            allocator,
+        )?;
+        if is_self_destruct_disabled(
+            *self.state_handler.borrow(),
+            handler_id,
+            self.gas_stack.meter(),
+            allocator,
+        )? {
+            return Err(SystemCode(UnauthorizedCallerAccess));
+        }
         destroy_account_data(
             *self.state_handler.borrow_mut(),
-            self.call_stack.active_account()?,
+            account,
             self.gas_stack.meter(),
         )
         .map_err(|_| SystemCode(FatalExecutionError))?;
-        Ok(())
+        // the deposit for all of the account's storage is refunded to the account which created it,
+        // or to the account itself if it was imported without a recorded creator
+        self.settle_storage_deposit(account, payer.unwrap_or(account), allocator)
     }
 }
 
 const CREATE_SELECTOR: u64 = message_selector!("ixc.account.v1.create");
+const CREATE_WITH_ADMIN_SELECTOR: u64 = message_selector!("ixc.account.v1.create_with_admin");
 const ON_CREATE_SELECTOR: u64 = message_selector!("ixc.account.v1.on_create");
 const MIGRATE_SELECTOR: u64 = message_selector!("ixc.account.v1.migrate");
 const ON_MIGRATE_SELECTOR: u64 = message_selector!("ixc.account.v1.on_migrate");
 const SELF_DESTRUCT_SELECTOR: u64 = message_selector!("ixc.account.v1.self_destruct");
+const BATCH_SELECTOR: u64 = message_selector!("ixc.account.v1.batch");
+const EMIT_EVENT_SELECTOR: u64 = message_selector!("ixc.events.1.emit");
+
+/// Returns true if the message selector is a system route which only the account manager can call.
+fn is_system_route(selector: u64) -> bool {
+    matches!(
+        selector,
+        ON_CREATE_SELECTOR
+            | ON_MIGRATE_SELECTOR
+            | AUTHENTICATE_SELECTOR
+            | STORAGE_DEPOSIT_SELECTOR
+            | REGISTER_ADDRESS_SELECTOR
+    )
+}
@@ -1,6 +1,8 @@
 //! ID generator trait.
 
+use core::cell::Cell;
 use ixc_message_api::code::ErrorCode;
+use ixc_message_api::code::SystemCode::FatalExecutionError;
 use ixc_message_api::AccountID;
 
 /// ID generator trait.
@@ -9,6 +11,9 @@ pub trait IDGenerator {
     fn new_account_id<S: Store>(&self, store: &mut S) -> Result<AccountID, ErrorCode>;
     /// Generates a new unique ID which can be used in any context.
     fn new_unique_id<S: Store>(&self, store: &mut S) -> Result<u128, ErrorCode>;
+    /// Called by the account manager with the position of each transaction before it runs it,
+    /// and of the messages run at the end of a block, so that IDs can be derived from it.
+    fn begin_tx(&self, _ctx: TxContext) {}
 }
 
 /// Store trait used by the ID generator.
@@ -42,3 +47,166 @@ impl IDGenerator for IncrementingIDGenerator {
         }
     }
 }
+
+/// The context of the transaction in which IDs are generated by a [`TxScopedIDGenerator`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TxContext {
+    /// The height of the block containing the transaction.
+    pub block_height: u64,
+    /// The index of the transaction within the block.
+    /// [`TxContext::END_BLOCK_INDEX`] should be used for messages run at the end of the block.
+    pub tx_index: u32,
+}
+
+impl TxContext {
+    /// The transaction index used for messages run at the end of a block, such as deferred messages.
+    pub const END_BLOCK_INDEX: u32 = u32::MAX;
+}
+
+/// An ID generator which derives IDs from the transaction they are generated in
+/// rather than from a global counter, so that transactions can create accounts concurrently.
+///
+/// The high 64 bits of each ID are the block height plus one, the next 32 bits are
+/// the transaction index and the low 32 bits are a sequence scoped to the transaction.
+/// Because a transaction position is never executed twice, IDs are never reused,
+/// even after the accounts they were assigned to are deleted,
+/// and they never overlap with the reserved range or the IDs of an [`IncrementingIDGenerator`].
+/// The account manager moves the generator to each transaction it runs when it is given
+/// the transaction's context with [`AccountManager::with_tx_context`](crate::AccountManager::with_tx_context),
+/// and the generator doesn't touch the store.
+#[derive(Debug)]
+pub struct TxScopedIDGenerator {
+    prefix: Cell<u128>,
+    sequence: Cell<u32>,
+}
+
+impl Default for TxScopedIDGenerator {
+    fn default() -> Self {
+        Self::new(TxContext::default())
+    }
+}
+
+impl TxScopedIDGenerator {
+    /// Creates a new ID generator for the transaction.
+    pub fn new(ctx: TxContext) -> Self {
+        Self {
+            prefix: Cell::new(tx_prefix(ctx)),
+            sequence: Cell::new(0),
+        }
+    }
+}
+
+fn tx_prefix(ctx: TxContext) -> u128 {
+    let height = ctx.block_height as u128 + 1;
+    (height << 64) | ((ctx.tx_index as u128) << 32)
+}
+
+impl IDGenerator for TxScopedIDGenerator {
+    fn new_account_id<S: Store>(&self, store: &mut S) -> Result<AccountID, ErrorCode> {
+        self.new_unique_id(store).map(AccountID::new)
+    }
+
+    fn new_unique_id<S: Store>(&self, _store: &mut S) -> Result<u128, ErrorCode> {
+        let seq = self.sequence.get();
+        let next = seq
+            .checked_add(1)
+            .ok_or(ErrorCode::SystemCode(FatalExecutionError))?;
+        self.sequence.set(next);
+        Ok(self.prefix.get() | seq as u128)
+    }
+
+    fn begin_tx(&self, ctx: TxContext) {
+        self.prefix.set(tx_prefix(ctx));
+        self.sequence.set(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use alloc::vec::Vec;
+    use std::collections::BTreeSet;
+
+    /// A store which fails on any access, proving that scoped generators don't share state.
+    struct NoStore;
+
+    impl Store for NoStore {
+        fn get(&self, _key: &[u8]) -> Result<Option<&[u8]>, ErrorCode> {
+            Err(ErrorCode::SystemCode(FatalExecutionError))
+        }
+
+        fn set(&mut self, _key: &[u8], _value: &[u8]) -> Result<(), ErrorCode> {
+            Err(ErrorCode::SystemCode(FatalExecutionError))
+        }
+    }
+
+    #[test]
+    fn test_tx_scoped_ids() {
+        let gen = TxScopedIDGenerator::new(TxContext {
+            block_height: 0,
+            tx_index: 0,
+        });
+        let first: u128 = gen.new_account_id(&mut NoStore).unwrap().into();
+        assert_eq!(first, 1 << 64);
+        assert_eq!(gen.new_unique_id(&mut NoStore).unwrap(), first + 1);
+
+        let gen = TxScopedIDGenerator::new(TxContext {
+            block_height: 5,
+            tx_index: 3,
+        });
+        assert_eq!(
+            gen.new_unique_id(&mut NoStore).unwrap(),
+            (6 << 64) | (3 << 32)
+        );
+    }
+
+    #[test]
+    fn test_no_collisions_across_parallel_txs() {
+        // every transaction in several blocks creates accounts on its own thread
+        let handles: Vec<_> = (0..4u64)
+            .flat_map(|block_height| {
+                [0, 1, 2, TxContext::END_BLOCK_INDEX].map(move |tx_index| {
+                    std::thread::spawn(move || {
+                        let gen = TxScopedIDGenerator::new(TxContext {
+                            block_height,
+                            tx_index,
+                        });
+                        (0..100)
+                            .map(|_| u128::from(gen.new_account_id(&mut NoStore).unwrap()))
+                            .collect::<Vec<_>>()
+                    })
+                })
+            })
+            .collect();
+        let mut ids = BTreeSet::new();
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                // no ID is in the reserved range or assigned twice
+                assert!(id >= 1 << 64);
+                assert!(ids.insert(id));
+            }
+        }
+        assert_eq!(ids.len(), 4 * 4 * 100);
+    }
+
+    #[test]
+    fn test_begin_tx() {
+        let gen = TxScopedIDGenerator::default();
+        let first = gen.new_unique_id(&mut NoStore).unwrap();
+        gen.begin_tx(TxContext {
+            block_height: 0,
+            tx_index: 1,
+        });
+        assert_eq!(gen.new_unique_id(&mut NoStore).unwrap(), first | (1 << 32));
+    }
+
+    #[test]
+    fn test_sequence_exhausted() {
+        let gen = TxScopedIDGenerator::new(TxContext::default());
+        gen.sequence.set(u32::MAX);
+        assert!(gen.new_unique_id(&mut NoStore).is_err());
+        // the last ID isn't handed out again
+        assert!(gen.new_unique_id(&mut NoStore).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Message interceptors which run around every message invoked by the account manager.
+use ixc_message_api::code::{ErrorCode, SystemCode};
+use ixc_message_api::message::{Request, Response};
+use ixc_message_api::AccountID;
+
+/// A message interceptor can observe and reject messages before and after they are executed.
+///
+/// Interceptors are run inside the same transaction frame as the message they intercept,
+/// so rejecting a message in either callback causes all of its state changes to be rolled back.
+/// Interceptors that need to track state (such as metrics or rate limits) should use
+/// interior mutability.
+pub trait MessageInterceptor {
+    /// Called before the message is dispatched to the target account's handler.
+    /// Returning an error rejects the message without executing it.
+    fn before_invoke(
+        &self,
+        _caller: &AccountID,
+        _target: &AccountID,
+        _request: &Request,
+    ) -> Result<(), SystemCode> {
+        Ok(())
+    }
+
+    /// Called after the message has been executed with the result of its execution.
+    /// Returning an error rejects the message and rolls back any state changes it made.
+    fn after_invoke(
+        &self,
+        _caller: &AccountID,
+        _target: &AccountID,
+        _request: &Request,
+        _result: &Result<Response, ErrorCode>,
+    ) -> Result<(), SystemCode> {
+        Ok(())
+    }
+}
+
+/// Runs the [`MessageInterceptor::before_invoke`] callbacks of the interceptors in order,
+/// stopping at the first rejection.
+pub(crate) fn run_before_invoke(
+    interceptors: &[&dyn MessageInterceptor],
+    caller: &AccountID,
+    target: &AccountID,
+    request: &Request,
+) -> Result<(), ErrorCode> {
+    for interceptor in interceptors {
+        interceptor
+            .before_invoke(caller, target, request)
+            .map_err(ErrorCode::SystemCode)?;
+    }
+    Ok(())
+}
+
+/// Runs the [`MessageInterceptor::after_invoke`] callbacks of the interceptors in reverse order,
+/// so that the first interceptor wraps all the others.
+/// If any interceptor rejects the message, its error code replaces the result
+/// and the remaining interceptors see the rejection.
+pub(crate) fn run_after_invoke<'a>(
+    interceptors: &[&dyn MessageInterceptor],
+    caller: &AccountID,
+    target: &AccountID,
+    request: &Request,
+    mut result: Result<Response<'a>, ErrorCode>,
+) -> Result<Response<'a>, ErrorCode> {
+    for interceptor in interceptors.iter().rev() {
+        if let Err(code) = interceptor.after_invoke(caller, target, request, &result) {
+            result = Err(ErrorCode::SystemCode(code));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+    use ixc_message_api::code::SystemCode::UnauthorizedCallerAccess;
+
+    struct Blocklist(AccountID);
+
+    impl MessageInterceptor for Blocklist {
+        fn before_invoke(
+            &self,
+            caller: &AccountID,
+            _target: &AccountID,
+            _request: &Request,
+        ) -> Result<(), SystemCode> {
+            if *caller == self.0 {
+                Err(UnauthorizedCallerAccess)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    struct Recorder<'a> {
+        name: &'static str,
+        log: &'a RefCell<Vec<(&'static str, bool)>>,
+    }
+
+    impl MessageInterceptor for Recorder<'_> {
+        fn before_invoke(
+            &self,
+            _caller: &AccountID,
+            _target: &AccountID,
+            _request: &Request,
+        ) -> Result<(), SystemCode> {
+            self.log.borrow_mut().push((self.name, true));
+            Ok(())
+        }
+
+        fn after_invoke(
+            &self,
+            _caller: &AccountID,
+            _target: &AccountID,
+            _request: &Request,
+            result: &Result<Response, ErrorCode>,
+        ) -> Result<(), SystemCode> {
+            self.log.borrow_mut().push((self.name, result.is_ok()));
+            if self.name == "reject" {
+                return Err(UnauthorizedCallerAccess);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_before_invoke_rejects() {
+        let blocked = AccountID::new(100);
+        let blocklist = Blocklist(blocked);
+        let interceptors: [&dyn MessageInterceptor; 1] = [&blocklist];
+        let req = Request::new(1);
+        let target = AccountID::new(200);
+        assert_eq!(
+            run_before_invoke(&interceptors, &blocked, &target, &req),
+            Err(ErrorCode::SystemCode(UnauthorizedCallerAccess))
+        );
+        assert!(run_before_invoke(&interceptors, &AccountID::new(101), &target, &req).is_ok());
+    }
+
+    #[test]
+    fn test_after_invoke_order() {
+        let log = RefCell::new(Vec::new());
+        let outer = Recorder {
+            name: "outer",
+            log: &log,
+        };
+        let reject = Recorder {
+            name: "reject",
+            log: &log,
+        };
+        let inner = Recorder {
+            name: "inner",
+            log: &log,
+        };
+        let interceptors: [&dyn MessageInterceptor; 3] = [&outer, &reject, &inner];
+        let req = Request::new(1);
+        let (caller, target) = (AccountID::new(100), AccountID::new(200));
+        run_before_invoke(&interceptors, &caller, &target, &req).unwrap();
+        let res = run_after_invoke(&interceptors, &caller, &target, &req, Ok(Response::new()));
+        assert!(matches!(
+            res,
+            Err(ErrorCode::SystemCode(UnauthorizedCallerAccess))
+        ));
+        assert_eq!(
+            *log.borrow(),
+            [
+                ("outer", true),
+                ("reject", true),
+                ("inner", true),
+                ("inner", true),
+                ("reject", true),
+                ("outer", false),
+            ]
+        );
+    }
+}
@@ -2,47 +2,91 @@
 #![no_std]
 extern crate alloc;
 
+pub mod block_info;
 mod call_stack;
+pub mod deferred;
+pub mod events;
 mod exec_ctx;
 pub mod gas;
 mod gas_stack;
 pub mod id_generator;
+pub mod interceptor;
 pub mod native_vm;
 mod query_ctx;
 mod scope_guard;
+pub mod simulation;
 pub mod state_handler;
+pub mod tx;
 mod wrapper;
 
 use crate::call_stack::CallStack;
 use crate::exec_ctx::ExecContext;
 use crate::gas::GasMeter;
 use crate::gas_stack::GasStack;
-use crate::id_generator::IDGenerator;
+use crate::id_generator::{IDGenerator, TxContext};
+use crate::interceptor::MessageInterceptor;
 use crate::query_ctx::QueryContext;
-use crate::state_handler::StateHandler;
+use crate::simulation::{RecordingStateHandler, SimulationResult, SIMULATION_GAS_LIMIT};
+use crate::state_handler::{init_account, set_self_destruct_disabled, StateHandler};
+use crate::tx::{Tx, TxError};
+use alloc::vec::Vec;
+use ixc_core_macros::message_selector;
 use ixc_message_api::code::ErrorCode;
+use ixc_message_api::gas::GasTracker;
 use ixc_message_api::handler::{Allocator, HostBackend, InvokeParams};
-use ixc_message_api::message::{Message, Response};
+use ixc_message_api::message::{Message, MessageSelector, Request, Response};
 use ixc_message_api::AccountID;
 use ixc_vm_api::{ReadonlyStore, VM};
 
+const ACCUMULATOR_ADD_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.accumulator_add");
+
 /// The default stack size for the account manager.
 pub const DEFAULT_STACK_SIZE: usize = 128;
 
 /// The account manager manages the execution, creation, and destruction of accounts.
 pub struct AccountManager<'a, CM: VM, const CALL_STACK_LIMIT: usize = DEFAULT_STACK_SIZE> {
     code_manager: &'a CM,
+    interceptors: &'a [&'a dyn MessageInterceptor],
+    tx_context: Option<TxContext>,
 }
 
 impl<'a, CM: VM, const CALL_STACK_LIMIT: usize> AccountManager<'a, CM, CALL_STACK_LIMIT> {
     /// Creates a new account manager.
     pub fn new(code_manager: &'a CM) -> Self {
-        Self { code_manager }
+        Self {
+            code_manager,
+            interceptors: &[],
+            tx_context: None,
+        }
+    }
+
+    /// Creates a new account manager with an ordered list of message interceptors
+    /// which are run around every message.
+    /// [`MessageInterceptor::before_invoke`] callbacks are called in order and
+    /// [`MessageInterceptor::after_invoke`] callbacks are called in reverse order.
+    pub fn with_interceptors(
+        code_manager: &'a CM,
+        interceptors: &'a [&'a dyn MessageInterceptor],
+    ) -> Self {
+        Self {
+            code_manager,
+            interceptors,
+            tx_context: None,
+        }
+    }
+
+    /// Sets the position of the transaction which the account manager runs,
+    /// which is given to the ID generator before the transaction starts.
+    pub fn with_tx_context(mut self, tx_context: TxContext) -> Self {
+        self.tx_context = Some(tx_context);
+        self
     }
 }
 
 impl<CM: VM, const CALL_STACK_LIMIT: usize> AccountManager<'_, CM, CALL_STACK_LIMIT> {
-    /// Returns a new host backend for the provided state handler.
+    /// Invokes the message as a transaction in the context of the provided state handler.
+    /// If the message succeeds, any messages it deferred to the end of the transaction
+    /// are run afterward with the remaining gas.
     pub fn invoke_msg<'b, ST: StateHandler, IDG: IDGenerator>(
         &self,
         state_handler: &mut ST,
@@ -51,14 +95,232 @@ impl<CM: VM, const CALL_STACK_LIMIT: usize> AccountManager<'_, CM, CALL_STACK_LI
         message: &Message,
         invoke_params: &InvokeParams<'b, '_>,
     ) -> Result<Response<'b>, ErrorCode> {
-        let exec_context = ExecContext::new(
+        self.begin_tx(id_generator);
+        self.invoke_msg_in_tx(
+            state_handler,
+            id_generator,
+            caller,
+            message,
+            invoke_params,
+            true,
+        )
+    }
+
+    fn invoke_msg_in_tx<'b, ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        caller: AccountID,
+        message: &Message,
+        invoke_params: &InvokeParams<'b, '_>,
+        drain_end_of_tx: bool,
+    ) -> Result<Response<'b>, ErrorCode> {
+        if !drain_end_of_tx {
+            return ExecContext::new(
+                self,
+                state_handler,
+                id_generator,
+                caller,
+                invoke_params.gas_tracker,
+            )
+            .do_invoke_msg(message, invoke_params);
+        }
+        // the message and the messages it deferred to the end of the transaction share a frame,
+        // so that the message is rolled back too if they can't be drained
+        let gas = GasMeter::unlimited();
+        state_handler.begin_tx(&gas)?;
+        let res = ExecContext::new(
             self,
             state_handler,
             id_generator,
             caller,
             invoke_params.gas_tracker,
-        );
-        exec_context.do_invoke_msg(message, invoke_params)
+        )
+        .do_invoke_msg(message, invoke_params)
+        .and_then(|response| {
+            let unlimited = GasTracker::unlimited();
+            let gas_tracker = invoke_params.gas_tracker.unwrap_or(&unlimited);
+            self.drain_end_of_tx(
+                state_handler,
+                id_generator,
+                gas_tracker,
+                invoke_params.allocator,
+            )?;
+            Ok(response)
+        });
+        if res.is_ok() {
+            state_handler.commit_tx(&gas)?;
+        } else {
+            state_handler.rollback_tx(&gas)?;
+        }
+        res
+    }
+
+    /// Executes a transaction in the context of the provided state handler.
+    /// The sender is first authenticated by sending the authenticate system message
+    /// to the sender account's handler, which verifies the transaction's nonce and credentials.
+    /// The messages are then run atomically with the sender as the caller
+    /// and their responses are returned in order,
+    /// followed by any messages deferred to the end of the transaction.
+    pub fn invoke_tx<'b, ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        tx: &Tx,
+        invoke_params: &InvokeParams<'b, '_>,
+    ) -> Result<Vec<Response<'b>>, TxError> {
+        self.begin_tx(id_generator);
+        let gas = GasMeter::unlimited();
+        state_handler.begin_tx(&gas).map_err(TxError::Fatal)?;
+        let res = ExecContext::new(
+            self,
+            state_handler,
+            id_generator,
+            tx.sender,
+            invoke_params.gas_tracker,
+        )
+        .do_invoke_tx(tx, invoke_params);
+        self.finish_tx(state_handler, id_generator, invoke_params, res)
+    }
+
+    /// Runs the messages atomically with the caller as the caller of every message,
+    /// returning their responses in order, followed by any messages deferred to the end of the transaction.
+    /// If one of the messages fails, the state changes of all of them are rolled back
+    /// and the index of the failing message is reported.
+    pub fn invoke_batch<'b, ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        caller: AccountID,
+        messages: &[Message],
+        invoke_params: &InvokeParams<'b, '_>,
+    ) -> Result<Vec<Response<'b>>, TxError> {
+        self.begin_tx(id_generator);
+        let gas = GasMeter::unlimited();
+        state_handler.begin_tx(&gas).map_err(TxError::Fatal)?;
+        let res = ExecContext::new(
+            self,
+            state_handler,
+            id_generator,
+            caller,
+            invoke_params.gas_tracker,
+        )
+        .do_invoke_batch(messages, invoke_params, true);
+        self.finish_tx(state_handler, id_generator, invoke_params, res)
+    }
+
+    /// Gives the transaction's position to the ID generator, if it was provided.
+    fn begin_tx<IDG: IDGenerator>(&self, id_generator: &IDG) {
+        if let Some(tx_context) = self.tx_context {
+            id_generator.begin_tx(tx_context);
+        }
+    }
+
+    /// Drains the messages deferred to the end of a successful transaction and then
+    /// closes the frame which [`AccountManager::invoke_tx`] or [`AccountManager::invoke_batch`]
+    /// opened for it, so that the transaction is rolled back if draining fails.
+    /// The frame of a transaction whose messages failed is committed to keep any changes
+    /// made while authenticating its sender.
+    fn finish_tx<'b, ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        invoke_params: &InvokeParams<'b, '_>,
+        res: Result<Vec<Response<'b>>, TxError>,
+    ) -> Result<Vec<Response<'b>>, TxError> {
+        let gas = GasMeter::unlimited();
+        let res = res.and_then(|responses| {
+            let unlimited = GasTracker::unlimited();
+            let gas_tracker = invoke_params.gas_tracker.unwrap_or(&unlimited);
+            self.drain_end_of_tx(
+                state_handler,
+                id_generator,
+                gas_tracker,
+                invoke_params.allocator,
+            )
+            .map_err(TxError::Fatal)?;
+            Ok(responses)
+        });
+        match res {
+            Ok(_) | Err(TxError::MessageFailed { .. }) => state_handler.commit_tx(&gas),
+            Err(_) => state_handler.rollback_tx(&gas),
+        }
+        .map_err(TxError::Fatal)?;
+        res
+    }
+
+    /// Simulates the execution of a message without committing any of its effects.
+    /// The message is run with [`SIMULATION_GAS_LIMIT`] and all of its state changes,
+    /// including those of nested calls and account creation and migration, are always rolled back.
+    /// The returned result reports the response, consumed gas, emitted events and the
+    /// state changes the message would have made.
+    pub fn simulate_msg<'b, ST: StateHandler, IDG: IDGenerator>(
+        &self,
+        state_handler: &mut ST,
+        id_generator: &IDG,
+        caller: AccountID,
+        message: &Message,
+        allocator: &'b dyn Allocator,
+    ) -> Result<SimulationResult<'b>, ErrorCode> {
+        // wrap the whole simulation in a transaction frame which we always roll back
+        let gas = GasMeter::unlimited();
+        state_handler.begin_tx(&gas)?;
+
+        let mut recorder = RecordingStateHandler::new(state_handler);
+        let gas_tracker = GasTracker::limited(SIMULATION_GAS_LIMIT);
+        let invoke_params = InvokeParams::new(allocator, Some(&gas_tracker));
+        let response =
+            self.invoke_msg(&mut recorder, id_generator, caller, message, &invoke_params);
+        let (state_changes, events) = recorder.finish();
+
+        state_handler.rollback_tx(&gas)?;
+        Ok(SimulationResult {
+            response,
+            gas_consumed: gas_tracker.consumed.get(),
+            events,
+            state_changes,
+        })
+    }
+
+    /// Imports an account's storage and accumulators directly without running any handler code,
+    /// for instance when restoring exported state at genesis.
+    /// If a handler ID is provided, the account's storage is initialized and it is bound
+    /// to the handler, otherwise the entries are written to the account's existing storage
+    /// and the accumulator values are added to its existing accumulators.
+    pub fn import_account<ST: StateHandler>(
+        &self,
+        state_handler: &mut ST,
+        account: AccountID,
+        handler_id: Option<&str>,
+        state: &[(&[u8], &[u8])],
+        accumulators: &[(&[u8], u128)],
+        allocator: &dyn Allocator,
+    ) -> Result<(), ErrorCode> {
+        let gas = GasMeter::unlimited();
+        if let Some(handler_id) = handler_id {
+            init_account(state_handler, account, handler_id, &gas)?;
+        }
+        for (key, value) in state {
+            state_handler.kv_set(account, key, value, &gas)?;
+        }
+        for (key, value) in accumulators {
+            let req = Request::new2(ACCUMULATOR_ADD_SELECTOR, (*key).into(), (*value).into());
+            state_handler.handle_exec(account, &req, &gas, allocator)?;
+        }
+        Ok(())
+    }
+
+    /// Disables or re-enables self-destruct for all accounts using the handler.
+    /// Accounts whose handler has self-destruct disabled get an
+    /// [`UnauthorizedCallerAccess`](ixc_message_api::code::SystemCode::UnauthorizedCallerAccess)
+    /// error when they try to self-destruct.
+    pub fn set_self_destruct_disabled<ST: StateHandler>(
+        &self,
+        state_handler: &mut ST,
+        handler_id: &str,
+        disabled: bool,
+    ) -> Result<(), ErrorCode> {
+        set_self_destruct_disabled(state_handler, handler_id, disabled, &GasMeter::unlimited())
     }
 
     /// Invokes the query in the context of the provided state handler.
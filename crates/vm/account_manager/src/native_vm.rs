@@ -4,6 +4,7 @@ extern crate alloc;
 use alloc::boxed::Box;
 use alloc::collections::btree_map::BTreeMap;
 use alloc::string::String;
+use alloc::vec::Vec;
 use allocator_api2::alloc::Allocator;
 use core::borrow::Borrow;
 use ixc_message_api::alloc_util;
@@ -15,18 +16,26 @@ use ixc_vm_api::{ReadonlyStore, VM};
 #[derive(Default)]
 pub struct NativeVMImpl {
     handlers: BTreeMap<String, Box<dyn RawHandler>>,
+    schemas: BTreeMap<String, Vec<u8>>,
 }
 
 /// The trait that VMs which support native execution must implement to be used with the test harness.
 pub trait NativeVM: VM {
     /// Registers a handler with the native VM.
     fn register_handler(&mut self, name: &str, handler: Box<dyn RawHandler>);
+
+    /// Registers the schema of a handler encoded with the native binary codec.
+    fn register_handler_schema(&mut self, name: &str, schema: &[u8]);
 }
 
 impl NativeVM for NativeVMImpl {
     fn register_handler(&mut self, name: &str, handler: Box<dyn RawHandler>) {
         self.handlers.insert(name.into(), handler);
     }
+
+    fn register_handler_schema(&mut self, name: &str, schema: &[u8]) {
+        self.schemas.insert(name.into(), schema.into());
+    }
 }
 
 impl VM for NativeVMImpl {
@@ -55,4 +64,16 @@ impl VM for NativeVMImpl {
             Err(ErrorCode::SystemCode(SystemCode::HandlerNotFound))
         }
     }
+
+    fn handler_schema<'a>(
+        &self,
+        _store: &dyn ReadonlyStore,
+        handler_id: &str,
+        allocator: &'a dyn Allocator,
+    ) -> Result<Option<&'a [u8]>, ErrorCode> {
+        match self.schemas.get(handler_id) {
+            Some(schema) => unsafe { Ok(Some(alloc_util::copy_bytes(allocator, schema)?)) },
+            None => Ok(None),
+        }
+    }
 }
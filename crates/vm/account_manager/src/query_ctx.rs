@@ -1,3 +1,4 @@
+use crate::block_info::handle_block_info_query;
 use crate::call_stack::CallStack;
 use crate::gas_stack::GasStack;
 use crate::state_handler::{get_account_handler_id, StateHandler};
@@ -6,10 +7,12 @@ use allocator_api2::alloc::Allocator;
 use ixc_core_macros::message_selector;
 use ixc_message_api::code::ErrorCode;
 use ixc_message_api::code::ErrorCode::SystemCode;
-use ixc_message_api::code::SystemCode::{AccountNotFound, MessageNotHandled};
+use ixc_message_api::code::SystemCode::{
+    AccountNotFound, HandlerNotFound, MessageNotHandled, VolatileAccessError,
+};
 use ixc_message_api::handler::{HostBackend, InvokeParams};
-use ixc_message_api::message::{Message, Request, Response};
-use ixc_message_api::ROOT_ACCOUNT;
+use ixc_message_api::message::{Message, Request, Response, Volatility};
+use ixc_message_api::{BLOCK_INFO_ACCOUNT, ROOT_ACCOUNT};
 use ixc_vm_api::VM;
 
 pub(crate) struct QueryContext<'b, 'a: 'b, CM: VM, ST: StateHandler, const CALL_STACK_LIMIT: usize>
@@ -46,9 +49,7 @@ impl<'b, 'a: 'b, CM: VM, ST: StateHandler, const CALL_STACK_LIMIT: usize> HostBa
         _message: &Message,
         _invoke_params: &InvokeParams<'c, '_>,
     ) -> Result<Response<'c>, ErrorCode> {
-        Err(SystemCode(
-            ixc_message_api::code::SystemCode::VolatileAccessError,
-        ))
+        Err(SystemCode(VolatileAccessError))
     }
 
     fn invoke_query<'c>(
@@ -56,6 +57,11 @@ impl<'b, 'a: 'b, CM: VM, ST: StateHandler, const CALL_STACK_LIMIT: usize> HostBa
         message: &Message,
         invoke_params: &InvokeParams<'c, '_>,
     ) -> Result<Response<'c>, ErrorCode> {
+        // volatile messages can't be routed to query handlers
+        if message.request().volatility() != Volatility::Query {
+            return Err(SystemCode(VolatileAccessError));
+        }
+
         let gas_scope = self.gas_stack.push(invoke_params.gas_tracker)?;
         let target_account = message.target_account();
         let allocator = invoke_params.allocator;
@@ -64,6 +70,17 @@ impl<'b, 'a: 'b, CM: VM, ST: StateHandler, const CALL_STACK_LIMIT: usize> HostBa
             return self.handle_system_query(message.request(), allocator);
         }
 
+        if target_account == BLOCK_INFO_ACCOUNT {
+            let res = handle_block_info_query(
+                self.state_handler,
+                message.request(),
+                self.gas_stack.meter(),
+                allocator,
+            );
+            gas_scope.pop();
+            return res;
+        }
+
         // find the account's handler ID
         let handler_id = get_account_handler_id(
             self.state_handler,
@@ -96,9 +113,7 @@ impl<'b, 'a: 'b, CM: VM, ST: StateHandler, const CALL_STACK_LIMIT: usize> HostBa
         _req: &Request,
         _invoke_params: &InvokeParams<'c, '_>,
     ) -> Result<Response<'c>, ErrorCode> {
-        Err(SystemCode(
-            ixc_message_api::code::SystemCode::VolatileAccessError,
-        ))
+        Err(SystemCode(VolatileAccessError))
     }
 
     fn query_state<'c>(
@@ -138,6 +153,14 @@ impl<'b, 'a: 'b, CM: VM, ST: StateHandler, const CALL_STACK_LIMIT: usize>
         unsafe {
             match req.message_selector() {
                 GET_HANDLER_ID_SELECTOR => self.handle_get_handler_id(req, allocator),
+                GET_SCHEMA_SELECTOR => self.handle_get_schema(req, allocator),
+                GET_STORAGE_USAGE_SELECTOR => {
+                    let account_id = req.in1().expect_account_id()?;
+                    let usage = self
+                        .state_handler
+                        .storage_usage(account_id, self.gas_stack.meter())?;
+                    Ok(Response::new1(usage.into()))
+                }
                 _ => Err(SystemCode(MessageNotHandled)),
             }
         }
@@ -164,6 +187,38 @@ impl<'b, 'a: 'b, CM: VM, ST: StateHandler, const CALL_STACK_LIMIT: usize>
 
         Ok(Response::new1(handler_id.into()))
     }
+
+    fn handle_get_schema<'c>(
+        &self,
+        req: &Request,
+        allocator: &'c dyn Allocator,
+    ) -> Result<Response<'c>, ErrorCode> {
+        let store =
+            ReadOnlyStoreWrapper::wrap(self.state_handler, self.gas_stack.meter(), allocator);
+        // the schema can be requested by handler ID or for an account's handler
+        let handler_id = match req.in1().as_account_id() {
+            Some(account_id) => get_account_handler_id(
+                self.state_handler,
+                account_id,
+                self.gas_stack.meter(),
+                allocator,
+            )?
+            .ok_or(SystemCode(AccountNotFound))?,
+            None => self
+                .account_manager
+                .code_manager
+                .resolve_handler_id(&store, req.in1().expect_string()?, allocator)?
+                .ok_or(SystemCode(HandlerNotFound))?,
+        };
+        let schema = self
+            .account_manager
+            .code_manager
+            .handler_schema(&store, handler_id, allocator)?
+            .ok_or(SystemCode(HandlerNotFound))?;
+        Ok(Response::new1(schema.into()))
+    }
 }
 
 const GET_HANDLER_ID_SELECTOR: u64 = message_selector!("ixc.account.v1.get_handler_id");
+const GET_STORAGE_USAGE_SELECTOR: u64 = message_selector!("ixc.account.v1.get_storage_usage");
+const GET_SCHEMA_SELECTOR: u64 = message_selector!("ixc.schema.v1.get");
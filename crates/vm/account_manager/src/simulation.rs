@@ -0,0 +1,315 @@
+//! Dry-run simulation of messages which reports their effects without committing them.
+use crate::events::EventMetadata;
+use crate::gas::GasMeter;
+use crate::state_handler::{StateHandler, StorageDeposit};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use ixc_core_macros::message_selector;
+use ixc_message_api::code::ErrorCode;
+use ixc_message_api::code::SystemCode::FatalExecutionError;
+use ixc_message_api::message::{MessageSelector, Request, Response};
+use ixc_message_api::AccountID;
+
+/// The gas limit used when simulating messages.
+/// It is high enough that simulated messages should never run out of gas
+/// while still bounding the execution of runaway messages.
+pub const SIMULATION_GAS_LIMIT: u64 = u64::MAX / 2;
+
+/// The result of simulating a message.
+pub struct SimulationResult<'a> {
+    /// The response the message would have returned or its error code.
+    pub response: Result<Response<'a>, ErrorCode>,
+    /// The amount of gas consumed by the message.
+    pub gas_consumed: u64,
+    /// The events the message would have emitted, in order.
+    pub events: Vec<SimulatedEvent>,
+    /// The state changes the message would have made, in order.
+    /// This is empty if the message failed.
+    pub state_changes: Vec<StateChange>,
+}
+
+/// An event emitted during a simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedEvent {
+    /// The account that emitted the event.
+    pub sender: AccountID,
+    /// The type selector of the event.
+    pub type_selector: u64,
+    /// The encoded event data.
+    pub data: Vec<u8>,
+    /// The metadata the state handler recorded with the event,
+    /// or the default metadata if it doesn't record any.
+    pub metadata: EventMetadata,
+}
+
+/// A change to state made during a simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateChange {
+    /// Storage was created for a new account.
+    CreateAccount {
+        /// The new account.
+        account: AccountID,
+    },
+    /// All the storage of an account was deleted.
+    DeleteAccount {
+        /// The deleted account.
+        account: AccountID,
+    },
+    /// A key was set in an account's storage.
+    Set {
+        /// The account whose storage was updated.
+        account: AccountID,
+        /// The key.
+        key: Vec<u8>,
+        /// The new value.
+        value: Vec<u8>,
+    },
+    /// A key was deleted from an account's storage.
+    Delete {
+        /// The account whose storage was updated.
+        account: AccountID,
+        /// The key.
+        key: Vec<u8>,
+    },
+    /// An amount was added to an accumulator in an account's storage.
+    AccumulatorAdd {
+        /// The account whose storage was updated.
+        account: AccountID,
+        /// The key of the accumulator.
+        key: Vec<u8>,
+        /// The amount added.
+        amount: u128,
+    },
+    /// An amount was subtracted from an accumulator in an account's storage.
+    /// Subtractions which were refused because they would have gone below zero aren't recorded.
+    AccumulatorSafeSub {
+        /// The account whose storage was updated.
+        account: AccountID,
+        /// The key of the accumulator.
+        key: Vec<u8>,
+        /// The amount subtracted.
+        amount: u128,
+    },
+}
+
+/// A state handler wrapper which records the state changes and events
+/// of committed transaction frames.
+pub(crate) struct RecordingStateHandler<'a, ST: StateHandler> {
+    state_handler: &'a mut ST,
+    frames: Vec<Frame>,
+}
+
+#[derive(Default)]
+struct Frame {
+    state_changes: Vec<StateChange>,
+    events: Vec<SimulatedEvent>,
+}
+
+impl<'a, ST: StateHandler> RecordingStateHandler<'a, ST> {
+    pub(crate) fn new(state_handler: &'a mut ST) -> Self {
+        Self {
+            state_handler,
+            frames: vec![Frame::default()],
+        }
+    }
+
+    /// Returns the recorded state changes and events of all committed frames.
+    pub(crate) fn finish(mut self) -> (Vec<StateChange>, Vec<SimulatedEvent>) {
+        let frame = self.frames.swap_remove(0);
+        (frame.state_changes, frame.events)
+    }
+
+    fn frame(&mut self) -> Result<&mut Frame, ErrorCode> {
+        self.frames
+            .last_mut()
+            .ok_or(ErrorCode::SystemCode(FatalExecutionError))
+    }
+}
+
+impl<ST: StateHandler> StateHandler for RecordingStateHandler<'_, ST> {
+    fn kv_get<'a>(
+        &self,
+        account_id: AccountID,
+        key: &[u8],
+        gas: &GasMeter,
+        allocator: &'a dyn Allocator,
+    ) -> Result<Option<&'a [u8]>, ErrorCode> {
+        self.state_handler.kv_get(account_id, key, gas, allocator)
+    }
+
+    fn kv_set(
+        &mut self,
+        account_id: AccountID,
+        key: &[u8],
+        value: &[u8],
+        gas: &GasMeter,
+    ) -> Result<(), ErrorCode> {
+        self.state_handler.kv_set(account_id, key, value, gas)?;
+        self.frame()?.state_changes.push(StateChange::Set {
+            account: account_id,
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn kv_delete(
+        &mut self,
+        account_id: AccountID,
+        key: &[u8],
+        gas: &GasMeter,
+    ) -> Result<(), ErrorCode> {
+        self.state_handler.kv_delete(account_id, key, gas)?;
+        self.frame()?.state_changes.push(StateChange::Delete {
+            account: account_id,
+            key: key.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn begin_tx(&mut self, gas: &GasMeter) -> Result<(), ErrorCode> {
+        self.state_handler.begin_tx(gas)?;
+        self.frames.push(Frame::default());
+        Ok(())
+    }
+
+    fn commit_tx(&mut self, gas: &GasMeter) -> Result<(), ErrorCode> {
+        self.state_handler.commit_tx(gas)?;
+        // merge the committed frame into its parent
+        let mut frame = self
+            .frames
+            .pop()
+            .ok_or(ErrorCode::SystemCode(FatalExecutionError))?;
+        let parent = self.frame()?;
+        parent.state_changes.append(&mut frame.state_changes);
+        parent.events.append(&mut frame.events);
+        Ok(())
+    }
+
+    fn rollback_tx(&mut self, gas: &GasMeter) -> Result<(), ErrorCode> {
+        self.state_handler.rollback_tx(gas)?;
+        // discard everything recorded in the rolled back frame
+        self.frames
+            .pop()
+            .ok_or(ErrorCode::SystemCode(FatalExecutionError))?;
+        Ok(())
+    }
+
+    fn handle_exec<'a>(
+        &mut self,
+        account_id: AccountID,
+        request: &Request,
+        gas: &GasMeter,
+        allocator: &'a dyn Allocator,
+    ) -> Result<Response<'a>, ErrorCode> {
+        let res = self
+            .state_handler
+            .handle_exec(account_id, request, gas, allocator)?;
+        match request.message_selector() {
+            SET_SELECTOR => self.frame()?.state_changes.push(StateChange::Set {
+                account: account_id,
+                key: request.in1().expect_bytes()?.to_vec(),
+                value: request.in2().expect_bytes()?.to_vec(),
+            }),
+            DELETE_SELECTOR => self.frame()?.state_changes.push(StateChange::Delete {
+                account: account_id,
+                key: request.in1().expect_bytes()?.to_vec(),
+            }),
+            ACCUMULATOR_ADD_SELECTOR => {
+                self.frame()?
+                    .state_changes
+                    .push(StateChange::AccumulatorAdd {
+                        account: account_id,
+                        key: request.in1().expect_bytes()?.to_vec(),
+                        amount: request.in2().expect_u128()?,
+                    })
+            }
+            // the response is 1 if the amount was subtracted, or 0 if it was refused
+            ACCUMULATOR_SAFE_SUB_SELECTOR if res.out1().expect_u64()? != 0 => self
+                .frame()?
+                .state_changes
+                .push(StateChange::AccumulatorSafeSub {
+                    account: account_id,
+                    key: request.in1().expect_bytes()?.to_vec(),
+                    amount: request.in2().expect_u128()?,
+                }),
+            EMIT_EVENT_SELECTOR => {
+                let metadata = self.state_handler.last_event_metadata().unwrap_or_default();
+                self.frame()?.events.push(SimulatedEvent {
+                    sender: account_id,
+                    type_selector: request.in2().expect_u64()?,
+                    data: request.in1().expect_bytes()?.to_vec(),
+                    metadata,
+                })
+            }
+            _ => {}
+        }
+        Ok(res)
+    }
+
+    fn handle_query<'a>(
+        &self,
+        account_id: AccountID,
+        request: &Request,
+        gas: &GasMeter,
+        allocator: &'a dyn Allocator,
+    ) -> Result<Response<'a>, ErrorCode> {
+        self.state_handler
+            .handle_query(account_id, request, gas, allocator)
+    }
+
+    fn create_account_storage(
+        &mut self,
+        account: AccountID,
+        gas: &GasMeter,
+    ) -> Result<(), ErrorCode> {
+        self.state_handler.create_account_storage(account, gas)?;
+        self.frame()?
+            .state_changes
+            .push(StateChange::CreateAccount { account });
+        Ok(())
+    }
+
+    fn delete_account_storage(
+        &mut self,
+        account: AccountID,
+        gas: &GasMeter,
+    ) -> Result<(), ErrorCode> {
+        self.state_handler.delete_account_storage(account, gas)?;
+        self.frame()?
+            .state_changes
+            .push(StateChange::DeleteAccount { account });
+        Ok(())
+    }
+
+    fn begin_message(&mut self, index: u32) -> Result<(), ErrorCode> {
+        self.state_handler.begin_message(index)
+    }
+
+    fn last_event_metadata(&self) -> Option<EventMetadata> {
+        self.state_handler.last_event_metadata()
+    }
+
+    fn storage_usage(&self, account: AccountID, gas: &GasMeter) -> Result<u64, ErrorCode> {
+        self.state_handler.storage_usage(account, gas)
+    }
+
+    fn storage_deposit_account(&self) -> Option<AccountID> {
+        self.state_handler.storage_deposit_account()
+    }
+
+    fn take_storage_deposit(
+        &mut self,
+        account: AccountID,
+    ) -> Result<Option<StorageDeposit>, ErrorCode> {
+        self.state_handler.take_storage_deposit(account)
+    }
+}
+
+const SET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.set");
+const DELETE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.delete");
+const ACCUMULATOR_ADD_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.accumulator_add");
+const ACCUMULATOR_SAFE_SUB_SELECTOR: MessageSelector =
+    message_selector!("ixc.store.v1.accumulator_safe_sub");
+const EMIT_EVENT_SELECTOR: MessageSelector = message_selector!("ixc.events.1.emit");
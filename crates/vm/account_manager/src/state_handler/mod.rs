@@ -1,14 +1,16 @@
 //! State handler traits.
 pub mod std;
 
+use crate::events::EventMetadata;
 use crate::gas::GasMeter;
 use crate::id_generator;
 use crate::id_generator::IDGenerator;
 use alloc::format;
 use allocator_api2::alloc::Allocator;
+use ixc_core_macros::message_selector;
 use ixc_message_api::code::ErrorCode;
 use ixc_message_api::code::SystemCode::EncodingError;
-use ixc_message_api::message::{Request, Response};
+use ixc_message_api::message::{MessageSelector, Request, Response};
 use ixc_message_api::{AccountID, ROOT_ACCOUNT};
 
 /// The state handler trait.
@@ -74,6 +76,54 @@ pub trait StateHandler {
         account: AccountID,
         gas: &GasMeter,
     ) -> Result<(), ErrorCode>;
+
+    /// Called before each top-level message of a transaction or batch is run
+    /// so that the events it emits can record its index.
+    fn begin_message(&mut self, _index: u32) -> Result<(), ErrorCode> {
+        Ok(())
+    }
+
+    /// Returns the metadata which was recorded with the most recently emitted event
+    /// in the current transaction frame, if the state handler records event metadata.
+    fn last_event_metadata(&self) -> Option<EventMetadata> {
+        None
+    }
+
+    /// Returns the number of bytes stored by the account if storage accounting is enabled.
+    fn storage_usage(&self, _account: AccountID, _gas: &GasMeter) -> Result<u64, ErrorCode> {
+        Ok(0)
+    }
+
+    /// Returns the account whose handler charges and refunds storage deposits,
+    /// if storage deposits are enabled.
+    fn storage_deposit_account(&self) -> Option<AccountID> {
+        None
+    }
+
+    /// Takes the deposit for the changes to the account's storage since this was last called.
+    /// The account manager calls this right after it updates an account's state
+    /// and settles the deposit with the storage deposit account.
+    fn take_storage_deposit(
+        &mut self,
+        _account: AccountID,
+    ) -> Result<Option<StorageDeposit>, ErrorCode> {
+        Ok(None)
+    }
+}
+
+/// The selector of the system message which is sent to the storage deposit account's handler
+/// to charge or refund a storage deposit.
+/// The request inputs are the account which pays or is refunded, the amount,
+/// and 1 for a refund or 0 for a charge, in that order.
+pub const STORAGE_DEPOSIT_SELECTOR: MessageSelector = message_selector!("ixc.storage.v1.deposit");
+
+/// A storage deposit which is owed because an account's storage usage changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageDeposit {
+    /// The account's storage usage grew, and the deposit must be charged to the account.
+    Charge(u128),
+    /// The account's storage usage shrank, and the deposit must be refunded to the account.
+    Refund(u128),
 }
 
 pub(crate) fn get_account_handler_id<'a, ST: StateHandler>(
@@ -101,15 +151,29 @@ pub(crate) fn init_next_account<ST: StateHandler, IDG: IDGenerator>(
     allocator: &dyn Allocator,
     gas: &GasMeter,
 ) -> Result<AccountID, ErrorCode> {
-    let id: u128 = id_generator
-        .new_account_id(&mut StoreWrapper::wrap(state_handler, gas, allocator))?
-        .into();
-    let id: AccountID = AccountID::new(id);
-    state_handler.create_account_storage(id, gas)?;
-    set_handler_id(state_handler, id, handler_id, gas)?;
+    // skip IDs which are already taken, for instance by accounts which were created
+    // with fixed IDs or imported at genesis
+    let id = loop {
+        let id =
+            id_generator.new_account_id(&mut StoreWrapper::wrap(state_handler, gas, allocator))?;
+        if get_account_handler_id(state_handler, id, gas, allocator)?.is_none() {
+            break id;
+        }
+    };
+    init_account(state_handler, id, handler_id, gas)?;
     Ok(id)
 }
 
+pub(crate) fn init_account<ST: StateHandler>(
+    state_handler: &mut ST,
+    id: AccountID,
+    handler_id: &str,
+    gas: &GasMeter,
+) -> Result<(), ErrorCode> {
+    state_handler.create_account_storage(id, gas)?;
+    set_handler_id(state_handler, id, handler_id, gas)
+}
+
 pub(crate) fn set_handler_id<ST: StateHandler>(
     state_handler: &mut ST,
     account_id: AccountID,
@@ -126,6 +190,104 @@ pub(crate) fn set_handler_id<ST: StateHandler>(
     )
 }
 
+pub(crate) fn get_account_admin<ST: StateHandler>(
+    state_handler: &ST,
+    account_id: AccountID,
+    gas: &GasMeter,
+    allocator: &dyn Allocator,
+) -> Result<Option<AccountID>, ErrorCode> {
+    let id: u128 = account_id.into();
+    let key = format!("a:{}", id);
+    match state_handler.kv_get(ROOT_ACCOUNT, key.as_bytes(), gas, allocator)? {
+        Some(value) => {
+            let admin = value
+                .try_into()
+                .map_err(|_| ErrorCode::SystemCode(EncodingError))?;
+            Ok(Some(AccountID::new(u128::from_be_bytes(admin))))
+        }
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn set_account_admin<ST: StateHandler>(
+    state_handler: &mut ST,
+    account_id: AccountID,
+    admin: AccountID,
+    gas: &GasMeter,
+) -> Result<(), ErrorCode> {
+    let id: u128 = account_id.into();
+    let admin: u128 = admin.into();
+    state_handler.kv_set(
+        ROOT_ACCOUNT,
+        format!("a:{}", id).as_bytes(),
+        &admin.to_be_bytes(),
+        gas,
+    )
+}
+
+/// Returns the account which paid the storage deposit for the account's initial state
+/// and is refunded when it self-destructs.
+pub(crate) fn get_deposit_payer<ST: StateHandler>(
+    state_handler: &ST,
+    account_id: AccountID,
+    gas: &GasMeter,
+    allocator: &dyn Allocator,
+) -> Result<Option<AccountID>, ErrorCode> {
+    let id: u128 = account_id.into();
+    let key = format!("p:{}", id);
+    match state_handler.kv_get(ROOT_ACCOUNT, key.as_bytes(), gas, allocator)? {
+        Some(value) => {
+            let payer = value
+                .try_into()
+                .map_err(|_| ErrorCode::SystemCode(EncodingError))?;
+            Ok(Some(AccountID::new(u128::from_be_bytes(payer))))
+        }
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn set_deposit_payer<ST: StateHandler>(
+    state_handler: &mut ST,
+    account_id: AccountID,
+    payer: AccountID,
+    gas: &GasMeter,
+) -> Result<(), ErrorCode> {
+    let id: u128 = account_id.into();
+    let payer: u128 = payer.into();
+    state_handler.kv_set(
+        ROOT_ACCOUNT,
+        format!("p:{}", id).as_bytes(),
+        &payer.to_be_bytes(),
+        gas,
+    )
+}
+
+pub(crate) fn is_self_destruct_disabled<ST: StateHandler>(
+    state_handler: &ST,
+    handler_id: &str,
+    gas: &GasMeter,
+    allocator: &dyn Allocator,
+) -> Result<bool, ErrorCode> {
+    let key = format!("d:{}", handler_id);
+    Ok(state_handler
+        .kv_get(ROOT_ACCOUNT, key.as_bytes(), gas, allocator)?
+        .is_some())
+}
+
+pub(crate) fn set_self_destruct_disabled<ST: StateHandler>(
+    state_handler: &mut ST,
+    handler_id: &str,
+    disabled: bool,
+    gas: &GasMeter,
+) -> Result<(), ErrorCode> {
+    let key = format!("d:{}", handler_id);
+    if disabled {
+        state_handler.kv_set(ROOT_ACCOUNT, key.as_bytes(), &[1], gas)
+    } else {
+        state_handler.kv_delete(ROOT_ACCOUNT, key.as_bytes(), gas)
+    }
+}
+
 pub(crate) fn destroy_account_data<ST: StateHandler>(
     state_handler: &mut ST,
     account: AccountID,
@@ -134,6 +296,10 @@ pub(crate) fn destroy_account_data<ST: StateHandler>(
     let id: u128 = account.into();
     let key = format!("h:{}", id);
     state_handler.kv_delete(ROOT_ACCOUNT, key.as_bytes(), gas)?;
+    let key = format!("a:{}", id);
+    state_handler.kv_delete(ROOT_ACCOUNT, key.as_bytes(), gas)?;
+    let key = format!("p:{}", id);
+    state_handler.kv_delete(ROOT_ACCOUNT, key.as_bytes(), gas)?;
     state_handler.delete_account_storage(account, gas)
 }
 
@@ -1,17 +1,26 @@
+use crate::events::EventMetadata;
 use crate::gas::GasMeter;
 use crate::state_handler::std::manager::StdStateManager;
-use crate::state_handler::StateHandler;
+use crate::state_handler::{StateHandler, StorageDeposit};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
 use allocator_api2::alloc::Allocator;
 use ixc_core_macros::message_selector;
 use ixc_message_api::code::ErrorCode;
-use ixc_message_api::code::SystemCode::MessageNotHandled;
+use ixc_message_api::code::SystemCode::{FatalExecutionError, MessageNotHandled};
 use ixc_message_api::message::{MessageSelector, Request, Response};
-use ixc_message_api::AccountID;
+use ixc_message_api::{AccountID, BLOCK_INFO_ACCOUNT, ROOT_ACCOUNT};
 
 /// The standard state handler.
 pub struct StdStateHandler<'a, S: StdStateManager> {
     state: &'a mut S,
     _gas_config: GasConfig,
+    storage_deposits: Option<StorageDepositConfig>,
+    // the change in bytes stored by each account which hasn't been taken as a deposit yet,
+    // with one map for each open transaction frame so that rolled back changes are discarded
+    pending_usage: Vec<BTreeMap<AccountID, i128>>,
 }
 
 /// Gas configuration for the standard state handler.
@@ -29,12 +38,82 @@ pub struct GasConfig {
     pub write_cost_per_byte: u64,
 }
 
+/// Storage deposit configuration for the standard state handler.
+///
+/// When storage deposits are enabled, the state handler tracks the number of bytes
+/// each account stores, counting both keys and values, and accounts must pay a deposit
+/// for each byte they add, which is refunded when the bytes are deleted.
+/// Non-zero accumulators count as their key plus 16 bytes for their value.
+/// The system accounts and the deposit account itself don't pay deposits.
+#[derive(Clone, Copy, Debug)]
+pub struct StorageDepositConfig {
+    /// The account whose handler charges and refunds deposits,
+    /// using its #[on_storage_deposit] function.
+    pub deposit_account: AccountID,
+    /// The deposit required for each byte of storage.
+    pub deposit_per_byte: u128,
+}
+
 impl<'a, S: StdStateManager> StdStateHandler<'a, S> {
     /// Create a new standard state handler.
     pub fn new(state: &'a mut S, gas_config: GasConfig) -> Self {
         Self {
             state,
             _gas_config: gas_config,
+            storage_deposits: None,
+            pending_usage: vec![BTreeMap::new()],
+        }
+    }
+
+    /// Enables storage deposits with the provided configuration.
+    pub fn with_storage_deposits(mut self, config: StorageDepositConfig) -> Self {
+        self.storage_deposits = Some(config);
+        self
+    }
+
+    fn tracks_usage(&self, account: AccountID) -> bool {
+        match &self.storage_deposits {
+            Some(config) => {
+                account != ROOT_ACCOUNT
+                    && account != BLOCK_INFO_ACCOUNT
+                    && account != config.deposit_account
+            }
+            None => false,
+        }
+    }
+
+    fn record_usage(&mut self, account: AccountID, delta: i128) -> Result<(), ErrorCode> {
+        let key = usage_key(account);
+        if delta >= 0 {
+            self.state
+                .accumulator_add(ROOT_ACCOUNT, None, key.as_bytes(), delta as u128)?;
+        } else if !self.state.accumulator_safe_sub(
+            ROOT_ACCOUNT,
+            None,
+            key.as_bytes(),
+            delta.unsigned_abs(),
+        )? {
+            return Err(FatalExecutionError.into());
+        }
+        let pending = self.pending_usage.last_mut().ok_or(FatalExecutionError)?;
+        *pending.entry(account).or_default() += delta;
+        Ok(())
+    }
+
+    /// Records the usage of an accumulator which goes from `old` to `new`.
+    /// Accumulators are counted as their key plus a 16-byte value while they are non-zero.
+    fn record_accumulator_usage(
+        &mut self,
+        account: AccountID,
+        key: &[u8],
+        old: u128,
+        new: u128,
+    ) -> Result<(), ErrorCode> {
+        let size = (key.len() + ACCUMULATOR_VALUE_SIZE) as i128;
+        match (old == 0, new == 0) {
+            (true, false) => self.record_usage(account, size),
+            (false, true) => self.record_usage(account, -size),
+            _ => Ok(()),
         }
     }
 }
@@ -57,6 +136,13 @@ impl<S: StdStateManager> StateHandler for StdStateHandler<'_, S> {
         value: &[u8],
         _gas: &GasMeter,
     ) -> Result<(), ErrorCode> {
+        if self.tracks_usage(account_id) {
+            let delta = match self.state.kv_value_len(account_id, None, key)? {
+                Some(old_len) => value.len() as i128 - old_len as i128,
+                None => (key.len() + value.len()) as i128,
+            };
+            self.record_usage(account_id, delta)?;
+        }
         self.state.kv_set(account_id, None, key, value)
     }
 
@@ -66,19 +152,47 @@ impl<S: StdStateManager> StateHandler for StdStateHandler<'_, S> {
         key: &[u8],
         _gas: &GasMeter,
     ) -> Result<(), ErrorCode> {
+        if self.tracks_usage(account_id) {
+            if let Some(old_len) = self.state.kv_value_len(account_id, None, key)? {
+                self.record_usage(account_id, -((key.len() + old_len) as i128))?;
+            }
+        }
         self.state.kv_delete(account_id, None, key)
     }
 
     fn begin_tx(&mut self, _gas: &GasMeter) -> Result<(), ErrorCode> {
-        self.state.begin_tx()
+        self.state.begin_tx()?;
+        self.pending_usage.push(BTreeMap::new());
+        Ok(())
     }
 
     fn commit_tx(&mut self, _gas: &GasMeter) -> Result<(), ErrorCode> {
-        self.state.commit_tx()
+        self.state.commit_tx()?;
+        // usage which is still pending carries over to the enclosing frame
+        if self.pending_usage.len() > 1 {
+            let committed = self.pending_usage.pop().unwrap_or_default();
+            let pending = self.pending_usage.last_mut().ok_or(FatalExecutionError)?;
+            for (account, delta) in committed {
+                *pending.entry(account).or_default() += delta;
+            }
+        }
+        Ok(())
     }
 
     fn rollback_tx(&mut self, _gas: &GasMeter) -> Result<(), ErrorCode> {
-        self.state.rollback_tx()
+        self.state.rollback_tx()?;
+        if self.pending_usage.len() > 1 {
+            self.pending_usage.pop();
+        }
+        Ok(())
+    }
+
+    fn begin_message(&mut self, index: u32) -> Result<(), ErrorCode> {
+        self.state.begin_message(index)
+    }
+
+    fn last_event_metadata(&self) -> Option<EventMetadata> {
+        self.state.last_event_metadata()
     }
 
     fn handle_exec<'a>(
@@ -100,10 +214,39 @@ impl<S: StdStateManager> StateHandler for StdStateHandler<'_, S> {
                 self.kv_delete(account_id, key, gas)?;
                 Ok(Default::default())
             }
+            ACCUMULATOR_ADD_SELECTOR => {
+                let key = request.in1().expect_bytes()?;
+                let value = request.in2().expect_u128()?;
+                if self.tracks_usage(account_id) {
+                    let old = self.state.accumulator_get(account_id, None, key)?;
+                    self.record_accumulator_usage(account_id, key, old, old.saturating_add(value))?;
+                }
+                let new_value = self.state.accumulator_add(account_id, None, key, value)?;
+                Ok(Response::new1(new_value.into()))
+            }
+            ACCUMULATOR_SAFE_SUB_SELECTOR => {
+                let key = request.in1().expect_bytes()?;
+                let value = request.in2().expect_u128()?;
+                let old = if self.tracks_usage(account_id) {
+                    Some(self.state.accumulator_get(account_id, None, key)?)
+                } else {
+                    None
+                };
+                let ok = self
+                    .state
+                    .accumulator_safe_sub(account_id, None, key, value)?;
+                if let (Some(old), true) = (old, ok) {
+                    self.record_accumulator_usage(account_id, key, old, old - value)?;
+                }
+                // 1 if the value was subtracted, or 0 if it would have gone below zero
+                Ok(Response::new1((ok as u64).into()))
+            }
             EMIT_EVENT_SELECTOR => {
                 let body = request.in1().expect_bytes()?;
                 let type_selector = request.in2().expect_u64()?;
-                self.state.emit_event(account_id, type_selector, body)?;
+                let depth = request.in3().expect_u64()? as u32;
+                self.state
+                    .emit_event(account_id, type_selector, body, depth)?;
                 Ok(Default::default())
             }
             _ => Err(MessageNotHandled.into()),
@@ -126,6 +269,27 @@ impl<S: StdStateManager> StateHandler for StdStateHandler<'_, S> {
                     _ => Ok(Default::default()),
                 }
             }
+            ACCUMULATOR_GET_SELECTOR => {
+                let key = request.in1().expect_bytes()?;
+                let value = self.state.accumulator_get(account_id, None, key)?;
+                Ok(Response::new1(value.into()))
+            }
+            RANGE_SELECTOR | REVERSE_RANGE_SELECTOR => {
+                let start = request.in1().expect_bytes()?;
+                let end = request.in2().as_slice();
+                let limit = request.in3().expect_u64()?;
+                let reverse = request.message_selector() == REVERSE_RANGE_SELECTOR;
+                let pairs = self.state.kv_range(
+                    account_id,
+                    None,
+                    start,
+                    end,
+                    reverse,
+                    limit as usize,
+                    allocator,
+                )?;
+                Ok(Response::new1(pairs.into()))
+            }
             _ => Err(MessageNotHandled.into()),
         }
     }
@@ -141,13 +305,339 @@ impl<S: StdStateManager> StateHandler for StdStateHandler<'_, S> {
     fn delete_account_storage(
         &mut self,
         account: AccountID,
-        _gas: &GasMeter,
+        gas: &GasMeter,
     ) -> Result<(), ErrorCode> {
+        if self.tracks_usage(account) {
+            // all of the account's storage is refunded
+            let usage = self.storage_usage(account, gas)?;
+            self.record_usage(account, -(usage as i128))?;
+        }
         self.state.delete_account_storage(account)
     }
+
+    fn storage_usage(&self, account: AccountID, _gas: &GasMeter) -> Result<u64, ErrorCode> {
+        let usage =
+            self.state
+                .accumulator_get(ROOT_ACCOUNT, None, usage_key(account).as_bytes())?;
+        u64::try_from(usage).map_err(|_| ErrorCode::SystemCode(FatalExecutionError))
+    }
+
+    fn storage_deposit_account(&self) -> Option<AccountID> {
+        self.storage_deposits.map(|config| config.deposit_account)
+    }
+
+    fn take_storage_deposit(
+        &mut self,
+        account: AccountID,
+    ) -> Result<Option<StorageDeposit>, ErrorCode> {
+        let Some(config) = self.storage_deposits else {
+            return Ok(None);
+        };
+        let delta = self
+            .pending_usage
+            .last_mut()
+            .and_then(|pending| pending.remove(&account))
+            .unwrap_or_default();
+        let amount = delta
+            .unsigned_abs()
+            .checked_mul(config.deposit_per_byte)
+            .ok_or(FatalExecutionError)?;
+        if amount == 0 {
+            Ok(None)
+        } else if delta > 0 {
+            Ok(Some(StorageDeposit::Charge(amount)))
+        } else {
+            Ok(Some(StorageDeposit::Refund(amount)))
+        }
+    }
+}
+
+// the number of bytes an accumulator's value is counted as
+const ACCUMULATOR_VALUE_SIZE: usize = 16;
+
+fn usage_key(account: AccountID) -> alloc::string::String {
+    let id: u128 = account.into();
+    format!("u:{}", id)
 }
 
 const GET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.get");
 const SET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.set");
 const DELETE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.delete");
+const RANGE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.range");
+const REVERSE_RANGE_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.reverse_range");
+const ACCUMULATOR_GET_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.accumulator_get");
+const ACCUMULATOR_ADD_SELECTOR: MessageSelector = message_selector!("ixc.store.v1.accumulator_add");
+const ACCUMULATOR_SAFE_SUB_SELECTOR: MessageSelector =
+    message_selector!("ixc.store.v1.accumulator_safe_sub");
 const EMIT_EVENT_SELECTOR: MessageSelector = message_selector!("ixc.events.1.emit");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use ixc_message_api::alloc_util::copy_bytes;
+
+    type Frame = (
+        BTreeMap<(AccountID, Vec<u8>), Vec<u8>>,
+        BTreeMap<(AccountID, Vec<u8>), u128>,
+    );
+
+    /// A minimal in-memory state manager with nested transaction frames.
+    #[derive(Default)]
+    struct MemStore {
+        frames: Vec<Frame>,
+    }
+
+    impl MemStore {
+        fn frame(&self) -> &Frame {
+            self.frames.last().unwrap()
+        }
+
+        fn frame_mut(&mut self) -> &mut Frame {
+            self.frames.last_mut().unwrap()
+        }
+    }
+
+    impl StdStateManager for MemStore {
+        fn kv_get<'a>(
+            &self,
+            account_id: AccountID,
+            _scope: Option<AccountID>,
+            key: &[u8],
+            allocator: &'a dyn Allocator,
+        ) -> Result<Option<&'a [u8]>, ErrorCode> {
+            match self.frame().0.get(&(account_id, key.to_vec())) {
+                Some(value) => unsafe { Ok(Some(copy_bytes(allocator, value)?)) },
+                None => Ok(None),
+            }
+        }
+
+        fn kv_value_len(
+            &self,
+            account_id: AccountID,
+            _scope: Option<AccountID>,
+            key: &[u8],
+        ) -> Result<Option<usize>, ErrorCode> {
+            Ok(self
+                .frame()
+                .0
+                .get(&(account_id, key.to_vec()))
+                .map(Vec::len))
+        }
+
+        fn kv_set(
+            &mut self,
+            account_id: AccountID,
+            _scope: Option<AccountID>,
+            key: &[u8],
+            value: &[u8],
+        ) -> Result<(), ErrorCode> {
+            self.frame_mut()
+                .0
+                .insert((account_id, key.to_vec()), value.to_vec());
+            Ok(())
+        }
+
+        fn kv_delete(
+            &mut self,
+            account_id: AccountID,
+            _scope: Option<AccountID>,
+            key: &[u8],
+        ) -> Result<(), ErrorCode> {
+            self.frame_mut().0.remove(&(account_id, key.to_vec()));
+            Ok(())
+        }
+
+        fn accumulator_get(
+            &self,
+            account_id: AccountID,
+            _scope: Option<AccountID>,
+            key: &[u8],
+        ) -> Result<u128, ErrorCode> {
+            Ok(self
+                .frame()
+                .1
+                .get(&(account_id, key.to_vec()))
+                .copied()
+                .unwrap_or_default())
+        }
+
+        fn accumulator_add(
+            &mut self,
+            account_id: AccountID,
+            _scope: Option<AccountID>,
+            key: &[u8],
+            value: u128,
+        ) -> Result<u128, ErrorCode> {
+            let entry = self
+                .frame_mut()
+                .1
+                .entry((account_id, key.to_vec()))
+                .or_default();
+            *entry = entry.saturating_add(value);
+            Ok(*entry)
+        }
+
+        fn accumulator_safe_sub(
+            &mut self,
+            account_id: AccountID,
+            _scope: Option<AccountID>,
+            key: &[u8],
+            value: u128,
+        ) -> Result<bool, ErrorCode> {
+            let entry = self
+                .frame_mut()
+                .1
+                .entry((account_id, key.to_vec()))
+                .or_default();
+            match entry.checked_sub(value) {
+                Some(new_value) => {
+                    *entry = new_value;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        }
+
+        fn begin_tx(&mut self) -> Result<(), ErrorCode> {
+            let frame = self.frame().clone();
+            self.frames.push(frame);
+            Ok(())
+        }
+
+        fn commit_tx(&mut self) -> Result<(), ErrorCode> {
+            let frame = self.frames.pop().unwrap();
+            *self.frame_mut() = frame;
+            Ok(())
+        }
+
+        fn rollback_tx(&mut self) -> Result<(), ErrorCode> {
+            self.frames.pop();
+            Ok(())
+        }
+
+        fn create_account_storage(&mut self, _account: AccountID) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn delete_account_storage(&mut self, _account: AccountID) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn emit_event(
+            &mut self,
+            _sender: AccountID,
+            _type_selector: u64,
+            _data: &[u8],
+            _depth: u32,
+        ) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+    }
+
+    const ACCOUNT: AccountID = AccountID::new(100);
+
+    fn new_store() -> MemStore {
+        MemStore {
+            frames: vec![Default::default()],
+        }
+    }
+
+    fn with_deposits(store: &mut MemStore) -> StdStateHandler<'_, MemStore> {
+        StdStateHandler::new(store, GasConfig::default()).with_storage_deposits(
+            StorageDepositConfig {
+                deposit_account: AccountID::new(99),
+                deposit_per_byte: 1,
+            },
+        )
+    }
+
+    #[test]
+    fn test_rolled_back_usage_is_not_charged() {
+        let mut store = new_store();
+        let mut handler = with_deposits(&mut store);
+        let gas = GasMeter::unlimited();
+        handler.begin_tx(&gas).unwrap();
+        handler.kv_set(ACCOUNT, b"key", b"value", &gas).unwrap();
+        handler.rollback_tx(&gas).unwrap();
+        assert_eq!(handler.storage_usage(ACCOUNT, &gas).unwrap(), 0);
+        assert_eq!(handler.take_storage_deposit(ACCOUNT).unwrap(), None);
+
+        // committed usage is still pending in the enclosing frame
+        handler.begin_tx(&gas).unwrap();
+        handler.kv_set(ACCOUNT, b"key", b"value", &gas).unwrap();
+        handler.commit_tx(&gas).unwrap();
+        let allocator = allocator_api2::alloc::Global;
+        assert_eq!(
+            handler.kv_get(ACCOUNT, b"key", &gas, &allocator).unwrap(),
+            Some(b"value".as_slice())
+        );
+        assert_eq!(handler.storage_usage(ACCOUNT, &gas).unwrap(), 8);
+        assert_eq!(
+            handler.take_storage_deposit(ACCOUNT).unwrap(),
+            Some(StorageDeposit::Charge(8))
+        );
+    }
+
+    #[test]
+    fn test_storage_usage_overflow() {
+        let mut store = new_store();
+        store
+            .accumulator_add(
+                ROOT_ACCOUNT,
+                None,
+                usage_key(ACCOUNT).as_bytes(),
+                u64::MAX as u128 + 1,
+            )
+            .unwrap();
+        let handler = with_deposits(&mut store);
+        let gas = GasMeter::unlimited();
+        assert!(handler.storage_usage(ACCOUNT, &gas).is_err());
+    }
+
+    #[test]
+    fn test_accumulator_usage() {
+        let mut store = new_store();
+        let mut handler = with_deposits(&mut store);
+        let gas = GasMeter::unlimited();
+        let add = Request::new2(
+            ACCUMULATOR_ADD_SELECTOR,
+            b"seq".as_slice().into(),
+            5u128.into(),
+        );
+        let sub = Request::new2(
+            ACCUMULATOR_SAFE_SUB_SELECTOR,
+            b"seq".as_slice().into(),
+            5u128.into(),
+        );
+        let allocator = allocator_api2::alloc::Global;
+
+        // an accumulator counts as its key and a 16-byte value once it is non-zero
+        handler
+            .handle_exec(ACCOUNT, &add, &gas, &allocator)
+            .unwrap();
+        assert_eq!(handler.storage_usage(ACCOUNT, &gas).unwrap(), 19);
+        handler
+            .handle_exec(ACCOUNT, &add, &gas, &allocator)
+            .unwrap();
+        assert_eq!(handler.storage_usage(ACCOUNT, &gas).unwrap(), 19);
+        assert_eq!(
+            handler.take_storage_deposit(ACCOUNT).unwrap(),
+            Some(StorageDeposit::Charge(19))
+        );
+
+        // and is refunded once it goes back to zero
+        handler
+            .handle_exec(ACCOUNT, &sub, &gas, &allocator)
+            .unwrap();
+        assert_eq!(handler.storage_usage(ACCOUNT, &gas).unwrap(), 19);
+        handler
+            .handle_exec(ACCOUNT, &sub, &gas, &allocator)
+            .unwrap();
+        assert_eq!(handler.storage_usage(ACCOUNT, &gas).unwrap(), 0);
+        assert_eq!(
+            handler.take_storage_deposit(ACCOUNT).unwrap(),
+            Some(StorageDeposit::Refund(19))
+        );
+    }
+}
@@ -1,5 +1,7 @@
+use crate::events::EventMetadata;
 use allocator_api2::alloc::Allocator;
-use ixc_message_api::{code::ErrorCode, AccountID};
+use ixc_message_api::code::{ErrorCode, SystemCode};
+use ixc_message_api::AccountID;
 
 /// The standard state manager trait which is the interface
 /// that the storage layer must implement in order to be
@@ -15,6 +17,13 @@ pub trait StdStateManager {
         key: &[u8],
         allocator: &'a dyn Allocator,
     ) -> Result<Option<&'a [u8]>, ErrorCode>;
+    /// Get the length of the value of a key in storage without copying the value.
+    fn kv_value_len(
+        &self,
+        account_id: AccountID,
+        scope: Option<AccountID>,
+        key: &[u8],
+    ) -> Result<Option<usize>, ErrorCode>;
     /// Set the value of a key in storage.
     fn kv_set(
         &mut self,
@@ -30,6 +39,24 @@ pub trait StdStateManager {
         scope: Option<AccountID>,
         key: &[u8],
     ) -> Result<(), ErrorCode>;
+    /// Get up to `limit` key-value pairs in storage with keys from `start` inclusive
+    /// to `end` exclusive, or to the last key if there is no end,
+    /// in ascending order of their keys or descending order if `reverse` is true.
+    /// The pairs are returned encoded with [`ixc_message_api::encoding::encode_kv_pairs`].
+    /// Storage which can't iterate over its keys doesn't need to implement this.
+    #[allow(clippy::too_many_arguments)]
+    fn kv_range<'a>(
+        &self,
+        _account_id: AccountID,
+        _scope: Option<AccountID>,
+        _start: &[u8],
+        _end: Option<&[u8]>,
+        _reverse: bool,
+        _limit: usize,
+        _allocator: &'a dyn Allocator,
+    ) -> Result<&'a [u8], ErrorCode> {
+        Err(ErrorCode::SystemCode(SystemCode::MessageNotHandled))
+    }
     /// Get the value of an accumulator in storage.
     fn accumulator_get(
         &self,
@@ -37,17 +64,18 @@ pub trait StdStateManager {
         scope: Option<AccountID>,
         key: &[u8],
     ) -> Result<u128, ErrorCode>;
-    /// Add to the value of an accumulator in storage.
+    /// Add to the value of an accumulator in storage and return its new value.
     /// Adds are saturating and can never overflow or fail.
-    /// Because of this, adds may be performed in an undefined order
-    /// because addition is commutative.
+    /// Because the new value is returned, an add is an ordered read-modify-write:
+    /// adds to the same accumulator must be applied in the order they were made,
+    /// and adds from concurrent transactions conflict like any other read and write.
     fn accumulator_add(
         &mut self,
         account_id: AccountID,
         scope: Option<AccountID>,
         key: &[u8],
         value: u128,
-    ) -> Result<(), ErrorCode>;
+    ) -> Result<u128, ErrorCode>;
     /// Safely subtract from the value of an accumulator in storage.
     /// If the operation would cause the value to go below zero, the
     /// operation fails and returns false.
@@ -69,11 +97,23 @@ pub trait StdStateManager {
     /// Delete all of an account's storage. Keys scoped under the account
     /// by another account, however, must not be deleted.
     fn delete_account_storage(&mut self, account: AccountID) -> Result<(), ErrorCode>;
-    /// Emit an event. The sender of the event is the account that emitted it.
+    /// Called before each top-level message of a transaction or batch is run
+    /// with the message's index, which should be recorded with the events it emits.
+    fn begin_message(&mut self, _index: u32) -> Result<(), ErrorCode> {
+        Ok(())
+    }
+    /// Returns the metadata which was recorded with the most recently emitted event
+    /// in the current transaction frame, if the state manager records event metadata.
+    fn last_event_metadata(&self) -> Option<EventMetadata> {
+        None
+    }
+    /// Emit an event. The sender of the event is the account that emitted it
+    /// and `depth` is the depth of its call, starting at 0 for a top-level message.
     fn emit_event(
         &mut self,
         sender: AccountID,
         type_selector: u64,
         data: &[u8],
+        depth: u32,
     ) -> Result<(), ErrorCode>;
 }
@@ -0,0 +1,93 @@
+//! Transaction envelopes and authentication.
+use alloc::vec::Vec;
+use ixc_message_api::code::ErrorCode;
+use ixc_message_api::encoding::write_message;
+use ixc_message_api::message::Message;
+use ixc_message_api::AccountID;
+
+/// A transaction envelope which contains one or more messages sent by a single sender.
+#[derive(Clone, Copy)]
+pub struct Tx<'a> {
+    /// The account sending the transaction, which is the caller of every message.
+    pub sender: AccountID,
+    /// The sender's nonce which is used to prevent transaction replay.
+    pub nonce: u64,
+    /// The messages to execute.
+    pub messages: &'a [Message<'a>],
+    /// Opaque credentials, such as signatures, which the sender account's handler
+    /// uses to authenticate the transaction.
+    pub credentials: &'a [u8],
+}
+
+impl Tx<'_> {
+    /// Returns the canonical bytes of the transaction which the credentials authenticate
+    /// on the chain with the given ID.
+    /// This is a deterministic encoding of the chain ID, sender, nonce and messages,
+    /// with each message encoded using [`ixc_message_api::encoding`].
+    /// Including the chain ID means that a transaction signed for one chain
+    /// can't be replayed on another chain where the sender has the same ID and nonce.
+    pub fn sign_bytes(&self, chain_id: &str) -> Vec<u8> {
+        let mut bz = Vec::new();
+        bz.extend_from_slice(&(chain_id.len() as u32).to_be_bytes());
+        bz.extend_from_slice(chain_id.as_bytes());
+        bz.extend_from_slice(&u128::from(self.sender).to_be_bytes());
+        bz.extend_from_slice(&self.nonce.to_be_bytes());
+        bz.extend_from_slice(&(self.messages.len() as u32).to_be_bytes());
+        for message in self.messages {
+            write_message(message, &mut |chunk| bz.extend_from_slice(chunk));
+        }
+        bz
+    }
+}
+
+/// An error returned when executing a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxError {
+    /// The sender could not be authenticated, so no state changes were made.
+    Unauthenticated(ErrorCode),
+    /// The message at the index failed, so the state changes of all the messages were rolled back.
+    /// State changes made while authenticating the sender, such as nonce increments, are retained.
+    MessageFailed {
+        /// The index of the message which failed.
+        index: usize,
+        /// The error code returned by the message.
+        code: ErrorCode,
+    },
+    /// The state handler failed to begin, commit or roll back a transaction frame.
+    Fatal(ErrorCode),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ixc_message_api::message::Request;
+
+    #[test]
+    fn test_sign_bytes() {
+        let messages = [Message::new(
+            AccountID::new(2),
+            Request::new2(7, b"foo".as_slice().into(), 5u64.into()),
+        )];
+        let tx = Tx {
+            sender: AccountID::new(1),
+            nonce: 3,
+            messages: &messages,
+            credentials: &[],
+        };
+        let bz = tx.sign_bytes("test");
+        assert_eq!(
+            bz.len(),
+            (4 + 4) + 16 + 8 + 4 + 16 + 8 + 1 + (1 + 4 + 3) + (1 + 8) + 1
+        );
+
+        // the sign bytes change when anything but the credentials change
+        let tx2 = Tx { nonce: 4, ..tx };
+        assert_ne!(bz, tx2.sign_bytes("test"));
+        assert_ne!(bz, tx.sign_bytes("other"));
+        let tx3 = Tx {
+            credentials: b"sig",
+            ..tx
+        };
+        assert_eq!(bz, tx3.sign_bytes("test"));
+    }
+}
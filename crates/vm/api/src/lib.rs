@@ -28,6 +28,17 @@ pub trait VM {
         allocator: &'b dyn Allocator,
     ) -> Result<&'b dyn RawHandler, ErrorCode>;
 
+    /// Returns the schema of the handler encoded with the native binary codec
+    /// or None if the VM doesn't have a schema for the handler.
+    fn handler_schema<'a>(
+        &self,
+        _store: &dyn ReadonlyStore,
+        _handler_id: &str,
+        _allocator: &'a dyn Allocator,
+    ) -> Result<Option<&'a [u8]>, ErrorCode> {
+        Ok(None)
+    }
+
     // /// Runs a handler with the provided message packet and host backend.
     // fn run_message(
     //     &self,